@@ -1,6 +1,7 @@
 use crate::envy_load;
 use serde::Deserialize;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 /// the api configuration of Recover State Server.
 #[derive(Default, Debug, Deserialize, Clone, PartialEq)]
@@ -11,6 +12,10 @@ pub struct ApiConfig {
     pub workers_num: usize,
     /// Enable cors cross-domain
     pub enable_http_cors: bool,
+    /// The maximum time(in milliseconds) to process a request, no limit if not set.
+    pub request_timeout_ms: Option<u64>,
+    /// The token required by the `X-Admin-Token` header of the admin api, admin api is disabled if not set.
+    pub admin_token: Option<String>,
 }
 
 impl ApiConfig {
@@ -21,4 +26,8 @@ impl ApiConfig {
     pub fn bind_addr(&self) -> SocketAddr {
         SocketAddr::new("0.0.0.0".parse().unwrap(), self.server_http_port)
     }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout_ms.map(Duration::from_millis)
+    }
 }
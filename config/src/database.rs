@@ -1,3 +1,6 @@
+// Built-in uses
+use std::path::Path;
+use std::time::Duration;
 // External uses
 use serde::Deserialize;
 
@@ -9,14 +12,78 @@ use crate::envy_load;
 pub struct DBConfig {
     /// Amount of open connections to the database held by server in the pool.
     pub pool_size: usize,
+    /// Amount of connections held by the api server, so that reads are not starved by the restore.
+    /// Falls back to `pool_size` if not set.
+    pub api_pool_size: Option<usize>,
+    /// Amount of connections held by the restore driver, falls back to `pool_size` if not set.
+    pub restore_pool_size: Option<usize>,
     /// Database URL.
     pub url: String,
+    /// The URL of the read replica serving the read-only queries, all queries go to `url` if not set.
+    pub read_replica_url: Option<String>,
+    /// Amount of connections to the read replica, falls back to `api_pool_size` if not set.
+    pub read_pool_size: Option<usize>,
+    /// The number of retries to get a connection before giving up, 120 if not set.
+    pub connection_retries: Option<usize>,
+    /// The delay(in milliseconds) between the retries to get a connection, 1000 if not set.
+    pub connection_retry_delay_ms: Option<u64>,
+    /// The time(in milliseconds) over which the heavy queries are logged as slow queries, never logged if not set.
+    pub slow_query_threshold_ms: Option<u64>,
+    /// The time(in milliseconds) over which the committed transactions are logged as slow transactions,
+    /// the `SLOW_TX_THRESHOLD` of the storage if not set.
+    pub slow_tx_threshold_ms: Option<u64>,
+    /// Skips the state invariant check when applying the state updates of the restored blocks, false if not set.
+    pub skip_state_invariant_check: Option<bool>,
 }
 
 impl DBConfig {
     pub fn from_env() -> Self {
         envy_load!("database", "DATABASE_")
     }
+
+    pub fn api_pool_size(&self) -> usize {
+        self.api_pool_size.unwrap_or(self.pool_size)
+    }
+
+    pub fn read_pool_size(&self) -> usize {
+        self.read_pool_size.unwrap_or_else(|| self.api_pool_size())
+    }
+
+    pub fn restore_pool_size(&self) -> usize {
+        self.restore_pool_size.unwrap_or(self.pool_size)
+    }
+
+    pub fn connection_retries(&self) -> usize {
+        self.connection_retries.unwrap_or(120)
+    }
+
+    pub fn connection_retry_delay(&self) -> Duration {
+        Duration::from_millis(self.connection_retry_delay_ms.unwrap_or(1000))
+    }
+
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        self.slow_query_threshold_ms.map(Duration::from_millis)
+    }
+
+    pub fn slow_tx_threshold(&self) -> Option<Duration> {
+        self.slow_tx_threshold_ms.map(Duration::from_millis)
+    }
+
+    pub fn skip_state_invariant_check(&self) -> bool {
+        self.skip_state_invariant_check.unwrap_or(false)
+    }
+
+    /// Reloads `DATABASE_URL` from the `.env` file under `zklink_home` so that the url changed after
+    /// startup(e.g. failover) can be observed, falls back to the environment variable if it's not in the file.
+    pub fn reload_url(zklink_home: &str) -> Option<String> {
+        dotenvy::from_path_iter(Path::new(zklink_home).join(".env"))
+            .ok()
+            .and_then(|mut vars| {
+                vars.find_map(|var| var.ok().filter(|(key, _)| key == "DATABASE_URL"))
+                    .map(|(_, url)| url)
+            })
+            .or_else(|| std::env::var("DATABASE_URL").ok())
+    }
 }
 
 #[cfg(test)]
@@ -27,7 +94,16 @@ mod tests {
     fn expected_config() -> DBConfig {
         DBConfig {
             pool_size: 10,
+            api_pool_size: Some(20),
+            restore_pool_size: None,
             url: "postgres://postgres@localhost/plasma".into(),
+            read_replica_url: Some("postgres://postgres@replica/plasma".into()),
+            read_pool_size: None,
+            connection_retries: Some(5),
+            connection_retry_delay_ms: None,
+            slow_query_threshold_ms: Some(500),
+            slow_tx_threshold_ms: None,
+            skip_state_invariant_check: None,
         }
     }
 
@@ -36,10 +112,40 @@ mod tests {
         let config = r#"
             DATABASE_URL="postgres://postgres@localhost/plasma"
             DATABASE_POOL_SIZE="10"
+            DATABASE_API_POOL_SIZE="20"
+            DATABASE_READ_REPLICA_URL="postgres://postgres@replica/plasma"
+            DATABASE_CONNECTION_RETRIES="5"
+            DATABASE_SLOW_QUERY_THRESHOLD_MS="500"
         "#;
         set_env(config);
 
         let actual = DBConfig::from_env();
         assert_eq!(actual, expected_config());
+        assert_eq!(actual.api_pool_size(), 20);
+        assert_eq!(actual.restore_pool_size(), 10);
+        assert_eq!(actual.read_pool_size(), 20);
+        assert_eq!(actual.connection_retries(), 5);
+        assert_eq!(actual.connection_retry_delay(), Duration::from_secs(1));
+        assert_eq!(
+            actual.slow_query_threshold(),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(actual.slow_tx_threshold(), None);
+        assert!(!actual.skip_state_invariant_check());
+    }
+
+    #[test]
+    fn reload_url_from_zklink_home() {
+        let zklink_home = std::env::temp_dir().join("reload_url_from_zklink_home");
+        std::fs::create_dir_all(&zklink_home).unwrap();
+        std::fs::write(
+            zklink_home.join(".env"),
+            "DATABASE_URL=postgres://postgres@failover/plasma\n",
+        )
+        .unwrap();
+
+        let url = DBConfig::reload_url(zklink_home.to_str().unwrap());
+        std::fs::remove_dir_all(&zklink_home).unwrap();
+        assert_eq!(url.as_deref(), Some("postgres://postgres@failover/plasma"));
     }
 }
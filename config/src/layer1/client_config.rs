@@ -16,6 +16,13 @@ pub struct ClientConfig {
     /// As `infura` may limit the requests, and then we need to delay sending the request for some time.
     /// Wait this amount of time if we hit rate limit on infura https://infura.io/docs/ethereum/json-rpc/ratelimits
     pub request_rate_limit_delay: u64,
+    /// The number of the latest layer1 blocks that are not scanned yet, 40 if not set.
+    ///
+    /// It's a scan offset: the events are fetched only after being buried by this depth,
+    /// so the ops of the unconfirmed layer1 blocks are never stored nor applied, and there
+    /// are no pending(unconfirmed) aggregated ops to track. The reorgs deeper than it are
+    /// left to the reorg detection of the watched block hashes.
+    pub confirmation_depth: Option<u64>,
 }
 
 impl ClientConfig {
@@ -50,6 +57,7 @@ mod tests {
             ],
             view_block_step: 1000,
             request_rate_limit_delay: 30,
+            confirmation_depth: Some(12),
         }
     }
 
@@ -60,6 +68,7 @@ mod tests {
         CHAIN_1_CLIENT_WEB3_URL="http://127.0.0.1:8545,http://127.0.0.1:8546"
         CHAIN_1_CLIENT_VIEW_BLOCK_STEP=1000
         CHAIN_1_CLIENT_REQUEST_RATE_LIMIT_DELAY=30
+        CHAIN_1_CLIENT_CONFIRMATION_DEPTH=12
         "#;
         set_env(config);
 
@@ -1,5 +1,6 @@
 // External uses
 use serde::Deserialize;
+use std::str::FromStr;
 // Workspace uses
 use zklink_types::{ZkLinkAddress, H256};
 // Local uses
@@ -8,8 +9,8 @@ use crate::envy_load;
 /// Data about deployed contracts.
 #[derive(Default, Debug, Deserialize, Clone, PartialEq)]
 pub struct ContractConfig {
-    /// The block number of contracts deployed.
-    pub deployment_block: u64,
+    /// The block number of contracts deployed, "auto" means discovering it from the chain at startup.
+    pub deployment_block: DeploymentBlock,
     /// The zkLink main contract address
     pub address: ZkLinkAddress,
     /// The zkLink contract deployed tx hash, used for recover data
@@ -20,6 +21,59 @@ impl ContractConfig {
     pub fn from_env(chain_id: u8) -> Self {
         envy_load!("contract", format!("CHAIN_{}_CONTRACT_", chain_id))
     }
+
+    /// Returns the block number of contracts deployed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deployment block is "auto" and has not been discovered yet.
+    pub fn deployment_block_number(&self) -> u64 {
+        match self.deployment_block {
+            DeploymentBlock::Number(number) => number,
+            DeploymentBlock::Auto => {
+                panic!(
+                    "The deployment block of contract {:?} is not discovered",
+                    self.address
+                )
+            }
+        }
+    }
+}
+
+/// The deployment block of contracts, configured as the block number or "auto".
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum DeploymentBlock {
+    /// Discovers the deployment block from the chain at startup.
+    Auto,
+    Number(u64),
+}
+
+impl Default for DeploymentBlock {
+    fn default() -> Self {
+        DeploymentBlock::Number(0)
+    }
+}
+
+impl FromStr for DeploymentBlock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(DeploymentBlock::Auto);
+        }
+        s.parse()
+            .map(DeploymentBlock::Number)
+            .map_err(|_| format!("Invalid deployment block: {}", s))
+    }
+}
+
+impl TryFrom<String> for DeploymentBlock {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
 }
 
 #[cfg(test)]
@@ -29,7 +83,7 @@ mod tests {
 
     fn expected_config() -> ContractConfig {
         ContractConfig {
-            deployment_block: 10000,
+            deployment_block: DeploymentBlock::Number(10000),
             address: "0x70a0F165d6f8054d0d0CF8dFd4DD2005f0AF6B55"
                 .parse()
                 .unwrap(),
@@ -51,4 +105,19 @@ mod tests {
         let actual = ContractConfig::from_env(1);
         assert_eq!(actual, expected_config());
     }
+
+    #[test]
+    fn auto_deployment_block_from_env() {
+        let config = r#"
+            CHAIN_2_CONTRACT_DEPLOYMENT_BLOCK="auto"
+            CHAIN_2_CONTRACT_ADDRESS="0x70a0F165d6f8054d0d0CF8dFd4DD2005f0AF6B55"
+            CHAIN_2_CONTRACT_GENESIS_TX_HASH="0xb99ebfea46cbe05a21cd80fe5597d97b204befc52a16303f579c607dc1ac2e2e"
+        "#;
+        set_env(config);
+
+        let actual = ContractConfig::from_env(2);
+        assert_eq!(actual.deployment_block, DeploymentBlock::Auto);
+        assert_eq!("10000".parse(), Ok(DeploymentBlock::Number(10000)));
+        assert!("latest".parse::<DeploymentBlock>().is_err());
+    }
 }
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use zklink_types::{ChainId, ZkLinkAddress};
 
 use crate::evn_tools::parse_env_to_vec_if_exists;
 pub use chain_config::ChainConfig;
 pub use chain_type::ChainType;
 pub use client_config::ClientConfig;
-pub use contract_config::ContractConfig;
+pub use contract_config::{ContractConfig, DeploymentBlock};
 
 mod chain_config;
 mod chain_type;
@@ -25,12 +25,71 @@ impl MultiChainConfigs {
             .iter()
             .map(|chain_id| Layer1Config::from_env((*chain_id).into()))
             .collect::<Vec<_>>();
-        Self {
+        let configs = Self {
             chain_ids,
             chain_configs,
+        };
+        configs.validate().expect("Invalid chain configuration");
+        configs
+    }
+
+    /// Checks that the chain ids are unique, nonzero and contiguous from 1, and the deployment block
+    /// and the view block step of every chain are nonzero, returns all the violations.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let mut chain_ids = HashSet::new();
+        for config in &self.chain_configs {
+            let chain_id = config.chain.chain_id;
+            if *chain_id == 0 {
+                errors.push("The chain id must be nonzero".to_string());
+            }
+            if !chain_ids.insert(chain_id) {
+                errors.push(format!("The chain id {} is duplicated", *chain_id));
+            }
+            // The "auto" deployment block is discovered from the chain at startup.
+            if config.contract.deployment_block == DeploymentBlock::Number(0) {
+                errors.push(format!(
+                    "The deployment block of chain {} must be nonzero",
+                    *chain_id
+                ));
+            }
+            if config.client.view_block_step == 0 {
+                errors.push(format!(
+                    "The view block step of chain {} must be nonzero",
+                    *chain_id
+                ));
+            }
+        }
+        // The exit circuit enumerates the global asset accounts of the chains `1..=max_chain_num`,
+        // so it assumes the configured chains are exactly these ones.
+        let max_chain_id = chain_ids
+            .iter()
+            .map(|chain_id| **chain_id)
+            .max()
+            .unwrap_or(0);
+        let missing_chain_ids: Vec<_> = (1..max_chain_id)
+            .filter(|chain_id| !chain_ids.contains(&ChainId(*chain_id)))
+            .collect();
+        if !missing_chain_ids.is_empty() {
+            errors.push(format!(
+                "The chain ids must be contiguous from 1, missing: {:?}",
+                missing_chain_ids
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 
+    /// Gets the config of the chain by the chain id defined by zkLink.
+    pub fn get_chain_config(&self, chain_id: ChainId) -> Option<&Layer1Config> {
+        self.chain_configs
+            .iter()
+            .find(|config| config.chain.chain_id == chain_id)
+    }
+
     pub fn get_contracts(&self) -> HashMap<ChainId, ZkLinkAddress> {
         self.chain_configs
             .iter()
@@ -59,3 +118,75 @@ impl Layer1Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer1_config(chain_id: u8) -> Layer1Config {
+        Layer1Config {
+            chain: ChainConfig {
+                chain_id: chain_id.into(),
+                ..Default::default()
+            },
+            contract: ContractConfig {
+                deployment_block: DeploymentBlock::Number(100),
+                ..Default::default()
+            },
+            client: ClientConfig {
+                view_block_step: 1000,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn multi_chain_configs(chain_configs: Vec<Layer1Config>) -> MultiChainConfigs {
+        MultiChainConfigs {
+            chain_ids: chain_configs.iter().map(|c| c.chain.chain_id).collect(),
+            chain_configs,
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let configs = multi_chain_configs(vec![layer1_config(1), layer1_config(2)]);
+        assert_eq!(configs.validate(), Ok(()));
+        assert_eq!(
+            configs.get_chain_config(2.into()).map(|c| c.chain.chain_id),
+            Some(2.into())
+        );
+        assert!(configs.get_chain_config(3.into()).is_none());
+
+        let mut auto_deployment_block = layer1_config(1);
+        auto_deployment_block.contract.deployment_block = DeploymentBlock::Auto;
+        let configs = multi_chain_configs(vec![auto_deployment_block]);
+        assert_eq!(configs.validate(), Ok(()));
+
+        // All the violations are reported.
+        let mut invalid_config = layer1_config(2);
+        invalid_config.contract.deployment_block = DeploymentBlock::Number(0);
+        invalid_config.client.view_block_step = 0;
+        let configs = multi_chain_configs(vec![layer1_config(0), layer1_config(2), invalid_config]);
+        assert_eq!(
+            configs.validate(),
+            Err(vec![
+                "The chain id must be nonzero".to_string(),
+                "The chain id 2 is duplicated".to_string(),
+                "The deployment block of chain 2 must be nonzero".to_string(),
+                "The view block step of chain 2 must be nonzero".to_string(),
+                "The chain ids must be contiguous from 1, missing: [1]".to_string(),
+            ])
+        );
+
+        // The sparse chain ids are rejected rather than producing invalid exit proofs.
+        let configs =
+            multi_chain_configs(vec![layer1_config(1), layer1_config(3), layer1_config(7)]);
+        assert_eq!(configs.get_max_chain_num(), 7);
+        assert_eq!(
+            configs.validate(),
+            Err(vec![
+                "The chain ids must be contiguous from 1, missing: [2, 4, 5, 6]".to_string()
+            ])
+        );
+    }
+}
@@ -2,8 +2,10 @@ use crate::evn_tools::{parse_env_if_exists, parse_env_to_vec_if_exists};
 pub use crate::{
     api::ApiConfig,
     database::DBConfig,
-    layer1::{ChainType, ClientConfig, ContractConfig, Layer1Config, MultiChainConfigs},
-    runtime::RuntimeConfig,
+    layer1::{
+        ChainType, ClientConfig, ContractConfig, DeploymentBlock, Layer1Config, MultiChainConfigs,
+    },
+    runtime::{RollupOpsFormat, RuntimeConfig},
 };
 
 mod api;
@@ -12,15 +14,28 @@ pub mod evn_tools;
 mod layer1;
 mod runtime;
 
+/// The default of `RecoverStateConfig::max_pending_tasks_per_address`.
+pub const DEFAULT_MAX_PENDING_TASKS_PER_ADDRESS: usize = 50;
+
 #[derive(Debug, Clone)]
 pub struct RecoverStateConfig {
     pub runtime: RuntimeConfig,
     pub api: ApiConfig,
     pub db: DBConfig,
     pub layer1: MultiChainConfigs,
-    pub upgrade_layer2_blocks: Vec<u32>,
+    /// The contract upgrades as `(layer2_block, contract_version)`, the blocks after `layer2_block`
+    /// are committed by `contract_version`. Parsed from `UPGRADED_LAYER2_BLOCKS`, see `parse_upgrade_layer2_blocks`.
+    pub upgrade_layer2_blocks: Vec<(u32, u32)>,
+    /// The blacklist window(in minutes) of the proof tasks requested by the same user,
+    /// `None` means the blacklist is disabled(`CLEAN_INTERVAL` is not configured or is 0).
     pub black_list_time: Option<u32>,
+    /// The maximum number of the pending proof tasks of a single address,
+    /// the new tasks of the address are rejected once it's reached. 50 if not set.
+    pub max_pending_tasks_per_address: usize,
+    /// Whether the proof tasks are claimed only by the operator priority, see `ProverConfig::skip_balance_ordering`.
+    pub skip_balance_ordering: bool,
     pub enable_sync_mode: bool,
+    pub tree_checkpoint_path: Option<String>,
 }
 
 impl RecoverStateConfig {
@@ -30,14 +45,90 @@ impl RecoverStateConfig {
             api: ApiConfig::from_env(),
             db: DBConfig::from_env(),
             layer1: MultiChainConfigs::from_env(),
-            upgrade_layer2_blocks: parse_env_to_vec_if_exists("UPGRADED_LAYER2_BLOCKS")
-                .unwrap_or_default(),
-            black_list_time: parse_env_if_exists("CLEAN_INTERVAL"),
+            upgrade_layer2_blocks: parse_upgrade_layer2_blocks(
+                parse_env_to_vec_if_exists("UPGRADED_LAYER2_BLOCKS").unwrap_or_default(),
+            ),
+            black_list_time: parse_env_if_exists("CLEAN_INTERVAL").filter(|&time| time != 0),
+            max_pending_tasks_per_address: parse_env_if_exists("MAX_PENDING_TASKS_PER_ADDRESS")
+                .unwrap_or(DEFAULT_MAX_PENDING_TASKS_PER_ADDRESS),
+            skip_balance_ordering: parse_env_if_exists("SKIP_BALANCE_ORDERING").unwrap_or_default(),
+            enable_sync_mode: parse_env_if_exists("ENABLE_SYNC_MODE").unwrap_or_default(),
+            tree_checkpoint_path: parse_env_if_exists("TREE_CHECKPOINT_PATH"),
+        }
+    }
+}
+
+/// The subset of `RecoverStateConfig` required by the exodus prover,
+/// loaded without the api configs that the prover-only hosts don't have.
+#[derive(Debug, Clone)]
+pub struct ProverConfig {
+    /// The key dir, the universal setup files and the proof checkpoints.
+    pub runtime: RuntimeConfig,
+    pub db: DBConfig,
+    pub layer1: MultiChainConfigs,
+    pub enable_sync_mode: bool,
+    /// The number of the prover workers, overridden by the command line. A sixteenth of the cpu cores if not set.
+    pub workers_num: Option<usize>,
+    /// Whether to claim the tasks only by the operator priority, instead of the balance of the exiting token first.
+    pub skip_balance_ordering: bool,
+}
+
+impl ProverConfig {
+    pub fn from_env() -> Self {
+        Self {
+            runtime: RuntimeConfig::from_env(),
+            db: DBConfig::from_env(),
+            layer1: MultiChainConfigs::from_env(),
             enable_sync_mode: parse_env_if_exists("ENABLE_SYNC_MODE").unwrap_or_default(),
+            workers_num: parse_env_if_exists("PROVER_WORKERS_NUM"),
+            skip_balance_ordering: parse_env_if_exists("SKIP_BALANCE_ORDERING").unwrap_or_default(),
         }
     }
 }
 
+impl From<RecoverStateConfig> for ProverConfig {
+    fn from(config: RecoverStateConfig) -> Self {
+        Self {
+            runtime: config.runtime,
+            db: config.db,
+            layer1: config.layer1,
+            enable_sync_mode: config.enable_sync_mode,
+            workers_num: None,
+            skip_balance_ordering: config.skip_balance_ordering,
+        }
+    }
+}
+
+/// Parses the entries formatted as `layer2_block:contract_version`, e.g. `1000:1,5000:2`.
+/// The version of a bare `layer2_block` entry is the version of the previous entry plus one(the genesis version is 0).
+///
+/// # Panics
+///
+/// Panics if the entry is not formatted as `layer2_block[:contract_version]`.
+fn parse_upgrade_layer2_blocks(entries: Vec<String>) -> Vec<(u32, u32)> {
+    let mut contract_version = 0;
+    entries
+        .iter()
+        .map(|entry| {
+            let (layer2_block, version) = match entry.split_once(':') {
+                Some((layer2_block, version)) => (layer2_block, version.trim().parse().ok()),
+                None => (entry.as_str(), Some(contract_version + 1)),
+            };
+            match (layer2_block.trim().parse(), version) {
+                (Ok(layer2_block), Some(version)) => {
+                    contract_version = version;
+                    (layer2_block, version)
+                }
+                _ => panic!(
+                    "Invalid UPGRADED_LAYER2_BLOCKS entry: {}, \
+                    expected `layer2_block[:contract_version]`",
+                    entry
+                ),
+            }
+        })
+        .collect()
+}
+
 /// Convenience macro that loads the structure from the environment variable given the prefix.
 ///
 /// # Panics
@@ -51,3 +142,29 @@ macro_rules! envy_load {
             .unwrap_or_else(|err| panic!("Cannot load config <{}>: {}", $name, err))
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_upgrade_layer2_blocks;
+
+    #[test]
+    fn upgrade_layer2_blocks() {
+        let entries = |entries: &[&str]| entries.iter().map(|e| e.to_string()).collect();
+        assert_eq!(parse_upgrade_layer2_blocks(vec![]), vec![]);
+        assert_eq!(
+            parse_upgrade_layer2_blocks(entries(&["1000:1", " 5000 : 3 "])),
+            vec![(1000, 1), (5000, 3)]
+        );
+        // The bare blocks upgrade by one version.
+        assert_eq!(
+            parse_upgrade_layer2_blocks(entries(&["1000", "3000:3", "5000"])),
+            vec![(1000, 1), (3000, 3), (5000, 4)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid UPGRADED_LAYER2_BLOCKS entry")]
+    fn invalid_upgrade_layer2_blocks() {
+        parse_upgrade_layer2_blocks(vec!["1000:v1".to_string()]);
+    }
+}
@@ -1,5 +1,10 @@
 use crate::envy_load;
+use bigdecimal::BigDecimal;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 /// Configuration for the zklink runtime.
 #[derive(Default, Debug, Deserialize, Clone, PartialEq)]
@@ -8,8 +13,88 @@ pub struct RuntimeConfig {
     pub zklink_home: String,
     /// Path to the directory with the cryptographical keys. Relative to `$ZKLINK_HOME`.
     pub key_dir: String,
+    /// The maximum layer1 blocks scanned by the restore driver per iteration, after every iteration
+    /// the tree checkpoint is persisted and the metrics are updated. No limit if not set.
+    pub max_blocks_per_iteration: Option<u64>,
+    /// The layer1 blocks watched by the restore driver between two checks of the layer1 reorgs,
+    /// `REORG_CHECK_INTERVAL` of the restore driver if not set.
+    pub reorg_check_interval: Option<u64>,
+    /// The retries of fetching the genesis transaction by the restore driver on the layer1 rpc failures,
+    /// `GENESIS_FETCH_RETRIES` of the restore driver if not set.
+    pub genesis_fetch_retries: Option<u32>,
+    /// Path to the directory with the universal setup files, `$ZKLINK_HOME/zklink_keys` if not set.
+    pub setup_keys_dir: Option<String>,
+    /// The file name overrides of the universal setup of the specified power of two,
+    /// formatted as `power_of_two:file_name`, e.g. `20:setup_20.key,21:setup_21.key`.
+    /// `setup_2^{power_of_two}.key` is used for the power of two not overridden.
+    pub setup_key_files: Option<Vec<String>>,
+    /// Whether to abort the restore when the balance of an op underflows,
+    /// otherwise the op is recorded as a failed tx and the restore continues.
+    #[serde(default)]
+    pub strict_restore: bool,
+    /// Whether the server starts in maintenance mode, the read apis keep working
+    /// but the proof task writes are rejected. It can be toggled by the admin api at runtime.
+    #[serde(default)]
+    pub read_only: bool,
+    /// The number of latest verified blocks whose balance/order/pubkey updates are retained,
+    /// the older updates are pruned periodically. Never prune if not set.
+    ///
+    /// Note: the historical account snapshot below the retained blocks can't be recovered after pruning.
+    pub update_retention_blocks: Option<u64>,
+    /// The expected p99 time(in milliseconds) of generating an exit proof, the prover warns if the p99 of
+    /// the latest proofs regresses more than 20% over it. Never checked if not set.
+    pub proof_p99_baseline_ms: Option<u64>,
+    /// Path to the directory where the prover caches the generated proofs that are not stored yet,
+    /// so that a restarted prover doesn't recompute them. Never cached if not set.
+    pub proof_result_cache_dir: Option<String>,
+    /// The time(in seconds) after which the cached proof of an unfinished task is considered stale and
+    /// removed at the prover startup, `DEFAULT_MAX_TASK_STALENESS_SECS` if not set.
+    pub max_task_staleness_secs: Option<u64>,
+    /// The representation of the rollup ops saved by the restore driver, `json` if not set.
+    /// The ops saved in either representation can be loaded regardless of it.
+    #[serde(default)]
+    pub rollup_ops_format: RollupOpsFormat,
+    /// The USD price of the tokens that are not priced in the `token_price` table when valuing
+    /// the accounts(e.g. prioritizing the exit proof tasks), they are valued as zero if not set.
+    pub default_price: Option<BigDecimal>,
 }
 
+/// The stored representation of the rollup ops.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupOpsFormat {
+    /// The readable JSON of the ops.
+    #[default]
+    Json,
+    /// The bincode of the public data of the ops, more compact and faster to decode.
+    Bincode,
+}
+
+impl RollupOpsFormat {
+    /// The name of the format that is stored along with the ops.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollupOpsFormat::Json => "json",
+            RollupOpsFormat::Bincode => "bincode",
+        }
+    }
+}
+
+impl FromStr for RollupOpsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(RollupOpsFormat::Json),
+            "bincode" => Ok(RollupOpsFormat::Bincode),
+            _ => Err(format!("Unsupported rollup ops format: {}", s)),
+        }
+    }
+}
+
+/// The default staleness of the cached proof of an unfinished task, one day.
+pub const DEFAULT_MAX_TASK_STALENESS_SECS: u64 = 24 * 60 * 60;
+
 impl RuntimeConfig {
     pub fn from_env() -> Self {
         let mut config: RuntimeConfig = envy_load!("runtime", "RUNTIME_CONFIG_");
@@ -17,6 +102,148 @@ impl RuntimeConfig {
         key_dir.push('/');
         key_dir.push_str(&config.key_dir);
         config.key_dir = key_dir;
+        config.validate().expect("Invalid runtime configuration");
         config
     }
+
+    /// Checks that the configured universal setup dir is an existing directory and the setup file
+    /// overrides are formatted as `power_of_two:file_name`, returns all the violations.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        // The default dir is only needed by the prover, so it's left to the loading of the setup files.
+        if let Some(dir) = &self.setup_keys_dir {
+            if !Path::new(dir).is_dir() {
+                errors.push(format!(
+                    "The universal setup dir {} is not a directory",
+                    dir
+                ));
+            }
+        }
+        for entry in self.setup_key_files.iter().flatten() {
+            if parse_setup_key_file(entry).is_none() {
+                errors.push(format!(
+                    "Invalid RUNTIME_CONFIG_SETUP_KEY_FILES entry: {}, \
+                    expected `power_of_two:file_name`",
+                    entry
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the directory of the universal setup files.
+    pub fn setup_keys_dir(&self) -> PathBuf {
+        match &self.setup_keys_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(&self.zklink_home).join("zklink_keys"),
+        }
+    }
+
+    /// Returns the path of the universal setup file in monomial form of the given power of two.
+    pub fn setup_key_path(&self, power_of_two: u32) -> PathBuf {
+        let file_name = self
+            .setup_key_file_overrides()
+            .remove(&power_of_two)
+            .unwrap_or_else(|| format!("setup_2^{}.key", power_of_two));
+        self.setup_keys_dir().join(file_name)
+    }
+
+    /// Returns the staleness of the cached proof of an unfinished task.
+    pub fn max_task_staleness(&self) -> Duration {
+        Duration::from_secs(
+            self.max_task_staleness_secs
+                .unwrap_or(DEFAULT_MAX_TASK_STALENESS_SECS),
+        )
+    }
+
+    /// Parses `setup_key_files` to the map from power of two to file name,
+    /// the invalid entries are rejected by `validate`.
+    fn setup_key_file_overrides(&self) -> HashMap<u32, String> {
+        self.setup_key_files
+            .iter()
+            .flatten()
+            .filter_map(|entry| parse_setup_key_file(entry))
+            .collect()
+    }
+}
+
+/// Parses the setup file override formatted as `power_of_two:file_name`.
+fn parse_setup_key_file(entry: &str) -> Option<(u32, String)> {
+    let (power_of_two, file_name) = entry.split_once(':')?;
+    Some((
+        power_of_two.trim().parse().ok()?,
+        file_name.trim().to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setup_key_path() {
+        let config = RuntimeConfig {
+            zklink_home: "/zklink".to_string(),
+            setup_key_files: Some(vec!["21:custom_21.key".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.setup_key_path(20),
+            PathBuf::from("/zklink/zklink_keys/setup_2^20.key")
+        );
+        assert_eq!(
+            config.setup_key_path(21),
+            PathBuf::from("/zklink/zklink_keys/custom_21.key")
+        );
+
+        let config = RuntimeConfig {
+            setup_keys_dir: Some("/air_gapped/keys".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.setup_key_path(21),
+            PathBuf::from("/air_gapped/keys/custom_21.key")
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let config = RuntimeConfig {
+            zklink_home: "/zklink".to_string(),
+            setup_key_files: Some(vec!["21:custom_21.key".to_string()]),
+            ..Default::default()
+        };
+        // The default setup dir isn't required to exist.
+        assert_eq!(config.validate(), Ok(()));
+
+        let setup_keys_dir = std::env::temp_dir().join("runtime_config_validate");
+        std::fs::create_dir_all(&setup_keys_dir).unwrap();
+        let config = RuntimeConfig {
+            setup_keys_dir: Some(setup_keys_dir.to_string_lossy().into_owned()),
+            ..config
+        };
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = RuntimeConfig {
+            setup_keys_dir: Some("/nonexistent/keys".to_string()),
+            setup_key_files: Some(vec![
+                "21:custom_21.key".to_string(),
+                "custom.key".to_string(),
+            ]),
+            ..config
+        };
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                "The universal setup dir /nonexistent/keys is not a directory".to_string(),
+                "Invalid RUNTIME_CONFIG_SETUP_KEY_FILES entry: custom.key, \
+                expected `power_of_two:file_name`"
+                    .to_string(),
+            ])
+        );
+    }
 }
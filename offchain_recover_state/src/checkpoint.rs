@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+// External deps
+use anyhow::format_err;
+use serde::{Deserialize, Serialize};
+// Workspace deps
+use zklink_crypto::convert::FeConvert;
+use zklink_state::state::ZkLinkState;
+use zklink_types::{Account, AccountId, BlockNumber, ChainId, ZkLinkAddress, H256};
+// Local deps
+use crate::tree_state::TreeState;
+
+/// The snapshot of the rollup accounts tree that is periodically saved to the local file,
+/// used to restore the tree state quickly without reloading all accounts from the storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeCheckpoint {
+    /// The layer2 block number of the tree state
+    pub block_number: BlockNumber,
+    /// The root hash of the tree state
+    pub root_hash: H256,
+    /// Last block sync hash
+    pub last_sync_hash: H256,
+    /// The last fee account address
+    pub last_fee_account_address: ZkLinkAddress,
+    /// the current serial id of priority op of all chain.
+    pub last_serial_ids: Vec<(ChainId, i64)>,
+    /// All accounts of the tree state
+    pub accounts: Vec<(AccountId, Account)>,
+}
+
+impl TreeCheckpoint {
+    /// Takes a checkpoint from the current tree state.
+    pub fn new(tree_state: &TreeState) -> Self {
+        Self {
+            block_number: tree_state.state.block_number,
+            root_hash: H256::from_slice(&tree_state.root_hash().to_bytes()),
+            last_sync_hash: tree_state.last_sync_hash,
+            last_fee_account_address: tree_state.last_fee_account_address.clone(),
+            last_serial_ids: tree_state
+                .last_serial_ids
+                .iter()
+                .map(|(chain_id, serial_id)| (*chain_id, *serial_id))
+                .collect(),
+            accounts: tree_state
+                .get_accounts()
+                .into_iter()
+                .map(|(id, account)| (AccountId(id), account))
+                .collect(),
+        }
+    }
+
+    /// Loads the checkpoint from the file, returns `None` if the file does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read(path)?;
+        let checkpoint = serde_json::from_slice(&content)
+            .map_err(|e| format_err!("Failed to parse tree checkpoint: {}", e))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Saves the checkpoint to the file.
+    /// Writes to a temporary file first, so that a crash never leaves a corrupted checkpoint.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Rebuilds the tree state from the checkpoint, and checks that its root hash is consistent.
+    pub fn into_tree_state(self) -> anyhow::Result<TreeState> {
+        let state =
+            ZkLinkState::from_acc_map(self.accounts.into_iter().collect(), self.block_number);
+        let root_hash = H256::from_slice(&state.root_hash().to_bytes());
+        if root_hash != self.root_hash {
+            return Err(format_err!(
+                "Tree checkpoint root hash mismatch: expected {:?}, rebuilt {:?}",
+                self.root_hash,
+                root_hash
+            ));
+        }
+        Ok(TreeState {
+            state,
+            last_sync_hash: self.last_sync_hash,
+            last_fee_account_address: self.last_fee_account_address,
+            last_serial_ids: self.last_serial_ids.into_iter().collect::<HashMap<_, _>>(),
+        })
+    }
+}
@@ -1,4 +1,4 @@
-pub use self::version::ZkLinkContractVersion;
+pub use self::version::{ContractUpgrades, ZkLinkContractVersion};
 pub use self::zklink_evm_contract::ZkLinkEvmContract;
 use async_trait::async_trait;
 use std::fmt::Debug;
@@ -51,6 +51,9 @@ pub trait BlockChain {
 
     /// Return the current block height of this layer1
     async fn block_number(&self) -> anyhow::Result<u64>;
+
+    /// Returns the hash of the layer1 block, `None` if the block is not produced yet.
+    async fn block_hash(&self, block_number: u64) -> anyhow::Result<Option<H256>>;
 }
 
 /// Abstracts the required api of ZkLink contract for recovering state.
@@ -81,4 +84,8 @@ pub trait ZkLinkContract: BlockChain {
 
     /// Returns logs about complete contract upgrades.
     async fn get_gatekeeper_logs(&self) -> anyhow::Result<Vec<Self::Log>>;
+
+    /// Checks that the deployed contract matches the bundled ABI, logs a warning on mismatch.
+    /// Returns error if there is no contract deployed at the configured address.
+    async fn verify_abi_compatibility(&self) -> anyhow::Result<()>;
 }
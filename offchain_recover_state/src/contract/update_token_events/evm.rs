@@ -5,6 +5,7 @@ use crate::contract::utils::{
 use crate::contract::LogInfo;
 use crate::storage_interactor::DatabaseStorageInteractor;
 use crate::storage_interactor::StorageInteractor;
+use crate::END_BLOCK_OFFSET;
 use anyhow::format_err;
 use async_trait::async_trait;
 use ethers::contract::Contract;
@@ -27,6 +28,7 @@ pub struct EvmTokenEvents {
     chain_id: ChainId,
     gas_token: String,
     view_block_step: u64,
+    confirmation_depth: u64,
     last_sync_block_number: u64,
     last_sync_serial_id: i64,
 }
@@ -40,7 +42,7 @@ impl EvmTokenEvents {
                 .last_watched_block_number(*config.chain.chain_id as i16, "token")
                 .await
                 .expect("Failed to get last watched block number")
-                .unwrap_or((config.contract.deployment_block as i64, -1))
+                .unwrap_or((config.contract.deployment_block_number() as i64, -1))
         };
         let address = Address::from_slice(config.contract.address.as_bytes());
         let zklink_abi = load_abi(ZKLINK_JSON);
@@ -53,6 +55,7 @@ impl EvmTokenEvents {
             chain_id: config.chain.chain_id,
             gas_token: config.chain.gas_token.clone(),
             view_block_step: config.client.view_block_step,
+            confirmation_depth: config.client.confirmation_depth.unwrap_or(END_BLOCK_OFFSET),
             last_sync_block_number: last_watched_block_number as u64,
             last_sync_serial_id,
             connection_pool,
@@ -166,12 +169,13 @@ impl EvmTokenEvents {
 #[async_trait]
 impl UpdateTokenEvents for EvmTokenEvents {
     fn reached_latest_block(&self, latest_block: u64) -> bool {
-        latest_block - self.last_sync_block_number <= END_BLOCK_DELTA
+        latest_block.saturating_sub(self.last_sync_block_number) <= END_BLOCK_DELTA
     }
 
     async fn block_number(&self) -> anyhow::Result<u64> {
         let block_number = self.contract.client().get_block_number().await?.as_u64();
-        Ok(block_number)
+        // Only the token events and priority requests buried by the confirmation depth are synced
+        Ok(block_number.saturating_sub(self.confirmation_depth))
     }
 
     async fn update_token_events(&mut self, latest_block: u64) -> anyhow::Result<u64> {
@@ -216,7 +220,7 @@ impl UpdateTokenEvents for EvmTokenEvents {
         let (token_events, symbols) = self.process_token_logs(token_logs).await?;
 
         // updated storage
-        let storage = self.connection_pool.access_storage_with_retry().await;
+        let storage = self.connection_pool.access_storage_with_retry().await?;
         let mut interactor = DatabaseStorageInteractor::new(storage);
         interactor
             .update_priority_ops_and_tokens(
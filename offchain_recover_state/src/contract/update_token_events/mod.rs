@@ -7,7 +7,7 @@ pub trait UpdateTokenEvents: Send + Sync {
     /// Check that the latest block has been reached
     fn reached_latest_block(&self, latest_block: u64) -> bool;
 
-    /// Get the newest block height of the layer1 at present
+    /// Get the newest block height of the layer1 buried by the confirmation depth at present
     async fn block_number(&self) -> anyhow::Result<u64>;
 
     /// Update all token events of the layer1
@@ -3,6 +3,7 @@ use std::convert::TryFrom;
 // External uses
 // Workspace uses
 use zklink_types::operations::ZkLinkOp;
+use zklink_types::BlockNumber;
 // Local uses
 use super::v0;
 use crate::contract::utils;
@@ -63,20 +64,6 @@ impl ZkLinkContractVersion {
         }
     }
 
-    /// Returns the contract version incremented by `num`.
-    ///
-    /// # Arguments
-    ///
-    /// * `num` - how many times to upgrade.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the the result is greater than the latest supported version.
-    pub fn upgrade(&self, num: u32) -> Self {
-        Self::try_from(i16::from(*self) as u32 + num)
-            .expect("cannot upgrade past the latest contract version")
-    }
-
     pub fn supported_ops_numbers(&self) -> &'static [usize] {
         use ZkLinkContractVersion::*;
         match self {
@@ -84,3 +71,123 @@ impl ZkLinkContractVersion {
         }
     }
 }
+
+/// The contract versions of the layer2 blocks, the version switches after each upgraded layer2 block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractUpgrades {
+    genesis_version: ZkLinkContractVersion,
+    /// The last layer2 block of the previous version and the upgraded version, in ascending order.
+    upgrades: Vec<(BlockNumber, ZkLinkContractVersion)>,
+}
+
+impl Default for ContractUpgrades {
+    fn default() -> Self {
+        Self {
+            genesis_version: ZkLinkContractVersion::V0,
+            upgrades: Vec::new(),
+        }
+    }
+}
+
+impl ContractUpgrades {
+    /// Creates the upgrades from the configured `(layer2_block, contract_version)`.
+    ///
+    /// Returns error if the blocks are not strictly ascending, or any version is unsupported
+    /// or not newer than the version before it(starting from the genesis version).
+    pub fn new(
+        genesis_version: ZkLinkContractVersion,
+        upgrades: &[(u32, u32)],
+    ) -> anyhow::Result<Self> {
+        let mut contract_upgrades = Self {
+            genesis_version,
+            upgrades: Vec::with_capacity(upgrades.len()),
+        };
+        for &(layer2_block, version) in upgrades {
+            let version = ZkLinkContractVersion::try_from(version)?;
+            let (last_block, last_version) = contract_upgrades
+                .upgrades
+                .last()
+                .map_or((None, genesis_version), |&(block, version)| {
+                    (Some(block), version)
+                });
+            anyhow::ensure!(
+                last_block.map_or(true, |block| *block < layer2_block),
+                "The upgraded layer2 blocks must be strictly ascending, {} follows {:?}",
+                layer2_block,
+                last_block
+            );
+            anyhow::ensure!(
+                i16::from(version) > i16::from(last_version),
+                "The contract upgraded at layer2 block {} to {:?} is not newer than {:?}",
+                layer2_block,
+                version,
+                last_version
+            );
+            contract_upgrades
+                .upgrades
+                .push((BlockNumber(layer2_block), version));
+        }
+        Ok(contract_upgrades)
+    }
+
+    /// Returns the contract version that committed the layer2 block.
+    pub fn version_of_block(&self, block_number: BlockNumber) -> ZkLinkContractVersion {
+        version_at(self.genesis_version, &self.upgrades, block_number)
+    }
+}
+
+/// Returns the version after the last upgrade whose layer2 block is before `block_number`,
+/// generic over the version so that the boundaries can be tested with more versions than supported.
+fn version_at<V: Copy>(
+    genesis_version: V,
+    upgrades: &[(BlockNumber, V)],
+    block_number: BlockNumber,
+) -> V {
+    upgrades
+        .iter()
+        .take_while(|(upgraded_block, _)| *upgraded_block < block_number)
+        .last()
+        .map_or(genesis_version, |&(_, version)| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{version_at, ContractUpgrades, ZkLinkContractVersion};
+    use zklink_types::BlockNumber;
+
+    #[test]
+    fn contract_upgrades() {
+        let upgrades = ContractUpgrades::new(ZkLinkContractVersion::V0, &[]).unwrap();
+        assert_eq!(upgrades, ContractUpgrades::default());
+        assert_eq!(
+            upgrades.version_of_block(BlockNumber(u32::MAX)),
+            ZkLinkContractVersion::V0
+        );
+
+        // The unsupported or not newer versions
+        assert!(ContractUpgrades::new(ZkLinkContractVersion::V0, &[(100, 1)]).is_err());
+        assert!(ContractUpgrades::new(ZkLinkContractVersion::V0, &[(100, 0)]).is_err());
+    }
+
+    #[test]
+    fn version_across_upgrade_boundaries() {
+        // Upgraded to version 1 after block 100, and to version 2 after block 200.
+        let upgrades = [(BlockNumber(100), 1), (BlockNumber(200), 2)];
+        let cases = [
+            (1, 0),
+            (100, 0),
+            (101, 1),
+            (200, 1),
+            (201, 2),
+            (u32::MAX, 2),
+        ];
+        for (block_number, version) in cases {
+            assert_eq!(
+                version_at(0, &upgrades, BlockNumber(block_number)),
+                version,
+                "block {}",
+                block_number
+            );
+        }
+    }
+}
@@ -4,14 +4,19 @@ use crate::contract::{
 };
 use anyhow::{ensure, format_err};
 use async_trait::async_trait;
-use ethers::abi::Address;
+use ethers::abi::{Address, Token};
 use ethers::contract::Contract;
-use ethers::core::types::BlockNumber as EthBlockNumber;
+use ethers::core::types::{BlockId, BlockNumber as EthBlockNumber};
 use ethers::prelude::{Filter, Http, Log, Middleware, Provider, Transaction};
-use recover_state_config::Layer1Config;
+use recover_state_config::{DeploymentBlock, Layer1Config};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
 use zklink_types::{Account, BlockNumber, ChainId, H256};
 
 const FUNC_NAME_HASH_LENGTH: usize = 4;
+/// The view functions of zkLink contract that recovering state depends on.
+const REQUIRED_VIEW_FUNCTIONS: [&str; 3] =
+    ["totalBlocksCommitted", "totalBlocksExecuted", "exodusMode"];
 
 #[derive(Debug)]
 pub struct ZkLinkEvmContract {
@@ -19,6 +24,8 @@ pub struct ZkLinkEvmContract {
     pub config: Layer1Config,
     pub contract: Contract<Provider<Http>>,
     pub version: ZkLinkContractVersion,
+    /// The cached deployment block discovered from the chain.
+    deployment_block: OnceCell<u64>,
 }
 
 impl ZkLinkEvmContract {
@@ -31,8 +38,55 @@ impl ZkLinkEvmContract {
             contract: Contract::new(contract_address, abi, client.into()),
             config,
             version: ZkLinkContractVersion::V0,
+            deployment_block: OnceCell::new(),
         }
     }
+
+    /// Discovers the block that the contract was deployed in by binary-searching `eth_getCode`
+    /// over the block history, the rpc node must be able to serve the historical state.
+    pub async fn discover_deployment_block(&self) -> anyhow::Result<u64> {
+        if let DeploymentBlock::Number(number) = self.config.contract.deployment_block {
+            return Ok(number);
+        }
+        self.deployment_block
+            .get_or_try_init(|| async {
+                let latest_block = self.block_number().await?;
+                ensure!(
+                    self.has_code_at(latest_block).await?,
+                    "The contract {:?} is not deployed on chain {}",
+                    self.contract.address(),
+                    *self.chain_id
+                );
+                let (mut low, mut high) = (0, latest_block);
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if self.has_code_at(mid).await? {
+                        high = mid;
+                    } else {
+                        low = mid + 1;
+                    }
+                }
+                info!(
+                    "Discovered the deployment block of chain {}: {}",
+                    *self.chain_id, low
+                );
+                Ok(low)
+            })
+            .await
+            .copied()
+    }
+
+    async fn has_code_at(&self, block_number: u64) -> anyhow::Result<bool> {
+        let code = self
+            .contract
+            .client()
+            .get_code(
+                self.contract.address(),
+                Some(BlockId::Number(EthBlockNumber::Number(block_number.into()))),
+            )
+            .await?;
+        Ok(!code.is_empty())
+    }
 }
 
 #[async_trait]
@@ -52,6 +106,15 @@ impl BlockChain for ZkLinkEvmContract {
         let block_number = self.contract.client().get_block_number().await?.as_u64();
         Ok(block_number)
     }
+
+    async fn block_hash(&self, block_number: u64) -> anyhow::Result<Option<H256>> {
+        let block = self
+            .contract
+            .client()
+            .get_block(BlockId::Number(EthBlockNumber::Number(block_number.into())))
+            .await?;
+        Ok(block.and_then(|block| block.hash))
+    }
 }
 
 #[async_trait]
@@ -128,6 +191,43 @@ impl ZkLinkContract for ZkLinkEvmContract {
             .map_err(|e| format_err!("Get logs: {}", e))?;
         Ok(result)
     }
+
+    async fn verify_abi_compatibility(&self) -> anyhow::Result<()> {
+        let address = self.contract.address();
+        let code = self.contract.client().get_code(address, None).await?;
+        ensure!(
+            !code.is_empty(),
+            "There is no contract code at {:?} of {:?}",
+            address,
+            self.chain_id
+        );
+
+        let mut missing_functions = Vec::new();
+        for name in REQUIRED_VIEW_FUNCTIONS {
+            let selector = self.contract.abi().function(name)?.short_signature();
+            if code.windows(selector.len()).any(|bytes| bytes == selector) {
+                continue;
+            }
+            // The selector is absent in the bytecode of a proxy, so probe it by calling.
+            if let Err(e) = self.contract.method::<_, Token>(name, ())?.call().await {
+                warn!("Failed to probe {} of zkLink contract: {}", name, e);
+                missing_functions.push(name);
+            }
+        }
+
+        if missing_functions.is_empty() {
+            info!(
+                "The zkLink contract {:?} of {:?} matches the bundled ABI",
+                address, self.chain_id
+            );
+        } else {
+            warn!(
+                "The zkLink contract {:?} of {:?} mismatches the bundled ABI, missing functions: {:?}",
+                address, self.chain_id, missing_functions
+            );
+        }
+        Ok(())
+    }
 }
 
 impl TransactionInfo for Transaction {
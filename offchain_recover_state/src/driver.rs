@@ -1,7 +1,9 @@
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 // External deps
 use parity_crypto::Keccak256;
+use tokio::sync::watch;
 use tracing::{debug, info, warn};
 // Workspace deps
 use recover_state_config::{ChainType, RecoverStateConfig};
@@ -10,30 +12,60 @@ use zklink_crypto::params::{
     FEE_ACCOUNT_ID, GLOBAL_ASSET_ACCOUNT_ADDR, GLOBAL_ASSET_ACCOUNT_ID, USD_TOKEN_ID,
 };
 use zklink_crypto::Fr;
+use zklink_storage::recover_state::records::{RecoveryCheckpoint, WatchedBlockHash};
 use zklink_storage::ConnectionPool;
 use zklink_types::{
     Account, AccountId, AccountMap, AccountUpdate, BlockNumber, ChainId, Token, ZkLinkAddress, H256,
 };
 
 // Local deps
+use crate::checkpoint::TreeCheckpoint;
 use crate::contract::update_token_events::{EvmTokenEvents, UpdateTokenEvents};
-use crate::contract::{ZkLinkContract, ZkLinkContractVersion};
+use crate::contract::{ContractUpgrades, ZkLinkContract, ZkLinkContractVersion};
 use crate::{
-    error, events::events_state::RollUpEvents, rollup_ops::RollupOpsBlock,
-    storage_interactor::StorageInteractor, tree_state::TreeState, PRC_REQUEST_FREQUENT_ERROR_SETS,
+    error, events::events_state::RollUpEvents, is_rate_limited, rollup_ops::RollupOpsBlock,
+    storage_interactor::StorageInteractor, tree_state::TreeState, with_rpc_retries,
+    END_BLOCK_OFFSET, GENESIS_FETCH_RETRIES, GENESIS_FETCH_RETRY_DELAY, RATE_LIMIT_DELAY,
+    REORG_CHECK_DEPTH, REORG_CHECK_INTERVAL, TREE_CHECKPOINT_INTERVAL,
 };
 
 /// Storage state update:
 /// - None - The state is updated completely last time - start from fetching the new events
 /// - Events - The events fetched and saved successfully - now get operations from them and update tree
 /// - Operations - There are operations that are not presented in the tree state - update tree state
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum StorageUpdateState {
     None,
     Events,
     Operations,
 }
 
+/// The phase of the recovery that the driver is in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RecoverPhase {
+    /// Downloading the registered tokens of all chains.
+    #[default]
+    Tokens,
+    /// Fetching the block events from layer1.
+    Events,
+    /// Fetching and saving the rollup operations of the block events.
+    Ops,
+    /// Applying the rollup operations to the tree state.
+    State,
+    /// All the verified blocks are recovered, only reached in finite mode.
+    Done,
+}
+
+/// The progress of the recovery, published by the driver after every batch.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RecoverStateProgress {
+    pub processed_l2_blocks: u32,
+    pub total_l2_blocks: u32,
+    /// The last watched layer1 block.
+    pub processed_l1_blocks: u64,
+    pub current_phase: RecoverPhase,
+}
+
 /// Recover state driver is a high level interface for all restoring components.
 /// It is actually a finite state machine, that has following states:
 /// - Empty - The state is new
@@ -54,17 +86,23 @@ pub struct RecoverStateDriver<I: StorageInteractor, T: ZkLinkContract> {
     pub update_token_events: Vec<(ChainId, Option<Box<dyn UpdateTokenEvents>>)>,
     /// Provides uncompressed(upload all pubdata) layer1 rollup contract interface.
     pub zklink_contract: T,
-    /// Layer2 blocks heights that include correct UpgradeComplete events.
-    /// Should be provided via config.
-    pub upgraded_layer2_blocks: Vec<u32>,
-    /// The initial version of the deployed zkLink contract.
-    pub init_contract_version: ZkLinkContractVersion,
+    /// The contract versions of the layer2 blocks, switched at the layer2 blocks
+    /// that include correct UpgradeComplete events. Should be provided via config.
+    pub contract_upgrades: ContractUpgrades,
     /// Rollup contract events state
     pub rollup_events: RollUpEvents,
     /// Rollup accounts state
     pub tree_state: TreeState,
     /// The step distance of viewing events in the layer1 blocks
     pub view_block_step: u64,
+    /// The maximum layer1 blocks scanned per iteration, no limit if `None`.
+    pub max_blocks_per_iteration: Option<u64>,
+    /// The number of the latest layer1 blocks that are not scanned for the block events yet.
+    pub confirmation_depth: u64,
+    /// The layer1 blocks watched between two checks of the layer1 reorgs.
+    pub reorg_check_interval: u64,
+    /// Whether to abort on the op whose balance underflows instead of recording it as a failed tx.
+    pub strict_restore: bool,
     /// Finite mode flag. In finite mode, driver will only work until
     /// amount of restored blocks will become equal to amount of known
     /// verified blocks. After that, it will stop.
@@ -72,6 +110,14 @@ pub struct RecoverStateDriver<I: StorageInteractor, T: ZkLinkContract> {
     /// Expected root hash to be observed after restoring process. Only
     /// available in finite mode, and intended for tests.
     pub final_hash: Option<Fr>,
+    /// The local file that the tree state checkpoint is saved to, used to speed up the restart.
+    pub tree_checkpoint_path: Option<PathBuf>,
+    /// When the tree checkpoint was saved last time.
+    tree_checkpoint_saved_at: Option<Instant>,
+    /// Whether the tree state has been updated since the tree checkpoint was saved last time.
+    tree_checkpoint_outdated: bool,
+    /// The progress of the recovery, see `subscribe_progress`.
+    progress: watch::Sender<RecoverStateProgress>,
     phantom_data: PhantomData<I>,
 }
 
@@ -115,6 +161,15 @@ where
             last_watched_block_number,
             ..Default::default()
         };
+        let confirmation_depth = config
+            .layer1
+            .get_chain_config(zklink_contract.layer2_chain_id())
+            .and_then(|chain| chain.client.confirmation_depth)
+            .unwrap_or(END_BLOCK_OFFSET);
+
+        let contract_upgrades =
+            ContractUpgrades::new(ZkLinkContractVersion::V0, &config.upgrade_layer2_blocks)
+                .expect("Invalid UPGRADED_LAYER2_BLOCKS");
 
         let mut update_token_events = Vec::with_capacity(config.layer1.chain_configs.len());
         for config in &config.layer1.chain_configs {
@@ -128,19 +183,54 @@ where
         }
         Self {
             update_token_events,
-            upgraded_layer2_blocks: config.upgrade_layer2_blocks.clone(),
-            init_contract_version: ZkLinkContractVersion::V0,
+            contract_upgrades,
             zklink_contract,
             rollup_events: events_state,
             tree_state: TreeState::default(),
             view_block_step,
+            max_blocks_per_iteration: config.runtime.max_blocks_per_iteration,
+            confirmation_depth,
+            reorg_check_interval: config
+                .runtime
+                .reorg_check_interval
+                .unwrap_or(REORG_CHECK_INTERVAL),
+            strict_restore: config.runtime.strict_restore,
             finite_mode,
             final_hash,
+            tree_checkpoint_path: config.tree_checkpoint_path.as_ref().map(PathBuf::from),
+            tree_checkpoint_saved_at: None,
+            tree_checkpoint_outdated: false,
+            progress: watch::channel(RecoverStateProgress::default()).0,
             phantom_data: Default::default(),
         }
     }
 
+    /// Subscribes to the progress of the recovery, the receiver sees the latest progress.
+    pub fn subscribe_progress(&self) -> watch::Receiver<RecoverStateProgress> {
+        self.progress.subscribe()
+    }
+
+    fn update_progress(&self, update: impl FnOnce(&mut RecoverStateProgress)) {
+        self.progress.send_modify(update);
+    }
+
+    fn set_phase(&self, phase: RecoverPhase) {
+        self.update_progress(|progress| progress.current_phase = phase);
+    }
+
+    /// Publishes the blocks recovered so far, out of the `total_verified_blocks` on layer1.
+    fn publish_blocks_progress(&self, total_verified_blocks: u32) {
+        let processed_l2_blocks = *self.tree_state.state.block_number;
+        let processed_l1_blocks = self.rollup_events.last_watched_block_number;
+        self.update_progress(|progress| {
+            progress.processed_l2_blocks = processed_l2_blocks;
+            progress.total_l2_blocks = total_verified_blocks;
+            progress.processed_l1_blocks = processed_l1_blocks;
+        });
+    }
+
     pub async fn download_registered_tokens(&mut self) {
+        self.set_phase(RecoverPhase::Tokens);
         let mut updates = Vec::new();
         // Because of the instability of the scroll and linea rpc nodes, the token synchronization is temporarily skipped
         for (chain_id, updating_event) in self.update_token_events.iter_mut() {
@@ -166,16 +256,13 @@ where
                                 );
                             }
                             Err(err) => {
-                                if PRC_REQUEST_FREQUENT_ERROR_SETS
-                                    .iter()
-                                    .any(|e| err.to_string().contains(e))
-                                {
+                                if is_rate_limited(&err) {
                                     warn!(
                                         "Rate limit was reached, as reported by {:?} node. \
                                         Entering the sleep mode(30s)",
                                         chain_id
                                     );
-                                    tokio::time::sleep(Duration::from_secs(30)).await;
+                                    tokio::time::sleep(RATE_LIMIT_DELAY).await;
                                 } else {
                                     error!("{:?} failed to update token events: {}", chain_id, err);
                                 }
@@ -206,12 +293,28 @@ where
             .iter()
             .find(|chain| !chain.chain.is_commit_compressed_blocks)
             .unwrap();
-        let genesis_transaction = self
-            .zklink_contract
-            .get_transaction(full_pubdata_chain_config.contract.genesis_tx_hash)
-            .await
-            .unwrap()
-            .expect("Cant get zkLink genesis transaction");
+        // Retries the transient rpc failures, as a failure here aborts the whole genesis run.
+        let genesis_tx_hash = full_pubdata_chain_config.contract.genesis_tx_hash;
+        let retries = config
+            .runtime
+            .genesis_fetch_retries
+            .unwrap_or(GENESIS_FETCH_RETRIES);
+        let genesis_transaction = with_rpc_retries(retries, GENESIS_FETCH_RETRY_DELAY, || async {
+            self.zklink_contract
+                .get_transaction(genesis_tx_hash)
+                .await?
+                .ok_or_else(|| anyhow::format_err!("The genesis transaction is not found"))
+        })
+        .await
+        .unwrap_or_else(|err| {
+            panic!(
+                "Cant get zkLink genesis transaction {:?} of contract {:?} deployed at block {:?}: {}",
+                genesis_tx_hash,
+                full_pubdata_chain_config.contract.address,
+                full_pubdata_chain_config.contract.deployment_block,
+                err
+            )
+        });
 
         // Setting last watched block number for events state
         let last_watched_block_number = self
@@ -286,7 +389,7 @@ where
             interactor
                 .init_token_event_progress(
                     chain_config.chain.chain_id,
-                    chain_config.contract.deployment_block.into(),
+                    chain_config.contract.deployment_block_number().into(),
                 )
                 .await;
         }
@@ -304,23 +407,32 @@ where
         self.rollup_events = interactor
             .get_block_events_state_from_storage(self.zklink_contract.layer2_chain_id())
             .await;
-        let chain_ids = self
-            .update_token_events
-            .iter()
-            .map(|(chain_id, _)| *chain_id)
-            .collect();
-        let tree_state = interactor.get_tree_state(chain_ids).await;
-        self.tree_state = TreeState::load(
-            tree_state.last_sync_hash,
-            tree_state.last_block_number,
-            tree_state.last_serial_ids,
-            tree_state.account_map,
-            tree_state.fee_acc_id,
-        );
+        self.tree_state = match self.load_tree_checkpoint(interactor).await {
+            Some(tree_state) => tree_state,
+            None => {
+                let chain_ids = self
+                    .update_token_events
+                    .iter()
+                    .map(|(chain_id, _)| *chain_id)
+                    .collect();
+                let tree_state = interactor.get_tree_state(chain_ids).await;
+                TreeState::load(
+                    tree_state.last_sync_hash,
+                    tree_state.last_block_number,
+                    tree_state.last_serial_ids,
+                    tree_state.account_map,
+                    tree_state.fee_acc_id,
+                )
+            }
+        };
         self.tree_state.state.token_by_id = interactor.load_tokens().await;
         let new_ops_blocks = match state {
             StorageUpdateState::Events => self.load_op_from_events_and_save_op(interactor).await,
-            StorageUpdateState::Operations => interactor.get_ops_blocks_from_storage().await,
+            StorageUpdateState::Operations => {
+                interactor
+                    .get_ops_blocks_from_storage(self.tree_state.state.block_number)
+                    .await
+            }
             StorageUpdateState::None => vec![],
         };
         info!("Continue Block[{:?}]", self.tree_state.state.block_number);
@@ -343,13 +455,141 @@ where
             total_verified_blocks,
             total_verified_blocks - *last_verified_block
         );
+        // The progress starts from the loaded state rather than zero when restarted.
+        self.publish_blocks_progress(total_verified_blocks);
 
         self.finite_mode && (total_verified_blocks == *last_verified_block)
     }
 
+    /// Resumes the recovery from the state stored by the last run, returns true if all the verified
+    /// blocks have been recovered in finite mode(same as `load_state_from_storage`).
+    ///
+    /// The recovery checkpoint records the last batch of rollup ops blocks that was applied,
+    /// the state loaded from storage must match it, otherwise the storage has been modified
+    /// outside the driver and resuming would build on an inconsistent state.
+    pub async fn resume_from_checkpoint(&mut self, interactor: &mut I) -> bool {
+        let finished = self.load_state_from_storage(interactor).await;
+
+        let chain_id = self.zklink_contract.layer2_chain_id();
+        let Some(checkpoint) = interactor.load_recovery_checkpoint(chain_id).await else {
+            info!(
+                "No recovery checkpoint of {:?}, resume from the last watched block: {}",
+                chain_id, self.rollup_events.last_watched_block_number
+            );
+            return finished;
+        };
+        if checkpoint.last_l2_block != self.tree_state.state.block_number
+            || checkpoint.ops_hash != self.tree_state.last_sync_hash
+        {
+            panic!(
+                "The stored state(block[{:?}], sync hash: {:?}) mismatches the recovery checkpoint: {:?}",
+                self.tree_state.state.block_number, self.tree_state.last_sync_hash, checkpoint
+            );
+        }
+        // The block events up to the checkpoint have been applied, never watch them again.
+        self.rollup_events.last_watched_block_number = self
+            .rollup_events
+            .last_watched_block_number
+            .max(checkpoint.last_l1_block);
+        let last_watched_block = self.rollup_events.last_watched_block_number;
+        self.update_progress(|progress| progress.processed_l1_blocks = last_watched_block);
+        info!(
+            "Resume from the recovery checkpoint, layer1 block: {}, layer2 block[{:?}]",
+            self.rollup_events.last_watched_block_number, checkpoint.last_l2_block
+        );
+        finished
+    }
+
+    /// Loads the tree state from the local checkpoint file.
+    /// Returns `None` if the checkpoint is absent or does not match the last stored block,
+    /// then the tree state should be fully rebuilt from storage.
+    async fn load_tree_checkpoint(&self, interactor: &mut I) -> Option<TreeState> {
+        let path = self.tree_checkpoint_path.clone()?;
+        // Reading and parsing the whole tree blocks, keep it off the async workers
+        let load_path = path.clone();
+        let checkpoint =
+            match tokio::task::spawn_blocking(move || TreeCheckpoint::load(&load_path)).await {
+                Ok(Ok(checkpoint)) => checkpoint?,
+                Ok(Err(e)) => {
+                    warn!("Failed to load tree checkpoint from {:?}: {}", path, e);
+                    return None;
+                }
+                Err(e) => {
+                    warn!("Tree checkpoint loading task failed: {}", e);
+                    return None;
+                }
+            };
+
+        let last_block = interactor.get_last_block().await;
+        if checkpoint.block_number != last_block.block_number {
+            info!(
+                "Tree checkpoint block[{:?}] mismatches the last stored block[{:?}], rebuild from storage",
+                checkpoint.block_number, last_block.block_number
+            );
+            return None;
+        }
+        let stored_root_hash = H256::from_slice(&last_block.new_root_hash.to_bytes());
+        if checkpoint.root_hash != stored_root_hash {
+            warn!(
+                "Tree checkpoint root hash mismatches the stored block[{:?}] root hash: {:?}",
+                last_block.block_number, stored_root_hash
+            );
+            return None;
+        }
+
+        match checkpoint.into_tree_state() {
+            Ok(tree_state) => {
+                info!("Loaded tree state from checkpoint: {:?}", path);
+                Some(tree_state)
+            }
+            Err(e) => {
+                warn!("Failed to rebuild tree state from checkpoint: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Saves the current tree state to the local checkpoint file if configured.
+    /// Unless forced, the save is skipped within `TREE_CHECKPOINT_INTERVAL` since the last one,
+    /// because serializing the whole tree after every batch is too expensive while catching up.
+    ///
+    /// The checkpoint is taken here, then serialized and written on the blocking threads.
+    async fn save_tree_checkpoint(&mut self, force: bool) {
+        let Some(path) = self.tree_checkpoint_path.clone() else {
+            return;
+        };
+        let throttled = matches!(
+            self.tree_checkpoint_saved_at,
+            Some(saved_at) if saved_at.elapsed() < TREE_CHECKPOINT_INTERVAL
+        );
+        if throttled && !force {
+            self.tree_checkpoint_outdated = true;
+            return;
+        }
+        let checkpoint = TreeCheckpoint::new(&self.tree_state);
+        let save_path = path.clone();
+        match tokio::task::spawn_blocking(move || checkpoint.save(&save_path)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to save tree checkpoint to {:?}: {}", path, e),
+            Err(e) => warn!("Tree checkpoint saving task failed: {}", e),
+        }
+        self.tree_checkpoint_saved_at = Some(Instant::now());
+        self.tree_checkpoint_outdated = false;
+    }
+
+    /// Saves the tree checkpoint skipped by the throttling, called once the driver catches up.
+    async fn flush_tree_checkpoint(&mut self) {
+        if self.tree_checkpoint_outdated {
+            self.save_tree_checkpoint(true).await;
+        }
+    }
+
     /// Activates states updates
-    pub async fn recover_state(&mut self, interactor: &mut I) {
+    ///
+    /// Returns an error if a layer1 reorg can't be reverted, the state must be restored from genesis then.
+    pub async fn recover_state(&mut self, interactor: &mut I) -> anyhow::Result<()> {
         let mut last_watched_block = self.rollup_events.last_watched_block_number;
+        let mut last_reorg_check_block = last_watched_block;
         let mut final_hash_was_found = false;
 
         // Loads the tokens of all chain.
@@ -359,13 +599,16 @@ where
             info!("Last watched layer1 block: {:?}", last_watched_block);
 
             // Update block events
+            self.set_phase(RecoverPhase::Events);
             match self.exist_events_state(interactor).await {
                 Ok(exist) if exist => {
                     // Update operations
+                    self.set_phase(RecoverPhase::Ops);
                     let new_ops_blocks = self.load_op_from_events_and_save_op(interactor).await;
 
                     if !new_ops_blocks.is_empty() {
                         // Update tree
+                        self.set_phase(RecoverPhase::State);
                         self.update_tree_state(interactor, new_ops_blocks).await;
 
                         let last_verified_block = self.tree_state.state.block_number;
@@ -390,6 +633,7 @@ where
                             total_verified_blocks,
                             total_verified_blocks - *last_verified_block
                         );
+                        self.publish_blocks_progress(total_verified_blocks);
 
                         // If there is an expected root hash, check if current root hash matches the observed
                         // one.
@@ -412,6 +656,8 @@ where
                             }
 
                             info!("We've recover all the blocks and verified states, our job is done.");
+                            self.flush_tree_checkpoint().await;
+                            self.set_phase(RecoverPhase::Done);
                             break;
                         }
                     }
@@ -426,11 +672,124 @@ where
             // update block events progress
             if last_watched_block == self.rollup_events.last_watched_block_number {
                 info!("sleep block");
+                self.flush_tree_checkpoint().await;
                 tokio::time::sleep(Duration::from_secs(5)).await;
             } else {
                 last_watched_block = self.rollup_events.last_watched_block_number;
+                self.update_progress(|progress| progress.processed_l1_blocks = last_watched_block);
+                if let Err(err) = self.record_watched_block_hash(interactor).await {
+                    warn!("Failed to record the watched block hash: {}", err);
+                }
+            }
+
+            if last_watched_block >= last_reorg_check_block + self.reorg_check_interval {
+                let chain_id = self.zklink_contract.layer2_chain_id();
+                let from_l1_block = last_watched_block.saturating_sub(REORG_CHECK_DEPTH);
+                match self.detect_reorg(interactor, chain_id, from_l1_block).await {
+                    Ok(Some(fork_l1_block)) => {
+                        self.revert_reorged_blocks(interactor, fork_l1_block)
+                            .await?;
+                        last_watched_block = self.rollup_events.last_watched_block_number;
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        error!("Failed to detect the reorg of {:?}: {}", chain_id, err);
+                        continue;
+                    }
+                }
+                last_reorg_check_block = last_watched_block;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-fetches the hashes of the watched layer1 blocks from `from_l1_block` and compares them
+    /// with the recorded ones, returns the first layer1 block whose hash changed(reorged) if any.
+    pub async fn detect_reorg(
+        &self,
+        interactor: &mut I,
+        chain_id: ChainId,
+        from_l1_block: u64,
+    ) -> anyhow::Result<Option<u64>> {
+        anyhow::ensure!(
+            chain_id == self.zklink_contract.layer2_chain_id(),
+            "The block events of {:?} are not watched by the driver",
+            chain_id
+        );
+        for watched in interactor
+            .load_watched_block_hashes(chain_id, from_l1_block)
+            .await
+        {
+            let block_hash = self.zklink_contract.block_hash(watched.l1_block).await?;
+            if block_hash != Some(watched.block_hash) {
+                warn!(
+                    "The layer1 block {} of {:?} is reorged, recorded hash: {:?}, current hash: {:?}",
+                    watched.l1_block, chain_id, watched.block_hash, block_hash
+                );
+                return Ok(Some(watched.l1_block));
             }
         }
+        Ok(None)
+    }
+
+    /// Reverts the recovered state to the last watched block before the reorged `fork_l1_block`
+    /// and reloads the driver state from storage, the block events after it are watched again.
+    ///
+    /// Returns an error if no block hash is watched before the fork, then the state is not reverted.
+    async fn revert_reorged_blocks(
+        &mut self,
+        interactor: &mut I,
+        fork_l1_block: u64,
+    ) -> anyhow::Result<()> {
+        let chain_id = self.zklink_contract.layer2_chain_id();
+        let safe_l2_block = interactor
+            .revert_to_fork(chain_id, fork_l1_block)
+            .await
+            .ok_or_else(|| {
+                anyhow::format_err!(
+                    "No watched block of {:?} before the reorged layer1 block {}, \
+                    the state must be restored from genesis",
+                    chain_id,
+                    fork_l1_block
+                )
+            })?;
+        info!(
+            "Reverted the recovered state of {:?} to block[{:?}] for the reorged layer1 block {}",
+            chain_id, safe_l2_block, fork_l1_block
+        );
+        metrics::increment_counter!("recover_state.reorgs");
+
+        self.load_state_from_storage(interactor).await;
+        Ok(())
+    }
+
+    /// Records the hash of the last watched layer1 block along with the applied layer2 block,
+    /// all the block events up to the layer1 block have been processed.
+    async fn record_watched_block_hash(&self, interactor: &mut I) -> anyhow::Result<()> {
+        let l1_block = self.rollup_events.last_watched_block_number;
+        let block_hash = self
+            .zklink_contract
+            .block_hash(l1_block)
+            .await?
+            .ok_or_else(|| anyhow::format_err!("The layer1 block {} is not found", l1_block))?;
+        interactor
+            .save_watched_block_hash(&WatchedBlockHash {
+                chain_id: self.zklink_contract.layer2_chain_id(),
+                l1_block,
+                block_hash,
+                last_l2_block: self.tree_state.state.block_number,
+            })
+            .await;
+        Ok(())
+    }
+
+    /// The layer1 blocks scanned per iteration, bounded by `max_blocks_per_iteration`
+    /// so that the checkpoint is persisted in time during the very long restore.
+    fn blocks_step_per_iteration(&self) -> u64 {
+        match self.max_blocks_per_iteration {
+            Some(max_blocks) => self.view_block_step.min(max_blocks),
+            None => self.view_block_step,
+        }
     }
 
     /// Updates events state, saves new blocks, tokens events and the last watched block number in storage
@@ -440,20 +799,19 @@ where
             "Loading block events from {:?} zklink contract!",
             self.zklink_contract.layer2_chain_id()
         );
-        let upgraded_num = self
-            .upgraded_layer2_blocks
-            .iter()
-            .filter(|&upgraded_block| *self.tree_state.state.block_number >= *upgraded_block)
-            .count() as u32;
-        let upgraded_contract_version = self.init_contract_version.upgrade(upgraded_num);
         let (block_events, last_watched_eth_block_number) = self
             .rollup_events
             .update_block_events(
                 &self.zklink_contract,
-                self.view_block_step,
-                upgraded_contract_version,
+                self.blocks_step_per_iteration(),
+                self.confirmation_depth,
+                &self.contract_upgrades,
             )
             .await?;
+        metrics::gauge!(
+            "recover_state.last_watched_block",
+            last_watched_eth_block_number as f64
+        );
         interactor
             .update_block_events_state(
                 self.zklink_contract.layer2_chain_id(),
@@ -474,43 +832,82 @@ where
     /// * `new_ops_blocks` - the new Rollup operations blocks
     ///
     async fn update_tree_state(&mut self, interactor: &mut I, new_ops_blocks: Vec<RollupOpsBlock>) {
+        let has_new_blocks = !new_ops_blocks.is_empty();
         let mut blocks_and_updates = Vec::with_capacity(new_ops_blocks.len());
         for op_block in new_ops_blocks {
-            let (block, acc_updates) =
-                self.tree_state
-                    .apply_ops_block(&op_block)
-                    .unwrap_or_else(|e| {
-                        panic!(
-                            "Failed to applying {:?} tree state: {}",
-                            op_block.block_num, e
-                        )
-                    });
+            let (block, acc_updates) = self
+                .tree_state
+                .apply_ops_block(&op_block, self.strict_restore)
+                .unwrap_or_else(|e| {
+                    panic!(
+                        "Failed to applying {:?} tree state: {}",
+                        op_block.block_num, e
+                    )
+                });
             blocks_and_updates.push((block, acc_updates));
         }
-        // To ensure collective update
+        // To ensure collective update, the recovery checkpoint is saved along with the blocks
+        let checkpoint = has_new_blocks.then(|| self.recovery_checkpoint());
         interactor
-            .store_blocks_and_updates(blocks_and_updates)
+            .store_blocks_and_updates(blocks_and_updates, checkpoint.as_ref())
             .await;
+        if has_new_blocks {
+            self.save_tree_checkpoint(false).await;
+            metrics::gauge!(
+                "recover_state.last_verified_block",
+                *self.tree_state.state.block_number as f64
+            );
+        }
 
         debug!("Updated tree state");
     }
 
+    /// The progress of the applied rollup operations blocks.
+    fn recovery_checkpoint(&self) -> RecoveryCheckpoint {
+        RecoveryCheckpoint {
+            chain_id: self.zklink_contract.layer2_chain_id(),
+            last_l1_block: self.rollup_events.last_watched_block_number,
+            last_l2_block: self.tree_state.state.block_number,
+            ops_hash: self.tree_state.last_sync_hash,
+        }
+    }
+
     /// Gets new operations blocks from events, updates rollup operations stored state.
     /// Returns new rollup operations blocks
+    ///
+    /// The operations blocks saved but not applied before a crash are loaded from storage,
+    /// only the operations of the later blocks are fetched from layer1.
     async fn load_op_from_events_and_save_op(&mut self, interactor: &mut I) -> Vec<RollupOpsBlock> {
-        let new_blocks = self.get_new_operation_blocks_from_events(interactor).await;
+        let applied_block = self.tree_state.state.block_number;
+        let (mut blocks, last_saved_block) = match interactor.get_last_rollup_ops_block().await {
+            Some(last_saved_block) if last_saved_block > applied_block => {
+                info!(
+                    "Reuse the saved operations of the blocks[{:?}, {:?}]",
+                    *applied_block + 1,
+                    last_saved_block
+                );
+                let saved_blocks = interactor.get_ops_blocks_from_storage(applied_block).await;
+                (saved_blocks, last_saved_block)
+            }
+            _ => (vec![], applied_block),
+        };
+        let new_blocks = self
+            .get_new_operation_blocks_from_events(interactor, last_saved_block)
+            .await;
 
         interactor.save_rollup_ops(&new_blocks).await;
 
         debug!("Updated operations storage");
 
-        new_blocks
+        blocks.extend(new_blocks);
+        blocks
     }
 
-    /// Returns verified committed operations blocks from verified op blocks events
+    /// Returns verified committed operations blocks after `after_block` from verified op blocks events
     pub async fn get_new_operation_blocks_from_events(
         &mut self,
         interactor: &mut I,
+        after_block: BlockNumber,
     ) -> Vec<RollupOpsBlock> {
         let mut blocks = Vec::new();
 
@@ -525,6 +922,10 @@ where
         }
 
         for event in events {
+            // The operations of the blocks up to `after_block` have been saved.
+            if event.end_block_num <= after_block {
+                continue;
+            }
             // We use an aggregated block in contracts, which means that several BlockEvent can include the same tx_hash,
             // but for correct restore we need to generate RollupBlocks from this tx only once.
             // These blocks go one after the other, and checking only the last transaction hash is safe
@@ -556,6 +957,7 @@ where
                         && block.block_num <= event.end_block_num
                 });
             }
+            rollup_blocks.retain(|block| block.block_num > after_block);
             blocks.extend(rollup_blocks);
             last_event_tx_hash = Some(transaction_hash);
         }
@@ -563,3 +965,165 @@ where
         blocks
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use tokio::sync::watch;
+    use zklink_types::{AccountId, BlockNumber, H256};
+
+    use super::{RecoverPhase, RecoverStateDriver, RecoverStateProgress};
+    use crate::contract::{ContractUpgrades, ZkLinkContractVersion, ZkLinkEvmContract};
+    use crate::events::{events_state::RollUpEvents, BlockEvent, EventType};
+    use crate::rollup_ops::RollupOpsBlock;
+    use crate::storage_interactor::inmemory::InMemoryStorageInteractor;
+    use crate::storage_interactor::StorageInteractor;
+    use crate::tests::utils::u32_to_32bytes;
+    use crate::tree_state::TreeState;
+
+    fn create_ops_block(block_number: u32) -> RollupOpsBlock {
+        RollupOpsBlock {
+            block_num: BlockNumber(block_number),
+            ops: vec![],
+            fee_account: AccountId(0),
+            timestamp: None,
+            previous_block_root_hash: H256::default(),
+            contract_version: Some(ZkLinkContractVersion::V0),
+        }
+    }
+
+    fn create_block_event(block_number: u32, block_type: EventType) -> BlockEvent {
+        BlockEvent {
+            start_block_num: BlockNumber(block_number),
+            end_block_num: BlockNumber(block_number),
+            transaction_hash: u32_to_32bytes(block_number).into(),
+            block_type,
+            contract_version: ZkLinkContractVersion::V0,
+        }
+    }
+
+    fn create_driver(
+        rollup_events: RollUpEvents,
+        tree_state: TreeState,
+    ) -> RecoverStateDriver<InMemoryStorageInteractor, ZkLinkEvmContract> {
+        RecoverStateDriver {
+            update_token_events: vec![],
+            zklink_contract: ZkLinkEvmContract::new(Default::default()),
+            contract_upgrades: ContractUpgrades::new(ZkLinkContractVersion::V0, &[]).unwrap(),
+            rollup_events,
+            tree_state,
+            view_block_step: 1,
+            max_blocks_per_iteration: None,
+            confirmation_depth: 0,
+            reorg_check_interval: 0,
+            strict_restore: false,
+            finite_mode: false,
+            final_hash: None,
+            tree_checkpoint_path: None,
+            tree_checkpoint_saved_at: None,
+            tree_checkpoint_outdated: false,
+            progress: watch::channel(Default::default()).0,
+            phantom_data: PhantomData,
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_saved_unapplied_ops() {
+        let mut interactor = InMemoryStorageInteractor::new();
+        // The ops of blocks [1, 3] are saved but only block 1 is applied before the crash.
+        interactor
+            .save_rollup_ops(&(1..=3).map(create_ops_block).collect::<Vec<_>>())
+            .await;
+        let mut tree_state = TreeState::new();
+        tree_state.state.block_number = BlockNumber(1);
+        // The events of the saved blocks are known, they must not be fetched again.
+        let rollup_events = RollUpEvents {
+            last_committed_num: BlockNumber(3),
+            committed_events: (1..=3)
+                .map(|block_number| create_block_event(block_number, EventType::Committed))
+                .collect(),
+            last_verified_num: BlockNumber(3),
+            verified_events: (1..=3)
+                .map(|block_number| create_block_event(block_number, EventType::Verified))
+                .collect(),
+            last_watched_block_number: 10,
+        };
+        let mut driver = create_driver(rollup_events, tree_state);
+
+        let blocks = driver
+            .load_op_from_events_and_save_op(&mut interactor)
+            .await;
+        let block_numbers = blocks
+            .iter()
+            .map(|block| block.block_num)
+            .collect::<Vec<_>>();
+        assert_eq!(block_numbers, vec![BlockNumber(2), BlockNumber(3)]);
+    }
+
+    #[tokio::test]
+    async fn revert_reorg_without_watched_block_fails() {
+        let mut interactor = InMemoryStorageInteractor::new();
+        let mut tree_state = TreeState::new();
+        tree_state.state.block_number = BlockNumber(1);
+        let mut driver = create_driver(RollUpEvents::default(), tree_state);
+
+        // No block hash is watched before the reorged layer1 block.
+        assert!(driver
+            .revert_reorged_blocks(&mut interactor, 10)
+            .await
+            .is_err());
+        assert_eq!(driver.tree_state.state.block_number, BlockNumber(1));
+    }
+
+    #[test]
+    fn publish_progress_transitions() {
+        // The driver is restarted with 5 of 8 verified blocks recovered.
+        let mut tree_state = TreeState::new();
+        tree_state.state.block_number = BlockNumber(5);
+        let rollup_events = RollUpEvents {
+            last_watched_block_number: 100,
+            ..Default::default()
+        };
+        let mut driver = create_driver(rollup_events, tree_state);
+        let mut progress = driver.subscribe_progress();
+        assert_eq!(
+            *progress.borrow_and_update(),
+            RecoverStateProgress::default()
+        );
+
+        // The progress is seeded from the loaded state.
+        driver.publish_blocks_progress(8);
+        assert!(progress.has_changed().unwrap());
+        assert_eq!(
+            *progress.borrow_and_update(),
+            RecoverStateProgress {
+                processed_l2_blocks: 5,
+                total_l2_blocks: 8,
+                processed_l1_blocks: 100,
+                current_phase: RecoverPhase::Tokens,
+            }
+        );
+
+        for phase in [RecoverPhase::Events, RecoverPhase::Ops, RecoverPhase::State] {
+            driver.set_phase(phase);
+            assert!(progress.has_changed().unwrap());
+            assert_eq!(progress.borrow_and_update().current_phase, phase);
+        }
+
+        // The rest of the blocks are applied.
+        driver.tree_state.state.block_number = BlockNumber(8);
+        driver.rollup_events.last_watched_block_number = 120;
+        driver.publish_blocks_progress(8);
+        driver.set_phase(RecoverPhase::Done);
+        assert_eq!(
+            *progress.borrow_and_update(),
+            RecoverStateProgress {
+                processed_l2_blocks: 8,
+                total_l2_blocks: 8,
+                processed_l1_blocks: 120,
+                current_phase: RecoverPhase::Done,
+            }
+        );
+    }
+}
@@ -5,10 +5,7 @@ use anyhow::format_err;
 use zklink_types::{BlockNumber, U256};
 // Local deps
 use super::{BlockEvent, EventType};
-use crate::contract::{
-    BlockChain, LogInfo, TransactionInfo, ZkLinkContract, ZkLinkContractVersion,
-};
-use crate::END_BLOCK_OFFSET;
+use crate::contract::{BlockChain, ContractUpgrades, LogInfo, TransactionInfo, ZkLinkContract};
 
 /// Rollup contract events states description
 #[derive(Debug, Default, Clone)]
@@ -51,14 +48,16 @@ impl RollUpEvents {
     ///
     /// * `zklink_contract` - Rollup contract
     /// * `view_blocks_step` - Blocks step for watching
-    /// * `upgraded_contract_version` - the upgraded contract version
+    /// * `confirmation_depth` - The number of the latest blocks that are not scanned yet
+    /// * `contract_upgrades` - the contract versions of the layer2 blocks
     ///
     #[allow(clippy::too_many_arguments)]
     pub async fn update_block_events<T: ZkLinkContract>(
         &mut self,
         zklink_contract: &T,
         view_block_step: u64,
-        upgraded_contract_version: ZkLinkContractVersion,
+        confirmation_depth: u64,
+        contract_upgrades: &ContractUpgrades,
     ) -> Result<(Vec<BlockEvent>, u64), anyhow::Error> {
         self.remove_verified_events();
 
@@ -66,13 +65,14 @@ impl RollUpEvents {
             zklink_contract,
             self.last_watched_block_number,
             view_block_step,
+            confirmation_depth,
         )
         .await?;
 
         // Pass Layer1 block numbers that correspond to `UpgradeComplete`
         // events emitted by the Upgrade GateKeeper. Should be provided by the config.
         self.last_watched_block_number = to_block_number;
-        self.update_blocks_state(zklink_contract, &block_events, upgraded_contract_version);
+        self.update_blocks_state(zklink_contract, &block_events, contract_upgrades);
 
         let mut events_to_return = self.committed_events.clone();
         events_to_return.extend(self.verified_events.clone());
@@ -87,15 +87,21 @@ impl RollUpEvents {
     /// * `zklink_contract` - Rollup contract
     /// * `last_watched_block_number` - the current last watched block
     /// * `view_blocks_step` - view layer1 blocks delta step
+    /// * `confirmation_depth` - The number of the latest blocks that are not scanned yet
     ///
     async fn get_block_events_and_last_watched_block<T: ZkLinkContract>(
         zklink_contract: &T,
         last_watched_block_number: u64,
         view_block_step: u64,
+        confirmation_depth: u64,
     ) -> anyhow::Result<(Vec<<T as BlockChain>::Log>, u64)> {
-        let latest_block_minus_delta = zklink_contract.block_number().await? - END_BLOCK_OFFSET;
-        if latest_block_minus_delta == last_watched_block_number {
-            return Ok((vec![], last_watched_block_number)); // No new layer1 blocks
+        let latest_block_minus_delta = zklink_contract
+            .block_number()
+            .await?
+            .saturating_sub(confirmation_depth);
+        // The confirmation depth may be deepened after the last watched block was confirmed
+        if latest_block_minus_delta <= last_watched_block_number {
+            return Ok((vec![], last_watched_block_number)); // No new confirmed layer1 blocks
         }
 
         let from_block_number = last_watched_block_number + 1;
@@ -118,12 +124,12 @@ impl RollUpEvents {
     ///
     /// * `contract` - Specified contract
     /// * `logs` - Block events with their info
-    /// * `contract_version` - the current contract version
+    /// * `contract_upgrades` - the contract versions of the layer2 blocks
     fn update_blocks_state<T: ZkLinkContract>(
         &mut self,
         contract: &T,
         logs: &[<T as BlockChain>::Log],
-        contract_version: ZkLinkContractVersion,
+        contract_upgrades: &ContractUpgrades,
     ) {
         if logs.is_empty() {
             return;
@@ -157,14 +163,14 @@ impl RollUpEvents {
 
             // Go into new blocks
             let transaction_hash = log.transaction_hash();
-            let layer2_block_number = log.topics()[1];
+            let layer2_block_number = BlockNumber(U256::from(log.topics()[1].as_bytes()).as_u32());
 
             let mut block = BlockEvent {
                 start_block_num: Default::default(),
-                end_block_num: BlockNumber(U256::from(layer2_block_number.as_bytes()).as_u32()),
+                end_block_num: layer2_block_number,
                 transaction_hash,
                 block_type: EventType::Committed,
-                contract_version,
+                contract_version: contract_upgrades.version_of_block(layer2_block_number),
             };
             if topic == block_verified_topic {
                 block.block_type = EventType::Verified;
@@ -244,7 +250,7 @@ mod test {
     use ethers::prelude::Bytes;
     use zklink_types::H160;
 
-    use crate::contract::{ZkLinkContractVersion, ZkLinkEvmContract};
+    use crate::contract::{ContractUpgrades, ZkLinkEvmContract};
     use crate::tests::utils::{create_log, u32_to_32bytes};
 
     #[test]
@@ -293,8 +299,8 @@ mod test {
             ));
         }
 
-        let v0 = ZkLinkContractVersion::V0;
-        events_state.update_blocks_state(&contract, &logs, v0);
+        let contract_upgrades = ContractUpgrades::default();
+        events_state.update_blocks_state(&contract, &logs, &contract_upgrades);
         assert_eq!(events_state.committed_events.len(), 32);
         assert_eq!(events_state.verified_events.len(), 32);
 
@@ -311,7 +317,7 @@ mod test {
             3,
             u32_to_32bytes(1).into(),
         );
-        events_state.update_blocks_state(&contract, &[log], v0);
+        events_state.update_blocks_state(&contract, &[log], &contract_upgrades);
         assert_eq!(events_state.committed_events.len(), 16);
         assert_eq!(events_state.verified_events.len(), 11);
     }
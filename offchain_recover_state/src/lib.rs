@@ -1,6 +1,10 @@
 use crate::contract::{ZkLinkContract, ZkLinkEvmContract};
-use recover_state_config::{ChainType, RecoverStateConfig};
+use recover_state_config::{ChainType, DeploymentBlock, MultiChainConfigs, RecoverStateConfig};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{info, warn};
 
+pub mod checkpoint;
 pub mod contract;
 pub mod driver;
 pub mod events;
@@ -12,24 +16,86 @@ pub mod tree_state;
 #[cfg(test)]
 mod tests;
 
-// Delta between last block and last watched block
+// Delta between last block and last watched block, the default confirmation depth of layer1 events
 pub const END_BLOCK_OFFSET: u64 = 40;
+// The upper bound of the overridden confirmation depth, a larger one is most likely a block number by mistake
+pub const MAX_END_BLOCK_OFFSET: u64 = 10_000;
+// The latest watched layer1 blocks whose hashes are re-fetched to detect the reorgs
+pub const REORG_CHECK_DEPTH: u64 = 256;
+// The default layer1 blocks watched between two checks of the reorgs
+pub const REORG_CHECK_INTERVAL: u64 = 100;
 
 // An error returned by the rpc server because the number of requests was too frequent.
 // It is configured according to the documentation of the rpc service.
 // The first error comes from the Infura docs(https://docs.infura.io/infura/networks/ethereum/how-to/avoid-rate-limiting).
 pub const PRC_REQUEST_FREQUENT_ERROR_SETS: [&str; 1] = ["429 Too Many Requests"];
+// The time to wait once the rate limit of the rpc server is reached
+pub const RATE_LIMIT_DELAY: Duration = Duration::from_secs(30);
+// The default retries of fetching the genesis transaction
+pub const GENESIS_FETCH_RETRIES: u32 = 5;
+// The delay before the first retry of fetching the genesis transaction, doubled on every retry
+pub const GENESIS_FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+// The minimum interval between two saves of the tree checkpoint file, it is saved again once caught up
+pub const TREE_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether the error is returned by the rpc server because the requests were too frequent.
+pub fn is_rate_limited(err: &anyhow::Error) -> bool {
+    let err = err.to_string();
+    PRC_REQUEST_FREQUENT_ERROR_SETS
+        .iter()
+        .any(|e| err.contains(e))
+}
+
+/// The delay before the retry after `attempt` failed attempts, doubled from `base_delay`
+/// on every retry and capped at `RATE_LIMIT_DELAY`.
+pub fn rpc_retry_delay(attempt: u32, base_delay: Duration) -> Duration {
+    2u32.checked_pow(attempt)
+        .map_or(RATE_LIMIT_DELAY, |factor| base_delay.saturating_mul(factor))
+        .min(RATE_LIMIT_DELAY)
+}
+
+/// Repeats the layer1 rpc request at most `retries` more times on failure. The delay is `rpc_retry_delay`,
+/// and is `RATE_LIMIT_DELAY` once the rate limit is reached.
+pub async fn with_rpc_retries<T, F, Fut>(
+    retries: u32,
+    base_delay: Duration,
+    mut request: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Ok(result) => return Ok(result),
+            Err(err) if attempt < retries => {
+                let delay = if is_rate_limited(&err) {
+                    RATE_LIMIT_DELAY
+                } else {
+                    rpc_retry_delay(attempt, base_delay)
+                };
+                attempt += 1;
+                warn!(
+                    "The layer1 rpc request failed: {}, retry {}/{} in {:?}",
+                    err, attempt, retries, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
 
 pub fn get_fully_on_chain_zklink_contract(
-    config: &RecoverStateConfig,
+    layer1: &MultiChainConfigs,
 ) -> (u64, u64, impl ZkLinkContract) {
-    let uncompress_chain_config = config
-        .layer1
+    let uncompress_chain_config = layer1
         .chain_configs
         .iter()
         .find(|chain| !chain.chain.is_commit_compressed_blocks)
         .unwrap();
-    let deploy_block_number = uncompress_chain_config.contract.deployment_block;
+    let deploy_block_number = uncompress_chain_config.contract.deployment_block_number();
     let view_block_step = uncompress_chain_config.client.view_block_step;
     (
         view_block_step,
@@ -40,3 +106,60 @@ pub fn get_fully_on_chain_zklink_contract(
         },
     )
 }
+
+/// Overrides the confirmation depth of all the chains, e.g. 0 for the local devnet with instant finality.
+///
+/// Lowering it below the finality of the chain risks applying the events of the reorged blocks,
+/// which corrupts the recovered state and requires restoring from genesis.
+pub fn override_confirmation_depth(
+    config: &mut RecoverStateConfig,
+    end_block_offset: u64,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        end_block_offset <= MAX_END_BLOCK_OFFSET,
+        "The end block offset {} exceeds the maximum {}",
+        end_block_offset,
+        MAX_END_BLOCK_OFFSET
+    );
+    for chain_config in config.layer1.chain_configs.iter_mut() {
+        let confirmation_depth = chain_config
+            .client
+            .confirmation_depth
+            .unwrap_or(END_BLOCK_OFFSET);
+        if end_block_offset < confirmation_depth {
+            warn!(
+                "Lowering the confirmation depth of chain {} from {} to {}, \
+                the events of the reorged blocks may corrupt the recovered state",
+                *chain_config.chain.chain_id, confirmation_depth, end_block_offset
+            );
+        }
+        chain_config.client.confirmation_depth = Some(end_block_offset);
+    }
+    Ok(())
+}
+
+/// Discovers the deployment blocks configured as "auto" from the chains,
+/// and caches them into the config so that all the later lookups use the discovered numbers.
+///
+/// Every binary sharing the config must call it before `get_fully_on_chain_zklink_contract`.
+pub async fn discover_deployment_blocks(layer1: &mut MultiChainConfigs) -> anyhow::Result<()> {
+    for chain_config in layer1.chain_configs.iter_mut() {
+        if chain_config.contract.deployment_block != DeploymentBlock::Auto {
+            continue;
+        }
+        let deployment_block = match chain_config.chain.chain_type {
+            ChainType::EVM => {
+                ZkLinkEvmContract::new(chain_config.clone())
+                    .discover_deployment_block()
+                    .await?
+            }
+            ChainType::STARKNET => panic!("Not currently supported!"),
+        };
+        info!(
+            "Using the discovered deployment block {} of chain {}",
+            deployment_block, *chain_config.chain.chain_id
+        );
+        chain_config.contract.deployment_block = DeploymentBlock::Number(deployment_block);
+    }
+    Ok(())
+}
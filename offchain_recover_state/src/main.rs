@@ -1,12 +1,15 @@
 use dotenvy::dotenv;
-use offchain_recover_state::driver::RecoverStateDriver;
+use offchain_recover_state::contract::ZkLinkContract;
+use offchain_recover_state::driver::{RecoverStateDriver, RecoverStateProgress};
 use offchain_recover_state::log::init;
 use offchain_recover_state::{
-    get_fully_on_chain_zklink_contract, storage_interactor::DatabaseStorageInteractor,
+    discover_deployment_blocks, get_fully_on_chain_zklink_contract, override_confirmation_depth,
+    storage_interactor::DatabaseStorageInteractor,
 };
-use recover_state_config::RecoverStateConfig;
+use recover_state_config::{DBConfig, RecoverStateConfig};
 use structopt::StructOpt;
-use tracing::info;
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
 use zklink_crypto::convert::FeConvert;
 use zklink_storage::ConnectionPool;
 
@@ -21,9 +24,9 @@ struct Opt {
     #[structopt(long)]
     genesis: bool,
 
-    /// Continues data restoring
-    #[structopt(long = "continue", name = "continue")]
-    continue_mode: bool,
+    /// Resumes data restoring from the stored state, checked against the last recovery checkpoint
+    #[structopt(long = "resume-from-checkpoint")]
+    resume_from_checkpoint: bool,
 
     /// Restore data until the last verified block and exit, on by default,
     #[structopt(long, parse(try_from_str), default_value = "true")]
@@ -32,6 +35,39 @@ struct Opt {
     /// Expected tree root hash after restoring. This argument is ignored if mode is not `finite`
     #[structopt(long)]
     final_hash: Option<String>,
+
+    /// Overrides the confirmation depth of all chains(40 or `CHAIN_{ID}_CLIENT_CONFIRMATION_DEPTH`),
+    /// e.g. 0 for the local devnet with instant finality.
+    /// Lowering it below the finality of the chain risks corrupting the recovered state by reorgs
+    #[structopt(long = "end-block-offset")]
+    end_block_offset: Option<u64>,
+
+    /// Verifies that the stored executed ops of the blocks in [FROM, TO] reconstruct
+    /// their committed pubdata and exits, nothing is restored
+    #[structopt(long = "verify-pubdata", number_of_values = 2, value_names = &["FROM", "TO"])]
+    verify_pubdata: Option<Vec<i64>>,
+}
+
+/// Returns the numbers of blocks in [from, to] whose executed ops mismatch the committed pubdata.
+async fn verify_pubdata(connection_pool: &ConnectionPool, from: i64, to: i64) -> Vec<i64> {
+    let mut storage = connection_pool.access_storage().await.unwrap();
+    let mut mismatched_blocks = Vec::new();
+    for block_number in from..=to {
+        let verified = storage
+            .chain()
+            .block_schema()
+            .verify_block_pubdata(block_number)
+            .await
+            .expect("Failed to verify the pubdata of block");
+        if !verified {
+            warn!(
+                "The executed ops of block {} mismatch its pubdata",
+                block_number
+            );
+            mismatched_blocks.push(block_number);
+        }
+    }
+    mismatched_blocks
 }
 
 #[tokio::main]
@@ -40,9 +76,42 @@ async fn main() {
     init();
 
     let opt: Opt = Opt::from_args();
-    let config = RecoverStateConfig::from_env();
+    let mut config = RecoverStateConfig::from_env();
+    if let Some(end_block_offset) = opt.end_block_offset {
+        override_confirmation_depth(&mut config, end_block_offset)
+            .expect("Invalid end block offset");
+    }
+    let connection_pool = ConnectionPool::new(config.db.url.clone(), config.db.restore_pool_size())
+        .with_retry_policy(
+            config.db.connection_retries(),
+            config.db.connection_retry_delay(),
+        )
+        .with_slow_query_threshold(config.db.slow_query_threshold())
+        .with_slow_tx_threshold(config.db.slow_tx_threshold())
+        .with_skip_state_invariant_check(config.db.skip_state_invariant_check());
+    let zklink_home = config.runtime.zklink_home.clone();
+    tokio::spawn(
+        connection_pool
+            .clone()
+            .watch_failover(move || DBConfig::reload_url(&zklink_home)),
+    );
+
+    if let Some([from, to]) = opt.verify_pubdata.as_deref() {
+        let mismatched_blocks = verify_pubdata(&connection_pool, *from, *to).await;
+        if !mismatched_blocks.is_empty() {
+            error!(
+                "The pubdata of blocks {:?} failed to verify",
+                mismatched_blocks
+            );
+            std::process::exit(1);
+        }
+        info!("The pubdata of blocks [{}, {}] verified", from, to);
+        std::process::exit(0);
+    }
 
-    let connection_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size);
+    discover_deployment_blocks(&mut config.layer1)
+        .await
+        .expect("Failed to discover the deployment blocks of contracts");
     let final_hash = opt
         .final_hash
         .filter(|_| opt.finite)
@@ -51,7 +120,14 @@ async fn main() {
     info!("Restoring ZkLink state from the contract");
     // Init RecoverStateDriver
     let (view_block_step, deploy_block_number, zklink_contract) =
-        get_fully_on_chain_zklink_contract(&config);
+        get_fully_on_chain_zklink_contract(&config.layer1);
+    if let Err(e) = zklink_contract.verify_abi_compatibility().await {
+        error!(
+            "Failed to verify the ABI compatibility of zkLink contract: {}",
+            e
+        );
+        std::process::exit(1);
+    }
     let mut driver = RecoverStateDriver::new(
         zklink_contract,
         &config,
@@ -62,10 +138,12 @@ async fn main() {
         connection_pool.clone(),
     )
     .await;
+    tokio::spawn(report_progress(driver.subscribe_progress()));
 
     // Init storage
     let storage = connection_pool.access_storage().await.unwrap();
-    let mut interactor = DatabaseStorageInteractor::new(storage);
+    let mut interactor = DatabaseStorageInteractor::new(storage)
+        .with_rollup_ops_format(config.runtime.rollup_ops_format);
 
     if opt.genesis {
         // There will be fetching contracts creation transactions to get first layer1 block and genesis acc address
@@ -75,11 +153,39 @@ async fn main() {
     // Get all token events
     driver.download_registered_tokens().await;
 
-    // Continue with recover_state as before
-    if opt.continue_mode && driver.load_state_from_storage(&mut interactor).await {
+    // Resume recover_state from the last recovery checkpoint
+    if opt.resume_from_checkpoint && driver.resume_from_checkpoint(&mut interactor).await {
         std::process::exit(0);
     }
 
     // Process block events
-    driver.recover_state(&mut interactor).await;
+    if let Err(e) = driver.recover_state(&mut interactor).await {
+        error!("Failed to recover state: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Reports the progress published by the driver to the metrics.
+async fn report_progress(mut progress: watch::Receiver<RecoverStateProgress>) {
+    while progress.changed().await.is_ok() {
+        let RecoverStateProgress {
+            processed_l2_blocks,
+            total_l2_blocks,
+            processed_l1_blocks,
+            current_phase,
+        } = *progress.borrow();
+        metrics::gauge!(
+            "recover_state.progress.processed_l2_blocks",
+            processed_l2_blocks as f64
+        );
+        metrics::gauge!(
+            "recover_state.progress.total_l2_blocks",
+            total_l2_blocks as f64
+        );
+        metrics::gauge!(
+            "recover_state.progress.processed_l1_blocks",
+            processed_l1_blocks as f64
+        );
+        debug!("Recover state progress: {:?}", current_phase);
+    }
 }
@@ -4,13 +4,17 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use ethers::prelude::H256;
 use num::One;
 // Workspace deps
+use recover_state_config::RollupOpsFormat;
 use zklink_crypto::convert::FeConvert;
 use zklink_crypto::params::{USD_SYMBOL, USD_TOKEN_ID};
 use zklink_storage::chain::operations::records::{
     AggType, StoredAggregatedOperation, StoredSubmitTransaction,
 };
+use zklink_storage::recover_state::records::{
+    NewRollupOpsBlock, RecoveryCheckpoint, WatchedBlockHash,
+};
 use zklink_storage::tokens::records::{DbToken, DbTokenOfChain};
-use zklink_storage::{recover_state::records::NewRollupOpsBlock, StorageProcessor};
+use zklink_storage::StorageProcessor;
 use zklink_types::{
     AccountId, BlockNumber, ChainId, Token, TokenId,
     {block::Block, AccountUpdate},
@@ -23,16 +27,27 @@ use super::{
 use crate::contract::utils::NewToken;
 use crate::{
     driver::StorageUpdateState, events::events_state::RollUpEvents, events::BlockEvent,
-    rollup_ops::RollupOpsBlock,
+    rollup_ops::RollupOpsBlock, REORG_CHECK_DEPTH,
 };
 
 pub struct DatabaseStorageInteractor<'a> {
     storage: StorageProcessor<'a>,
+    /// The representation that the rollup ops are saved in.
+    rollup_ops_format: RollupOpsFormat,
 }
 
 impl<'a> DatabaseStorageInteractor<'a> {
     pub fn new(storage: StorageProcessor<'a>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            rollup_ops_format: RollupOpsFormat::default(),
+        }
+    }
+
+    /// Saves the rollup ops in `rollup_ops_format`, the ops saved before in any format can still be loaded.
+    pub fn with_rollup_ops_format(mut self, rollup_ops_format: RollupOpsFormat) -> Self {
+        self.rollup_ops_format = rollup_ops_format;
+        self
     }
 
     pub fn storage(&mut self) -> &mut StorageProcessor<'a> {
@@ -132,7 +147,7 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
 
         self.storage
             .recover_schema()
-            .save_rollup_ops(ops.as_slice())
+            .save_rollup_ops(ops.as_slice(), self.rollup_ops_format)
             .await
             .expect("Cant update rollup operations");
     }
@@ -140,8 +155,14 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
     async fn store_blocks_and_updates(
         &mut self,
         blocks_and_updates: Vec<(Block, Vec<(AccountId, AccountUpdate, H256)>)>,
+        checkpoint: Option<&RecoveryCheckpoint>,
     ) {
         let new_state = self.storage.recover_schema().new_storage_state("None");
+        let last_block_number = blocks_and_updates
+            .last()
+            .map(|(block, _)| block.block_number);
+        // The checkpoint must never fall behind the stored blocks, so they are saved together.
+        let mut transaction = self.storage.start_transaction().await.unwrap();
         for (block, accounts_updated) in blocks_and_updates {
             let block_number = *block.block_number;
             let commit_aggregated_operation = StoredAggregatedOperation {
@@ -161,31 +182,51 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
                 confirmed: true,
             };
 
-            self.storage
+            transaction
                 .chain()
                 .state_schema()
                 .commit_state_update(block.block_number, &accounts_updated)
                 .await
                 .expect("Cant execute verify operation");
 
-            self.storage
+            transaction
                 .recover_schema()
                 .save_block_operations(&commit_aggregated_operation, &execute_aggregated_operation)
                 .await
                 .expect("Cant execute verify operation");
 
-            self.storage
+            transaction
                 .chain()
                 .block_schema()
                 .save_block(block)
                 .await
                 .expect("Unable to save block");
         }
-        self.storage
+        if let Some(last_block_number) = last_block_number {
+            transaction
+                .recover_schema()
+                .remove_applied_rollup_ops(last_block_number)
+                .await
+                .expect("Unable to remove applied rollup operations");
+        }
+        if let Some(checkpoint) = checkpoint {
+            transaction
+                .recover_schema()
+                .save_recovery_checkpoint(
+                    checkpoint.chain_id,
+                    checkpoint.last_l1_block,
+                    checkpoint.last_l2_block,
+                    checkpoint.ops_hash,
+                )
+                .await
+                .expect("Unable to save recovery checkpoint");
+        }
+        transaction
             .recover_schema()
             .update_storage_state(new_state)
             .await
             .expect("Unable to update storage state");
+        transaction.commit().await.unwrap();
     }
 
     async fn init_token_event_progress(
@@ -361,17 +402,23 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
             .expect("Cant get the last block from storage")
             .expect("There are no last block in storage - restart driver");
 
-        let mut last_serial_ids = HashMap::with_capacity(chain_ids.len());
-        for chain_id in chain_ids {
-            let last_serial_id = self
-                .storage
-                .chain()
-                .operations_schema()
-                .get_last_serial_id(*chain_id as i16)
-                .await
-                .expect("Failed to get the last serial id");
-            last_serial_ids.insert(chain_id, last_serial_id);
-        }
+        let next_serial_ids = self
+            .storage
+            .chain()
+            .operations_schema()
+            .next_serial_ids()
+            .await
+            .expect("Failed to get the next serial ids");
+        let last_serial_ids = chain_ids
+            .into_iter()
+            .map(|chain_id| {
+                let next_serial_id = next_serial_ids
+                    .get(&(*chain_id as i16))
+                    .copied()
+                    .unwrap_or_default();
+                (chain_id, next_serial_id - 1)
+            })
+            .collect();
 
         StoredTreeState {
             last_sync_hash: block.sync_hash,
@@ -382,10 +429,30 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
         }
     }
 
-    async fn get_ops_blocks_from_storage(&mut self) -> Vec<RollupOpsBlock> {
+    async fn get_last_block(&mut self) -> Block {
+        let last_block_number = self
+            .storage
+            .chain()
+            .block_schema()
+            .get_last_block_number()
+            .await
+            .expect("Cant load last layer2 block number");
+        self.storage
+            .chain()
+            .block_schema()
+            .get_block(last_block_number)
+            .await
+            .expect("Cant get the last block from storage")
+            .expect("There are no last block in storage - restart driver")
+    }
+
+    async fn get_ops_blocks_from_storage(
+        &mut self,
+        after_block: BlockNumber,
+    ) -> Vec<RollupOpsBlock> {
         self.storage
             .recover_schema()
-            .load_rollup_ops_blocks()
+            .load_rollup_ops_blocks(i64::from(*after_block))
             .await
             .expect("Cant load operation blocks")
             .into_iter()
@@ -393,6 +460,15 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
             .collect()
     }
 
+    async fn get_last_rollup_ops_block(&mut self) -> Option<BlockNumber> {
+        self.storage
+            .recover_schema()
+            .last_rollup_ops_block()
+            .await
+            .expect("Cant load the last operation block")
+            .map(|block_num| BlockNumber(block_num as u32))
+    }
+
     async fn get_storage_state(&mut self) -> StorageUpdateState {
         let storage_state_string = self
             .storage
@@ -409,4 +485,44 @@ impl StorageInteractor for DatabaseStorageInteractor<'_> {
             _ => panic!("Unknown storage state"),
         }
     }
+
+    async fn load_recovery_checkpoint(&mut self, chain_id: ChainId) -> Option<RecoveryCheckpoint> {
+        self.storage
+            .recover_schema()
+            .load_recovery_checkpoint(chain_id)
+            .await
+            .expect("Cant load recovery checkpoint")
+    }
+
+    async fn save_watched_block_hash(&mut self, watched: &WatchedBlockHash) {
+        self.storage
+            .recover_schema()
+            .save_watched_block_hash(watched, REORG_CHECK_DEPTH)
+            .await
+            .expect("Unable to save watched block hash");
+    }
+
+    async fn load_watched_block_hashes(
+        &mut self,
+        chain_id: ChainId,
+        from_l1_block: u64,
+    ) -> Vec<WatchedBlockHash> {
+        self.storage
+            .recover_schema()
+            .load_watched_block_hashes(chain_id, from_l1_block)
+            .await
+            .expect("Cant load watched block hashes")
+    }
+
+    async fn revert_to_fork(
+        &mut self,
+        chain_id: ChainId,
+        fork_l1_block: u64,
+    ) -> Option<BlockNumber> {
+        self.storage
+            .recover_schema()
+            .revert_to_fork(chain_id, fork_l1_block)
+            .await
+            .expect("Unable to revert the reorged blocks")
+    }
 }
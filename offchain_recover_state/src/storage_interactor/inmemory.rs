@@ -2,6 +2,7 @@ use ethers::prelude::H256;
 use std::cmp::max;
 use std::collections::HashMap;
 use zklink_storage::chain::operations::records::StoredSubmitTransaction;
+use zklink_storage::recover_state::records::{RecoveryCheckpoint, WatchedBlockHash};
 use zklink_types::block::Block;
 use zklink_types::utils::calculate_actual_token;
 use zklink_types::{
@@ -27,7 +28,13 @@ pub struct InMemoryStorageInteractor {
     #[allow(dead_code)]
     last_committed_block: BlockNumber,
     last_verified_block: BlockNumber,
+    last_block: Option<Block>,
+    blocks: Vec<Block>,
     accounts: AccountMap,
+    /// The accounts after applying every stored block(and the genesis), restored on reverting to the block.
+    account_snapshots: HashMap<BlockNumber, AccountMap>,
+    recovery_checkpoints: HashMap<ChainId, RecoveryCheckpoint>,
+    watched_block_hashes: Vec<WatchedBlockHash>,
 }
 
 impl Default for InMemoryStorageInteractor {
@@ -61,14 +68,26 @@ impl StorageInteractor for InMemoryStorageInteractor {
     }
 
     async fn save_rollup_ops(&mut self, blocks: &[RollupOpsBlock]) {
-        self.rollups = blocks.to_vec();
+        self.rollups.retain(|saved| {
+            blocks
+                .iter()
+                .all(|block| block.block_num != saved.block_num)
+        });
+        self.rollups.extend_from_slice(blocks);
+        self.rollups.sort_by_key(|block| block.block_num);
         self.storage_state = StorageUpdateState::Operations
     }
 
     async fn store_blocks_and_updates(
         &mut self,
         blocks_and_updates: Vec<(Block, Vec<(AccountId, AccountUpdate, H256)>)>,
+        checkpoint: Option<&RecoveryCheckpoint>,
     ) {
+        if let Some((last_block, _)) = blocks_and_updates.last() {
+            let last_block_number = last_block.block_number;
+            self.rollups
+                .retain(|block| block.block_num > last_block_number);
+        }
         for (block, accounts_updated) in blocks_and_updates {
             let commit_op = Operation {
                 action: Action::Commit,
@@ -88,8 +107,16 @@ impl StorageInteractor for InMemoryStorageInteractor {
             self.last_verified_block = verify_op.block.block_number;
 
             self.commit_state_update(*block.block_number, &accounts_updated);
+            self.account_snapshots
+                .insert(block.block_number, self.accounts.clone());
+            self.blocks.push(block.clone());
+            self.last_block = Some(block);
             self.storage_state = StorageUpdateState::None
         }
+        if let Some(checkpoint) = checkpoint {
+            self.recovery_checkpoints
+                .insert(checkpoint.chain_id, checkpoint.clone());
+        }
     }
 
     async fn init_token_event_progress(
@@ -103,9 +130,9 @@ impl StorageInteractor for InMemoryStorageInteractor {
     async fn init_block_events_state(
         &mut self,
         _chain_id: ChainId,
-        _last_watched_block_number: u64,
+        last_watched_block_number: u64,
     ) {
-        todo!()
+        self.last_watched_block = last_watched_block_number;
     }
 
     async fn update_block_events_state(
@@ -130,6 +157,8 @@ impl StorageInteractor for InMemoryStorageInteractor {
         genesis_updates: &[(AccountId, AccountUpdate, H256)],
     ) {
         self.commit_state_update(0, genesis_updates);
+        self.account_snapshots
+            .insert(BlockNumber(0), self.accounts.clone());
     }
 
     async fn get_block_events_state_from_storage(&mut self, _chain_id: ChainId) -> RollUpEvents {
@@ -138,13 +167,13 @@ impl StorageInteractor for InMemoryStorageInteractor {
             .iter()
             .map(|event| event.end_block_num)
             .max()
-            .unwrap_or_default();
+            .unwrap_or(self.last_verified_block);
         let verified_events = self.load_verified_events_state();
         let last_verified_num = verified_events
             .iter()
             .map(|event| event.end_block_num)
             .max()
-            .unwrap_or_default();
+            .unwrap_or(self.last_verified_block);
         RollUpEvents {
             last_committed_num,
             committed_events,
@@ -159,23 +188,111 @@ impl StorageInteractor for InMemoryStorageInteractor {
             .into_iter()
             .map(|chain_id| (chain_id, -1))
             .collect();
-        // TODO find a way how to get unprocessed_prior_ops and fee_acc_id
+        let (last_sync_hash, fee_acc_id) = self
+            .last_block
+            .as_ref()
+            .map(|block| (block.sync_hash, block.fee_account))
+            .unwrap_or_default();
         StoredTreeState {
-            last_sync_hash: Default::default(),
+            last_sync_hash,
             last_block_number: self.last_verified_block,
             last_serial_ids,
             account_map: self.accounts.clone(),
-            fee_acc_id: AccountId(0),
+            fee_acc_id,
         }
     }
 
-    async fn get_ops_blocks_from_storage(&mut self) -> Vec<RollupOpsBlock> {
-        self.rollups.clone()
+    async fn get_last_block(&mut self) -> Block {
+        self.last_block
+            .clone()
+            .expect("There are no last block in storage - restart driver")
+    }
+
+    async fn get_ops_blocks_from_storage(
+        &mut self,
+        after_block: BlockNumber,
+    ) -> Vec<RollupOpsBlock> {
+        self.rollups
+            .iter()
+            .filter(|block| block.block_num > after_block)
+            .cloned()
+            .collect()
+    }
+
+    async fn get_last_rollup_ops_block(&mut self) -> Option<BlockNumber> {
+        self.rollups.last().map(|block| block.block_num)
     }
 
     async fn get_storage_state(&mut self) -> StorageUpdateState {
         self.storage_state
     }
+
+    async fn load_recovery_checkpoint(&mut self, chain_id: ChainId) -> Option<RecoveryCheckpoint> {
+        self.recovery_checkpoints.get(&chain_id).cloned()
+    }
+
+    async fn save_watched_block_hash(&mut self, watched: &WatchedBlockHash) {
+        self.watched_block_hashes
+            .retain(|hash| hash.chain_id != watched.chain_id || hash.l1_block != watched.l1_block);
+        self.watched_block_hashes.push(watched.clone());
+        self.watched_block_hashes.sort_by_key(|hash| hash.l1_block);
+    }
+
+    async fn load_watched_block_hashes(
+        &mut self,
+        chain_id: ChainId,
+        from_l1_block: u64,
+    ) -> Vec<WatchedBlockHash> {
+        self.watched_block_hashes
+            .iter()
+            .filter(|hash| hash.chain_id == chain_id && hash.l1_block >= from_l1_block)
+            .cloned()
+            .collect()
+    }
+
+    async fn revert_to_fork(
+        &mut self,
+        chain_id: ChainId,
+        fork_l1_block: u64,
+    ) -> Option<BlockNumber> {
+        // The hashes of the reorged blocks are never valid again.
+        self.watched_block_hashes
+            .retain(|hash| hash.chain_id != chain_id || hash.l1_block < fork_l1_block);
+        let safe_point = self
+            .watched_block_hashes
+            .iter()
+            .filter(|hash| hash.chain_id == chain_id)
+            .last()
+            .cloned()?;
+        let safe_l2_block = safe_point.last_l2_block;
+
+        self.rollups
+            .retain(|block| block.block_num <= safe_l2_block);
+        self.events_state
+            .retain(|event| event.end_block_num <= safe_l2_block);
+        self.blocks
+            .retain(|block| block.block_number <= safe_l2_block);
+        self.account_snapshots
+            .retain(|block_number, _| *block_number <= safe_l2_block);
+        self.accounts = self
+            .account_snapshots
+            .get(&safe_l2_block)
+            .cloned()
+            .unwrap_or_default();
+        self.last_block = self.blocks.last().cloned();
+        self.last_committed_block = safe_l2_block;
+        self.last_verified_block = safe_l2_block;
+
+        // The block events after the safe point are watched again.
+        self.last_watched_block = safe_point.l1_block;
+        self.watched_block_hashes
+            .retain(|hash| hash.last_l2_block <= safe_l2_block);
+        self.recovery_checkpoints
+            .retain(|_, checkpoint| checkpoint.last_l2_block <= safe_l2_block);
+        self.storage_state = StorageUpdateState::None;
+
+        Some(safe_l2_block)
+    }
 }
 
 impl InMemoryStorageInteractor {
@@ -188,7 +305,12 @@ impl InMemoryStorageInteractor {
             last_watched_block: 0,
             last_committed_block: BlockNumber(0),
             last_verified_block: BlockNumber(0),
+            last_block: None,
+            blocks: vec![],
             accounts: Default::default(),
+            account_snapshots: Default::default(),
+            recovery_checkpoints: Default::default(),
+            watched_block_hashes: vec![],
         }
     }
 
@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use zklink_storage::chain::operations::records::StoredSubmitTransaction;
 use zklink_storage::recover_state::records::{
-    NewBlockEvent, StoredBlockEvent, StoredRollupOpsBlock,
+    NewBlockEvent, RecoveryCheckpoint, StoredBlockEvent, StoredRollupOpsBlock, WatchedBlockHash,
 };
 use zklink_types::{
     block::Block, AccountId, AccountMap, AccountUpdate, BlockNumber, ChainId, Token, TokenId, H256,
@@ -69,10 +69,13 @@ pub trait StorageInteractor {
     /// # Arguments
     ///
     /// * `blocks_updated` - blocks and account updated
+    /// * `checkpoint` - the recovery checkpoint after applying the blocks, saved along with them
     ///
+    /// The rollup operations of the stored blocks are removed, they are never loaded again.
     async fn store_blocks_and_updates(
         &mut self,
         blocks_and_updates: Vec<(Block, Vec<(AccountId, AccountUpdate, H256)>)>,
+        checkpoint: Option<&RecoveryCheckpoint>,
     );
 
     /// Init the progress of syncing token events.
@@ -129,11 +132,41 @@ pub trait StorageInteractor {
     /// Returns the current Rollup block, tree accounts map, unprocessed priority ops and the last fee acc from storage
     async fn get_tree_state(&mut self, chain_ids: Vec<ChainId>) -> StoredTreeState;
 
-    /// Returns Rollup operations blocks from storage
-    async fn get_ops_blocks_from_storage(&mut self) -> Vec<RollupOpsBlock>;
+    /// Returns the last stored Rollup block
+    async fn get_last_block(&mut self) -> Block;
+
+    /// Returns Rollup operations blocks after `after_block` from storage
+    async fn get_ops_blocks_from_storage(
+        &mut self,
+        after_block: BlockNumber,
+    ) -> Vec<RollupOpsBlock>;
+
+    /// Returns the last Rollup block whose operations have been saved in storage
+    async fn get_last_rollup_ops_block(&mut self) -> Option<BlockNumber>;
 
     /// Returns last recovery state update step from storage
     async fn get_storage_state(&mut self) -> StorageUpdateState;
+
+    /// Returns the last saved progress of recovering from the chain
+    async fn load_recovery_checkpoint(&mut self, chain_id: ChainId) -> Option<RecoveryCheckpoint>;
+
+    /// Records the hash of the watched layer1 block to detect the reorgs later
+    async fn save_watched_block_hash(&mut self, watched: &WatchedBlockHash);
+
+    /// Returns the recorded hashes of the watched layer1 blocks from `from_l1_block` in ascending order
+    async fn load_watched_block_hashes(
+        &mut self,
+        chain_id: ChainId,
+        from_l1_block: u64,
+    ) -> Vec<WatchedBlockHash>;
+
+    /// Reverts the recovered state to the last watched layer1 block before the reorged `fork_l1_block`,
+    /// returns the layer2 block reverted to, `None` if there is no watched block before the fork.
+    async fn revert_to_fork(
+        &mut self,
+        chain_id: ChainId,
+        fork_l1_block: u64,
+    ) -> Option<BlockNumber>;
 }
 
 /// Returns Rollup contract event from its stored representation
@@ -188,7 +221,9 @@ pub fn block_event_into_stored_block_event(event: &BlockEvent) -> NewBlockEvent
 /// * `op_block` - Stored ZkLink operations block description
 ///
 pub fn stored_ops_block_into_ops_block(op_block: StoredRollupOpsBlock) -> RollupOpsBlock {
-    let ops = serde_json::from_value(op_block.operation).unwrap();
+    let ops = op_block
+        .ops()
+        .expect("invalid rollup operations in the database");
     RollupOpsBlock {
         block_num: BlockNumber::from(op_block.block_num as u32),
         ops,
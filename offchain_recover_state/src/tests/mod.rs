@@ -1,30 +1,36 @@
 pub(crate) mod utils;
 
 use crate::{
-    driver::RecoverStateDriver,
-    storage_interactor::database::DatabaseStorageInteractor,
+    contract::ZkLinkContractVersion,
+    driver::StorageUpdateState,
+    events::{BlockEvent, EventType},
+    is_rate_limited, override_confirmation_depth,
+    rollup_ops::RollupOpsBlock,
+    rpc_retry_delay,
     storage_interactor::inmemory::InMemoryStorageInteractor,
-    tests::utils::{create_log, u32_to_32bytes},
+    storage_interactor::StorageInteractor,
+    tests::utils::u32_to_32bytes,
+    with_rpc_retries, MAX_END_BLOCK_OFFSET, RATE_LIMIT_DELAY,
 };
-use aggegate::agg_op::commit_op::BlocksCommitOperation;
 use chrono::Utc;
-use ethers::abi::{ethabi, Tokenize};
-use ethers::prelude::{Log, Transaction};
-use futures::future;
-use jsonrpc_core::Params;
 use num::BigUint;
-use serde_json::{json, Value};
-use std::cmp::max;
-use std::{collections::HashMap, future::Future};
-use zklink_blockchain::eth::contract::{load_abi, ZKLINK_JSON};
-use zklink_storage::{
-    chain::account::AccountSchema, recover_state::RecoverSchema, StorageProcessor,
+use recover_state_config::{
+    ClientConfig, Layer1Config, MultiChainConfigs, RecoverStateConfig,
+    DEFAULT_MAX_PENDING_TASKS_PER_ADDRESS,
 };
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use zklink_storage::recover_state::records::{RecoveryCheckpoint, WatchedBlockHash};
 use zklink_types::{
-    block::Block, AccountId, BlockNumber, ChainId, Deposit, DepositOp, ExecutedTx, Nonce,
-    SubAccountId, TokenId, Withdraw, WithdrawOp, ZkLinkAddress, ZkLinkOp, H160, H256,
+    block::Block, AccountId, AccountMap, AccountUpdate, BlockNumber, ChainId, Deposit, DepositOp,
+    ExecutedTx, Nonce, SubAccountId, TokenId, Withdraw, WithdrawOp, ZkLinkAddress, ZkLinkOp, H256,
 };
 
+const CHAIN_ID: ChainId = ChainId(1);
+const TOKEN_ID: TokenId = TokenId(18);
+const FEE_ACCOUNT_ID: AccountId = AccountId(0);
+const ACCOUNT_ID: AccountId = AccountId(1);
+
 fn create_withdraw_operations(account_id: AccountId, to: ZkLinkAddress, amount: u32) -> ExecutedTx {
     let withdraw_op = ZkLinkOp::Withdraw(Box::new(WithdrawOp {
         tx: Withdraw::new(
@@ -85,392 +91,412 @@ fn create_block(block_number: BlockNumber, transactions: Vec<ExecutedTx>) -> Blo
     Block::new(
         block_number,
         Default::default(),
-        AccountId(0),
+        FEE_ACCOUNT_ID,
         transactions,
         20,
         100,
         1_000_000.into(),
         1_500_000.into(),
         H256::default(),
-        H256::default(),
+        u32_to_32bytes(*block_number).into(),
         0,
     )
 }
 
-// fn create_transaction_v4(number: u32, stored_block: Block, blocks: Vec<Block>) -> Transaction {
-//     let hash: H256 = u32_to_32bytes(number).into();
-//     let block_number = blocks
-//         .last()
-//         .expect("at least one should exist")
-//         .block_number
-//         .0;
-//     let fake_data = [0u8; 4];
-//     let mut input_data = vec![];
-//     let op = BlocksCommitOperation {
-//         last_committed_block: stored_block,
-//         blocks,
-//     };
-//     input_data.extend_from_slice(&fake_data);
-//     input_data.extend_from_slice(&ethabi::encode(op.get_eth_tx_args().as_ref()));
-//
-//     Transaction {
-//         hash,
-//         nonce: u32_to_32bytes(1).into(),
-//         block_hash: Some(u32_to_32bytes(100).into()),
-//         block_number: Some(block_number.into()),
-//         transaction_index: Some(block_number.into()),
-//         from: [5u8; 20].into(),
-//         to: Some([7u8; 20].into()),
-//         value: u32_to_32bytes(10).into(),
-//         gas_price: u32_to_32bytes(1).into(),
-//         gas: u32_to_32bytes(1).into(),
-//         input: input_data.into(),
-//         ..Default::default()
-//     }
-// }
-
-// fn create_transaction(number: u32, block: Block) -> Transaction {
-//     let hash: H256 = u32_to_32bytes(number).into();
-//     let root = block.get_eth_encoded_root();
-//     let public_data = block.get_eth_public_data();
-//     let witness_data = block.get_eth_witness_data();
-//     let fake_data = [0u8; 4];
-//     let params = (
-//         u64::from(*block.block_number),
-//         u64::from(*block.fee_account),
-//         vec![root],
-//         public_data,
-//         witness_data.0,
-//         witness_data.1,
-//     );
-//     let mut input_data = vec![];
-//     input_data.extend_from_slice(&fake_data);
-//     input_data.extend_from_slice(&ethabi::encode(params.into_tokens().as_ref()));
-//
-//     Transaction {
-//         hash,
-//         nonce: u32_to_32bytes(1).into(),
-//         block_hash: Some(u32_to_32bytes(100).into()),
-//         block_number: Some((*block.block_number).into()),
-//         transaction_index: Some((*block.block_number).into()),
-//         from: [5u8; 20].into(),
-//         to: Some([7u8; 20].into()),
-//         value: u32_to_32bytes(10).into(),
-//         gas_price: u32_to_32bytes(1).into(),
-//         gas: u32_to_32bytes(1).into(),
-//         input: input_data.into(),
-//         ..Default::default()
-//     }
-// }
-
-#[derive(Debug, Clone)]
-pub(crate) struct Web3Transport {
-    transactions: HashMap<String, Transaction>,
-    logs: HashMap<String, Vec<Log>>,
-    last_block: u32,
+fn create_block_event(block_number: u32, block_type: EventType) -> BlockEvent {
+    BlockEvent {
+        start_block_num: BlockNumber(block_number),
+        end_block_num: BlockNumber(block_number),
+        transaction_hash: u32_to_32bytes(block_number).into(),
+        block_type,
+        contract_version: ZkLinkContractVersion::V0,
+    }
 }
 
-impl Web3Transport {
-    fn new() -> Self {
-        Self {
-            transactions: HashMap::default(),
-            logs: HashMap::default(),
-            last_block: 0,
-        }
+fn create_ops_block(block: &Block) -> RollupOpsBlock {
+    RollupOpsBlock {
+        block_num: block.block_number,
+        ops: block
+            .block_transactions
+            .iter()
+            .map(|tx| tx.op.clone())
+            .collect(),
+        fee_account: block.fee_account,
+        timestamp: Some(1_000 * u64::from(*block.block_number)),
+        previous_block_root_hash: H256::default(),
+        contract_version: Some(ZkLinkContractVersion::V0),
     }
-    fn push_transactions(&mut self, transactions: Vec<Transaction>) {
-        for transaction in transactions {
-            self.last_block = max(transaction.block_number.unwrap().as_u32(), self.last_block);
-            self.transactions
-                .insert(format!("{:?}", &transaction.hash), transaction);
-        }
+}
+
+fn update_balance(old_balance: u32, new_balance: u32, new_nonce: Nonce) -> AccountUpdate {
+    AccountUpdate::UpdateBalance {
+        balance_update: (
+            TOKEN_ID,
+            SubAccountId(0),
+            BigUint::from(old_balance),
+            BigUint::from(new_balance),
+        ),
+        old_nonce: Nonce(0),
+        new_nonce,
     }
+}
+
+type EventFields = (
+    BlockNumber,
+    BlockNumber,
+    H256,
+    EventType,
+    ZkLinkContractVersion,
+);
 
-    fn insert_logs(&mut self, topic: String, logs: Vec<Log>) {
-        self.logs.insert(topic, logs);
+/// The observable storage state after running a sequence of recovering steps,
+/// events and ops blocks are compared by all their fields(`BlockEvent` equals only by the end block).
+#[derive(Debug, PartialEq)]
+struct InteractorSnapshot {
+    storage_state: StorageUpdateState,
+    events: Vec<EventFields>,
+    last_watched_block_number: u64,
+    ops_blocks: Vec<(BlockNumber, AccountId, Option<u64>, serde_json::Value)>,
+    last_block_number: BlockNumber,
+    last_sync_hash: H256,
+    fee_acc_id: AccountId,
+    account_map: AccountMap,
+    checkpoint: Option<RecoveryCheckpoint>,
+}
+
+/// Runs the steps of recovering the genesis and two blocks(a deposit and a withdrawal)
+/// through the interactor in the same order as the driver.
+async fn run_recover_sequence<I: StorageInteractor>(interactor: &mut I) -> InteractorSnapshot {
+    let fee_address = ZkLinkAddress::from(vec![0xfe; 20]);
+    let address = ZkLinkAddress::from(vec![0xab; 20]);
+
+    interactor.init_block_events_state(CHAIN_ID, 0).await;
+    interactor
+        .save_genesis_tree_state(&[(
+            FEE_ACCOUNT_ID,
+            AccountUpdate::Create {
+                address: fee_address,
+                nonce: Nonce(0),
+            },
+            H256::zero(),
+        )])
+        .await;
+
+    let events = [
+        create_block_event(1, EventType::Committed),
+        create_block_event(2, EventType::Committed),
+        create_block_event(1, EventType::Verified),
+        create_block_event(2, EventType::Verified),
+    ];
+    interactor
+        .update_block_events_state(CHAIN_ID, &events, 10)
+        .await
+        .unwrap();
+
+    let blocks_and_updates = vec![
+        (
+            create_block(
+                BlockNumber(1),
+                vec![create_deposit(address.clone(), address.clone(), 50)],
+            ),
+            vec![
+                (
+                    ACCOUNT_ID,
+                    AccountUpdate::Create {
+                        address: address.clone(),
+                        nonce: Nonce(0),
+                    },
+                    u32_to_32bytes(1).into(),
+                ),
+                (
+                    ACCOUNT_ID,
+                    update_balance(0, 50, Nonce(0)),
+                    u32_to_32bytes(1).into(),
+                ),
+            ],
+        ),
+        (
+            create_block(
+                BlockNumber(2),
+                vec![create_withdraw_operations(ACCOUNT_ID, address, 10)],
+            ),
+            vec![(
+                ACCOUNT_ID,
+                update_balance(50, 40, Nonce(1)),
+                u32_to_32bytes(2).into(),
+            )],
+        ),
+    ];
+    let ops_blocks = blocks_and_updates
+        .iter()
+        .map(|(block, _)| create_ops_block(block))
+        .collect::<Vec<_>>();
+    let last_block = &blocks_and_updates.last().unwrap().0;
+    let checkpoint = RecoveryCheckpoint {
+        chain_id: CHAIN_ID,
+        last_l1_block: 10,
+        last_l2_block: last_block.block_number,
+        ops_hash: last_block.sync_hash,
+    };
+    interactor.save_rollup_ops(&ops_blocks).await;
+    interactor
+        .store_blocks_and_updates(blocks_and_updates, Some(&checkpoint))
+        .await;
+
+    let tree_state = interactor.get_tree_state(vec![CHAIN_ID]).await;
+
+    let rollup_events = interactor
+        .get_block_events_state_from_storage(CHAIN_ID)
+        .await;
+    InteractorSnapshot {
+        storage_state: interactor.get_storage_state().await,
+        events: rollup_events
+            .committed_events
+            .iter()
+            .chain(rollup_events.verified_events.iter())
+            .map(|event| {
+                (
+                    event.start_block_num,
+                    event.end_block_num,
+                    event.transaction_hash,
+                    event.block_type,
+                    event.contract_version,
+                )
+            })
+            .collect(),
+        last_watched_block_number: rollup_events.last_watched_block_number,
+        ops_blocks: interactor
+            .get_ops_blocks_from_storage(BlockNumber(0))
+            .await
+            .into_iter()
+            .map(|block| {
+                (
+                    block.block_num,
+                    block.fee_account,
+                    block.timestamp,
+                    serde_json::to_value(block.ops).unwrap(),
+                )
+            })
+            .collect(),
+        last_block_number: tree_state.last_block_number,
+        last_sync_hash: tree_state.last_sync_hash,
+        fee_acc_id: tree_state.fee_acc_id,
+        account_map: tree_state.account_map,
+        checkpoint: interactor.load_recovery_checkpoint(CHAIN_ID).await,
     }
+}
 
-    fn get_logs(&self, filter: Value) -> Vec<Log> {
-        let topics = if let Ok(topics) =
-            serde_json::from_value::<Vec<Vec<String>>>(filter.get("topics").unwrap().clone())
-        {
-            topics.first().unwrap().clone()
-        } else {
-            serde_json::from_value::<Vec<String>>(filter.get("topics").unwrap().clone()).unwrap()
-        };
-        let mut logs = vec![];
+#[tokio::test]
+async fn test_with_inmemory_storage() {
+    let mut interactor = InMemoryStorageInteractor::new();
+    let snapshot = run_recover_sequence(&mut interactor).await;
 
-        for topic in &topics {
-            if let Some(topic_logs) = self.logs.get(topic) {
-                logs.extend_from_slice(topic_logs)
-            }
-        }
+    assert_eq!(snapshot.storage_state, StorageUpdateState::None);
+    assert_eq!(snapshot.events.len(), 4);
+    // The ops of the applied blocks are removed along with storing them.
+    assert!(snapshot.ops_blocks.is_empty());
+    assert_eq!(snapshot.last_block_number, BlockNumber(2));
+    assert_eq!(snapshot.last_sync_hash, H256::from(u32_to_32bytes(2)));
+    let account = &snapshot.account_map[&ACCOUNT_ID];
+    assert_eq!(account.get_balance(TOKEN_ID), BigUint::from(40u32));
+    assert_eq!(account.nonce, Nonce(1));
+    assert!(snapshot.account_map.contains_key(&FEE_ACCOUNT_ID));
 
-        logs
+    // The ops of block 3 are saved but the driver crashes before applying them,
+    // then only the unapplied ops are resumed, and the refetched ones overwrite the saved ones.
+    let block = create_block(BlockNumber(3), vec![]);
+    interactor
+        .save_rollup_ops(&[create_ops_block(&block)])
+        .await;
+    interactor
+        .save_rollup_ops(&[create_ops_block(&block)])
+        .await;
+    assert_eq!(
+        interactor.get_last_rollup_ops_block().await,
+        Some(BlockNumber(3))
+    );
+    let unapplied = interactor
+        .get_ops_blocks_from_storage(snapshot.last_block_number)
+        .await;
+    assert_eq!(unapplied.len(), 1);
+    assert_eq!(unapplied[0].block_num, BlockNumber(3));
+    assert_eq!(
+        interactor
+            .get_ops_blocks_from_storage(BlockNumber(0))
+            .await
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn revert_to_fork_with_inmemory_storage() {
+    let mut interactor = InMemoryStorageInteractor::new();
+    run_recover_sequence(&mut interactor).await;
+
+    // There is no watched block before the fork, nothing is reverted.
+    assert_eq!(interactor.revert_to_fork(CHAIN_ID, 3).await, None);
+    assert_eq!(
+        interactor.get_last_block().await.block_number,
+        BlockNumber(2)
+    );
+
+    // The layer1 block 8 is reorged, block 2 applied after it is reverted,
+    // and so are the ops of block 3 saved but not applied yet.
+    let block = create_block(BlockNumber(3), vec![]);
+    interactor
+        .save_rollup_ops(&[create_ops_block(&block)])
+        .await;
+    for (l1_block, last_l2_block) in [(5, 1), (10, 2)] {
+        interactor
+            .save_watched_block_hash(&WatchedBlockHash {
+                chain_id: CHAIN_ID,
+                l1_block,
+                block_hash: u32_to_32bytes(l1_block as u32).into(),
+                last_l2_block: BlockNumber(last_l2_block),
+            })
+            .await;
     }
+    assert_eq!(
+        interactor.revert_to_fork(CHAIN_ID, 8).await,
+        Some(BlockNumber(1))
+    );
+    let tree_state = interactor.get_tree_state(vec![CHAIN_ID]).await;
+    assert_eq!(tree_state.last_block_number, BlockNumber(1));
+    assert_eq!(tree_state.last_sync_hash, H256::from(u32_to_32bytes(1)));
+    let account = &tree_state.account_map[&ACCOUNT_ID];
+    assert_eq!(account.get_balance(TOKEN_ID), BigUint::from(50u32));
+    assert_eq!(account.nonce, Nonce(0));
+    assert_eq!(
+        interactor.get_last_block().await.block_number,
+        BlockNumber(1)
+    );
+
+    assert!(interactor
+        .get_ops_blocks_from_storage(BlockNumber(0))
+        .await
+        .is_empty());
+    let rollup_events = interactor
+        .get_block_events_state_from_storage(CHAIN_ID)
+        .await;
+    assert_eq!(rollup_events.committed_events.len(), 1);
+    assert_eq!(rollup_events.verified_events.len(), 1);
+    assert_eq!(rollup_events.last_watched_block_number, 5);
+    assert_eq!(
+        interactor.get_storage_state().await,
+        StorageUpdateState::None
+    );
+    assert_eq!(interactor.load_recovery_checkpoint(CHAIN_ID).await, None);
+    assert_eq!(
+        interactor
+            .load_watched_block_hashes(CHAIN_ID, 0)
+            .await
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn rpc_request_retried_until_success() {
+    let attempts = AtomicU32::new(0);
+    let request = || async {
+        if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+            anyhow::bail!("connection reset by peer")
+        }
+        Ok(attempts.load(Ordering::Relaxed))
+    };
+    let result = with_rpc_retries(2, Duration::from_millis(1), request).await;
+    assert_eq!(result.unwrap(), 3);
+
+    // The error of the last attempt is returned once the retries are exhausted.
+    attempts.store(0, Ordering::Relaxed);
+    let result = with_rpc_retries(1, Duration::from_millis(1), request).await;
+    assert_eq!(result.unwrap_err().to_string(), "connection reset by peer");
+    assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    assert!(is_rate_limited(&anyhow::format_err!(
+        "(code: 429) 429 Too Many Requests"
+    )));
+}
+
+#[test]
+fn rpc_retry_delay_capped() {
+    let base_delay = Duration::from_secs(1);
+    assert_eq!(rpc_retry_delay(0, base_delay), base_delay);
+    assert_eq!(rpc_retry_delay(3, base_delay), Duration::from_secs(8));
+    // Neither the overflowed factor nor the overflowed delay panics.
+    assert_eq!(rpc_retry_delay(10, base_delay), RATE_LIMIT_DELAY);
+    assert_eq!(rpc_retry_delay(40, base_delay), RATE_LIMIT_DELAY);
+    assert_eq!(rpc_retry_delay(31, Duration::MAX), RATE_LIMIT_DELAY);
+}
+
+#[test]
+fn end_block_offset_overrides_confirmation_depth() {
+    let layer1_config = |chain_id: u8, confirmation_depth| {
+        let mut config = Layer1Config {
+            chain: Default::default(),
+            contract: Default::default(),
+            client: ClientConfig {
+                confirmation_depth,
+                ..Default::default()
+            },
+        };
+        config.chain.chain_id = ChainId(chain_id);
+        config
+    };
+    let mut config = RecoverStateConfig {
+        runtime: Default::default(),
+        api: Default::default(),
+        db: Default::default(),
+        layer1: MultiChainConfigs {
+            chain_ids: vec![ChainId(1), ChainId(2)],
+            chain_configs: vec![layer1_config(1, None), layer1_config(2, Some(100))],
+        },
+        upgrade_layer2_blocks: Vec::new(),
+        black_list_time: None,
+        max_pending_tasks_per_address: DEFAULT_MAX_PENDING_TASKS_PER_ADDRESS,
+        skip_balance_ordering: false,
+        enable_sync_mode: false,
+        tree_checkpoint_path: None,
+    };
+    let confirmation_depths = |config: &RecoverStateConfig| {
+        config
+            .layer1
+            .chain_configs
+            .iter()
+            .map(|chain_config| chain_config.client.confirmation_depth)
+            .collect::<Vec<_>>()
+    };
+
+    // The offset that is most likely a block number is rejected without changing any chain.
+    assert!(override_confirmation_depth(&mut config, MAX_END_BLOCK_OFFSET + 1).is_err());
+    assert_eq!(confirmation_depths(&config), vec![None, Some(100)]);
+
+    // Every chain is overridden, whether its depth is configured or the default one.
+    override_confirmation_depth(&mut config, MAX_END_BLOCK_OFFSET).unwrap();
+    assert_eq!(
+        confirmation_depths(&config),
+        vec![Some(MAX_END_BLOCK_OFFSET); 2]
+    );
+    override_confirmation_depth(&mut config, 0).unwrap();
+    assert_eq!(confirmation_depths(&config), vec![Some(0); 2]);
+}
+
+/// The in-memory interactor is used as the fast test backend of the driver,
+/// so it must leave the same state as the database interactor.
+/// Needs the freshly migrated database since the genesis state can only be saved into the empty one.
+#[cfg(feature = "db_test")]
+#[tokio::test]
+async fn inmemory_storage_same_as_database() {
+    use crate::storage_interactor::database::DatabaseStorageInteractor;
+    use zklink_storage::StorageProcessor;
+
+    let mut interactor = InMemoryStorageInteractor::new();
+    let inmemory_snapshot = run_recover_sequence(&mut interactor).await;
+
+    let mut storage = StorageProcessor::mock().await.unwrap();
+    let transaction = storage.start_transaction().await.unwrap();
+    let mut interactor = DatabaseStorageInteractor::new(transaction);
+    let database_snapshot = run_recover_sequence(&mut interactor).await;
+
+    assert_eq!(inmemory_snapshot, database_snapshot);
 }
-//
-// impl Transport for Web3Transport {
-//     type Out = dyn Future<Output = Result<Value, web3::Error>> + Send + Unpin;
-//
-//     fn prepare(
-//         &self,
-//         method: &str,
-//         params: Vec<Value>,
-//     ) -> (RequestId, jsonrpc_core::Call) {
-//         (
-//             1,
-//             jsonrpc_core::Call::MethodCall(jsonrpc_core::MethodCall {
-//                 jsonrpc: Some(jsonrpc_core::Version::V2),
-//                 method: method.to_string(),
-//                 params: Params::Array(params),
-//                 id: jsonrpc_core::Id::Num(1),
-//             }),
-//         )
-//     }
-//
-//     fn send(&self, _id: RequestId, request: jsonrpc_core::Call) -> Box<Self::Out> {
-//         Box::new(future::ready({
-//             if let jsonrpc_core::Call::MethodCall(req) = request {
-//                 let mut params = if let Params::Array(params) = req.params {
-//                     params
-//                 } else {
-//                     unreachable!()
-//                 };
-//                 match req.method.as_str() {
-//                     "eth_blockNumber" => Ok(json!("0x80")),
-//                     "eth_getLogs" => {
-//                         let filter = params.pop().unwrap();
-//                         Ok(json!(self.get_logs(filter)))
-//                     }
-//                     "eth_getTransactionByHash" => {
-//                         // TODO Cut `"` from start and end of the string
-//                         let hash = &format!("{}", params.pop().unwrap())[1..67];
-//                         if let Some(transaction) = self.transactions.get(hash) {
-//                             Ok(json!(transaction))
-//                         } else {
-//                             unreachable!()
-//                         }
-//                     }
-//                     "eth_call" => {
-//                         // Now it's call only for one function totalVerifiedBlocks later,
-//                         // if it's necessary, add more complex logic for routing
-//                         Ok(json!(format!("{:#066x}", self.last_block)))
-//                     }
-//                     _ => Err(web3::Error::Unreachable),
-//                 }
-//             } else {
-//                 Err(web3::Error::Unreachable)
-//             }
-//         }))
-//     }
-// }
-
-// #[ignore]
-// #[tokio::test]
-// async fn test_with_inmemory_storage() {
-//     let contract_addr = H160::from([1u8; 20]);
-//     // Start with V3, upgrade it after a couple of blocks to V4.
-//     let init_contract_version: u32 = 3;
-//     let contract_upgrade_eth_blocks = vec![3];
-//
-//     let mut transport = Web3Transport::new();
-//
-//     let mut interactor = InMemoryStorageInteractor::new();
-//     let contract = load_abi(ZKLINK_JSON);
-//
-//     let block_verified_topic = contract
-//         .event("BlockVerification")
-//         .expect("Main contract abi error")
-//         .signature();
-//     let block_verified_topic_string = format!("{:?}", block_verified_topic);
-//     // Starting from Eth block number 3 the version is upgraded.
-//     transport.insert_logs(
-//         block_verified_topic_string,
-//         vec![
-//             create_log(
-//                 contract_addr,
-//                 block_verified_topic,
-//                 vec![u32_to_32bytes(1).into()],
-//                 vec![].into(),
-//                 1,
-//                 u32_to_32bytes(1).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_verified_topic,
-//                 vec![u32_to_32bytes(2).into()],
-//                 vec![].into(),
-//                 2,
-//                 u32_to_32bytes(2).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_verified_topic,
-//                 vec![u32_to_32bytes(3).into()],
-//                 vec![].into(),
-//                 3,
-//                 u32_to_32bytes(3).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_verified_topic,
-//                 vec![u32_to_32bytes(4).into()],
-//                 vec![].into(),
-//                 4,
-//                 u32_to_32bytes(3).into(),
-//             ),
-//         ],
-//     );
-//
-//     let block_committed_topic = contract
-//         .event("BlockCommit")
-//         .expect("Main contract abi error")
-//         .signature();
-//     let block_commit_topic_string = format!("{:?}", block_committed_topic);
-//     transport.insert_logs(
-//         block_commit_topic_string,
-//         vec![
-//             create_log(
-//                 contract_addr,
-//                 block_committed_topic,
-//                 vec![u32_to_32bytes(1).into()],
-//                 vec![].into(),
-//                 1,
-//                 u32_to_32bytes(1).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_committed_topic,
-//                 vec![u32_to_32bytes(2).into()],
-//                 vec![].into(),
-//                 2,
-//                 u32_to_32bytes(2).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_committed_topic,
-//                 vec![u32_to_32bytes(3).into()],
-//                 vec![].into(),
-//                 3,
-//                 u32_to_32bytes(3).into(),
-//             ),
-//             create_log(
-//                 contract_addr,
-//                 block_committed_topic,
-//                 vec![u32_to_32bytes(4).into()],
-//                 vec![].into(),
-//                 4,
-//                 u32_to_32bytes(3).into(),
-//             ),
-//         ],
-//     );
-//
-//     let reverted_topic = contract
-//         .event("BlocksRevert")
-//         .expect("Main contract abi error")
-//         .signature();
-//     let _reverted_topic_string = format!("{:?}", reverted_topic);
-//
-//     let new_token_topic = contract
-//         .event("NewToken")
-//         .expect("Main contract abi error")
-//         .signature();
-//     let new_token_topic_string = format!("{:?}", new_token_topic);
-//     transport.insert_logs(
-//         new_token_topic_string,
-//         vec![create_log(
-//             contract_addr,
-//             new_token_topic,
-//             vec![[0; 32].into(), u32_to_32bytes(3).into()],
-//             vec![].into(),
-//             3,
-//             u32_to_32bytes(1).into(),
-//         )],
-//     );
-//
-//     transport.push_transactions(vec![
-//         create_transaction(
-//             1,
-//             create_block(
-//                 BlockNumber(1),
-//                 vec![create_deposit(Default::default(), Default::default(), 50)],
-//             ),
-//         ),
-//         create_transaction(
-//             2,
-//             create_block(
-//                 BlockNumber(2),
-//                 vec![create_withdraw_operations(
-//                     AccountId(0),
-//                     Default::default(),
-//                     10,
-//                 )],
-//             ),
-//         ),
-//         create_transaction_v4(
-//             3,
-//             create_block(
-//                 BlockNumber(2),
-//                 vec![create_deposit(Default::default(), Default::default(), 50)],
-//             ),
-//             vec![
-//                 create_block(
-//                     BlockNumber(3),
-//                     vec![create_deposit(Default::default(), Default::default(), 50)],
-//                 ),
-//                 create_block(
-//                     BlockNumber(4),
-//                     vec![create_withdraw_operations(
-//                         AccountId(0),
-//                         Default::default(),
-//                         10,
-//                     )],
-//                 ),
-//             ],
-//         ),
-//     ]);
-//
-//
-//     let mut driver = RecoverStateDriver::new(
-//         contract_addr,
-//         contract_upgrade_eth_blocks.clone(),
-//         init_contract_version,
-//         init_contract_version,
-//         VIEW_BLOCKS_STEP,
-//         END_BLOCK_OFFSET,
-//         true,
-//         None,
-//     );
-//
-//     driver.recover_state(&mut interactor).await;
-//
-//     // Check that it's stores some account, created by deposit
-//     let (_, account) = interactor
-//         .get_account_by_address(&Default::default())
-//         .unwrap();
-//     let balance = account.get_balance(TokenId(0));
-//
-//     assert_eq!(BigUint::from(80u32), balance);
-//     assert_eq!(driver.rollup_events.committed_events.len(), 4);
-//     let events = interactor.load_committed_events_state();
-//
-//     assert_eq!(driver.rollup_events.committed_events.len(), events.len());
-//
-//     // Nullify the state of driver
-//     let mut driver = RecoverStateDriver::new(
-//         contract_addr,
-//         contract_upgrade_eth_blocks,
-//         init_contract_version,
-//         VIEW_BLOCKS_STEP,
-//         END_BLOCK_OFFSET,
-//         true,
-//         None,
-//         // ZkLinkEvmContract::version4(eth, [1u8; 20].into()),
-//     );
-//
-//     // Load state from db and check it
-//     assert!(driver.load_state_from_storage(&mut interactor).await);
-//     assert_eq!(driver.rollup_events.committed_events.len(), events.len());
-//     assert_eq!(*driver.tree_state.state.block_number, 4)
-// }
@@ -2,7 +2,7 @@ use crate::rollup_ops::RollupOpsBlock;
 use anyhow::format_err;
 use parity_crypto::Keccak256;
 use std::collections::HashMap;
-use tracing::info;
+use tracing::{info, warn};
 use zklink_crypto::convert::FeConvert;
 use zklink_crypto::Fr;
 use zklink_state::state::TransferOutcome;
@@ -13,8 +13,9 @@ use zklink_state::{
 use zklink_types::block::{Block, ExecutedTx};
 use zklink_types::operations::ZkLinkOp;
 use zklink_types::{
-    Account, AccountId, AccountMap, AccountUpdate, BlockNumber, ChainId, ChangePubKey, Deposit,
-    ForcedExit, FullExit, OrderMatching, Transfer, Withdraw, ZkLinkAddress, H256,
+    Account, AccountId, AccountMap, AccountUpdate, AccountUpdates, BalanceUnderflow, BlockNumber,
+    ChainId, ChangePubKey, Deposit, ForcedExit, FullExit, OrderMatching, Transfer, Withdraw,
+    ZkLinkAddress, H256,
 };
 
 type BlockAndUpdates = (Block, Vec<(AccountId, AccountUpdate, H256)>);
@@ -79,9 +80,17 @@ impl TreeState {
 
     /// Updates Rollup accounts states from Rollup operations block
     /// Returns current rollup block and updated accounts
+    ///
+    /// # Arguments
+    ///
+    /// * `ops_block` - Rollup operations block
+    /// * `strict` - Whether to abort on the op whose balance underflows, otherwise
+    /// the op is recorded as a failed tx without any account update.
+    ///
     pub fn apply_ops_block(
         &mut self,
         ops_block: &RollupOpsBlock,
+        strict: bool,
     ) -> Result<BlockAndUpdates, anyhow::Error> {
         info!("Applying layer2 block[{:?}]", ops_block.block_num);
         assert_eq!(self.state.block_number + 1, ops_block.block_num);
@@ -113,6 +122,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        None,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -126,9 +136,10 @@ impl TreeState {
                     op.tx.nonce = from.nonce;
 
                     let mut op = TransferOutcome::TransferToNew(*op);
-                    let updates =
-                        <ZkLinkState as TxHandler<Transfer>>::apply_op(&mut self.state, &mut op)
-                            .map_err(|e| format_err!("TransferToNew fail: {}", e))?;
+                    let result =
+                        <ZkLinkState as TxHandler<Transfer>>::apply_op(&mut self.state, &mut op);
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "TransferToNew")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: op.into_franklin_op(),
@@ -136,6 +147,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -155,9 +167,10 @@ impl TreeState {
                     op.tx.nonce = from.nonce;
 
                     let mut op = TransferOutcome::Transfer(*op);
-                    let updates =
-                        <ZkLinkState as TxHandler<Transfer>>::apply_op(&mut self.state, &mut op)
-                            .map_err(|e| format_err!("Transfer fail: {}", e))?;
+                    let result =
+                        <ZkLinkState as TxHandler<Transfer>>::apply_op(&mut self.state, &mut op);
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "Transfer")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: op.into_franklin_op(),
@@ -165,6 +178,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -178,9 +192,10 @@ impl TreeState {
                         .ok_or_else(|| format_err!("Withdraw fail: Nonexistent account"))?;
                     op.tx.nonce = account.nonce;
 
-                    let updates =
-                        <ZkLinkState as TxHandler<Withdraw>>::apply_op(&mut self.state, &mut op)
-                            .map_err(|e| format_err!("Withdraw fail: {}", e))?;
+                    let result =
+                        <ZkLinkState as TxHandler<Withdraw>>::apply_op(&mut self.state, &mut op);
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "Withdraw")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: (*op).into(),
@@ -188,6 +203,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -205,9 +221,10 @@ impl TreeState {
                     // Set the fields unknown from the pubdata.
                     op.tx.nonce = initiator_account.nonce;
 
-                    let updates =
-                        <ZkLinkState as TxHandler<ForcedExit>>::apply_op(&mut self.state, &mut op)
-                            .map_err(|e| format_err!("ForcedExit fail: {}", e))?;
+                    let result =
+                        <ZkLinkState as TxHandler<ForcedExit>>::apply_op(&mut self.state, &mut op);
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "ForcedExit")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: (*op).into(),
@@ -215,6 +232,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -224,9 +242,10 @@ impl TreeState {
                     let mut op =
                         <ZkLinkState as TxHandler<FullExit>>::create_op(&self.state, op.tx)
                             .map_err(|e| format_err!("Create FullExit fail: {}", e))?;
-                    let updates =
-                        <ZkLinkState as TxHandler<FullExit>>::apply_op(&mut self.state, &mut op)
-                            .map_err(|e| format_err!("FullExit fail: {}", e))?;
+                    let result =
+                        <ZkLinkState as TxHandler<FullExit>>::apply_op(&mut self.state, &mut op);
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "FullExit")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: op.into(),
@@ -234,6 +253,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -245,11 +265,12 @@ impl TreeState {
                     })?;
                     op.tx.nonce = account.nonce;
 
-                    let updates = <ZkLinkState as TxHandler<ChangePubKey>>::apply_op(
+                    let result = <ZkLinkState as TxHandler<ChangePubKey>>::apply_op(
                         &mut self.state,
                         &mut op,
-                    )
-                    .map_err(|e| format_err!("ChangePubKeyOffchain fail: {}", e))?;
+                    );
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "ChangePubKeyOffchain")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: (*op).into(),
@@ -257,17 +278,19 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
                     );
                 }
                 ZkLinkOp::OrderMatching(mut op) => {
-                    let updates = <ZkLinkState as TxHandler<OrderMatching>>::unsafe_apply_op(
+                    let result = <ZkLinkState as TxHandler<OrderMatching>>::unsafe_apply_op(
                         &mut self.state,
                         &mut op,
-                    )
-                    .map_err(|e| format_err!("OrderMatching fail: {}", e))?;
+                    );
+                    let (updates, fail_reason) =
+                        Self::check_apply_result(result, strict, "OrderMatching")?;
                     let tx_result = OpSuccess {
                         updates,
                         executed_op: (*op).into(),
@@ -275,6 +298,7 @@ impl TreeState {
 
                     current_op_block_index = self.update_from_tx(
                         tx_result,
+                        fail_reason,
                         &mut accounts_updated,
                         current_op_block_index,
                         &mut ops,
@@ -324,12 +348,39 @@ impl TreeState {
         Ok((block, accounts_updated))
     }
 
+    /// Splits the result of applying an op into the account updates and the fail reason.
+    /// The balance underflow is tolerated unless in strict mode, and the failed op leaves
+    /// the state untouched as the handlers only insert the updated accounts on success.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of applying the op
+    /// * `strict` - Whether to abort on the balance underflow
+    /// * `op_name` - The op name used in the error message
+    ///
+    fn check_apply_result(
+        result: Result<AccountUpdates, anyhow::Error>,
+        strict: bool,
+        op_name: &str,
+    ) -> Result<(AccountUpdates, Option<String>), anyhow::Error> {
+        match result {
+            Ok(updates) => Ok((updates, None)),
+            Err(e) if !strict && e.downcast_ref::<BalanceUnderflow>().is_some() => {
+                let fail_reason = format!("{} fail: {}", op_name, e);
+                warn!("Recording the op as a failed tx: {}", fail_reason);
+                Ok((Vec::new(), Some(fail_reason)))
+            }
+            Err(e) => Err(format_err!("{} fail: {}", op_name, e)),
+        }
+    }
+
     /// Updates the list of accounts that has been updated, aggregates fees, updates blocks operations list from Rollup transaction
     /// Returns current operation index
     ///
     /// # Arguments
     ///
     /// * `op_result` - Rollup transaction execution result
+    /// * `fail_reason` - The reason why the transaction failed, `None` if succeeded
     /// * `accounts_updated` - Updated accounts
     /// * `current_op_block_index` - Current operation index
     /// * `ops` - Current block operations list
@@ -337,6 +388,7 @@ impl TreeState {
     fn update_from_tx(
         &mut self,
         tx_result: OpSuccess,
+        fail_reason: Option<String>,
         accounts_updated: &mut Vec<(AccountId, AccountUpdate, H256)>,
         current_op_block_index: u32,
         ops: &mut Vec<ExecutedTx>,
@@ -369,9 +421,9 @@ impl TreeState {
         let block_index = current_op_block_index;
         let exec_result = ExecutedTx {
             tx,
-            success: true,
+            success: fail_reason.is_none(),
             op: executed_op,
-            fail_reason: None,
+            fail_reason,
             block_index: Some(block_index),
             created_at: chrono::Utc::now(),
         };
@@ -402,16 +454,18 @@ impl TreeState {
 
 #[cfg(test)]
 mod test {
-    use crate::contract::default::get_rollup_ops_from_data;
     use crate::contract::utils::get_rollup_ops_from_data;
+    use crate::contract::ZkLinkContractVersion;
     use crate::rollup_ops::RollupOpsBlock;
     use crate::tree_state::TreeState;
     use num::BigUint;
+    use std::collections::HashMap;
+    use zklink_crypto::convert::FeConvert;
     use zklink_types::{
-        AccountId, BlockNumber, ChainId, ChangePubKey, ChangePubKeyOp, Deposit, DepositOp,
-        ForcedExit, ForcedExitOp, FullExit, FullExitOp, Nonce, Order, OrderMatching,
-        OrderMatchingOp, PubKeyHash, SlotId, SubAccountId, TokenId, Transfer, TransferOp,
-        TransferToNewOp, Withdraw, WithdrawOp, ZkLinkOp,
+        Account, AccountId, AccountMap, BlockNumber, ChainId, ChangePubKey, ChangePubKeyOp,
+        Deposit, DepositOp, ForcedExit, ForcedExitOp, FullExit, FullExitOp, Nonce, Order,
+        OrderMatching, OrderMatchingOp, PubKeyHash, SlotId, SubAccountId, TokenId, Transfer,
+        TransferOp, TransferToNewOp, Withdraw, WithdrawOp, ZkLinkOp, H256,
     };
 
     const ZKL_TOKEN: TokenId = TokenId(32);
@@ -924,7 +978,7 @@ mod test {
 
         let mut tree = TreeState::default();
         let available_block_chunk_sizes = vec![10, 32, 72, 156, 322, 654];
-        tree.apply_ops_block(&block)
+        tree.apply_ops_block(&block, true)
             .expect("Cant update state from block");
 
         assert_eq!(tree.get_accounts().len(), 2);
@@ -938,4 +992,162 @@ mod test {
         assert_eq!(first_acc.address, vec![8u8; 20].into());
         assert_eq!(first_acc.get_balance(TokenId(1)), BigUint::from(0u32));
     }
+
+    /// Creates the tree whose account 2 has 100 ZKL and the global asset account has no token.
+    fn create_underflow_tree() -> TreeState {
+        let mut target_account = Account::default_with_address(&vec![7u8; 20].into());
+        target_account.add_balance(ZKL_TOKEN, &BigUint::from(100u32));
+        let mut accounts = AccountMap::default();
+        accounts.insert(
+            AccountId(0),
+            Account::default_with_address(&vec![9u8; 20].into()),
+        );
+        accounts.insert(
+            AccountId(1),
+            Account::default_with_address(&vec![8u8; 20].into()),
+        );
+        accounts.insert(AccountId(2), target_account);
+        TreeState::load(
+            H256::default(),
+            BlockNumber(0),
+            HashMap::from([(ChainId(1), -1)]),
+            accounts,
+            AccountId(0),
+        )
+    }
+
+    /// Applies the op whose balance underflows to the tree of `create_underflow_tree`,
+    /// checks that it aborts in strict mode and is recorded as a failed tx otherwise.
+    fn assert_balance_underflow(op: ZkLinkOp) {
+        let tree = create_underflow_tree();
+        let block = RollupOpsBlock {
+            block_num: BlockNumber(1),
+            ops: vec![op],
+            fee_account: AccountId(0),
+            timestamp: None,
+            previous_block_root_hash: H256::from_slice(&tree.root_hash().to_bytes()),
+            contract_version: Some(ZkLinkContractVersion::V0),
+        };
+
+        // Abort in strict mode without touching the state.
+        let mut tree = create_underflow_tree();
+        let err = tree.apply_ops_block(&block, true).unwrap_err();
+        assert!(err.to_string().contains("Balance underflow"));
+        assert_eq!(tree.state.block_number, BlockNumber(0));
+        let target_account = tree.get_account(AccountId(2)).unwrap();
+        assert_eq!(target_account.get_balance(ZKL_TOKEN), BigUint::from(100u32));
+        assert_eq!(target_account.nonce, Nonce(0));
+
+        // Otherwise the op is recorded as a failed tx.
+        let mut tree = create_underflow_tree();
+        let (block, updates) = tree
+            .apply_ops_block(&block, false)
+            .expect("Balance underflow should be tolerated");
+        assert!(updates.is_empty());
+        assert_eq!(block.block_transactions.len(), 1);
+        let failed_tx = &block.block_transactions[0];
+        assert!(!failed_tx.success);
+        assert!(failed_tx
+            .fail_reason
+            .as_ref()
+            .unwrap()
+            .contains("Balance underflow"));
+        let target_account = tree.get_account(AccountId(2)).unwrap();
+        assert_eq!(target_account.get_balance(ZKL_TOKEN), BigUint::from(100u32));
+        assert_eq!(target_account.nonce, Nonce(0));
+    }
+
+    #[test]
+    fn test_balance_underflow() {
+        // The global asset account has no token of the chain to forced exit to.
+        let tx = ForcedExit::new(
+            ChainId(1),
+            AccountId(0),
+            SubAccountId(0),
+            vec![7u8; 20].into(),
+            SubAccountId(0),
+            ZKL_TOKEN,
+            ZKL_TOKEN,
+            ZKL_TOKEN,
+            BigUint::from(0u32),
+            Nonce(0),
+            None,
+            Default::default(),
+        );
+        assert_balance_underflow(ZkLinkOp::ForcedExit(Box::new(ForcedExitOp {
+            tx,
+            target_account_id: AccountId(2),
+            withdraw_amount: BigUint::from(100u32).into(),
+            l1_target_token_after_mapping: ZKL_TOKEN,
+        })));
+    }
+
+    #[test]
+    fn test_transfer_balance_underflow() {
+        // The amount and fee are more than the balance.
+        let tx = Transfer::new(
+            AccountId(2),
+            vec![9u8; 20].into(),
+            SubAccountId(0),
+            SubAccountId(0),
+            ZKL_TOKEN,
+            BigUint::from(100u32),
+            BigUint::from(1u32),
+            Nonce(0),
+            None,
+            Default::default(),
+        );
+        assert_balance_underflow(ZkLinkOp::Transfer(Box::new(TransferOp {
+            tx,
+            from: AccountId(2),
+            to: AccountId(0),
+        })));
+    }
+
+    #[test]
+    fn test_withdraw_balance_underflow() {
+        // The account has enough balance, but the global asset account has no token to withdraw.
+        let tx = Withdraw::new(
+            AccountId(2),
+            SubAccountId(0),
+            ChainId(1),
+            vec![7u8; 20].into(),
+            ZKL_TOKEN,
+            ZKL_TOKEN,
+            BigUint::from(50u32),
+            BigUint::from(1u32),
+            Nonce(0),
+            false,
+            0,
+            None,
+            Default::default(),
+        );
+        assert_balance_underflow(ZkLinkOp::Withdraw(Box::new(WithdrawOp {
+            tx,
+            account_id: AccountId(2),
+            l1_target_token_after_mapping: ZKL_TOKEN,
+        })));
+    }
+
+    #[test]
+    fn test_change_pubkey_balance_underflow() {
+        // The fee is more than the balance.
+        let tx = ChangePubKey::new(
+            ChainId(1),
+            AccountId(2),
+            SubAccountId(0),
+            PubKeyHash::from_hex("sync:0000000000000000000000000000000000000001").unwrap(),
+            ZKL_TOKEN,
+            BigUint::from(101u32),
+            Nonce(0),
+            None,
+            None,
+            Default::default(),
+        );
+        assert_balance_underflow(ZkLinkOp::ChangePubKeyOffchain(Box::new(ChangePubKeyOp {
+            tx,
+            account_id: AccountId(2),
+            address: Default::default(),
+        })));
+    }
 }
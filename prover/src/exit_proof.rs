@@ -3,25 +3,69 @@
 use crate::proving_cache::ProvingCache;
 use anyhow::format_err;
 use num::BigUint;
-use recover_state_config::RecoverStateConfig;
+use recover_state_config::ProverConfig;
 use std::fs::File;
 use std::time::Instant;
 use tracing::info;
-use zklink_circuit::witness::create_exit_circuit_with_public_input;
+use zklink_circuit::witness::{create_exit_circuit_with_public_input, get_exit_balance_and_sum};
 use zklink_crypto::bellman::plonk::better_cs::{keys::VerificationKey, verifier::verify};
 use zklink_crypto::bellman::plonk::{
     commitments::transcript::keccak_transcript::RollingKeccakTranscript, prove_by_steps,
 };
 use zklink_crypto::circuit::CircuitAccountTree;
+use zklink_crypto::franklin_crypto::bellman::pairing::ff::Field;
 use zklink_crypto::franklin_crypto::bellman::Circuit;
 use zklink_crypto::proof::EncodedSingleProof;
 use zklink_crypto::proof::SingleProof;
 use zklink_crypto::{Engine, Fr};
 use zklink_types::{AccountId, ChainId, SubAccountId, TokenId};
 
+/// The number of the latest proofs whose p99 total time is checked against the baseline.
+pub const PROOF_TIMINGS_WINDOW: usize = 1000;
+/// The p99 regression(in percent) over the baseline that is warned.
+pub const P99_REGRESSION_PERCENT: u64 = 20;
+
+/// The time(in milliseconds) spent on each phase of generating an exit proof.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProofTimings {
+    /// Generating the witness of the exit circuit.
+    pub witness_ms: u64,
+    /// Synthesizing the constraint system and proving(including the verification of the proof).
+    pub synthesis_ms: u64,
+    /// Serializing the proof.
+    pub serialization_ms: u64,
+    pub total_ms: u64,
+}
+
+/// The total times of the latest proofs, reports the p99 once every `PROOF_TIMINGS_WINDOW` proofs.
+#[derive(Debug, Default)]
+pub struct ProofTimingsWindow {
+    total_ms: Vec<u64>,
+}
+
+impl ProofTimingsWindow {
+    /// Records the total time of a proof, returns the p99 of the window once it's full
+    /// and starts the next window.
+    pub fn record(&mut self, total_ms: u64) -> Option<u64> {
+        self.total_ms.push(total_ms);
+        if self.total_ms.len() < PROOF_TIMINGS_WINDOW {
+            return None;
+        }
+        let mut total_ms = std::mem::take(&mut self.total_ms);
+        total_ms.sort_unstable();
+        let p99_index = (total_ms.len() * 99).div_ceil(100) - 1;
+        Some(total_ms[p99_index])
+    }
+}
+
+/// Whether the p99 total time regresses more than `P99_REGRESSION_PERCENT` over the baseline.
+pub fn is_p99_regressed(p99_ms: u64, baseline_ms: u64) -> bool {
+    p99_ms * 100 > baseline_ms * (100 + P99_REGRESSION_PERCENT)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn create_exit_proof(
-    config: &RecoverStateConfig,
+    config: &ProverConfig,
     circuit_account_tree: &CircuitAccountTree,
     cache: &ProvingCache,
     account_id: AccountId,
@@ -31,6 +75,42 @@ pub fn create_exit_proof(
     chain_id: ChainId,
     max_chain_num: usize,
 ) -> Result<(EncodedSingleProof, BigUint), anyhow::Error> {
+    let (proof, withdraw_amount, _) = create_exit_proof_with_timings(
+        config,
+        circuit_account_tree,
+        cache,
+        account_id,
+        sub_account_id,
+        l2_source_token,
+        l1_target_token,
+        chain_id,
+        max_chain_num,
+    )?;
+    Ok((proof, withdraw_amount))
+}
+
+/// Same as `create_exit_proof`, but also returns the time spent on each phase.
+#[allow(clippy::too_many_arguments)]
+pub fn create_exit_proof_with_timings(
+    config: &ProverConfig,
+    circuit_account_tree: &CircuitAccountTree,
+    cache: &ProvingCache,
+    account_id: AccountId,
+    sub_account_id: SubAccountId,
+    l2_source_token: TokenId,
+    l1_target_token: TokenId,
+    chain_id: ChainId,
+    max_chain_num: usize,
+) -> Result<(EncodedSingleProof, BigUint, ProofTimings), anyhow::Error> {
+    check_exit_balance(
+        circuit_account_tree,
+        account_id,
+        sub_account_id,
+        l2_source_token,
+        l1_target_token,
+        max_chain_num,
+    )?;
+
     let timer = Instant::now();
     let (exit_circuit, withdraw_amount) = create_exit_circuit_with_public_input(
         circuit_account_tree,
@@ -41,18 +121,67 @@ pub fn create_exit_proof(
         chain_id,
         max_chain_num,
     );
-    info!("Exit witness generated: {} s", timer.elapsed().as_secs());
+    let witness_elapsed = timer.elapsed();
+    info!("Exit witness generated: {} s", witness_elapsed.as_secs());
 
     let proof = gen_verified_proof_for_exit_circuit(config, exit_circuit, cache)
         .map_err(|e| format_err!("Failed to generate proof: {}", e))?;
+    let synthesis_elapsed = timer.elapsed() - witness_elapsed;
+
+    let proof = proof.serialize_single_proof();
+    let total_elapsed = timer.elapsed();
+    info!("Exit proof created: {} s", total_elapsed.as_secs());
 
-    info!("Exit proof created: {} s", timer.elapsed().as_secs());
-    Ok((proof.serialize_single_proof(), withdraw_amount))
+    let timings = ProofTimings {
+        witness_ms: witness_elapsed.as_millis() as u64,
+        synthesis_ms: synthesis_elapsed.as_millis() as u64,
+        serialization_ms: (total_elapsed - witness_elapsed - synthesis_elapsed).as_millis() as u64,
+        total_ms: total_elapsed.as_millis() as u64,
+    };
+    Ok((proof, withdraw_amount, timings))
+}
+
+/// Checks that the account exists, and both the balance of the account and the sum of the
+/// global asset account balances are nonzero, otherwise the withdraw amount is meaningless.
+pub fn check_exit_balance(
+    circuit_account_tree: &CircuitAccountTree,
+    account_id: AccountId,
+    sub_account_id: SubAccountId,
+    l2_source_token: TokenId,
+    l1_target_token: TokenId,
+    max_chain_num: usize,
+) -> Result<(), anyhow::Error> {
+    anyhow::ensure!(
+        circuit_account_tree.get(*account_id).is_some(),
+        "Account {:?} does not exist in the account tree",
+        account_id
+    );
+    let (balance, sum) = get_exit_balance_and_sum(
+        circuit_account_tree,
+        account_id,
+        sub_account_id,
+        l2_source_token,
+        l1_target_token,
+        max_chain_num,
+    );
+    anyhow::ensure!(
+        !balance.is_zero(),
+        "NonBalance: the token {:?} of account {:?}(sub account {:?}) has no balance",
+        l2_source_token,
+        account_id,
+        sub_account_id
+    );
+    anyhow::ensure!(
+        !sum.is_zero(),
+        "NonBalance: the global asset account has no balance of the token {:?}",
+        l2_source_token
+    );
+    Ok(())
 }
 
 /// Generates proof for exit given circuit using step-by-step algorithm.
 pub fn gen_verified_proof_for_exit_circuit<C: Circuit<Engine> + Clone>(
-    config: &RecoverStateConfig,
+    config: &ProverConfig,
     circuit: C,
     cache: &ProvingCache,
 ) -> Result<SingleProof, anyhow::Error> {
@@ -76,3 +205,87 @@ pub fn gen_verified_proof_for_exit_circuit<C: Circuit<Engine> + Clone>(
     info!("Proof for circuit successful");
     Ok(proof.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use zklink_crypto::circuit::account::{Balance, CircuitAccount};
+    use zklink_crypto::circuit::CircuitAccountTree;
+    use zklink_crypto::convert::FeConvert;
+    use zklink_crypto::params::{account_tree_depth, GLOBAL_ASSET_ACCOUNT_ID};
+    use zklink_crypto::Fr;
+    use zklink_types::utils::calculate_actual_token;
+    use zklink_types::{AccountId, SubAccountId, TokenId};
+
+    use super::{check_exit_balance, is_p99_regressed, ProofTimingsWindow, PROOF_TIMINGS_WINDOW};
+
+    #[test]
+    fn proof_timings_window_reports_p99() {
+        let mut window = ProofTimingsWindow::default();
+        for total_ms in (1..PROOF_TIMINGS_WINDOW as u64).rev() {
+            assert_eq!(window.record(total_ms), None);
+        }
+        assert_eq!(
+            window.record(PROOF_TIMINGS_WINDOW as u64),
+            Some(PROOF_TIMINGS_WINDOW as u64 * 99 / 100)
+        );
+
+        // The next window starts empty.
+        for _ in 1..PROOF_TIMINGS_WINDOW {
+            assert_eq!(window.record(5), None);
+        }
+        assert_eq!(window.record(5), Some(5));
+    }
+
+    #[test]
+    fn p99_regression() {
+        assert!(!is_p99_regressed(1000, 1000));
+        assert!(!is_p99_regressed(1200, 1000));
+        assert!(is_p99_regressed(1201, 1000));
+        assert!(!is_p99_regressed(0, 0));
+        assert!(is_p99_regressed(1, 0));
+    }
+
+    #[test]
+    fn exit_balance_mismatch() {
+        let (account_id, sub_account_id, token_id) = (AccountId(10), SubAccountId(1), TokenId(18));
+        let max_chain_num = 4;
+        let balance = |sub_account_id, value| {
+            let mut account = CircuitAccount::default();
+            account.subtree.insert(
+                *calculate_actual_token(sub_account_id, token_id),
+                Balance {
+                    value: Fr::from_u64(value),
+                },
+            );
+            account
+        };
+        let check = |account_tree: &CircuitAccountTree| {
+            check_exit_balance(
+                account_tree,
+                account_id,
+                sub_account_id,
+                token_id,
+                token_id,
+                max_chain_num,
+            )
+            .map_err(|e| e.to_string())
+        };
+        let mut account_tree = CircuitAccountTree::new(account_tree_depth());
+        account_tree.insert(*GLOBAL_ASSET_ACCOUNT_ID, balance(SubAccountId(2), 100));
+        assert!(check(&account_tree).unwrap_err().contains("does not exist"));
+
+        // The account has no balance of the token, though the global asset account has.
+        account_tree.insert(*account_id, CircuitAccount::default());
+        assert!(check(&account_tree).unwrap_err().contains("has no balance"));
+
+        // The account has a balance of the token, but the global asset account has none.
+        account_tree.insert(*account_id, balance(sub_account_id, 10));
+        account_tree.insert(*GLOBAL_ASSET_ACCOUNT_ID, CircuitAccount::default());
+        assert!(check(&account_tree)
+            .unwrap_err()
+            .contains("global asset account has no balance"));
+
+        account_tree.insert(*GLOBAL_ASSET_ACCOUNT_ID, balance(SubAccountId(2), 100));
+        assert_eq!(check(&account_tree), Ok(()));
+    }
+}
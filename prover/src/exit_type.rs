@@ -2,7 +2,7 @@ use num::bigint::ToBigInt;
 use serde::{Deserialize, Serialize};
 use zklink_crypto::proof::EncodedSingleProof;
 use zklink_storage::prover::records::{StoredExitInfo, StoredExitProof};
-use zklink_types::{AccountId, ChainId, SubAccountId, TokenId, ZkLinkAddress};
+use zklink_types::{AccountId, ChainId, SubAccountId, TokenId, ZkLinkAddress, H256};
 use zklink_utils::BigUintSerdeWrapper;
 
 pub type ProofId = u64;
@@ -27,6 +27,11 @@ impl ProofInfo {
             ..Default::default()
         }
     }
+
+    /// Returns whether the proof has been generated.
+    pub fn is_completed(&self) -> bool {
+        self.proof.is_some()
+    }
 }
 
 impl From<&ExitProofData> for StoredExitProof {
@@ -50,6 +55,12 @@ impl From<&ExitProofData> for StoredExitProof {
                 .map(|proof| serde_json::to_value(proof).unwrap()),
             created_at: None,
             finished_at: None,
+            cancelled_at: None,
+            request_id: None,
+            started_at: None,
+            proof_size_bytes: None,
+            prove_duration_ms: None,
+            priority: 0,
         }
     }
 }
@@ -71,7 +82,7 @@ impl From<StoredExitProof> for ExitProofData {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExitInfo {
     pub chain_id: ChainId,
     pub account_address: ZkLinkAddress,
@@ -79,6 +90,35 @@ pub struct ExitInfo {
     pub sub_account_id: SubAccountId,
     pub l1_target_token: TokenId,
     pub l2_source_token: TokenId,
+    /// The state root the client quoted the exit against, the task is rejected by the server if the current
+    /// root differs from it. It's a precondition of the request rather than a part of the exit, so it's neither
+    /// stored nor compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_root: Option<H256>,
+}
+
+impl PartialEq for ExitInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.chain_id == other.chain_id
+            && self.account_address == other.account_address
+            && self.account_id == other.account_id
+            && self.sub_account_id == other.sub_account_id
+            && self.l1_target_token == other.l1_target_token
+            && self.l2_source_token == other.l2_source_token
+    }
+}
+
+impl Eq for ExitInfo {}
+
+impl std::hash::Hash for ExitInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.chain_id.hash(state);
+        self.account_address.hash(state);
+        self.account_id.hash(state);
+        self.sub_account_id.hash(state);
+        self.l1_target_token.hash(state);
+        self.l2_source_token.hash(state);
+    }
 }
 
 impl From<&StoredExitProof> for ExitInfo {
@@ -90,6 +130,21 @@ impl From<&StoredExitProof> for ExitInfo {
             sub_account_id: value.sub_account_id.into(),
             l1_target_token: value.l1_target_token.into(),
             l2_source_token: value.l2_source_token.into(),
+            expected_root: None,
+        }
+    }
+}
+
+impl From<&StoredExitInfo> for ExitInfo {
+    fn from(value: &StoredExitInfo) -> Self {
+        Self {
+            chain_id: value.chain_id.into(),
+            account_address: Default::default(),
+            account_id: value.account_id.into(),
+            sub_account_id: value.sub_account_id.into(),
+            l1_target_token: value.l1_target_token.into(),
+            l2_source_token: value.l2_source_token.into(),
+            expected_root: None,
         }
     }
 }
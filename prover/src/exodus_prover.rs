@@ -1,9 +1,12 @@
-use crate::exit_proof::create_exit_proof;
+use crate::exit_proof::{
+    create_exit_proof_with_timings, is_p99_regressed, ProofTimings, ProofTimingsWindow,
+    P99_REGRESSION_PERCENT,
+};
 use crate::exit_type::{ExitProofData, ProofInfo};
-use crate::proving_cache::ProvingCache;
+use crate::proving_cache::{CachedProof, ProvingCache};
 use crate::ExitInfo;
-use recover_state_config::RecoverStateConfig;
-use std::sync::Arc;
+use recover_state_config::{DBConfig, ProverConfig};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::interval;
 use tracing::{info, warn};
@@ -14,16 +17,23 @@ use zklink_storage::ConnectionPool;
 use zklink_types::block::Block;
 
 pub struct ExodusProver {
-    config: RecoverStateConfig,
+    config: ProverConfig,
     proving_cache: ProvingCache,
     conn_pool: ConnectionPool,
     circuit_account_tree: CircuitAccountTree,
+    proof_timings: Mutex<ProofTimingsWindow>,
     pub last_executed_block: Block,
 }
 
 impl ExodusProver {
-    pub async fn from_config(config: RecoverStateConfig, proving_cache: ProvingCache) -> Self {
-        let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size);
+    pub async fn from_config(config: ProverConfig, proving_cache: ProvingCache) -> Self {
+        let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size)
+            .with_retry_policy(
+                config.db.connection_retries(),
+                config.db.connection_retry_delay(),
+            )
+            .with_slow_query_threshold(config.db.slow_query_threshold())
+            .with_slow_tx_threshold(config.db.slow_tx_threshold());
         let mut storage = conn_pool
             .access_storage()
             .await
@@ -61,44 +71,71 @@ impl ExodusProver {
             .await
             .expect("Failed to get last verified confirmed block")
             .expect("Block should be existed");
+        // The mismatched decimals break the assumption of the circuit and make the withdraw amounts wrong.
+        for (token_id, chain_decimals) in storage
+            .tokens_schema()
+            .find_decimal_mismatches()
+            .await
+            .expect("Failed to check the token decimals")
+        {
+            warn!(
+                "Token {} has different decimals across chains(chain id, decimals): {:?}",
+                *token_id, chain_decimals
+            );
+        }
         drop(storage);
+        let zklink_home = config.runtime.zklink_home.clone();
+        tokio::spawn(
+            conn_pool
+                .clone()
+                .watch_failover(move || DBConfig::reload_url(&zklink_home)),
+        );
         Self {
             config,
             proving_cache,
             conn_pool,
             circuit_account_tree,
+            proof_timings: Default::default(),
             last_executed_block,
         }
     }
 
     /// The number of tasks that are currently generating proof.
     pub async fn running_tasks_num(&self) -> anyhow::Result<u32> {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+        let mut storage = self.conn_pool.access_storage_with_retry().await?;
         let running_task_num = storage.prover_schema().count_running_tasks().await?;
         Ok(running_task_num as u32)
     }
 
-    pub async fn load_new_task(&self, index: usize) -> anyhow::Result<Option<(i64, ExitInfo)>> {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+    /// Loads a new task, returns the proof id, exit info and the request id that created the task.
+    pub async fn load_new_task(
+        &self,
+        index: usize,
+    ) -> anyhow::Result<Option<(i64, ExitInfo, Option<String>)>> {
+        let mut storage = self.conn_pool.access_storage_with_retry().await?;
         let task = storage
             .prover_schema()
-            .load_exit_proof_task()
+            .load_exit_proof_task(!self.config.skip_balance_ordering)
             .await?
             .map(|t| {
-                info!("[Worker{}] loading new task: {}", index, t);
+                info!(
+                    "[Worker{}] loading new task: {}, request_id: {:?}",
+                    index, t, t.request_id
+                );
                 assert!(
-                    t.created_at.is_none()
+                    t.created_at.is_some()
                         && t.finished_at.is_none()
+                        && t.cancelled_at.is_none()
                         && t.proof.is_none()
                         && t.amount.is_none()
                 );
-                (t.id, ExitInfo::from(&t))
+                (t.id, ExitInfo::from(&t), t.request_id.clone())
             });
         Ok(task)
     }
 
     pub async fn cancel_this_task(&self, exit_info: &ExitInfo) -> anyhow::Result<()> {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+        let mut storage = self.conn_pool.access_storage_with_retry().await?;
         storage
             .prover_schema()
             .cancel_this_exit_proof_task(exit_info.into())
@@ -107,7 +144,11 @@ impl ExodusProver {
     }
 
     pub async fn check_exit_info(&self, mut exit_info: ExitInfo) -> ExitInfo {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+        let mut storage = self
+            .conn_pool
+            .access_storage_with_retry()
+            .await
+            .expect("Db access fail");
         storage
             .tokens_schema()
             .get_token(*exit_info.l1_target_token as i32)
@@ -137,7 +178,34 @@ impl ExodusProver {
     }
 
     pub fn create_exit_proof(&self, exit_info: ExitInfo) -> anyhow::Result<ExitProofData> {
-        let (proof, amount) = create_exit_proof(
+        let (proof_data, _) = self.create_exit_proof_with_timings(exit_info)?;
+        Ok(proof_data)
+    }
+
+    /// Same as `create_exit_proof`, but also returns the time spent on each phase,
+    /// no timings if the proof is restored from the proof cache.
+    pub fn create_exit_proof_with_timings(
+        &self,
+        exit_info: ExitInfo,
+    ) -> anyhow::Result<(ExitProofData, Option<ProofTimings>)> {
+        let state_root = self.last_executed_block.get_eth_encoded_root();
+        if let Some(cached_proof) = self
+            .proving_cache
+            .cached_proof_result(&exit_info, state_root)
+        {
+            info!("Restored exit proof from the cache: {}", exit_info);
+            let proof_data = ExitProofData {
+                exit_info,
+                proof_info: ProofInfo {
+                    id: 0,
+                    amount: Some(cached_proof.amount),
+                    proof: Some(cached_proof.proof),
+                },
+            };
+            return Ok((proof_data, None));
+        }
+
+        let (proof, amount, timings) = create_exit_proof_with_timings(
             &self.config,
             &self.circuit_account_tree,
             &self.proving_cache,
@@ -147,26 +215,70 @@ impl ExodusProver {
             exit_info.l1_target_token,
             exit_info.chain_id,
             self.config.layer1.get_max_chain_num(),
-        )
-        .expect("Failed to generate exit proof");
+        )?;
+
+        let cached_proof = CachedProof {
+            proof,
+            amount: amount.into(),
+        };
+        if let Err(err) =
+            self.proving_cache
+                .cache_proof_result(&exit_info, state_root, &cached_proof)
+        {
+            warn!("Failed to cache exit proof of {}: {}", exit_info, err);
+        }
 
         let proof_data = ExitProofData {
             exit_info,
             proof_info: ProofInfo {
                 id: 0,
-                amount: Some(amount.into()),
-                proof: Some(proof),
+                amount: Some(cached_proof.amount),
+                proof: Some(cached_proof.proof),
             },
         };
-        Ok(proof_data)
+        Ok((proof_data, Some(timings)))
+    }
+
+    /// Records the time of each phase to the metrics, and warns if the p99 total time of the latest
+    /// `PROOF_TIMINGS_WINDOW` proofs regresses more than `P99_REGRESSION_PERCENT` over the baseline.
+    pub fn record_proof_timings(&self, timings: &ProofTimings) {
+        metrics::histogram!("prover.exit_proof.witness_ms", timings.witness_ms as f64);
+        metrics::histogram!(
+            "prover.exit_proof.synthesis_ms",
+            timings.synthesis_ms as f64
+        );
+        metrics::histogram!(
+            "prover.exit_proof.serialization_ms",
+            timings.serialization_ms as f64
+        );
+        metrics::histogram!("prover.exit_proof.total_ms", timings.total_ms as f64);
+
+        let Some(p99_ms) = self.proof_timings.lock().unwrap().record(timings.total_ms) else {
+            return;
+        };
+        info!("The p99 time of the latest exit proofs: {} ms", p99_ms);
+        if let Some(baseline_ms) = self.config.runtime.proof_p99_baseline_ms {
+            if is_p99_regressed(p99_ms, baseline_ms) {
+                warn!(
+                    "The p99 time of the latest exit proofs({} ms) regressed more than {}% over the baseline({} ms)",
+                    p99_ms, P99_REGRESSION_PERCENT, baseline_ms
+                );
+            }
+        }
     }
 
     pub async fn update_task_heartbeat(self: Arc<Self>, proof_id: i64) {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
         let mut heartbeat_ticker = interval(Duration::from_secs(10));
         loop {
             heartbeat_ticker.tick().await;
 
+            let mut storage = match self.conn_pool.access_storage_with_retry().await {
+                Ok(storage) => storage,
+                Err(err) => {
+                    warn!("Failed to update heartbeat time: {}", err);
+                    continue;
+                }
+            };
             if let Err(err) = storage
                 .prover_schema()
                 .update_heartbeat_time(proof_id)
@@ -178,11 +290,15 @@ impl ExodusProver {
     }
 
     pub(crate) async fn store_exit_proof(&self, proof: &ExitProofData) -> anyhow::Result<()> {
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+        let mut storage = self.conn_pool.access_storage_with_retry().await?;
         storage
             .prover_schema()
             .store_exit_proof(proof.into())
             .await?;
+        self.proving_cache.remove_cached_proof_result(
+            &proof.exit_info,
+            self.last_executed_block.get_eth_encoded_root(),
+        );
         Ok(())
     }
 }
@@ -4,11 +4,11 @@ pub use exit_type::{ExitInfo, ExitProofData};
 pub use exodus_prover::ExodusProver;
 use futures::FutureExt;
 use offchain_recover_state::{contract::ZkLinkContract, get_fully_on_chain_zklink_contract};
-use recover_state_config::RecoverStateConfig;
+use recover_state_config::ProverConfig;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::{interval, sleep};
-use tracing::{error, info, warn};
+use tracing::{error, info, info_span, warn, Instrument};
 use zklink_storage::ConnectionPool;
 
 pub mod exit_proof;
@@ -21,18 +21,24 @@ pub mod utils;
 pub const SETUP_MIN_POW2: u32 = 20;
 pub const SETUP_MAX_POW2: u32 = 26;
 
-pub async fn run_exodus_prover(config: RecoverStateConfig, workers_num: Option<usize>) {
+pub async fn run_exodus_prover(config: ProverConfig, workers_num: Option<usize>) {
     // Priority generate cache.
     let proving_cache =
         ProvingCache::from_config(&config).expect("Failed to generate proving cache");
     // clean old tasks
-    let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size);
+    let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size)
+        .with_retry_policy(
+            config.db.connection_retries(),
+            config.db.connection_retry_delay(),
+        );
     tokio::spawn(clean_old_task(conn_pool));
     // And then wait completed recovered state.
     if config.enable_sync_mode {
         wait_recovered_state(&config).await;
     }
 
+    // The workers of the command line take precedence over the config.
+    let workers_num = workers_num.or(config.workers_num);
     let prover = Arc::new(ExodusProver::from_config(config, proving_cache).await);
     let core_num = num_cpus::get();
     let workers_num = workers_num.map_or(core_num / 16, |workers| workers.min(core_num));
@@ -45,8 +51,15 @@ pub async fn run_exodus_prover(config: RecoverStateConfig, workers_num: Option<u
             loop {
                 match prover.load_new_task(i).await {
                     Ok(task) => {
-                        if let Some((proof_id, exit_info)) = task {
-                            process_task(prover.clone(), proof_id, exit_info).await;
+                        if let Some((proof_id, exit_info, request_id)) = task {
+                            let span = info_span!(
+                                "exit_task",
+                                proof_id,
+                                request_id = request_id.as_deref().unwrap_or_default()
+                            );
+                            process_task(prover.clone(), proof_id, exit_info)
+                                .instrument(span)
+                                .await;
                         } else {
                             info!("[Worker{}] is waiting for the new exit proof task......", i);
                             sleep(Duration::from_secs(5)).await;
@@ -60,11 +73,18 @@ pub async fn run_exodus_prover(config: RecoverStateConfig, workers_num: Option<u
     let _ = futures::future::select_all(workers).await;
 }
 
-async fn wait_recovered_state(config: &RecoverStateConfig) {
-    let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size);
-    let mut storage = conn_pool.access_storage_with_retry().await;
+async fn wait_recovered_state(config: &ProverConfig) {
+    let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size)
+        .with_retry_policy(
+            config.db.connection_retries(),
+            config.db.connection_retry_delay(),
+        );
+    let mut storage = conn_pool
+        .access_storage_with_retry()
+        .await
+        .expect("Failed to access the storage");
 
-    let (_, _, zklink_contract) = get_fully_on_chain_zklink_contract(config);
+    let (_, _, zklink_contract) = get_fully_on_chain_zklink_contract(&config.layer1);
     let total_verified_block = zklink_contract
         .get_total_verified_blocks()
         .await
@@ -75,6 +95,18 @@ async fn wait_recovered_state(config: &RecoverStateConfig) {
     loop {
         ticker.tick().await;
 
+        // The empty database must not be treated as the recovered block 0
+        match storage.chain().block_schema().has_genesis_block().await {
+            Ok(true) => {}
+            Ok(false) => {
+                info!("Waiting for the genesis block to be recovered......");
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to check the genesis block from db: {}", e);
+                continue;
+            }
+        }
         match storage.chain().block_schema().get_last_block_number().await {
             Ok(verified_block) => verified_block_num = verified_block as u32,
             Err(e) => warn!("Failed to get last block number from db: {}", e),
@@ -101,7 +133,15 @@ async fn process_task(prover: Arc<ExodusProver>, proof_id: i64, exit_info: ExitI
         let exit_info = prover.check_exit_info(exit_info).await;
         let (result_sender, result_receiver) = tokio::sync::oneshot::channel();
         std::thread::spawn(move || {
-            let prover_with_proof = prover.create_exit_proof(exit_info);
+            let prover_with_proof =
+                prover
+                    .create_exit_proof_with_timings(exit_info)
+                    .map(|(proof_data, timings)| {
+                        if let Some(timings) = timings {
+                            prover.record_proof_timings(&timings);
+                        }
+                        proof_data
+                    });
             result_sender.send(prover_with_proof).unwrap();
         });
 
@@ -117,10 +157,10 @@ async fn process_task(prover: Arc<ExodusProver>, proof_id: i64, exit_info: ExitI
                 match result.as_ref() {
                     Ok(exit_proof_data) => {
                         after_prover.store_exit_proof(exit_proof_data).await?;
-                        info!("Stored exit proof");
+                        info!("Stored exit proof: {}", exit_proof_data.exit_info);
                     }
                     Err(error) => {
-                        error!("Failed to compute proof:{}", error);
+                        error!("Failed to compute proof of {}: {}", exit_info_clone, error);
                         after_prover.cancel_this_task(&exit_info_clone).await?;
                     }
                 }
@@ -133,9 +173,16 @@ async fn process_task(prover: Arc<ExodusProver>, proof_id: i64, exit_info: ExitI
 }
 
 async fn clean_old_task(conn_pool: ConnectionPool) {
-    let mut storage = conn_pool.access_storage_with_retry().await;
     let mut clean_ticker = interval(Duration::from_secs(10));
     loop {
+        let mut storage = match conn_pool.access_storage_with_retry().await {
+            Ok(storage) => storage,
+            Err(err) => {
+                warn!("Failed to clean old tasks: {}", err);
+                clean_ticker.tick().await;
+                continue;
+            }
+        };
         if let Err(err) = storage.prover_schema().clean_old_task().await {
             warn!("Failed to update heartbeat time: {}", err);
         };
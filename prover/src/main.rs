@@ -1,7 +1,8 @@
 //! Generate exit proof for exodus mode given account and token
 //! correct verified state should be present in the db (could be restored using `data-restore` module)
 
-use recover_state_config::RecoverStateConfig;
+use offchain_recover_state::discover_deployment_blocks;
+use recover_state_config::ProverConfig;
 use std::time::Instant;
 use structopt::StructOpt;
 use tracing::info;
@@ -50,12 +51,15 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let opt = Opt::from_args();
-    let recover_state_config = RecoverStateConfig::from_env();
+    let mut prover_config = ProverConfig::from_env();
+    discover_deployment_blocks(&mut prover_config.layer1)
+        .await
+        .expect("Failed to discover the deployment blocks of contracts");
 
     match opt {
         Opt::Tasks { workers_num } => {
             info!("Run the task mode of exodus prover for exit proof tasks!");
-            run_exodus_prover(recover_state_config, workers_num).await;
+            run_exodus_prover(prover_config, workers_num).await;
         }
         Opt::Single {
             chain_id,
@@ -73,10 +77,11 @@ async fn main() {
                 sub_account_id: sub_account_id.into(),
                 l1_target_token: l1_target_token.into(),
                 l2_source_token: l2_source_token.into(),
+                expected_root: None,
             };
-            let proving_cache = ProvingCache::from_config(&recover_state_config)
+            let proving_cache = ProvingCache::from_config(&prover_config)
                 .expect("Failed to generate proving cache");
-            let prover = ExodusProver::from_config(recover_state_config, proving_cache).await;
+            let prover = ExodusProver::from_config(prover_config, proving_cache).await;
 
             info!("Start proving");
             let timer = Instant::now();
@@ -1,5 +1,11 @@
-use crate::SETUP_MIN_POW2;
-use recover_state_config::RecoverStateConfig;
+use crate::{ExitInfo, SETUP_MIN_POW2};
+use recover_state_config::ProverConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
 use zklink_circuit::exit_circuit::ZkLinkExitCircuit;
 use zklink_crypto::bellman::bn256::Bn256;
 use zklink_crypto::bellman::kate_commitment::{Crs, CrsForMonomialForm};
@@ -7,30 +13,296 @@ use zklink_crypto::bellman::plonk;
 use zklink_crypto::bellman::plonk::better_cs::adaptor::TranspilationVariant;
 use zklink_crypto::bellman::plonk::better_cs::cs::PlonkCsWidth4WithNextStepParams;
 use zklink_crypto::bellman::plonk::SetupPolynomials;
+use zklink_crypto::proof::EncodedSingleProof;
+use zklink_storage::prover::records::StoredExitInfo;
+use zklink_types::H256;
+use zklink_utils::BigUintSerdeWrapper;
+
+const CACHED_PROOF_EXTENSION: &str = "proof";
+
+/// The generated exit proof that is cached on disk until the proof is stored.
+///
+/// Note: this caches the proving result, not the synthesized constraint system. bellman synthesizes
+/// and proves in one step without exposing the assembly, so a crash while proving still loses the work.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedProof {
+    pub proof: EncodedSingleProof,
+    pub amount: BigUintSerdeWrapper,
+}
 
 pub struct ProvingCache {
     pub(crate) hints: Vec<(usize, TranspilationVariant)>,
     pub(crate) setup: SetupPolynomials<Bn256, PlonkCsWidth4WithNextStepParams>,
     pub(crate) key_monomial_form: Crs<Bn256, CrsForMonomialForm>,
+    pub(crate) cached_proofs: Option<ProofResultCache>,
 }
 
 impl ProvingCache {
-    pub fn from_config(config: &RecoverStateConfig) -> anyhow::Result<Self> {
+    pub fn from_config(config: &ProverConfig) -> anyhow::Result<Self> {
         let exit_circuit = ZkLinkExitCircuit::generate(config.layer1.get_max_chain_num());
         let hints = plonk::transpile(exit_circuit.clone())?;
         let setup = plonk::setup(exit_circuit, &hints)?;
         let size_log2 = setup.n.next_power_of_two().trailing_zeros();
 
         let size_log2 = std::cmp::max(size_log2, SETUP_MIN_POW2); // for exit circuit
-        let key_monomial_form = crate::utils::get_universal_setup_monomial_form(
-            &config.runtime.zklink_home,
-            size_log2,
-        )?;
+        let key_monomial_form =
+            crate::utils::get_universal_setup_monomial_form(&config.runtime, size_log2)?;
+
+        let cached_proofs = match &config.runtime.proof_result_cache_dir {
+            Some(dir) => Some(ProofResultCache::open(
+                PathBuf::from(dir),
+                config.runtime.max_task_staleness(),
+            )?),
+            None => None,
+        };
 
         Ok(Self {
             hints,
             setup,
             key_monomial_form,
+            cached_proofs,
         })
     }
+
+    /// Caches the generated proof of the exit task against the state root,
+    /// does nothing if the proof cache dir isn't configured.
+    pub fn cache_proof_result(
+        &self,
+        exit_info: &ExitInfo,
+        state_root: H256,
+        cached_proof: &CachedProof,
+    ) -> anyhow::Result<()> {
+        match &self.cached_proofs {
+            Some(cached_proofs) => cached_proofs.write(exit_info, state_root, cached_proof),
+            None => Ok(()),
+        }
+    }
+
+    /// Loads the proof of the exit task cached against the same state root before.
+    pub fn cached_proof_result(
+        &self,
+        exit_info: &ExitInfo,
+        state_root: H256,
+    ) -> Option<CachedProof> {
+        self.cached_proofs.as_ref()?.read(exit_info, state_root)
+    }
+
+    /// Removes the cached proof of the exit task after its proof is stored.
+    pub fn remove_cached_proof_result(&self, exit_info: &ExitInfo, state_root: H256) {
+        if let Some(cached_proofs) = &self.cached_proofs {
+            cached_proofs.remove(exit_info, state_root);
+        }
+    }
+}
+
+/// The directory of the cached exit proofs, a cached proof is keyed by the exit task and the state root
+/// it's proved against, so that the proof of a requeued task is never restored against another state.
+pub struct ProofResultCache {
+    dir: PathBuf,
+}
+
+impl ProofResultCache {
+    /// Opens the cache dir(created if missing), the cached proofs older than `max_staleness` are removed.
+    pub fn open(dir: PathBuf, max_staleness: Duration) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        remove_stale_cached_proofs(&dir, max_staleness)?;
+        Ok(Self { dir })
+    }
+
+    pub fn write(
+        &self,
+        exit_info: &ExitInfo,
+        state_root: H256,
+        cached_proof: &CachedProof,
+    ) -> anyhow::Result<()> {
+        let path = self.cached_proof_path(exit_info, state_root);
+        // Writes to a temporary file first to never leave a truncated cached proof after a crash
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(cached_proof)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The missing or unreadable cached proof is ignored.
+    pub fn read(&self, exit_info: &ExitInfo, state_root: H256) -> Option<CachedProof> {
+        let path = self.cached_proof_path(exit_info, state_root);
+        let bytes = fs::read(&path).ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(cached_proof) => Some(cached_proof),
+            Err(err) => {
+                warn!(
+                    "Ignored the corrupted cached proof {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn remove(&self, exit_info: &ExitInfo, state_root: H256) {
+        let path = self.cached_proof_path(exit_info, state_root);
+        if let Err(err) = fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!(
+                    "Failed to remove the cached proof {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    fn cached_proof_path(&self, exit_info: &ExitInfo, state_root: H256) -> PathBuf {
+        self.dir.join(cached_proof_file_name(exit_info, state_root))
+    }
+}
+
+/// The cached proof file name is the hash of the fields that identify the exit task and the state root.
+fn cached_proof_file_name(exit_info: &ExitInfo, state_root: H256) -> String {
+    let exit_info = StoredExitInfo::from(exit_info);
+    let mut hasher = Sha256::new();
+    hasher.update(exit_info.chain_id.to_be_bytes());
+    hasher.update(exit_info.account_id.to_be_bytes());
+    hasher.update(exit_info.sub_account_id.to_be_bytes());
+    hasher.update(exit_info.l1_target_token.to_be_bytes());
+    hasher.update(exit_info.l2_source_token.to_be_bytes());
+    hasher.update(state_root.as_bytes());
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", hash, CACHED_PROOF_EXTENSION)
+}
+
+/// Removes the cached proofs not modified within `max_staleness`,
+/// the entry that can't be inspected or removed is skipped with a warning.
+fn remove_stale_cached_proofs(dir: &Path, max_staleness: Duration) -> anyhow::Result<()> {
+    let now = SystemTime::now();
+    for entry in fs::read_dir(dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(err) => {
+                warn!(
+                    "Failed to read the proof cache dir {}: {}",
+                    dir.display(),
+                    err
+                );
+                continue;
+            }
+        };
+        if path
+            .extension()
+            .map_or(true, |ext| ext != CACHED_PROOF_EXTENSION)
+        {
+            continue;
+        }
+        let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(err) => {
+                warn!("Skipped the cached proof {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        if now.duration_since(modified).unwrap_or_default() > max_staleness {
+            info!("Removing the stale cached proof {}", path.display());
+            if let Err(err) = fs::remove_file(&path) {
+                warn!(
+                    "Failed to remove the stale cached proof {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedProof, ProofResultCache};
+    use crate::ExitInfo;
+    use num::BigUint;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use zklink_crypto::proof::EncodedSingleProof;
+    use zklink_types::{AccountId, ChainId, SubAccountId, TokenId, H256};
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "zklink_prover_cached_proofs_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn exit_info() -> ExitInfo {
+        ExitInfo {
+            chain_id: ChainId(1),
+            account_address: Default::default(),
+            account_id: AccountId(10),
+            sub_account_id: SubAccountId(1),
+            l1_target_token: TokenId(17),
+            l2_source_token: TokenId(1),
+            expected_root: None,
+        }
+    }
+
+    fn cached_proof() -> CachedProof {
+        CachedProof {
+            proof: EncodedSingleProof {
+                inputs: vec![],
+                proof: vec![],
+            },
+            amount: BigUint::from(100u32).into(),
+        }
+    }
+
+    #[test]
+    fn write_read_remove_cached_proof() {
+        let dir = cache_dir("write_read_remove");
+        let cached_proofs = ProofResultCache::open(dir.clone(), Duration::from_secs(60)).unwrap();
+        let root = H256::from_low_u64_be(1);
+        assert!(cached_proofs.read(&exit_info(), root).is_none());
+
+        cached_proofs
+            .write(&exit_info(), root, &cached_proof())
+            .unwrap();
+        let restored = cached_proofs.read(&exit_info(), root).unwrap();
+        assert_eq!(restored.amount, cached_proof().amount);
+        // The proof cached against another state root is never restored.
+        assert!(cached_proofs
+            .read(&exit_info(), H256::from_low_u64_be(2))
+            .is_none());
+
+        cached_proofs.remove(&exit_info(), root);
+        assert!(cached_proofs.read(&exit_info(), root).is_none());
+        // Removing the missing cached proof is fine.
+        cached_proofs.remove(&exit_info(), root);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stale_cached_proofs_are_removed_at_open() {
+        let dir = cache_dir("stale");
+        let root = H256::from_low_u64_be(1);
+        let cached_proofs = ProofResultCache::open(dir.clone(), Duration::from_secs(60)).unwrap();
+        cached_proofs
+            .write(&exit_info(), root, &cached_proof())
+            .unwrap();
+        std::fs::write(dir.join("unrelated.txt"), b"kept").unwrap();
+
+        // The fresh cached proof is kept.
+        let cached_proofs = ProofResultCache::open(dir.clone(), Duration::from_secs(60)).unwrap();
+        assert!(cached_proofs.read(&exit_info(), root).is_some());
+
+        std::thread::sleep(Duration::from_millis(10));
+        let cached_proofs = ProofResultCache::open(dir.clone(), Duration::ZERO).unwrap();
+        assert!(cached_proofs.read(&exit_info(), root).is_none());
+        assert!(dir.join("unrelated.txt").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
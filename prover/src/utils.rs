@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 use super::{SETUP_MAX_POW2, SETUP_MIN_POW2};
 use anyhow::format_err;
+use recover_state_config::RuntimeConfig;
 use std::fs::{create_dir_all, remove_file, File};
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
@@ -14,7 +15,7 @@ use zklink_crypto::Engine;
 /// Generates PLONK verification key for given circuit and saves key at the given path.
 /// Returns used setup power of two. (e.g. 22)
 fn generate_verification_key<C: Circuit<Engine> + Clone, P: AsRef<Path>>(
-    zklink_home: &str,
+    runtime: &RuntimeConfig,
     circuit: C,
     path: P,
 ) -> u32 {
@@ -88,8 +89,8 @@ fn generate_verification_key<C: Circuit<Engine> + Clone, P: AsRef<Path>>(
         gates_count, size_log2
     );
 
-    let key_monomial_form = get_universal_setup_monomial_form(zklink_home, size_log2)
-        .expect("Failed to read setup file.");
+    let key_monomial_form =
+        get_universal_setup_monomial_form(runtime, size_log2).expect("Failed to read setup file.");
 
     info!("Generating setup");
     let setup = setup(circuit, &transpilation_hints).expect("failed to make setup");
@@ -105,16 +106,15 @@ fn generate_verification_key<C: Circuit<Engine> + Clone, P: AsRef<Path>>(
 
 /// Returns universal setup in the monomial form of the given power of two (range: SETUP_MIN_POW2..=SETUP_MAX_POW2). Checks if file exists
 pub fn get_universal_setup_monomial_form(
-    zklink_home: &str,
+    runtime: &RuntimeConfig,
     power_of_two: u32,
 ) -> Result<Crs<Engine, CrsForMonomialForm>, anyhow::Error> {
     anyhow::ensure!(
         (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&power_of_two),
         "setup power of two is not in the correct range"
     );
-    // zklink for test
-    let setup_file_name = format!("setup_2^{}.key", power_of_two);
-    let mut buf_reader = get_universal_setup_file_buff_reader(zklink_home, &setup_file_name)?;
+    let setup_file = runtime.setup_key_path(power_of_two);
+    let mut buf_reader = get_universal_setup_file_buff_reader(&setup_file, power_of_two)?;
     Crs::<Engine, CrsForMonomialForm>::read(&mut buf_reader)
         .map_err(|e| format_err!("Failed to read Crs from setup file: {}", e))
 }
@@ -122,14 +122,16 @@ pub fn get_universal_setup_monomial_form(
 /// Returns universal setup in lagrange form of the given power of two (range: SETUP_MIN_POW2..=SETUP_MAX_POW2). Checks if file exists
 pub fn get_universal_setup_lagrange_form(
     power_of_two: u32,
-    zklink_home: &str,
+    runtime: &RuntimeConfig,
 ) -> Result<Crs<Engine, CrsForLagrangeForm>, anyhow::Error> {
     anyhow::ensure!(
         (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&power_of_two),
         "setup power of two is not in the correct range"
     );
-    let setup_file_name = format!("setup_2^{}_lagrange.key", power_of_two);
-    let mut buf_reader = get_universal_setup_file_buff_reader(zklink_home, &setup_file_name)?;
+    let setup_file = runtime
+        .setup_keys_dir()
+        .join(format!("setup_2^{}_lagrange.key", power_of_two));
+    let mut buf_reader = get_universal_setup_file_buff_reader(&setup_file, power_of_two)?;
     Crs::<Engine, CrsForLagrangeForm>::read(&mut buf_reader)
         .map_err(|e| format_err!("Failed to read Crs from setup file: {}", e))
 }
@@ -142,28 +144,22 @@ pub fn get_exodus_verification_key_path(key_dir: &str) -> PathBuf {
 }
 
 fn get_universal_setup_file_buff_reader(
-    zklink_home: &str,
-    setup_file_name: &str,
+    setup_file: &Path,
+    power_of_two: u32,
 ) -> Result<BufReader<File>, anyhow::Error> {
-    let setup_file = {
-        let mut path = base_universal_setup_dir(zklink_home)?;
-        path.push(setup_file_name);
-        File::open(path).map_err(|e| {
-            format_err!(
-                "Failed to open universal setup file {}, err: {}",
-                setup_file_name,
-                e
-            )
-        })?
-    };
+    anyhow::ensure!(
+        setup_file.exists(),
+        "Missing universal setup file {} of the expected degree 2^{}, \
+        check RUNTIME_CONFIG_SETUP_KEYS_DIR and RUNTIME_CONFIG_SETUP_KEY_FILES",
+        setup_file.display(),
+        power_of_two
+    );
+    let setup_file = File::open(setup_file).map_err(|e| {
+        format_err!(
+            "Failed to open universal setup file {}, err: {}",
+            setup_file.display(),
+            e
+        )
+    })?;
     Ok(BufReader::with_capacity(1 << 29, setup_file))
 }
-
-fn base_universal_setup_dir(zklink_home: &str) -> Result<PathBuf, anyhow::Error> {
-    let mut dir = PathBuf::new();
-    // root is used by default for provers
-    dir.push(zklink_home);
-    dir.push("zklink_keys");
-    anyhow::ensure!(dir.exists(), "Universal setup dir does not exits");
-    Ok(dir)
-}
@@ -6,15 +6,19 @@ mod recovered_state;
 
 pub use acquired_tokens::{AcquiredTokens, TokenInfo};
 pub use proofs_cache::ProofsCache;
-pub use recover_progress::{Progress, RecoverProgress};
+pub use recover_progress::{Progress, RecoverProgress, SyncState, SyncStatus};
 pub use recovered_state::RecoveredState;
 
-use bigdecimal::num_bigint::ToBigInt;
+use bigdecimal::num_bigint::{BigUint, ToBigInt};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use recover_state_config::RecoverStateConfig;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use tokio::sync::OnceCell;
+use tokio::sync::{broadcast, OnceCell};
 use tokio::time::interval;
 use tracing::{debug, info, warn};
 
@@ -22,53 +26,94 @@ use zklink_crypto::params::USD_TOKEN_ID;
 use zklink_prover::exit_type::{ProofId, ProofInfo};
 use zklink_prover::{ExitInfo, ExitProofData};
 use zklink_storage::chain::account::records::{StorageAccount, StorageBalance};
+use zklink_storage::chain::block::records::StorageBlock;
+use zklink_storage::prover::records::{CompletedExitProof, StoredExitProof, StoredTaskStatus};
+use zklink_storage::prover::{
+    listen_exit_proof_events, EXIT_PROOF_COMPLETED_CHANNEL, EXIT_PROOF_FAILED_CHANNEL,
+};
+use zklink_storage::tokens::records::DbToken;
 use zklink_storage::{ConnectionPool, StorageProcessor};
 use zklink_types::block::StoredBlockInfo;
 use zklink_types::utils::check_source_token_and_target_token;
-use zklink_types::{AccountId, ChainId, SubAccountId, TokenId, ZkLinkAddress, ZkLinkTx};
+use zklink_types::{
+    AccountId, BlockNumber, ChainId, SubAccountId, TokenId, ZkLinkAddress, ZkLinkTx, H256,
+};
 
 use crate::request::BatchExitRequest;
 use crate::response::{
-    ExodusResponse, ExodusStatus, PendingTasksCount, Proofs, PublicData, SubAccountBalances,
-    TaskId, UnprocessedPriorityOp,
+    BalancesPage, CompletedProof, CurrentRoot, DbHealth, EligibilityResult, ExodusResponse,
+    ExodusStatus, FinishedProofs, PaginatedResponse, PendingTasksCount, ProofEvent,
+    ProofEventStatus, Proofs, ProverStats, PublicData, QueuePosition, SerialId, SubAccountBalances,
+    TableSize, TaskId, TokenVolumes, UnprocessedPriorityOp,
 };
 
 const GET_PROOFS_NUM_LIMIT: u32 = 100;
+const GET_BLOCKS_NUM_LIMIT: u32 = 100;
+const VOLUME_BLOCKS_NUM_LIMIT: u32 = 10000;
+const GET_BALANCES_NUM_LIMIT: u32 = 1000;
+const DEFAULT_BALANCES_NUM: u32 = 100;
+const REPLAY_PROOFS_NUM_LIMIT: i64 = 1000;
+const COMPLETED_PROOFS_CHANNEL_SIZE: usize = 1024;
+const PROOF_EVENTS_CHANNEL_SIZE: usize = 1024;
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+
+/// The `(finished_at, id)` of the last replayed completed proof.
+pub(crate) type ReplayCursor = (DateTime<Utc>, i64);
 
 pub struct AppData {
     conn_pool: ConnectionPool,
-    enable_black_list: bool,
     enable_sync_mode: bool,
-    pub black_list_time: u32,
+    /// The blacklist window(in minutes), `None` means the blacklist is disabled.
+    pub black_list_time: Option<u32>,
+    /// The maximum number of the pending proof tasks of a single address.
+    max_pending_tasks_per_address: usize,
+    /// Whether the prover claims the tasks only by priority, the queue positions are ranked the same way.
+    skip_balance_ordering: bool,
+    admin_token: Option<String>,
+    /// The maintenance mode, the proof task writes are rejected while it is on.
+    read_only: AtomicBool,
+    /// The USD price of the unpriced tokens, see `token_prices`.
+    default_price: Option<BigDecimal>,
 
     pub contracts: HashMap<ChainId, ZkLinkAddress>,
     pub(crate) recover_progress: RecoverProgress,
     proofs_cache: ProofsCache,
 
+    /// The global broadcast of the completed proofs, published by `store_exit_proof`.
+    completed_proofs: broadcast::Sender<CompletedProof>,
+    /// The global broadcast of the finished(completed or failed) proof tasks.
+    proof_events: broadcast::Sender<ProofEvent>,
+
     pub recovered_state: OnceCell<RecoveredState>,
     pub acquired_tokens: OnceCell<AcquiredTokens>,
+    /// The USD prices of the tokens, loaded once by the first exit task.
+    token_prices: OnceCell<HashMap<TokenId, BigDecimal>>,
 }
 
 impl AppData {
     pub async fn new(
-        enable_black_list: bool,
-        enable_sync_mode: bool,
-        black_list_time: u32,
+        config: &RecoverStateConfig,
         conn_pool: ConnectionPool,
-        contracts: HashMap<ChainId, ZkLinkAddress>,
         proofs_cache: ProofsCache,
         recover_progress: RecoverProgress,
     ) -> AppData {
         Self {
             conn_pool,
-            enable_black_list,
-            enable_sync_mode,
-            black_list_time,
-            contracts,
+            enable_sync_mode: config.enable_sync_mode,
+            black_list_time: config.black_list_time,
+            max_pending_tasks_per_address: config.max_pending_tasks_per_address,
+            skip_balance_ordering: config.skip_balance_ordering,
+            admin_token: config.api.admin_token.clone(),
+            read_only: AtomicBool::new(config.runtime.read_only),
+            default_price: config.runtime.default_price.clone(),
+            contracts: config.layer1.get_contracts(),
             recover_progress,
             proofs_cache,
+            completed_proofs: broadcast::channel(COMPLETED_PROOFS_CHANNEL_SIZE).0,
+            proof_events: broadcast::channel(PROOF_EVENTS_CHANNEL_SIZE).0,
             recovered_state: Default::default(),
             acquired_tokens: Default::default(),
+            token_prices: Default::default(),
         }
     }
 
@@ -78,6 +123,24 @@ impl AppData {
             || (self.enable_sync_mode && !self.recover_progress.is_completed())
     }
 
+    /// Checks the admin token, all admin requests are rejected if the admin token is not configured.
+    pub fn is_admin(&self, token: Option<&str>) -> bool {
+        matches!((&self.admin_token, token), (Some(admin_token), Some(token)) if admin_token == token)
+    }
+
+    /// Turns the maintenance mode on or off.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Rejects the writes while the server is in maintenance mode.
+    pub(crate) fn check_writable(&self) -> Result<(), ExodusStatus> {
+        if self.read_only.load(Ordering::Relaxed) {
+            return Err(ExodusStatus::Maintenance);
+        }
+        Ok(())
+    }
+
     pub fn recovered_state(&self) -> &RecoveredState {
         self.recovered_state.get().unwrap()
     }
@@ -88,9 +151,16 @@ impl AppData {
 
     // Periodically clean up blacklisted users (to prevent users from requesting too many proof tasks)
     pub async fn black_list_escaping(self: Arc<Self>, black_list_time: u32) {
-        let mut storage = self.access_storage().await;
         let mut ticker = interval(Duration::from_secs(10));
         loop {
+            let mut storage = match self.conn_pool.access_storage_with_retry().await {
+                Ok(storage) => storage,
+                Err(err) => {
+                    warn!("Failed to clean escaped user, err: {}", err);
+                    ticker.tick().await;
+                    continue;
+                }
+            };
             if let Err(err) = storage
                 .recover_schema()
                 .clean_escaped_user(black_list_time)
@@ -103,6 +173,102 @@ impl AppData {
         }
     }
 
+    // Periodically prune the account updates of the blocks out of the retention window
+    pub async fn prune_state_updates(self: Arc<Self>, retention_blocks: u64) {
+        let mut ticker = interval(Duration::from_secs(PRUNE_INTERVAL_SECS));
+        loop {
+            ticker.tick().await;
+            let mut storage = match self.conn_pool.access_storage_with_retry().await {
+                Ok(storage) => storage,
+                Err(err) => {
+                    warn!("Failed to prune account updates, err: {}", err);
+                    continue;
+                }
+            };
+            let last_verified_block = match storage
+                .chain()
+                .block_schema()
+                .get_last_verified_confirmed_block()
+                .await
+            {
+                Ok(block_number) => block_number,
+                Err(err) => {
+                    warn!("Failed to load the last verified block, err: {}", err);
+                    continue;
+                }
+            };
+            let prune_before = last_verified_block + 1 - retention_blocks as i64;
+            if prune_before <= 0 {
+                continue;
+            }
+            match storage
+                .chain()
+                .state_schema()
+                .prune_updates_before(prune_before)
+                .await
+            {
+                Ok(pruned) => info!(
+                    "Pruned {} account updates before block {}",
+                    pruned, prune_before
+                ),
+                Err(err) => warn!("Failed to prune account updates, err: {}", err),
+            }
+        }
+    }
+
+    // Rebroadcasts the completed proofs and failed tasks notified by the prover to
+    // the subscribers of proofs stream and proof updates
+    pub async fn broadcast_completed_proofs(self: Arc<Self>, database_url: String) {
+        let mut listener = loop {
+            match listen_exit_proof_events(&database_url).await {
+                Ok(listener) => break listener,
+                Err(err) => {
+                    warn!("Failed to listen exit proof events, err: {}", err);
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
+            }
+        };
+        loop {
+            // The listener reconnects automatically if the connection is lost
+            let notification = match listener.recv().await {
+                Ok(notification) => notification,
+                Err(err) => {
+                    warn!("Failed to receive exit proof event, err: {}", err);
+                    continue;
+                }
+            };
+            // No subscribers is not an error
+            match notification.channel() {
+                EXIT_PROOF_COMPLETED_CHANNEL => {
+                    match serde_json::from_str::<CompletedExitProof>(notification.payload()) {
+                        Ok(completed_proof) => {
+                            let _ = self.proof_events.send(ProofEvent {
+                                task_id: completed_proof.id as ProofId,
+                                status: ProofEventStatus::Completed,
+                            });
+                            let _ = self
+                                .completed_proofs
+                                .send(self.completed_proof(completed_proof));
+                        }
+                        Err(err) => {
+                            warn!("Invalid completed exit proof notification, err: {}", err)
+                        }
+                    }
+                }
+                EXIT_PROOF_FAILED_CHANNEL => match notification.payload().parse::<ProofId>() {
+                    Ok(task_id) => {
+                        let _ = self.proof_events.send(ProofEvent {
+                            task_id,
+                            status: ProofEventStatus::Failed,
+                        });
+                    }
+                    Err(err) => warn!("Invalid failed exit proof notification, err: {}", err),
+                },
+                channel => warn!("Unexpected exit proof notification channel: {}", channel),
+            }
+        }
+    }
+
     pub(crate) async fn sync_recover_progress(self: Arc<Self>) {
         if self.enable_sync_mode {
             self.recover_progress
@@ -138,15 +304,23 @@ impl AppData {
             .await;
     }
 
-    async fn access_storage(&self) -> StorageProcessor<'_> {
-        self.conn_pool.access_storage_with_retry().await
+    async fn access_storage(&self) -> Result<StorageProcessor<'_>, ExodusStatus> {
+        Ok(self.conn_pool.access_storage_with_retry().await?)
     }
 
     pub(crate) async fn get_balances_by_storage(
         &self,
         account_address: ZkLinkAddress,
-    ) -> Result<SubAccountBalances, ExodusStatus> {
-        let mut storage = self.access_storage().await;
+        sub_account_id: Option<SubAccountId>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<BalancesPage, ExodusStatus> {
+        let limit = limit.unwrap_or(DEFAULT_BALANCES_NUM);
+        if limit > GET_BALANCES_NUM_LIMIT {
+            return Err(ExodusStatus::BalancePageTooLarge);
+        }
+        let offset = offset.unwrap_or_default();
+        let mut storage = self.conn_pool.access_storage_readonly().await?;
         let Some(StorageAccount{id, ..}) = storage.chain()
             .account_schema()
             .account_by_address(account_address.as_bytes())
@@ -154,13 +328,56 @@ impl AppData {
         {
             return Err(ExodusStatus::AccountNotExist)
         };
-        let balances = storage
+        let (total, balances) = storage
             .chain()
             .account_schema()
-            .account_balances(id, None)
+            .account_balances_paginated(
+                id,
+                sub_account_id.map(|sub_account_id| *sub_account_id as i32),
+                i64::from(limit),
+                i64::from(offset),
+            )
             .await?;
 
-        Ok(convert_balance_resp(balances))
+        Ok(BalancesPage {
+            balances: convert_balance_resp(balances),
+            limit,
+            offset,
+            total: total as u64,
+        })
+    }
+
+    pub(crate) fn get_account_tokens(
+        &self,
+        account_address: ZkLinkAddress,
+        sub_account_id: SubAccountId,
+    ) -> Result<Vec<TokenId>, ExodusStatus> {
+        self.recovered_state()
+            .account_tokens(&account_address, sub_account_id)
+    }
+
+    /// Gets the sub accounts of the address that hold a nonzero balance.
+    pub(crate) async fn get_sub_accounts(
+        &self,
+        account_address: ZkLinkAddress,
+    ) -> Result<Vec<SubAccountId>, ExodusStatus> {
+        let Some(&account_id) = self
+            .recovered_state()
+            .account_id_by_address
+            .get(&account_address)
+        else {
+            return Err(ExodusStatus::AccountNotExist);
+        };
+        let mut storage = self.conn_pool.access_storage_readonly().await?;
+        let sub_account_ids = storage
+            .chain()
+            .account_schema()
+            .sub_accounts_with_balances(*account_id as i64)
+            .await?;
+        Ok(sub_account_ids
+            .into_iter()
+            .map(|sub_account_id| SubAccountId::from(sub_account_id as u8))
+            .collect())
     }
 
     pub(crate) async fn get_proof(
@@ -190,6 +407,54 @@ impl AppData {
         Ok(exit_data)
     }
 
+    /// Same as `get_proof`, but the task whose proof is not generated yet is reported as `ProofGenerating`,
+    /// for the clients that re-fetch the proof by the exit parameters instead of tracking the task id.
+    pub(crate) async fn get_proof_by_params(
+        &self,
+        exit_info: ExitInfo,
+    ) -> Result<ExitProofData, ExodusStatus> {
+        let exit_data = self.get_proof(exit_info).await?;
+        if !exit_data.proof_info.is_completed() {
+            return Err(ExodusStatus::ProofGenerating);
+        }
+        Ok(exit_data)
+    }
+
+    /// Quotes the withdraw amount of the exit over the recovered state,
+    /// without resolving the account by address or generating the proof.
+    pub(crate) fn quote_exit_amount(&self, exit_info: ExitInfo) -> Result<BigUint, ExodusStatus> {
+        let (is_correct_tokens, l1_target_token_after_mapping) =
+            check_source_token_and_target_token(
+                exit_info.l2_source_token,
+                exit_info.l1_target_token,
+            );
+        if !is_correct_tokens {
+            return Err(ExodusStatus::InvalidL1L2Token);
+        }
+        if !self.contracts.contains_key(&exit_info.chain_id) {
+            return Err(ExodusStatus::ChainNotExist);
+        }
+        let recovered_state = self.recovered_state();
+        if !recovered_state.accounts.contains_key(&exit_info.account_id) {
+            return Err(ExodusStatus::AccountNotExist);
+        }
+
+        let max_chain_num = self
+            .contracts
+            .keys()
+            .map(|chain_id| **chain_id as usize)
+            .max()
+            .unwrap_or_default();
+        Ok(recovered_state.exit_amount(
+            exit_info.account_id,
+            exit_info.sub_account_id,
+            exit_info.l2_source_token,
+            l1_target_token_after_mapping,
+            exit_info.chain_id,
+            max_chain_num,
+        ))
+    }
+
     pub(crate) async fn get_proofs(
         &self,
         exit_info: BatchExitRequest,
@@ -231,20 +496,52 @@ impl AppData {
                 exit_task.l2_source_token,
             )?
             .0;
-        let mut storage = self.access_storage().await;
-        let remaining_tasks = storage
+        let mut storage = self.access_storage().await?;
+        let task_status = storage
             .prover_schema()
-            .get_task_id((&exit_task).into())
-            .await?
-            .map(Into::into)
-            .ok_or(ExodusStatus::ExitProofTaskNotExist)?;
-        Ok(remaining_tasks)
+            .get_task_status((&exit_task).into())
+            .await?;
+        task_id_by_status(task_status)
+    }
+
+    pub(crate) async fn requeue_proof(&self, mut exit_info: ExitInfo) -> Result<(), ExodusStatus> {
+        self.check_writable()?;
+        if !check_source_token_and_target_token(
+            exit_info.l2_source_token,
+            exit_info.l1_target_token,
+        )
+        .0
+        {
+            return Err(ExodusStatus::InvalidL1L2Token);
+        }
+        exit_info.account_id = *self
+            .recovered_state()
+            .account_id_by_address
+            .get(&exit_info.account_address)
+            .ok_or(ExodusStatus::AccountNotExist)?;
+
+        let mut storage = self.access_storage().await?;
+        let requeued = storage
+            .prover_schema()
+            .requeue_proof((&exit_info).into())
+            .await?;
+        if !requeued {
+            return Err(ExodusStatus::ExitProofTaskNotExist);
+        }
+        info!("Requeued exit proof task: {}", exit_info);
+
+        // The stale proof must not be served from cache
+        self.proofs_cache.cache.invalidate(&exit_info).await;
+        Ok(())
     }
 
     pub(crate) async fn generate_proof_task(
         &self,
         mut exit_info: ExitInfo,
+        request_id: &str,
     ) -> Result<TaskId, ExodusStatus> {
+        self.check_writable()?;
+        self.check_expected_root(exit_info.expected_root)?;
         if !check_source_token_and_target_token(
             exit_info.l2_source_token,
             exit_info.l1_target_token,
@@ -264,22 +561,23 @@ impl AppData {
             return Err(ExodusStatus::ProofTaskAlreadyExists);
         }
 
-        let mut storage = self.access_storage().await;
-        // Check for black list
-        if self.enable_black_list {
-            let exist_address = storage
-                .recover_schema()
-                .exist_or_insert_user(exit_info.account_address.as_bytes())
-                .await?;
-            if exist_address {
-                return Err(ExodusStatus::ExistTaskWithinThreeHour);
-            }
-        }
+        let priority = self.exit_task_priority(exit_info.account_id).await?;
         // Update to database
-        let task_id = storage
+        let mut storage = self.access_storage().await?;
+        let mut transaction = storage.start_transaction().await?;
+        self.check_pending_tasks_limit(
+            &mut transaction,
+            exit_info.account_id,
+            &exit_info.account_address,
+            1,
+        )
+        .await?;
+        self.check_black_list(&exit_info.account_address).await?;
+        let task_id = transaction
             .prover_schema()
-            .insert_exit_task((&exit_info).into())
+            .insert_exit_task_with_priority((&exit_info).into(), priority, Some(request_id))
             .await?;
+        transaction.commit().await?;
 
         // Update to cache
         self.proofs_cache
@@ -289,10 +587,80 @@ impl AppData {
         Ok(task_id.into())
     }
 
+    /// Derives the priority of the exit task from the total USD value of the account
+    /// (priced by `token_prices`), so that the high-value accounts are served first.
+    async fn exit_task_priority(&self, account_id: AccountId) -> Result<i16, ExodusStatus> {
+        let prices = self.token_prices().await?;
+        let usd_value = self.recovered_state().usd_value(account_id, prices);
+        Ok(priority_by_usd_value(&usd_value))
+    }
+
+    /// Returns the USD prices of the tokens, loaded from the `token_price` table only once.
+    async fn token_prices(&self) -> Result<&HashMap<TokenId, BigDecimal>, ExodusStatus> {
+        self.token_prices
+            .get_or_try_init(|| async {
+                let tokens = self
+                    .access_storage()
+                    .await?
+                    .tokens_schema()
+                    .load_tokens()
+                    .await?;
+                Ok(token_prices(tokens, self.default_price.as_ref()))
+            })
+            .await
+    }
+
+    /// Rejects the `new_tasks` tasks of the address if its pending tasks would exceed
+    /// `max_pending_tasks_per_address`, so that a single address can't starve the others.
+    /// The pending tasks stay locked until the transaction inserting the new ones is committed.
+    async fn check_pending_tasks_limit(
+        &self,
+        transaction: &mut StorageProcessor<'_>,
+        account_id: AccountId,
+        address: &ZkLinkAddress,
+        new_tasks: usize,
+    ) -> Result<(), ExodusStatus> {
+        let mut recover_schema = transaction.recover_schema();
+        recover_schema
+            .lock_pending_tasks(i64::from(*account_id))
+            .await?;
+        let pending_tasks = recover_schema
+            .count_pending_tasks_by_address(address.as_bytes())
+            .await?;
+        if pending_tasks as usize + new_tasks > self.max_pending_tasks_per_address {
+            return Err(ExodusStatus::RateLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Checks and inserts the user into the black list, skipped if the black list is disabled.
+    pub(crate) async fn check_black_list(
+        &self,
+        address: &ZkLinkAddress,
+    ) -> Result<(), ExodusStatus> {
+        if self.black_list_time.is_none() {
+            return Ok(());
+        }
+        let exist_address = self
+            .access_storage()
+            .await?
+            .recover_schema()
+            .exist_or_insert_user(address.as_bytes())
+            .await?;
+        if exist_address {
+            return Err(ExodusStatus::ExistTaskWithinThreeHour);
+        }
+        Ok(())
+    }
+
     pub(crate) async fn generate_proof_tasks(
         &self,
         batch_exit_info: BatchExitRequest,
+        request_id: &str,
     ) -> Result<HashMap<ProofId, ExitInfo>, ExodusStatus> {
+        self.check_writable()?;
+        self.check_expected_root(batch_exit_info.expected_root)?;
+        let address = batch_exit_info.address.clone();
         let (&account_id, token_info) = self.check_exit_info(
             &batch_exit_info.address,
             batch_exit_info.sub_account_id,
@@ -314,11 +682,26 @@ impl AppData {
         }
 
         // Update to database
-        let mut storage = self.access_storage().await;
-        let tasks_ids = storage
+        let priority = self.exit_task_priority(account_id).await?;
+        let mut storage = self.access_storage().await?;
+        let mut transaction = storage.start_transaction().await?;
+        // The whole batch is refused if it doesn't fit
+        self.check_pending_tasks_limit(
+            &mut transaction,
+            account_id,
+            &address,
+            batch_exit_tasks.len(),
+        )
+        .await?;
+        let tasks_ids = transaction
             .prover_schema()
-            .insert_batch_exit_tasks(batch_exit_tasks.iter().map(|t| t.into()).collect())
+            .insert_batch_exit_tasks(
+                batch_exit_tasks.iter().map(|t| t.into()).collect(),
+                priority,
+                Some(request_id),
+            )
             .await?;
+        transaction.commit().await?;
 
         // Update to cache
         let mut tasks = HashMap::with_capacity(batch_exit_tasks.len());
@@ -336,7 +719,7 @@ impl AppData {
         &self,
         chain_id: ChainId,
     ) -> Result<Vec<UnprocessedPriorityOp>, ExodusStatus> {
-        let mut storage = self.access_storage().await;
+        let mut storage = self.access_storage().await?;
         let priority_ops = storage
             .chain()
             .operations_schema()
@@ -345,10 +728,11 @@ impl AppData {
         let unprocessed_priority_ops = priority_ops
             .into_iter()
             .map(|(serial_id, tx)| UnprocessedPriorityOp {
+                chain_id,
                 serial_id,
                 pub_data: match tx {
                     ZkLinkTx::Deposit(op) => PublicData::Deposit((*op).into()),
-                    ZkLinkTx::FullExit(_) => PublicData::FullExit,
+                    ZkLinkTx::FullExit(op) => PublicData::FullExit((*op).into()),
                     _ => unreachable!(),
                 },
             })
@@ -356,24 +740,268 @@ impl AppData {
         Ok(unprocessed_priority_ops)
     }
 
+    pub(crate) async fn get_priority_op(
+        &self,
+        chain_id: ChainId,
+        serial_id: SerialId,
+    ) -> Result<UnprocessedPriorityOp, ExodusStatus> {
+        if !self.contracts.contains_key(&chain_id) {
+            return Err(ExodusStatus::ChainNotExist);
+        }
+        let mut storage = self.access_storage().await?;
+        let Some(tx) = storage
+            .chain()
+            .operations_schema()
+            .get_priority_tx_by_chain_and_serial(*chain_id as i16, serial_id as i64)
+            .await?
+        else {
+            return Err(ExodusStatus::PriorityOpNotExist);
+        };
+        Ok(UnprocessedPriorityOp {
+            chain_id,
+            serial_id,
+            pub_data: match tx {
+                ZkLinkTx::Deposit(op) => PublicData::Deposit((*op).into()),
+                ZkLinkTx::FullExit(op) => PublicData::FullExit((*op).into()),
+                _ => unreachable!(),
+            },
+        })
+    }
+
+    pub(crate) async fn get_pending_deposits(
+        &self,
+        address: ZkLinkAddress,
+    ) -> Result<Vec<UnprocessedPriorityOp>, ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let deposits = storage
+            .chain()
+            .operations_schema()
+            .unexecuted_deposits_by_address(address.as_bytes())
+            .await?;
+        let pending_deposits = deposits
+            .into_iter()
+            .map(|deposit| {
+                let pub_data = match serde_json::from_value(deposit.tx_data)? {
+                    ZkLinkTx::Deposit(op) => PublicData::Deposit((*op).into()),
+                    _ => anyhow::bail!(
+                        "The unexecuted priority op {} is not a deposit",
+                        deposit.nonce
+                    ),
+                };
+                Ok(UnprocessedPriorityOp {
+                    chain_id: ChainId(deposit.chain_id as u8),
+                    serial_id: deposit.nonce as SerialId,
+                    pub_data,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(pending_deposits)
+    }
+
+    pub(crate) fn subscribe_completed_proofs(&self) -> broadcast::Receiver<CompletedProof> {
+        self.completed_proofs.subscribe()
+    }
+
+    pub(crate) fn subscribe_proof_events(&self) -> broadcast::Receiver<ProofEvent> {
+        self.proof_events.subscribe()
+    }
+
+    /// The events of the specified tasks that have already finished, sent to the new subscribers
+    /// since no more events will be published for them.
+    pub(crate) async fn finished_proof_events(
+        &self,
+        task_ids: &[ProofId],
+    ) -> Result<Vec<ProofEvent>, ExodusStatus> {
+        let task_ids: Vec<i64> = task_ids.iter().map(|&id| id as i64).collect();
+        let mut storage = self.access_storage().await?;
+        let tasks_status = storage
+            .prover_schema()
+            .get_finished_tasks_status(&task_ids)
+            .await?;
+        Ok(tasks_status
+            .into_iter()
+            .map(|task| ProofEvent {
+                task_id: task.id as ProofId,
+                status: if task.completed {
+                    ProofEventStatus::Completed
+                } else {
+                    ProofEventStatus::Failed
+                },
+            })
+            .collect())
+    }
+
+    /// Returns the replay cursor of the proofs completed after the proof `from_id`.
+    pub(crate) async fn replay_cursor(
+        &self,
+        from_id: ProofId,
+    ) -> Result<ReplayCursor, ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let finished_at = storage
+            .prover_schema()
+            .proof_finished_at(from_id as i64)
+            .await?
+            .ok_or(ExodusStatus::ExitProofTaskNotExist)?;
+        Ok((finished_at, from_id as i64))
+    }
+
+    /// Loads a page of at most `REPLAY_PROOFS_NUM_LIMIT` proofs completed after the cursor in order
+    /// of completion, and the cursor of the next page. The next cursor is `None` once caught up.
+    pub(crate) async fn replay_completed_proofs(
+        &self,
+        (finished_at, id): ReplayCursor,
+    ) -> Result<(Vec<CompletedProof>, Option<ReplayCursor>), ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let proofs = storage
+            .prover_schema()
+            .get_completed_proofs_after(finished_at, id, REPLAY_PROOFS_NUM_LIMIT)
+            .await?;
+        let next_cursor = match proofs.last() {
+            Some(last) if proofs.len() as i64 == REPLAY_PROOFS_NUM_LIMIT => Some((
+                last.finished_at
+                    .expect("The completed proof must be finished"),
+                last.id,
+            )),
+            _ => None,
+        };
+        let replayed = proofs
+            .iter()
+            .map(|proof| self.completed_proof(proof.into()))
+            .collect();
+        Ok((replayed, next_cursor))
+    }
+
+    fn completed_proof(&self, completed_proof: CompletedExitProof) -> CompletedProof {
+        let mut exit_info: ExitInfo = (&completed_proof.exit_info).into();
+        if let Some(account) = self
+            .recovered_state
+            .get()
+            .and_then(|state| state.accounts.get(&exit_info.account_id))
+        {
+            exit_info.account_address = account.address.clone();
+        }
+        CompletedProof {
+            id: completed_proof.id as ProofId,
+            exit_info,
+        }
+    }
+
     pub(crate) fn get_contracts(&self) -> ExodusResponse<HashMap<ChainId, ZkLinkAddress>> {
         ExodusResponse::Ok().data(self.contracts.clone())
     }
 
     pub(crate) async fn running_max_task_id(&self) -> Result<TaskId, ExodusStatus> {
-        let mut storage = self.access_storage().await;
+        let mut storage = self.access_storage().await?;
         let running_task_id = storage.prover_schema().get_running_max_task_id().await?;
         Ok(running_task_id.into())
     }
 
     pub(crate) async fn pending_tasks_count(&self) -> Result<PendingTasksCount, ExodusStatus> {
-        let mut storage = self.access_storage().await;
+        let mut storage = self.access_storage().await?;
         let pending_tasks_count = storage.prover_schema().get_pending_tasks_count().await?;
+        let running_tasks_count = storage.prover_schema().count_running_tasks().await?;
+        let completed_tasks_count = storage
+            .prover_schema()
+            .get_total_completed_proofs_num()
+            .await?;
         Ok(PendingTasksCount {
             count: pending_tasks_count as u32,
+            in_progress: running_tasks_count as u32,
+            completed: completed_tasks_count as u32,
         })
     }
 
+    pub(crate) async fn get_prover_stats(&self) -> Result<ProverStats, ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let stats = storage.prover_schema().get_prover_stats().await?;
+        let withdraw_amounts = storage
+            .prover_schema()
+            .completed_withdraw_amounts()
+            .await?
+            .into_iter()
+            .map(|(token_id, amount)| {
+                (
+                    TokenId::from(token_id as u32),
+                    amount.to_bigint().unwrap().into(),
+                )
+            })
+            .collect();
+        Ok(ProverStats {
+            pending: stats.pending as u32,
+            running: stats.running as u32,
+            completed: stats.completed as u32,
+            avg_duration_ms: stats.avg_duration_ms.map(|ms| ms as u64),
+            withdraw_amounts,
+        })
+    }
+
+    pub(crate) async fn get_queue_position(
+        &self,
+        task_id: ProofId,
+    ) -> Result<QueuePosition, ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let position = storage
+            .prover_schema()
+            .task_queue_position(task_id as i64, !self.skip_balance_ordering)
+            .await?;
+        Ok(QueuePosition {
+            position: position.map(|p| p as u32),
+        })
+    }
+
+    pub(crate) fn db_health(&self) -> DbHealth {
+        self.conn_pool.pool_status().into()
+    }
+
+    pub(crate) async fn table_sizes(&self) -> Result<Vec<TableSize>, ExodusStatus> {
+        let mut storage = self.access_storage().await?;
+        let sizes = storage.chain().stats_schema().table_sizes().await?;
+        Ok(sizes
+            .into_iter()
+            .map(|(table, bytes)| TableSize { table, bytes })
+            .collect())
+    }
+
+    /// Returns the total amount moved by the transfers in the blocks `[from, to]`
+    /// of the token if specified, otherwise of all tokens.
+    pub(crate) async fn block_volume(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        token_id: Option<TokenId>,
+    ) -> Result<TokenVolumes, ExodusStatus> {
+        if to < from {
+            return Err(ExodusStatus::InvalidBlockRange);
+        }
+        if *to - *from >= VOLUME_BLOCKS_NUM_LIMIT {
+            return Err(ExodusStatus::BlocksLoadTooMany);
+        }
+        let (from, to) = (i64::from(*from), i64::from(*to));
+        let mut storage = self.access_storage().await?;
+        let volumes = match token_id {
+            Some(token_id) => {
+                let volume = storage
+                    .chain()
+                    .block_schema()
+                    .get_block_volume_by_token(from, to, *token_id as i32)
+                    .await?;
+                HashMap::from([(token_id, volume)])
+            }
+            None => storage
+                .chain()
+                .block_schema()
+                .get_block_volume_all_tokens(from, to)
+                .await?
+                .into_iter()
+                .map(|(coin_id, volume)| (TokenId::from(coin_id as u32), volume))
+                .collect(),
+        };
+        Ok(volumes
+            .into_iter()
+            .map(|(token_id, volume)| (token_id, volume.to_bigint().unwrap().into()))
+            .collect())
+    }
+
     pub(crate) async fn get_proofs_by_page(
         &self,
         page: u32,
@@ -382,23 +1010,14 @@ impl AppData {
         if num > GET_PROOFS_NUM_LIMIT {
             return Err(ExodusStatus::ProofsLoadTooMany);
         }
-        let mut storage = self.access_storage().await;
+        let mut storage = self.access_storage().await?;
         let proofs = storage
             .prover_schema()
             .get_proofs_by_page(page as i64, num as i64)
             .await?;
         let proofs = proofs
             .into_iter()
-            .map(|proof| {
-                let mut proof: ExitProofData = proof.into();
-                let account = self
-                    .recovered_state()
-                    .accounts
-                    .get(&proof.exit_info.account_id)
-                    .unwrap();
-                proof.exit_info.account_address = account.address.clone();
-                proof
-            })
+            .map(|proof| self.exit_proof_data(proof))
             .collect();
 
         let total_completed_num = storage
@@ -411,6 +1030,83 @@ impl AppData {
         })
     }
 
+    /// Loads the page of the blocks numbered in `[from, to]` in ascending order of number,
+    /// at most `GET_BLOCKS_NUM_LIMIT` blocks are returned per page.
+    pub(crate) async fn get_blocks_by_page(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        page: u32,
+        limit: Option<u32>,
+    ) -> Result<PaginatedResponse<StorageBlock>, ExodusStatus> {
+        let limit = limit.unwrap_or(GET_BLOCKS_NUM_LIMIT);
+        if limit > GET_BLOCKS_NUM_LIMIT {
+            return Err(ExodusStatus::BlocksLoadTooMany);
+        }
+        let mut storage = self.access_storage().await?;
+        let blocks = storage
+            .chain()
+            .block_schema()
+            .get_blocks_in_range(
+                from,
+                to,
+                i64::from(limit),
+                i64::from(page) * i64::from(limit),
+            )
+            .await?;
+        let total = storage
+            .chain()
+            .block_schema()
+            .count_blocks_in_range(from, to)
+            .await?;
+        Ok(PaginatedResponse::new(blocks, page, limit, total as u64))
+    }
+
+    /// Loads the proofs finished after the cursor `(since, after_id)` for exporting incrementally,
+    /// or all the proofs finished at or after `since` if `after_id` is not set.
+    /// At most `GET_PROOFS_NUM_LIMIT` proofs are returned per request.
+    pub(crate) async fn proofs_finished_since(
+        &self,
+        since: DateTime<Utc>,
+        after_id: Option<ProofId>,
+        limit: Option<u32>,
+    ) -> Result<FinishedProofs, ExodusStatus> {
+        let limit = limit.unwrap_or(GET_PROOFS_NUM_LIMIT);
+        if limit > GET_PROOFS_NUM_LIMIT {
+            return Err(ExodusStatus::ProofsLoadTooMany);
+        }
+        // The proof ids start from 1, so the cursor id 0 includes all the proofs finished at `since`.
+        let proofs = self
+            .access_storage()
+            .await?
+            .prover_schema()
+            .get_completed_proofs_after(since, after_id.unwrap_or(0) as i64, limit as i64)
+            .await?;
+        let next_since = proofs.last().and_then(|proof| proof.finished_at);
+        let next_after_id = proofs.last().map(|proof| proof.id as ProofId);
+        let proofs = proofs
+            .into_iter()
+            .map(|proof| self.exit_proof_data(proof))
+            .collect();
+        Ok(FinishedProofs {
+            proofs,
+            next_since,
+            next_after_id,
+        })
+    }
+
+    /// Converts the stored proof, resolving the account address from the recovered state.
+    fn exit_proof_data(&self, proof: StoredExitProof) -> ExitProofData {
+        let mut proof: ExitProofData = proof.into();
+        let account = self
+            .recovered_state()
+            .accounts
+            .get(&proof.exit_info.account_id)
+            .unwrap();
+        proof.exit_info.account_address = account.address.clone();
+        proof
+    }
+
     pub(crate) fn get_stored_block_info(
         &self,
         chain_id: ChainId,
@@ -421,9 +1117,31 @@ impl AppData {
         Ok(self.recovered_state().stored_block_info(chain_id))
     }
 
+    /// Rejects the request if the client expected a state root other than the current one,
+    /// the proof of the exit quoted against a stale root would mismatch the client's expectation.
+    pub(crate) fn check_expected_root(
+        &self,
+        expected_root: Option<H256>,
+    ) -> Result<(), ExodusStatus> {
+        match expected_root {
+            Some(expected_root) if expected_root != self.recovered_state().current_root() => {
+                Err(ExodusStatus::StaleRoot)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn get_current_root(&self) -> CurrentRoot {
+        let recovered_state = self.recovered_state();
+        CurrentRoot {
+            block_number: recovered_state.last_block_info.block_number,
+            root: recovered_state.current_root(),
+        }
+    }
+
     pub(crate) async fn get_recover_progress(&self) -> Result<Progress, ExodusStatus> {
         if !self.recover_progress.is_completed() {
-            let mut storage = self.access_storage().await;
+            let mut storage = self.access_storage().await?;
             let verified_block_num = storage
                 .chain()
                 .block_schema()
@@ -436,6 +1154,22 @@ impl AppData {
         Ok(self.recover_progress.get_progress())
     }
 
+    /// Gets the state of loading the recovered state and the percentage of the recovered blocks,
+    /// the requests that require the recovered state are rejected until it's ready.
+    pub(crate) async fn get_sync_status(&self) -> Result<SyncStatus, ExodusStatus> {
+        let progress = self.get_recover_progress().await?;
+        let state = if self.is_not_sync_completed() {
+            SyncState::Loading
+        } else {
+            SyncState::Ready
+        };
+        Ok(SyncStatus {
+            state,
+            percentage: progress.percentage(),
+            progress,
+        })
+    }
+
     pub async fn generate_batch_proofs_tasks(
         &self,
         batch_exit_info: BatchExitRequest,
@@ -453,6 +1187,7 @@ impl AppData {
                     sub_account_id: batch_exit_info.sub_account_id,
                     l1_target_token: batch_exit_info.token_id,
                     l2_source_token: batch_exit_info.token_id,
+                    expected_root: None,
                 });
             }
         } else {
@@ -466,6 +1201,7 @@ impl AppData {
                         sub_account_id: batch_exit_info.sub_account_id,
                         l1_target_token: token_id,
                         l2_source_token: batch_exit_info.token_id,
+                        expected_root: None,
                     });
                 }
             }
@@ -473,6 +1209,37 @@ impl AppData {
         exit_infos
     }
 
+    /// Checks the exit eligibility of the accounts in bulk against the recovered state(without the database):
+    /// the account exists, the token can be exited to layer1 and the balance is nonzero.
+    pub(crate) fn check_exit_eligibility(
+        &self,
+        accounts: &[(ZkLinkAddress, SubAccountId, TokenId)],
+    ) -> Vec<EligibilityResult> {
+        accounts
+            .iter()
+            .map(|(address, sub_account_id, token_id)| {
+                // USD is exited as USDX tokens, and USDX tokens can't be exited directly.
+                let valid_token_pair = **token_id == USD_TOKEN_ID
+                    || check_source_token_and_target_token(*token_id, *token_id).0;
+                let status = if !valid_token_pair {
+                    ExodusStatus::InvalidL1L2Token
+                } else {
+                    match self.check_exit_info(address, *sub_account_id, *token_id) {
+                        Ok(_) => ExodusStatus::Ok,
+                        Err(status) => status,
+                    }
+                };
+                EligibilityResult {
+                    address: address.clone(),
+                    sub_account_id: *sub_account_id,
+                    token_id: *token_id,
+                    eligible: status == ExodusStatus::Ok,
+                    status,
+                }
+            })
+            .collect()
+    }
+
     fn check_exit_info(
         &self,
         address: &ZkLinkAddress,
@@ -500,6 +1267,67 @@ impl AppData {
     }
 }
 
+/// Maps the status of the exit proof task to the task id:
+/// the task id is returned only when the proof of the task has been completed.
+pub fn task_id_by_status(task_status: Option<StoredTaskStatus>) -> Result<TaskId, ExodusStatus> {
+    match task_status {
+        None => Err(ExodusStatus::ExitProofTaskNotExist),
+        Some(StoredTaskStatus {
+            completed: false,
+            cancelled: true,
+            ..
+        }) => Err(ExodusStatus::ProofTaskCancelled),
+        Some(StoredTaskStatus {
+            completed: false, ..
+        }) => Err(ExodusStatus::ProofGenerating),
+        Some(StoredTaskStatus {
+            id,
+            completed: true,
+            ..
+        }) => Ok(id.into()),
+    }
+}
+
+/// Collects the USD prices of the tokens, the unpriced(zero price) tokens take `default_price`,
+/// and are left out(valued as zero) with a warning if it isn't configured.
+pub(crate) fn token_prices(
+    tokens: Vec<DbToken>,
+    default_price: Option<&BigDecimal>,
+) -> HashMap<TokenId, BigDecimal> {
+    let mut prices = HashMap::new();
+    let mut unpriced_tokens = Vec::new();
+    for token in tokens {
+        let token_id = TokenId(token.token_id as u32);
+        match (token.usd_price.is_zero(), default_price) {
+            (false, _) => {
+                prices.insert(token_id, token.usd_price);
+            }
+            (true, Some(default_price)) => {
+                prices.insert(token_id, default_price.clone());
+            }
+            (true, None) => unpriced_tokens.push(token_id),
+        }
+    }
+    if !unpriced_tokens.is_empty() {
+        warn!(
+            "The tokens {:?} are not priced and RUNTIME_CONFIG_DEFAULT_PRICE is not set, \
+            they are valued as zero",
+            unpriced_tokens
+        );
+    }
+    prices
+}
+
+/// The priority of the exit task is the order of magnitude of the USD value,
+/// e.g. 0 for less than $10, 1 for less than $100 and 6 for a million dollars.
+pub(crate) fn priority_by_usd_value(usd_value: &BigDecimal) -> i16 {
+    let (integer, _) = usd_value.with_scale(0).into_bigint_and_exponent();
+    if integer <= Zero::zero() {
+        return 0;
+    }
+    (integer.to_string().len() - 1) as i16
+}
+
 pub fn convert_balance_resp(balances: Vec<StorageBalance>) -> SubAccountBalances {
     let mut resp: SubAccountBalances = HashMap::new();
     for balance in balances.iter() {
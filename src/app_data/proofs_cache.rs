@@ -64,7 +64,7 @@ impl ProofsCache {
             });
         }
 
-        let mut storage = self.conn_pool.access_storage_with_retry().await;
+        let mut storage = self.conn_pool.access_storage_with_retry().await?;
         if let Some(stored_proof) = storage
             .prover_schema()
             .get_proof_by_exit_info((&exit_info).into())
@@ -17,8 +17,15 @@ pub struct RecoverProgress {
 
 impl RecoverProgress {
     pub async fn from_config(config: &RecoverStateConfig) -> Self {
-        let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.pool_size);
-        let mut storage = conn_pool.access_storage_with_retry().await;
+        let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.api_pool_size())
+            .with_retry_policy(
+                config.db.connection_retries(),
+                config.db.connection_retry_delay(),
+            );
+        let mut storage = conn_pool
+            .access_storage_with_retry()
+            .await
+            .expect("Failed to access the storage");
         let verified_block_num = storage
             .chain()
             .block_schema()
@@ -26,7 +33,7 @@ impl RecoverProgress {
             .await
             .expect("Failed to get last verified block number from database");
 
-        let (_, _, zklink_contract) = get_fully_on_chain_zklink_contract(config);
+        let (_, _, zklink_contract) = get_fully_on_chain_zklink_contract(&config.layer1);
         let total_verified_block = zklink_contract
             .get_total_verified_blocks()
             .await
@@ -41,7 +48,10 @@ impl RecoverProgress {
 
     pub async fn sync_from_database(&self, conn_pool: &ConnectionPool) {
         let mut ticker = interval(Duration::from_secs(1));
-        let mut storage = conn_pool.access_storage_with_retry().await;
+        let mut storage = conn_pool
+            .access_storage_with_retry()
+            .await
+            .expect("Failed to access the storage");
         info!("Sync recovering state started!");
         loop {
             if self.is_completed() {
@@ -82,3 +92,29 @@ pub struct Progress {
     pub(crate) current_block: BlockNumber,
     pub(crate) total_verified_block: BlockNumber,
 }
+
+impl Progress {
+    /// The percentage of the verified blocks that have been recovered.
+    pub fn percentage(&self) -> u8 {
+        if *self.total_verified_block == 0 {
+            return 100;
+        }
+        let percentage = *self.current_block as u64 * 100 / *self.total_verified_block as u64;
+        percentage.min(100) as u8
+    }
+}
+
+/// Whether the recovered state that the requests are served from has been loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncState {
+    Loading,
+    Ready,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub(crate) state: SyncState,
+    pub(crate) percentage: u8,
+    pub(crate) progress: Progress,
+}
@@ -1,12 +1,19 @@
 use crate::response::ExodusStatus;
 use crate::response::SubAccountBalances;
+use bigdecimal::num_bigint::BigUint;
+use bigdecimal::{BigDecimal, Zero};
 use std::collections::HashMap;
+use zklink_crypto::params::{
+    GLOBAL_ASSET_ACCOUNT_ID, TOKEN_MAX_PRECISION, USDX_TOKEN_ID_LOWER_BOUND,
+    USDX_TOKEN_ID_UPPER_BOUND, USD_TOKEN_ID,
+};
+use zklink_storage::chain::state::LOAD_STATE_CHUNK_SIZE;
 use zklink_storage::ConnectionPool;
 use zklink_types::block::{Block, StoredBlockInfo};
 use zklink_types::utils::{
     calculate_actual_token, recover_raw_token, recover_sub_account_by_token,
 };
-use zklink_types::{AccountId, AccountMap, ChainId, SubAccountId, TokenId, ZkLinkAddress};
+use zklink_types::{AccountId, AccountMap, ChainId, SubAccountId, TokenId, ZkLinkAddress, H256};
 
 #[derive(Debug, Clone, Default)]
 pub struct RecoveredState {
@@ -30,7 +37,7 @@ impl RecoveredState {
         let accounts = storage
             .chain()
             .state_schema()
-            .load_circuit_state(last_executed_block_number)
+            .load_circuit_state_streaming(last_executed_block_number, LOAD_STATE_CHUNK_SIZE)
             .await
             .expect("Failed to load verified state")
             .1;
@@ -80,6 +87,54 @@ impl RecoveredState {
         }
     }
 
+    /// Returns the tokens with nonzero balance held by the sub account, in ascending order of token id.
+    pub(crate) fn account_tokens(
+        &self,
+        account_address: &ZkLinkAddress,
+        sub_account_id: SubAccountId,
+    ) -> Result<Vec<TokenId>, ExodusStatus> {
+        let Some(id) = self.account_id_by_address.get(account_address) else {
+            return Err(ExodusStatus::AccountNotExist);
+        };
+        let mut tokens: Vec<TokenId> = self
+            .accounts
+            .get(id)
+            .expect("Account should be exist")
+            .get_existing_token_balances()
+            .iter()
+            .filter(|&(&token_id, balance)| {
+                recover_sub_account_by_token(token_id) == sub_account_id && !balance.is_zero()
+            })
+            .map(|(&token_id, _)| recover_raw_token(token_id))
+            .collect();
+        tokens.sort();
+        Ok(tokens)
+    }
+
+    /// Returns the total USD value of all the sub accounts of the account,
+    /// the tokens without price are counted as zero.
+    pub(crate) fn usd_value(
+        &self,
+        account_id: AccountId,
+        prices: &HashMap<TokenId, BigDecimal>,
+    ) -> BigDecimal {
+        let Some(account) = self.accounts.get(&account_id) else {
+            return BigDecimal::zero();
+        };
+        account
+            .get_existing_token_balances()
+            .iter()
+            .filter_map(|(&token_id, balance)| {
+                let price = prices.get(&recover_raw_token(token_id))?;
+                let amount = BigDecimal::new(
+                    balance.reserve0.0.clone().into(),
+                    TOKEN_MAX_PRECISION as i64,
+                );
+                Some(amount * price)
+            })
+            .sum()
+    }
+
     pub fn empty_balance(
         &self,
         account_id: AccountId,
@@ -94,7 +149,64 @@ impl RecoveredState {
             .map_or(true, |balance| balance.is_zero())
     }
 
+    fn balance(
+        &self,
+        account_id: AccountId,
+        sub_account_id: SubAccountId,
+        token_id: TokenId,
+    ) -> BigUint {
+        let real_token_id = calculate_actual_token(sub_account_id, token_id);
+        self.accounts
+            .get(&account_id)
+            .and_then(|account| account.get_existing_token_balances().get(&real_token_id))
+            .map_or_else(BigUint::zero, |balance| balance.reserve0.0.clone())
+    }
+
+    /// Computes the withdraw amount of the exit in the same way as the exit circuit:
+    /// the ratio of the balance to the global assets of all chains, multiplied by the global asset of the target chain.
+    pub fn exit_amount(
+        &self,
+        account_id: AccountId,
+        sub_account_id: SubAccountId,
+        l2_source_token: TokenId,
+        l1_target_token_after_mapping: TokenId,
+        chain_id: ChainId,
+        max_chain_num: usize,
+    ) -> BigUint {
+        // USD is backed by all the USDX tokens of the global asset account.
+        let global_tokens = if *l2_source_token == USD_TOKEN_ID {
+            (USDX_TOKEN_ID_LOWER_BOUND..=USDX_TOKEN_ID_UPPER_BOUND)
+                .map(TokenId)
+                .collect()
+        } else {
+            vec![l1_target_token_after_mapping]
+        };
+        let global_asset = |chain_id: u8, token_id: TokenId| {
+            self.balance(GLOBAL_ASSET_ACCOUNT_ID, SubAccountId(chain_id), token_id)
+        };
+        let total_global_asset: BigUint = (1..=max_chain_num as u8)
+            .flat_map(|chain_id| {
+                global_tokens
+                    .iter()
+                    .map(move |&token_id| global_asset(chain_id, token_id))
+            })
+            .sum();
+        if total_global_asset.is_zero() {
+            return BigUint::zero();
+        }
+
+        let precision = BigUint::from(10u8).pow(TOKEN_MAX_PRECISION as u32);
+        let withdraw_ratio = self.balance(account_id, sub_account_id, l2_source_token) * &precision
+            / total_global_asset;
+        global_asset(*chain_id, l1_target_token_after_mapping) * withdraw_ratio / precision
+    }
+
     pub(crate) fn stored_block_info(&self, chain_id: ChainId) -> StoredBlockInfo {
         self.last_block_info.stored_block_info(chain_id)
     }
+
+    /// Returns the root of the loaded account tree, encoded the same as the `state_hash` stored on chain.
+    pub fn current_root(&self) -> H256 {
+        self.last_block_info.get_eth_encoded_root()
+    }
 }
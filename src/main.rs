@@ -1,3 +1,4 @@
+use offchain_recover_state::discover_deployment_blocks;
 use recover_state_config::RecoverStateConfig;
 use recover_state_server::run_server;
 
@@ -6,6 +7,9 @@ async fn main() {
     dotenvy::dotenv().expect(".env file not found");
     tracing_subscriber::fmt::init();
 
-    let config = RecoverStateConfig::from_env();
+    let mut config = RecoverStateConfig::from_env();
+    discover_deployment_blocks(&mut config.layer1)
+        .await
+        .expect("Failed to discover the deployment blocks of contracts");
     run_server(config).await.unwrap();
 }
@@ -1,11 +1,59 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use zklink_types::{ChainId, SubAccountId, TokenId, ZkLinkAddress};
+use uuid::Uuid;
+use zklink_prover::exit_type::ProofId;
+use zklink_types::{BlockNumber, ChainId, SubAccountId, TokenId, ZkLinkAddress, H256};
+
+use crate::response::SerialId;
+
+/// The maximum length of the request id taken from the client.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// The correlation id of the api request, propagated from or generated for the `X-Request-Id` header.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Takes the `X-Request-Id` of the client if it's at most `MAX_REQUEST_ID_LEN` printable ASCII chars,
+    /// otherwise generates a new one, since the id is logged and stored along with the proof tasks.
+    pub fn from_header(header: Option<&str>) -> Self {
+        let request_id = header
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LEN
+                    && id.bytes().all(|byte| (b' '..=b'~').contains(&byte))
+            })
+            .map(ToString::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        Self(request_id)
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BalanceRequest {
     pub address: ZkLinkAddress,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalancesPageRequest {
+    pub address: ZkLinkAddress,
+    /// Only the balances of this sub account are returned if specified.
+    pub sub_account_id: Option<SubAccountId>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountTokensRequest {
+    pub address: ZkLinkAddress,
+    pub sub_account_id: SubAccountId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubAccountsRequest {
+    pub address: ZkLinkAddress,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StoredBlockInfoRequest {
     pub chain_id: ChainId,
@@ -16,6 +64,17 @@ pub struct UnprocessedDepositRequest {
     pub chain_id: ChainId,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriorityOpRequest {
+    pub chain_id: ChainId,
+    pub serial_id: SerialId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingDepositsRequest {
+    pub address: ZkLinkAddress,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TokenRequest {
     pub token_id: TokenId,
@@ -27,9 +86,66 @@ pub struct ProofsRequest {
     pub proofs_num: u32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuePositionRequest {
+    pub task_id: ProofId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofsStreamRequest {
+    /// Replays the completed proofs whose id is greater than `from_id` before streaming.
+    pub from_id: Option<ProofId>,
+}
+
+/// The message sent by the client of proof updates websocket, the subscriptions are accumulated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofUpdatesRequest {
+    pub subscribe: Vec<ProofId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofsSinceRequest {
+    /// The RFC3339 timestamp, the proofs finished at or after it are returned.
+    pub since: DateTime<Utc>,
+    /// The id of the last proof of the previous page, only the proofs finished at `since`
+    /// with a greater id are returned along with the later ones.
+    pub after_id: Option<ProofId>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlocksRequest {
+    /// The first block number of the range(inclusive).
+    pub from: BlockNumber,
+    /// The last block number of the range(inclusive).
+    pub to: BlockNumber,
+    /// The page number starting from 0.
+    #[serde(default)]
+    pub page: u32,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumeRequest {
+    /// The first block number of the range(inclusive).
+    pub from: BlockNumber,
+    /// The last block number of the range(inclusive).
+    pub to: BlockNumber,
+    /// Only the volume of this token is returned if specified, otherwise the volumes of all tokens.
+    pub token_id: Option<TokenId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MaintenanceRequest {
+    pub read_only: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BatchExitRequest {
     pub(crate) address: ZkLinkAddress,
     pub(crate) sub_account_id: SubAccountId,
     pub(crate) token_id: TokenId,
+    /// The tasks are rejected if the current state root differs from it.
+    #[serde(default)]
+    pub(crate) expected_root: Option<H256>,
 }
@@ -32,33 +32,49 @@ impl<T: Serialize + Clone> ExodusResponse<T> {
         }
     }
 
+    pub fn code(mut self, code: ExodusStatus) -> ExodusResponse<T> {
+        self.code = code as i32;
+        self
+    }
+
     pub fn data(mut self, data: T) -> ExodusResponse<T> {
         self.data = data.into();
         self
     }
 }
 
-#[derive(Copy, Clone, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
 pub enum ExodusStatus {
     Ok = 0,
     ProofTaskAlreadyExists = 50,
     ProofGenerating = 51,
     ProofCompleted = 52,
+    ProofTaskCancelled = 53,
     NonBalance = 60,
     ExistTaskWithinThreeHour = 61,
+    RateLimitExceeded = 62,
     RecoverStateUnfinished = 70,
 
     ApiClosedTemporarily = 90,
+    Maintenance = 91,
 
     TokenNotExist = 101,
     AccountNotExist = 102,
     ChainNotExist = 103,
     ExitProofTaskNotExist = 104,
+    PriorityOpNotExist = 105,
 
     InvalidL1L2Token = 201,
     ProofsLoadTooMany = 202,
+    BlocksLoadTooMany = 203,
+    StaleRoot = 204,
+    BalancePageTooLarge = 205,
+    InvalidBlockRange = 206,
+
+    Unauthorized = 401,
 
     InternalErr = 500,
+    RequestTimeout = 504,
 }
 
 impl From<anyhow::Error> for ExodusStatus {
@@ -76,27 +92,39 @@ impl ToString for ExodusStatus {
             ExodusStatus::ProofTaskAlreadyExists => "The proof Task already exists",
             ExodusStatus::ProofGenerating => "The proof task is running",
             ExodusStatus::ProofCompleted => "The task has been completed",
+            ExodusStatus::ProofTaskCancelled => "The proof task failed and has been cancelled",
             ExodusStatus::NonBalance => "The token of the account is no balance",
             ExodusStatus::ExistTaskWithinThreeHour => {
                 "The task has been generated within three hours"
             }
+            ExodusStatus::RateLimitExceeded => "There are too many pending tasks of the address",
             ExodusStatus::RecoverStateUnfinished => "Recovering state is unfinished",
             ExodusStatus::ApiClosedTemporarily => "The api closed temporarily",
+            ExodusStatus::Maintenance => "The server is under maintenance, writes are rejected",
 
             // Not exist info
             ExodusStatus::TokenNotExist => "The token not exist",
             ExodusStatus::AccountNotExist => "The account not exist",
             ExodusStatus::ChainNotExist => "The chain not exist",
             ExodusStatus::ExitProofTaskNotExist => "The exit proof task not exist",
+            ExodusStatus::PriorityOpNotExist => "The priority op not exist",
 
             // Invalid parameters
             ExodusStatus::InvalidL1L2Token => {
                 "The relationship between l1 token and l2 token is incorrect"
             }
             ExodusStatus::ProofsLoadTooMany => "There are too many proofs to obtain",
+            ExodusStatus::BlocksLoadTooMany => "There are too many blocks to obtain",
+            ExodusStatus::StaleRoot => "The state root has changed since the expected root",
+            ExodusStatus::BalancePageTooLarge => "There are too many balances to obtain",
+            ExodusStatus::InvalidBlockRange => "The end block is before the start block",
+
+            // Permission denied
+            ExodusStatus::Unauthorized => "The admin token is missing or invalid",
 
             // Internal error,
             ExodusStatus::InternalErr => "Exodus server internal error",
+            ExodusStatus::RequestTimeout => "The request timed out",
         }
         .to_string()
     }
@@ -1,32 +1,176 @@
 use bigdecimal::num_bigint::BigUint;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use zklink_prover::exit_type::ProofId;
-use zklink_prover::ExitProofData;
-use zklink_types::{ChainId, Deposit, SubAccountId, TokenId, ZkLinkAddress};
+use zklink_prover::{ExitInfo, ExitProofData};
+use zklink_storage::PoolStatus;
+use zklink_types::{
+    AccountId, BlockNumber, ChainId, Deposit, FullExit, SubAccountId, TokenId, ZkLinkAddress, H256,
+};
 use zklink_utils::{BigUintSerdeAsRadix10Str, BigUintSerdeWrapper};
 
+use super::ExodusStatus;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Proofs {
     pub(crate) total_completed_num: u32,
     pub(crate) proofs: Vec<ExitProofData>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FinishedProofs {
+    pub(crate) proofs: Vec<ExitProofData>,
+    /// The `finished_at` of the last proof, used as the `since` of the next request.
+    pub(crate) next_since: Option<DateTime<Utc>>,
+    /// The id of the last proof, used as the `after_id` of the next request.
+    pub(crate) next_after_id: Option<ProofId>,
+}
+
+/// A page of the items, `total` is the number of the items of all pages.
+#[derive(Debug, Serialize, Clone)]
+pub struct PaginatedResponse<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) page: u32,
+    pub(crate) limit: u32,
+    pub(crate) total: u64,
+    pub(crate) total_pages: u64,
+}
+
+impl<T> PaginatedResponse<T> {
+    pub fn new(items: Vec<T>, page: u32, limit: u32, total: u64) -> Self {
+        let total_pages = if limit == 0 {
+            0
+        } else {
+            (total + u64::from(limit) - 1) / u64::from(limit)
+        };
+        Self {
+            items,
+            page,
+            limit,
+            total,
+            total_pages,
+        }
+    }
+}
+
 pub type SerialId = u64;
 pub type SubAccountBalances = HashMap<SubAccountId, HashMap<TokenId, BigUintSerdeWrapper>>;
+/// A page of the balances of an account, ordered by sub account and token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BalancesPage {
+    pub(crate) balances: SubAccountBalances,
+    pub(crate) limit: u32,
+    pub(crate) offset: u32,
+    /// The number of the balances of all pages.
+    pub(crate) total: u64,
+}
+/// The total amount moved by the transfers of each token.
+pub type TokenVolumes = HashMap<TokenId, BigUintSerdeWrapper>;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UnprocessedPriorityOp {
+    pub(crate) chain_id: ChainId,
     pub(crate) serial_id: SerialId,
     pub(crate) pub_data: PublicData,
 }
 
+/// The numbers of the exit proof tasks by status.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PendingTasksCount {
+    /// The tasks waiting in the queue, not claimed by any prover yet.
     pub(crate) count: u32,
+    /// The tasks claimed by the provers whose proofs are being generated.
+    pub(crate) in_progress: u32,
+    /// The tasks whose proofs have been generated.
+    pub(crate) completed: u32,
 }
 
+/// The aggregate statistics of the exit proof tasks for monitoring.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProverStats {
+    /// The tasks waiting in the queue, not claimed by any prover yet.
+    pub(crate) pending: u32,
+    /// The tasks claimed by the provers whose proofs are being generated.
+    pub(crate) running: u32,
+    /// The tasks whose proofs have been generated.
+    pub(crate) completed: u32,
+    /// The average time spent on generating a proof, absent before any proof is completed.
+    pub(crate) avg_duration_ms: Option<u64>,
+    /// The sum of the withdrawal amounts of the completed proofs by layer2 source token.
+    pub(crate) withdraw_amounts: TokenVolumes,
+}
+
+/// The number of unstarted tasks ahead of the task, `None` if the task is not waiting in the queue.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct QueuePosition {
+    pub(crate) position: Option<u32>,
+}
+
+/// The total size in bytes(including indexes and toast) of the storage table.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TableSize {
+    pub(crate) table: String,
+    pub(crate) bytes: i64,
+}
+
+/// The connection metrics of the database pool, healthy if no caller is waiting for a connection.
+#[derive(Debug, Serialize, Clone)]
+pub struct DbHealth {
+    #[serde(flatten)]
+    pub(crate) pool_status: PoolStatus,
+    pub(crate) healthy: bool,
+}
+
+impl From<PoolStatus> for DbHealth {
+    fn from(pool_status: PoolStatus) -> Self {
+        Self {
+            pool_status,
+            healthy: pool_status.waiting == 0,
+        }
+    }
+}
+
+/// The merkle root of the recovered state and the block number it corresponds to.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CurrentRoot {
+    pub block_number: BlockNumber,
+    pub root: H256,
+}
+
+/// The exit eligibility of the (account, sub account, token), `status` is the reason if not eligible.
+#[derive(Debug, Serialize, Clone)]
+pub struct EligibilityResult {
+    pub(crate) address: ZkLinkAddress,
+    pub(crate) sub_account_id: SubAccountId,
+    pub(crate) token_id: TokenId,
+    pub(crate) eligible: bool,
+    pub(crate) status: ExodusStatus,
+}
+
+/// The completed proof event of the proofs stream.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletedProof {
+    pub(crate) id: ProofId,
+    pub(crate) exit_info: ExitInfo,
+}
+
+/// The final status of the proof task pushed by the proof updates websocket.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofEventStatus {
+    Completed,
+    Failed,
+}
+
+/// The event of proof updates websocket, published when the proof task is finished.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProofEvent {
+    pub(crate) task_id: ProofId,
+    pub(crate) status: ProofEventStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct TaskId {
     id: ProofId,
 }
@@ -42,7 +186,7 @@ impl From<i64> for TaskId {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum PublicData {
     Deposit(DepositData),
-    FullExit,
+    FullExit(FullExitData),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -68,3 +212,26 @@ impl From<Deposit> for DepositData {
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FullExitData {
+    chain_id: ChainId,
+    account_id: AccountId,
+    sub_account_id: SubAccountId,
+    l2_source_token_id: TokenId,
+    l1_target_token_id: TokenId,
+    exit_address: ZkLinkAddress,
+}
+
+impl From<FullExit> for FullExitData {
+    fn from(value: FullExit) -> Self {
+        Self {
+            chain_id: value.to_chain_id,
+            account_id: value.account_id,
+            sub_account_id: value.sub_account_id,
+            l2_source_token_id: value.l2_source_token,
+            l1_target_token_id: value.l1_target_token,
+            exit_address: value.exit_address,
+        }
+    }
+}
@@ -1,21 +1,33 @@
+use std::collections::HashSet;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
 use actix_cors::Cors;
-use actix_web::dev::Service;
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::{web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer};
+use actix_ws::Message;
+use futures::{stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn, Instrument};
 
-use recover_state_config::RecoverStateConfig;
+use recover_state_config::{DBConfig, RecoverStateConfig};
+use zklink_prover::exit_type::ProofId;
 use zklink_prover::ExitInfo as ExitRequest;
 use zklink_storage::ConnectionPool;
+use zklink_utils::BigUintSerdeWrapper;
 
 use crate::app_data::{ProofsCache, RecoverProgress};
 use crate::request::{
-    BalanceRequest, BatchExitRequest, ProofsRequest, StoredBlockInfoRequest, TokenRequest,
-    UnprocessedDepositRequest,
+    AccountTokensRequest, BalanceRequest, BalancesPageRequest, BatchExitRequest, BlocksRequest,
+    MaintenanceRequest, PendingDepositsRequest, PriorityOpRequest, ProofUpdatesRequest,
+    ProofsRequest, ProofsSinceRequest, ProofsStreamRequest, QueuePositionRequest, RequestId,
+    StoredBlockInfoRequest, SubAccountsRequest, TokenRequest, UnprocessedDepositRequest,
+    VolumeRequest,
 };
-use crate::response::{ExodusResponse, ExodusStatus};
+use crate::response::{CompletedProof, ExodusResponse, ExodusStatus};
 use crate::AppData;
 
 /// Get the ZkLink contract addresses of all blockchain.
@@ -43,6 +55,21 @@ async fn recover_progress(data: web::Data<Arc<AppData>>) -> actix_web::Result<Ht
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Request to get whether the recovered state is loaded and the recover percentage.
+async fn sync_status(data: web::Data<Arc<AppData>>) -> actix_web::Result<HttpResponse> {
+    let response = match data.get_sync_status().await {
+        Ok(status) => ExodusResponse::Ok().data(status),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Request to get the merkle root of the recovered state the server is using.
+async fn current_root(data: web::Data<Arc<AppData>>) -> actix_web::Result<HttpResponse> {
+    let response = ExodusResponse::Ok().data(data.get_current_root());
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Request to get max running task id.
 async fn running_max_task_id(data: web::Data<Arc<AppData>>) -> actix_web::Result<HttpResponse> {
     let response = match data.running_max_task_id().await {
@@ -61,6 +88,28 @@ async fn pending_tasks_count(data: web::Data<Arc<AppData>>) -> actix_web::Result
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Request to get the aggregate statistics of the exit proof tasks.
+async fn prover_stats(data: web::Data<Arc<AppData>>) -> actix_web::Result<HttpResponse> {
+    let response = match data.get_prover_stats().await {
+        Ok(stats) => ExodusResponse::Ok().data(stats),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Request to get the number of unstarted tasks ahead of the specified task.
+async fn get_task_queue_position(
+    request: web::Json<QueuePositionRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let task_id = request.into_inner().task_id;
+    let response = match data.get_queue_position(task_id).await {
+        Ok(position) => ExodusResponse::Ok().data(position),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Get token info(supported chains, token's contract addresses) by token_id
 async fn get_token(
     token_request: web::Json<TokenRequest>,
@@ -104,6 +153,56 @@ async fn get_balances(
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Get a page of the balances of ZkLinkAddress from the database
+async fn get_balances_page(
+    request: web::Query<BalancesPageRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let BalancesPageRequest {
+        address,
+        sub_account_id,
+        limit,
+        offset,
+    } = request.into_inner();
+    let response = match data
+        .get_balances_by_storage(address, sub_account_id, limit, offset)
+        .await
+    {
+        Ok(balances) => ExodusResponse::Ok().data(balances),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get the sub accounts with nonzero balance of ZkLinkAddress
+async fn get_sub_accounts(
+    request: web::Json<SubAccountsRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let address = request.into_inner().address;
+    let response = match data.get_sub_accounts(address).await {
+        Ok(sub_account_ids) => ExodusResponse::Ok().data(sub_account_ids),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get the tokens with nonzero balance held by the sub account of ZkLinkAddress
+async fn get_account_tokens(
+    request: web::Json<AccountTokensRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let AccountTokensRequest {
+        address,
+        sub_account_id,
+    } = request.into_inner();
+    let response = match data.get_account_tokens(address, sub_account_id) {
+        Ok(tokens) => ExodusResponse::Ok().data(tokens),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Get all unprocessed priority ops of target chain  by chain_id
 async fn get_unprocessed_priority_ops(
     unprocessed_deposit_request: web::Json<UnprocessedDepositRequest>,
@@ -117,6 +216,35 @@ async fn get_unprocessed_priority_ops(
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Get the priority op by chain id and serial id
+async fn get_priority_op(
+    priority_op_request: web::Json<PriorityOpRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let PriorityOpRequest {
+        chain_id,
+        serial_id,
+    } = priority_op_request.into_inner();
+    let response = match data.get_priority_op(chain_id, serial_id).await {
+        Ok(op) => ExodusResponse::Ok().data(op),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get all deposits of the recipient that have never been executed in layer2 by ZkLinkAddress
+async fn get_pending_deposits(
+    pending_deposits_request: web::Json<PendingDepositsRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let address = pending_deposits_request.into_inner().address;
+    let response = match data.get_pending_deposits(address).await {
+        Ok(deposits) => ExodusResponse::Ok().data(deposits),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Get the specified number of proofs closer to the id by page(page 1,num 20 => proofs ids: 1~20)
 async fn get_proofs_by_page(
     proofs_request: web::Json<ProofsRequest>,
@@ -146,6 +274,32 @@ async fn get_proof_by_info(
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Get the completed proof by the exit parameters in the query string.
+async fn get_proof_by_params(
+    exit_request: web::Query<ExitRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let exit_info = exit_request.into_inner();
+    let response = match data.get_proof_by_params(exit_info).await {
+        Ok(proof) => ExodusResponse::Ok().data(proof),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Quote the withdraw amount of the specified exit info without generating the proof.
+async fn quote_exit(
+    exit_request: web::Json<ExitRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let exit_info = exit_request.into_inner();
+    let response = match data.quote_exit_amount(exit_info) {
+        Ok(amount) => ExodusResponse::Ok().data(BigUintSerdeWrapper(amount)),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
 /// Get all proofs of all blockchain by the specified ZkLinkAddress and TokenId.
 async fn get_proofs_by_token(
     batch_exit_info: web::Json<BatchExitRequest>,
@@ -162,10 +316,11 @@ async fn get_proofs_by_token(
 /// Request to generate single proof for the specified exit info.
 async fn generate_proof_task_by_info(
     exit_request: web::Json<ExitRequest>,
+    request_id: web::ReqData<RequestId>,
     data: web::Data<Arc<AppData>>,
 ) -> actix_web::Result<HttpResponse> {
     let exit_info = exit_request.into_inner();
-    let response = match data.generate_proof_task(exit_info).await {
+    let response = match data.generate_proof_task(exit_info, &request_id.0).await {
         Ok(task_id) => ExodusResponse::Ok().data(task_id),
         Err(err) => err.into(),
     };
@@ -175,10 +330,14 @@ async fn generate_proof_task_by_info(
 /// Request to generate batch proofs of all blockchain for the specified token.
 async fn generate_proof_tasks_by_token(
     batch_exit_info: web::Json<BatchExitRequest>,
+    request_id: web::ReqData<RequestId>,
     data: web::Data<Arc<AppData>>,
 ) -> actix_web::Result<HttpResponse> {
     let batch_exit_info = batch_exit_info.into_inner();
-    let response = match data.generate_proof_tasks(batch_exit_info).await {
+    let response = match data
+        .generate_proof_tasks(batch_exit_info, &request_id.0)
+        .await
+    {
         Ok(tasks) => ExodusResponse::Ok().data(tasks),
         Err(err) => err.into(),
     };
@@ -192,43 +351,322 @@ async fn get_proof_task_id(
 ) -> actix_web::Result<HttpResponse> {
     let task_info = task_info.into_inner();
     let response = match data.get_proof_task_id(task_info).await {
-        Ok(task_id) => ExodusResponse::Ok().data(task_id),
+        Ok(task_id) => ExodusResponse::Ok()
+            .code(ExodusStatus::ProofCompleted)
+            .data(task_id),
         Err(err) => err.into(),
     };
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Returns the unauthorized response if the admin token header of the request is missing or invalid.
+fn check_admin(req: &HttpRequest, data: &AppData) -> Option<HttpResponse> {
+    let admin_token = req
+        .headers()
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if data.is_admin(admin_token) {
+        return None;
+    }
+    let response: ExodusResponse<()> = ExodusStatus::Unauthorized.into();
+    Some(HttpResponse::Unauthorized().json(response))
+}
+
+/// Admin request to regenerate the proof of the specified exit info
+async fn requeue_proof(
+    req: HttpRequest,
+    exit_info: web::Json<ExitRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    if let Some(unauthorized) = check_admin(&req, &data) {
+        return Ok(unauthorized);
+    }
+
+    let exit_info = exit_info.into_inner();
+    let response = match data.requeue_proof(exit_info).await {
+        Ok(()) => ExodusResponse::Ok(),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Admin request to turn the maintenance mode on or off
+async fn set_maintenance(
+    req: HttpRequest,
+    request: web::Json<MaintenanceRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    if let Some(unauthorized) = check_admin(&req, &data) {
+        return Ok(unauthorized);
+    }
+
+    let read_only = request.into_inner().read_only;
+    data.set_read_only(read_only);
+    warn!("The maintenance mode is switched to {}", read_only);
+    Ok(HttpResponse::Ok().json(ExodusResponse::<()>::Ok()))
+}
+
+/// Request to get the connection metrics of the database pool.
+async fn db_health(data: web::Data<Arc<AppData>>) -> actix_web::Result<HttpResponse> {
+    let response = ExodusResponse::Ok().data(data.db_health());
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Admin request to get the sizes of the storage tables for capacity planning
+async fn table_sizes(
+    req: HttpRequest,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    if let Some(unauthorized) = check_admin(&req, &data) {
+        return Ok(unauthorized);
+    }
+
+    let response = match data.table_sizes().await {
+        Ok(sizes) => ExodusResponse::Ok().data(sizes),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get the page of the stored blocks in the block number range.
+async fn get_blocks(
+    request: web::Query<BlocksRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let BlocksRequest {
+        from,
+        to,
+        page,
+        limit,
+    } = request.into_inner();
+    let response = match data.get_blocks_by_page(from, to, page, limit).await {
+        Ok(blocks) => ExodusResponse::Ok().data(blocks),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get the total amount moved by the transfers in the block number range, per token.
+async fn block_volume(
+    request: web::Query<VolumeRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let VolumeRequest { from, to, token_id } = request.into_inner();
+    let response = match data.block_volume(from, to, token_id).await {
+        Ok(volumes) => ExodusResponse::Ok().data(volumes),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Get the proofs finished after the specified cursor, for exporting the proofs incrementally.
+async fn proofs_since(
+    request: web::Query<ProofsSinceRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let request = request.into_inner();
+    let response = match data
+        .proofs_finished_since(request.since, request.after_id, request.limit)
+        .await
+    {
+        Ok(proofs) => ExodusResponse::Ok().data(proofs),
+        Err(err) => err.into(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Streams the completed proofs as server-sent events in order of completion,
+/// the completed proofs after `from_id` are replayed first so that reconnecting subscribers miss nothing.
+async fn proofs_stream(
+    request: web::Query<ProofsStreamRequest>,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    // Subscribes before replaying, so that no proof is missed between them
+    let receiver = data.subscribe_completed_proofs();
+    let replay_cursor = match request.from_id {
+        Some(from_id) => match data.replay_cursor(from_id).await {
+            Ok(cursor) => Some(cursor),
+            Err(err) => {
+                let response: ExodusResponse<()> = err.into();
+                return Ok(HttpResponse::Ok().json(response));
+            }
+        },
+        None => None,
+    };
+
+    let events = stream::unfold(
+        (data, receiver, replay_cursor, HashSet::new()),
+        |(data, mut receiver, replay_cursor, mut replayed_ids)| async move {
+            // Replays a page per pass until caught up, then follows the live proofs
+            if let Some(cursor) = replay_cursor {
+                let (proofs, next_cursor) = match data.replay_completed_proofs(cursor).await {
+                    Ok(page) => page,
+                    Err(err) => {
+                        warn!("Failed to replay the completed proofs, err: {:?}", err);
+                        return None;
+                    }
+                };
+                replayed_ids.extend(proofs.iter().map(|proof| proof.id));
+                let events: Vec<_> = proofs.into_iter().map(completed_proof_event).collect();
+                return Some((events, (data, receiver, next_cursor, replayed_ids)));
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(proof) if replayed_ids.contains(&proof.id) => continue,
+                    Ok(proof) => {
+                        let events = vec![completed_proof_event(proof)];
+                        return Some((events, (data, receiver, None, replayed_ids)));
+                    }
+                    Err(RecvError::Lagged(num)) => {
+                        warn!("Proofs stream subscriber lagged, {} proofs skipped", num)
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    )
+    .flat_map(stream::iter);
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
+
+fn completed_proof_event(proof: CompletedProof) -> Result<Bytes, actix_web::Error> {
+    let data = serde_json::to_string(&proof)?;
+    Ok(Bytes::from(format!(
+        "id: {}\nevent: proof_completed\ndata: {}\n\n",
+        proof.id, data
+    )))
+}
+
+/// Pushes the completed or failed events of the subscribed proof tasks over websocket,
+/// the client subscribes the tasks by sending `{"subscribe": [task_ids]}`.
+/// The subscribed tasks that have already finished are pushed right away.
+async fn proof_updates(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<Arc<AppData>>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut receiver = data.subscribe_proof_events();
+
+    actix_rt::spawn(async move {
+        let mut subscribed = HashSet::new();
+        loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        let request = match serde_json::from_str::<ProofUpdatesRequest>(&text) {
+                            Ok(request) => request,
+                            Err(err) => {
+                                debug!("Invalid proof updates request, err: {}", err);
+                                continue;
+                            }
+                        };
+                        subscribed.extend(request.subscribe.iter().copied());
+                        // The tasks finished before subscribing get their current status at once
+                        let Ok(events) = data.finished_proof_events(&request.subscribe).await else {
+                            continue;
+                        };
+                        for event in events {
+                            if !subscribed.remove(&event.task_id) {
+                                continue;
+                            }
+                            let Ok(text) = serde_json::to_string(&event) else {
+                                continue;
+                            };
+                            if session.text(text).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break,
+                },
+                event = receiver.recv() => match event {
+                    // The task is finished, no more events will be published for it
+                    Ok(event) if subscribed.remove(&event.task_id) => {
+                        let Ok(text) = serde_json::to_string(&event) else {
+                            continue;
+                        };
+                        if session.text(text).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(num)) => {
+                        warn!("Proof updates subscriber lagged, {} events skipped", num)
+                    }
+                    Err(RecvError::Closed) => {
+                        let _ = session.close(None).await;
+                        break;
+                    }
+                },
+            }
+        }
+        // The receiver is dropped here, so that the disconnected client holds no broadcast slot
+    });
+
+    Ok(response)
+}
+
 const RECOVER_PROGRESS_PATH: &str = "/recover_progress";
 const CONTRACTS_PATH: &str = "/contracts";
+const SYNC_STATUS_PATH: &str = "/status/sync";
 const GENERATE_PROOF_TASKS_BY_TOKEN: &str = "/generate_proof_tasks_by_token";
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
 
 pub async fn run_server(config: RecoverStateConfig) -> std::io::Result<()> {
     let addrs = config.api.bind_addr();
     let num = config.api.workers_num;
     let enable_http_cors = config.api.enable_http_cors;
-    let contracts = config.layer1.get_contracts();
-    let enable_black_list = config.black_list_time.is_some();
-    let enable_sync_mode = config.enable_sync_mode;
-    let black_list_time = config.black_list_time.unwrap_or(0);
+    let request_timeout = config.api.request_timeout();
+    let black_list_time = config.black_list_time;
 
     let recover_progress = RecoverProgress::from_config(&config).await;
-    let conn_pool = ConnectionPool::new(config.db.url, config.db.pool_size);
-    let proofs_cache = ProofsCache::from_database(conn_pool.clone()).await;
-    let app_data = Arc::new(
-        AppData::new(
-            enable_black_list,
-            enable_sync_mode,
-            black_list_time,
-            conn_pool.clone(),
-            contracts,
-            proofs_cache,
-            recover_progress,
+    let mut conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.api_pool_size())
+        .with_retry_policy(
+            config.db.connection_retries(),
+            config.db.connection_retry_delay(),
         )
-        .await,
-    );
+        .with_slow_query_threshold(config.db.slow_query_threshold())
+        .with_slow_tx_threshold(config.db.slow_tx_threshold());
+    if let Some(read_url) = config.db.read_replica_url.clone() {
+        conn_pool = conn_pool.with_replica(read_url, config.db.read_pool_size());
+    }
+    let proofs_cache = ProofsCache::from_database(conn_pool.clone()).await;
+    let app_data =
+        Arc::new(AppData::new(&config, conn_pool.clone(), proofs_cache, recover_progress).await);
 
-    tokio::spawn(app_data.clone().black_list_escaping(black_list_time));
+    if let Some(black_list_time) = black_list_time {
+        tokio::spawn(app_data.clone().black_list_escaping(black_list_time));
+    }
     tokio::spawn(app_data.clone().sync_recover_progress());
+    if let Some(retention_blocks) = config.runtime.update_retention_blocks {
+        tokio::spawn(app_data.clone().prune_state_updates(retention_blocks));
+    }
+    let zklink_home = config.runtime.zklink_home.clone();
+    tokio::spawn(
+        conn_pool
+            .clone()
+            .watch_failover(move || DBConfig::reload_url(&zklink_home)),
+    );
+    tokio::spawn(
+        app_data
+            .clone()
+            .broadcast_completed_proofs(config.db.url.clone()),
+    );
 
     HttpServer::new(move || {
         let cors = if enable_http_cors {
@@ -237,11 +675,36 @@ pub async fn run_server(config: RecoverStateConfig) -> std::io::Result<()> {
             Cors::default()
         };
         App::new()
+            .wrap_fn(move |req, srv| {
+                // Cancels the handler when it exceeds the request timeout.
+                // The dropped handler releases its connection, and the `ROLLBACK` queued by
+                // an unfinished transaction is flushed when the pool recycles the connection.
+                let http_req = req.request().clone();
+                let fut = srv.call(req);
+                async move {
+                    let Some(timeout) = request_timeout else {
+                        return fut.await;
+                    };
+                    match tokio::time::timeout(timeout, fut).await {
+                        Ok(res) => res,
+                        Err(_) => {
+                            warn!("Request timed out: {}", http_req.path());
+                            let response: ExodusResponse<()> = ExodusStatus::RequestTimeout.into();
+                            Ok(ServiceResponse::new(
+                                http_req,
+                                HttpResponse::GatewayTimeout().json(response),
+                            ))
+                        }
+                    }
+                }
+            })
             .wrap_fn(|req, srv| {
                 let data = req.app_data::<web::Data<Arc<AppData>>>().unwrap();
 
                 let fut: Pin<Box<dyn Future<Output = Result<_, _>>>> = match req.path() {
-                    RECOVER_PROGRESS_PATH | CONTRACTS_PATH => Box::pin(srv.call(req)),
+                    RECOVER_PROGRESS_PATH | CONTRACTS_PATH | SYNC_STATUS_PATH => {
+                        Box::pin(srv.call(req))
+                    }
                     GENERATE_PROOF_TASKS_BY_TOKEN => Box::pin(async move {
                         let response: ExodusResponse<()> =
                             ExodusStatus::ApiClosedTemporarily.into();
@@ -265,6 +728,31 @@ pub async fn run_server(config: RecoverStateConfig) -> std::io::Result<()> {
                 }
             })
             .wrap(cors)
+            .wrap_fn(|req, srv| {
+                // Propagates the request id from the client or generates a new one.
+                let RequestId(request_id) = RequestId::from_header(
+                    req.headers()
+                        .get(REQUEST_ID_HEADER)
+                        .and_then(|id| id.to_str().ok()),
+                );
+                req.extensions_mut().insert(RequestId(request_id.clone()));
+                let span = tracing::info_span!(
+                    "request",
+                    request_id = %request_id,
+                    path = %req.path()
+                );
+
+                let fut = span.in_scope(|| srv.call(req));
+                async move {
+                    let mut res = fut.await?;
+                    if let Ok(value) = HeaderValue::from_str(&request_id) {
+                        res.headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(res)
+                }
+                .instrument(span)
+            })
             .app_data(web::Data::new(app_data.clone()))
             .configure(exodus_config)
     })
@@ -278,20 +766,35 @@ pub fn exodus_config(cfg: &mut web::ServiceConfig) {
     cfg.route(CONTRACTS_PATH, web::get().to(get_contracts))
         .route("/tokens", web::get().to(get_tokens))
         .route(RECOVER_PROGRESS_PATH, web::get().to(recover_progress))
+        .route(SYNC_STATUS_PATH, web::get().to(sync_status))
+        .route("/root", web::get().to(current_root))
+        .route("/health/db", web::get().to(db_health))
         .route("/running_max_task_id", web::get().to(running_max_task_id))
         .route("/pending_tasks_count", web::get().to(pending_tasks_count))
+        .route("/prover_stats", web::get().to(prover_stats))
+        .route(
+            "/get_task_queue_position",
+            web::post().to(get_task_queue_position),
+        )
         .route(
             "/get_unprocessed_priority_ops",
             web::post().to(get_unprocessed_priority_ops),
         )
+        .route("/get_priority_op", web::post().to(get_priority_op))
+        .route("/pending_deposits", web::post().to(get_pending_deposits))
         .route("/get_token", web::post().to(get_token))
         .route(
             "/get_stored_block_info",
             web::post().to(get_stored_block_info),
         )
         .route("/get_balances", web::post().to(get_balances))
+        .route("/balances", web::get().to(get_balances_page))
+        .route("/account_tokens", web::post().to(get_account_tokens))
+        .route("/sub_accounts", web::post().to(get_sub_accounts))
         .route("/get_proofs_by_page", web::post().to(get_proofs_by_page))
         .route("/get_proof_by_info", web::post().to(get_proof_by_info))
+        .route("/proof", web::get().to(get_proof_by_params))
+        .route("/quote_exit", web::post().to(quote_exit))
         .route("/get_proofs_by_token", web::post().to(get_proofs_by_token))
         .route(
             "/generate_proof_task_by_info",
@@ -301,5 +804,13 @@ pub fn exodus_config(cfg: &mut web::ServiceConfig) {
             GENERATE_PROOF_TASKS_BY_TOKEN,
             web::post().to(generate_proof_tasks_by_token),
         )
-        .route("/get_proof_task_id", web::post().to(get_proof_task_id));
+        .route("/get_proof_task_id", web::post().to(get_proof_task_id))
+        .route("/proofs/stream", web::get().to(proofs_stream))
+        .route("/proofs/since", web::get().to(proofs_since))
+        .route("/proof_updates", web::get().to(proof_updates))
+        .route("/blocks", web::get().to(get_blocks))
+        .route("/stats/volume", web::get().to(block_volume))
+        .route("/admin/requeue_proof", web::post().to(requeue_proof))
+        .route("/admin/maintenance", web::post().to(set_maintenance))
+        .route("/admin/table_sizes", web::get().to(table_sizes));
 }
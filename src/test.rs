@@ -1,32 +1,47 @@
 use actix_web::{http::StatusCode, test, web, App};
+use bigdecimal::num_bigint::BigUint;
+use bigdecimal::BigDecimal;
 use recover_state_config::RecoverStateConfig;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::AtomicU32;
+use zklink_crypto::convert::FeConvert;
+use zklink_crypto::params::GLOBAL_ASSET_ACCOUNT_ID;
+use zklink_crypto::Fr;
+use zklink_prover::ExitInfo;
+use zklink_storage::prover::records::StoredTaskStatus;
+use zklink_storage::tokens::records::DbToken;
 use zklink_storage::ConnectionPool;
-use zklink_types::{ChainId, TokenId, ZkLinkAddress};
+use zklink_types::block::Block;
+use zklink_types::utils::calculate_actual_token;
+use zklink_types::{
+    Account, AccountId, BlockNumber, ChainId, SubAccountId, TokenId, ZkLinkAddress, H256,
+};
 
 use crate::app_data::{
-    AcquiredTokens, AppData, ExodusResponse, Progress, ProofsCache, RecoverProgress, TokenInfo,
+    priority_by_usd_value, task_id_by_status, token_prices, AcquiredTokens, AppData,
+    ExodusResponse, Progress, ProofsCache, RecoverProgress, RecoveredState, SyncState, TokenInfo,
 };
-use crate::request::TokenRequest;
+use crate::request::{ProofUpdatesRequest, RequestId, TokenRequest};
+use crate::response::{ExodusStatus, PaginatedResponse, ProofEvent, ProofEventStatus};
 use crate::server::exodus_config;
 
 async fn create_app_data() -> AppData {
+    create_app_data_with_sync_mode(false).await
+}
+
+async fn create_app_data_with_sync_mode(enable_sync_mode: bool) -> AppData {
     dotenvy::dotenv().unwrap();
-    let config = RecoverStateConfig::from_env();
+    let mut config = RecoverStateConfig::from_env();
+    config.enable_sync_mode = enable_sync_mode;
+    config.black_list_time = None;
+    config.skip_balance_ordering = false;
+    config.api.admin_token = None;
+    config.runtime.read_only = false;
+    config.runtime.default_price = None;
     let recover_progress = RecoverProgress::new(&config).await;
-    let conn_pool = ConnectionPool::new(config.db.url, config.db.pool_size);
+    let conn_pool = ConnectionPool::new(config.db.url.clone(), config.db.api_pool_size());
     let proofs_cache = ProofsCache::from_database(conn_pool.clone()).await;
-    let contracts = config.layer1.get_contracts();
-    AppData::new(
-        false,
-        1,
-        conn_pool,
-        contracts,
-        proofs_cache,
-        recover_progress,
-    )
-    .await
+    AppData::new(&config, conn_pool, proofs_cache, recover_progress).await
 }
 
 // Initialize an instance of Recover Progress for testing
@@ -136,6 +151,48 @@ async fn test_get_token() {
     assert_eq!(token, expected_token);
 }
 
+#[actix_rt::test]
+async fn test_sync_completed_requires_all_states() {
+    for state_loaded in [false, true] {
+        for tokens_loaded in [false, true] {
+            for enable_sync_mode in [false, true] {
+                for recovered in [false, true] {
+                    let mut app_data = create_app_data_with_sync_mode(enable_sync_mode).await;
+                    app_data.recover_progress = get_test_recover_progress();
+                    if recovered {
+                        app_data.recover_progress.update_progress(BlockNumber(20));
+                    }
+                    if state_loaded {
+                        app_data
+                            .recovered_state
+                            .set(RecoveredState::default())
+                            .unwrap();
+                    }
+                    if tokens_loaded {
+                        app_data
+                            .acquired_tokens
+                            .set(AcquiredTokens::default())
+                            .unwrap();
+                    }
+
+                    // The progress of the recovery is only waited for in sync mode.
+                    let completed =
+                        state_loaded && tokens_loaded && (!enable_sync_mode || recovered);
+                    assert_eq!(
+                        app_data.is_not_sync_completed(),
+                        !completed,
+                        "state loaded: {}, tokens loaded: {}, sync mode: {}, recovered: {}",
+                        state_loaded,
+                        tokens_loaded,
+                        enable_sync_mode,
+                        recovered
+                    );
+                }
+            }
+        }
+    }
+}
+
 #[actix_rt::test]
 async fn test_get_recover_progress() {
     // Create a test service instance
@@ -153,3 +210,376 @@ async fn test_get_recover_progress() {
     assert_eq!(result.data.unwrap().current_block, 0.into());
     assert_eq!(result.data.unwrap().total_verified_block, 20.into());
 }
+
+#[test]
+fn test_progress_percentage() {
+    assert_eq!(get_test_recover_progress().get_progress().percentage(), 50);
+    let progress = |current_block: u32, total_verified_block: u32| Progress {
+        current_block: current_block.into(),
+        total_verified_block: total_verified_block.into(),
+    };
+    assert_eq!(progress(0, 0).percentage(), 100);
+    assert_eq!(progress(1, 3).percentage(), 33);
+    // The recovered blocks may be ahead of the total verified blocks queried at startup.
+    assert_eq!(progress(30, 20).percentage(), 100);
+    assert_eq!(
+        serde_json::to_value(SyncState::Loading).unwrap(),
+        serde_json::json!("loading")
+    );
+}
+
+#[test]
+fn test_task_id_by_status() {
+    assert_eq!(
+        task_id_by_status(None),
+        Err(ExodusStatus::ExitProofTaskNotExist)
+    );
+    assert_eq!(
+        task_id_by_status(Some(StoredTaskStatus {
+            id: 1,
+            completed: false,
+            cancelled: false,
+        })),
+        Err(ExodusStatus::ProofGenerating)
+    );
+    assert_eq!(
+        task_id_by_status(Some(StoredTaskStatus {
+            id: 1,
+            completed: false,
+            cancelled: true,
+        })),
+        Err(ExodusStatus::ProofTaskCancelled)
+    );
+    assert_eq!(
+        task_id_by_status(Some(StoredTaskStatus {
+            id: 1,
+            completed: true,
+            cancelled: false,
+        })),
+        Ok(1.into())
+    );
+}
+
+#[actix_rt::test]
+async fn test_disabled_black_list() {
+    let app_data = create_app_data().await;
+    assert_eq!(app_data.black_list_time, None);
+
+    // The same user can request proof tasks repeatedly if the black list is disabled.
+    let address = ZkLinkAddress::from(vec![1u8; 20]);
+    for _ in 0..3 {
+        assert_eq!(app_data.check_black_list(&address).await, Ok(()));
+    }
+}
+
+#[actix_rt::test]
+async fn test_block_volume_range() {
+    let app_data = create_app_data().await;
+    let volume = |from: u32, to: u32| app_data.block_volume(from.into(), to.into(), None);
+    assert_eq!(
+        volume(10, 9).await.err(),
+        Some(ExodusStatus::InvalidBlockRange)
+    );
+    assert_eq!(
+        volume(1, 10000).await.err(),
+        Some(ExodusStatus::BlocksLoadTooMany)
+    );
+    assert_eq!(volume(1, 9999).await.err(), None);
+}
+
+#[actix_rt::test]
+async fn test_maintenance_mode() {
+    let app_data = create_app_data().await;
+    assert_eq!(app_data.check_writable(), Ok(()));
+
+    // The writes are rejected until the maintenance mode is turned off.
+    app_data.set_read_only(true);
+    assert_eq!(app_data.check_writable(), Err(ExodusStatus::Maintenance));
+    app_data.set_read_only(false);
+    assert_eq!(app_data.check_writable(), Ok(()));
+}
+
+#[test]
+fn test_exit_amount() {
+    let token_id = TokenId(18);
+    let actual_token =
+        |sub_account_id| calculate_actual_token(SubAccountId(sub_account_id), token_id);
+    let mut user = Account::default();
+    user.set_balance(actual_token(1), 100u32.into());
+    // The global assets of every chain are stored in the sub account of the chain id.
+    let mut global_asset = Account::default();
+    global_asset.set_balance(actual_token(1), 100u32.into());
+    global_asset.set_balance(actual_token(2), 300u32.into());
+    let recovered_state = RecoveredState {
+        accounts: [
+            (AccountId(10), user),
+            (GLOBAL_ASSET_ACCOUNT_ID, global_asset),
+        ]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    };
+
+    // The user owns a quarter of the global assets of all chains.
+    let exit_amount = |account_id, chain_id| {
+        recovered_state.exit_amount(account_id, SubAccountId(1), token_id, token_id, chain_id, 2)
+    };
+    assert_eq!(exit_amount(AccountId(10), ChainId(1)), 25u32.into());
+    assert_eq!(exit_amount(AccountId(10), ChainId(2)), 75u32.into());
+    assert_eq!(exit_amount(AccountId(11), ChainId(1)), 0u32.into());
+}
+
+#[test]
+fn test_current_root() {
+    let recovered_state = RecoveredState {
+        last_block_info: Block {
+            block_number: BlockNumber(5),
+            new_root_hash: Fr::from_u64(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // The root is encoded the same as the one committed on chain.
+    let stored_block_info = recovered_state.stored_block_info(ChainId(1));
+    assert_eq!(recovered_state.current_root(), stored_block_info.state_hash);
+    assert_eq!(recovered_state.current_root(), H256::from_low_u64_be(1));
+}
+
+#[test]
+fn test_expected_root() {
+    let current_root = H256::from_low_u64_be(1);
+    let exit_info: ExitInfo = serde_json::from_value(serde_json::json!({
+        "chain_id": 1,
+        "account_address": "0x0000000000000000000000000000000000000001",
+        "account_id": 10,
+        "sub_account_id": 0,
+        "l1_target_token": 18,
+        "l2_source_token": 18,
+    }))
+    .unwrap();
+    assert_eq!(exit_info.expected_root, None);
+
+    let expected = |root| ExitInfo {
+        expected_root: Some(root),
+        ..exit_info.clone()
+    };
+    // The expected root is not a part of the task identity.
+    assert_eq!(expected(H256::from_low_u64_be(2)), exit_info);
+    assert!([exit_info.clone()]
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .contains(&expected(current_root)));
+}
+
+#[actix_rt::test]
+async fn test_stale_expected_root() {
+    let app_data = create_app_data().await;
+    app_data
+        .recovered_state
+        .set(RecoveredState {
+            last_block_info: Block {
+                new_root_hash: Fr::from_u64(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+    let (current_root, stale_root) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+    let exit_info = |expected_root| ExitInfo {
+        chain_id: ChainId(1),
+        account_address: ZkLinkAddress::from(vec![1u8; 20]),
+        account_id: AccountId(10),
+        sub_account_id: SubAccountId(0),
+        l1_target_token: TokenId(18),
+        l2_source_token: TokenId(18),
+        expected_root,
+    };
+
+    // The task quoted against a stale root is rejected before anything else is checked.
+    assert_eq!(
+        app_data
+            .generate_proof_task(exit_info(Some(stale_root)), "test")
+            .await,
+        Err(ExodusStatus::StaleRoot)
+    );
+    assert_eq!(app_data.check_expected_root(Some(current_root)), Ok(()));
+    assert_eq!(app_data.check_expected_root(None), Ok(()));
+}
+
+#[actix_rt::test]
+async fn test_exit_eligibility() {
+    let app_data = create_app_data().await;
+    let (address, token_id) = (ZkLinkAddress::from(vec![1u8; 20]), TokenId(18));
+    let mut account = Account::default();
+    account.set_balance(
+        calculate_actual_token(SubAccountId(1), token_id),
+        100u32.into(),
+    );
+    app_data
+        .recovered_state
+        .set(RecoveredState {
+            account_id_by_address: [(address.clone(), AccountId(10))].into_iter().collect(),
+            accounts: [(AccountId(10), account)].into_iter().collect(),
+            ..Default::default()
+        })
+        .unwrap();
+    let token_info = TokenInfo {
+        token_id,
+        symbol: "TEST".to_string(),
+        addresses: HashMap::new(),
+    };
+    app_data
+        .acquired_tokens
+        .set(AcquiredTokens {
+            token_by_id: [(token_id, token_info)].into_iter().collect(),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // Every exit is checked on its own, the ineligible ones carry the reason.
+    let unknown_address = ZkLinkAddress::from(vec![2u8; 20]);
+    let exits = [
+        (address.clone(), SubAccountId(1), token_id),
+        (address.clone(), SubAccountId(0), token_id),
+        (address.clone(), SubAccountId(1), TokenId(2)),
+        (address.clone(), SubAccountId(1), TokenId(19)),
+        (unknown_address, SubAccountId(1), token_id),
+    ];
+    let results: Vec<_> = app_data
+        .check_exit_eligibility(&exits)
+        .into_iter()
+        .map(|result| (result.eligible, result.status))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            (true, ExodusStatus::Ok),
+            (false, ExodusStatus::NonBalance),
+            (false, ExodusStatus::InvalidL1L2Token),
+            (false, ExodusStatus::TokenNotExist),
+            (false, ExodusStatus::AccountNotExist),
+        ]
+    );
+}
+
+#[test]
+fn test_request_id() {
+    let request_id = |header: Option<&str>| RequestId::from_header(header).0;
+    assert_eq!(request_id(Some("client-id 1")), "client-id 1");
+    let max_len_id = "a".repeat(64);
+    assert_eq!(request_id(Some(max_len_id.as_str())), max_len_id);
+
+    // The missing, empty, too long or unprintable ids are replaced by the generated uuids.
+    for header in [
+        None,
+        Some(""),
+        Some(&*"a".repeat(65)),
+        Some("client\nid"),
+        Some("client\u{7f}"),
+        Some("客户端"),
+    ] {
+        let generated = request_id(header);
+        assert!(uuid::Uuid::parse_str(&generated).is_ok(), "{:?}", header);
+    }
+}
+
+#[test]
+fn test_account_tokens() {
+    let address = ZkLinkAddress::from(vec![1u8; 20]);
+    let actual_token = |sub_account_id, token_id| {
+        calculate_actual_token(SubAccountId(sub_account_id), TokenId(token_id))
+    };
+    let mut account = Account::default();
+    account.set_balance(actual_token(1, 31), 100u32.into());
+    account.set_balance(actual_token(1, 18), 100u32.into());
+    account.set_balance(actual_token(1, 17), 0u32.into());
+    account.set_balance(actual_token(2, 19), 100u32.into());
+    let recovered_state = RecoveredState {
+        account_id_by_address: [(address.clone(), AccountId(10))].into_iter().collect(),
+        accounts: [(AccountId(10), account)].into_iter().collect(),
+        ..Default::default()
+    };
+
+    // Only the nonzero balances of the specified sub account are returned.
+    assert_eq!(
+        recovered_state.account_tokens(&address, SubAccountId(1)),
+        Ok(vec![TokenId(18), TokenId(31)])
+    );
+    assert_eq!(
+        recovered_state.account_tokens(&address, SubAccountId(3)),
+        Ok(vec![])
+    );
+    assert_eq!(
+        recovered_state.account_tokens(&ZkLinkAddress::from(vec![2u8; 20]), SubAccountId(1)),
+        Err(ExodusStatus::AccountNotExist)
+    );
+}
+
+#[test]
+fn test_exit_task_priority() {
+    let mut account = Account::default();
+    let eth = calculate_actual_token(SubAccountId(1), TokenId(18));
+    let unpriced = calculate_actual_token(SubAccountId(2), TokenId(17));
+    account.set_balance(eth, BigUint::from(2500u32) * BigUint::from(10u8).pow(18));
+    account.set_balance(unpriced, BigUint::from(10u8).pow(30));
+    let recovered_state = RecoveredState {
+        accounts: [(AccountId(10), account)].into_iter().collect(),
+        ..Default::default()
+    };
+    let prices = [(TokenId(18), BigDecimal::from(2))].into_iter().collect();
+
+    // The tokens without price are not counted.
+    let usd_value = recovered_state.usd_value(AccountId(10), &prices);
+    assert_eq!(usd_value, BigDecimal::from(5000));
+    assert_eq!(priority_by_usd_value(&usd_value), 3);
+    assert_eq!(
+        recovered_state.usd_value(AccountId(11), &prices),
+        BigDecimal::from(0)
+    );
+    assert_eq!(priority_by_usd_value(&BigDecimal::from(0)), 0);
+    assert_eq!(priority_by_usd_value(&"9.99".parse().unwrap()), 0);
+    assert_eq!(priority_by_usd_value(&BigDecimal::from(10)), 1);
+
+    // The unpriced tokens take the default price if configured.
+    let token = |token_id: i32, usd_price: u32| DbToken {
+        token_id,
+        symbol: "".to_string(),
+        price_id: "".to_string(),
+        usd_price: BigDecimal::from(usd_price),
+        last_update_time: Default::default(),
+    };
+    let prices = token_prices(vec![token(18, 2), token(17, 0)], None);
+    assert_eq!(prices, [(TokenId(18), BigDecimal::from(2))].into());
+    let prices = token_prices(vec![token(18, 2), token(17, 0)], Some(&BigDecimal::from(1)));
+    assert_eq!(prices[&TokenId(17)], BigDecimal::from(1));
+    assert_eq!(
+        recovered_state.usd_value(AccountId(10), &prices),
+        BigDecimal::from(5000) + BigDecimal::from(10u64.pow(12))
+    );
+}
+
+#[test]
+fn test_paginated_response() {
+    let page = PaginatedResponse::new(vec![1, 2], 0, 2, 5);
+    assert_eq!(page.total_pages, 3);
+    let page = PaginatedResponse::new(vec![1, 2], 1, 2, 4);
+    assert_eq!(page.total_pages, 2);
+    let page = PaginatedResponse::<u32>::new(vec![], 0, 2, 0);
+    assert_eq!(page.total_pages, 0);
+}
+
+#[test]
+fn test_proof_updates_messages() {
+    let request: ProofUpdatesRequest = serde_json::from_str(r#"{"subscribe": [1, 2]}"#).unwrap();
+    assert_eq!(request.subscribe, vec![1, 2]);
+
+    let event = ProofEvent {
+        task_id: 2,
+        status: ProofEventStatus::Failed,
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"task_id":2,"status":"failed"}"#
+    );
+}
@@ -0,0 +1 @@
+ALTER TABLE exit_proofs DROP COLUMN cancelled_at;
@@ -0,0 +1 @@
+ALTER TABLE exit_proofs ADD COLUMN cancelled_at timestamp with time zone; -- the time when the task was cancelled
@@ -0,0 +1 @@
+ALTER TABLE exit_proofs DROP COLUMN request_id;
@@ -0,0 +1 @@
+ALTER TABLE exit_proofs ADD COLUMN request_id text; -- the correlation id of the api request that created the task
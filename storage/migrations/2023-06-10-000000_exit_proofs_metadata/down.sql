@@ -0,0 +1,3 @@
+ALTER TABLE exit_proofs DROP COLUMN prove_duration_ms;
+ALTER TABLE exit_proofs DROP COLUMN proof_size_bytes;
+ALTER TABLE exit_proofs DROP COLUMN started_at;
@@ -0,0 +1,3 @@
+ALTER TABLE exit_proofs ADD COLUMN started_at timestamp with time zone; -- the time when the prover claimed the task(not updated by heartbeat)
+ALTER TABLE exit_proofs ADD COLUMN proof_size_bytes bigint; -- the size of the stored proof
+ALTER TABLE exit_proofs ADD COLUMN prove_duration_ms bigint; -- the time spent on generating the proof
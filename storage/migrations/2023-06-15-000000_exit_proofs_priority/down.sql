@@ -0,0 +1,2 @@
+DROP INDEX idx_exit_proofs_priority;
+ALTER TABLE exit_proofs DROP COLUMN priority;
@@ -0,0 +1,2 @@
+ALTER TABLE exit_proofs ADD COLUMN priority smallint NOT NULL DEFAULT 0; -- the tasks of higher priority are claimed first
+CREATE INDEX idx_exit_proofs_priority ON exit_proofs (priority DESC, id ASC) WHERE created_at IS NULL AND finished_at IS NULL;
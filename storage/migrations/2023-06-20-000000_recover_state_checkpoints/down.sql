@@ -0,0 +1 @@
+DROP TABLE recover_state_checkpoints;
@@ -0,0 +1,10 @@
+CREATE TABLE recover_state_checkpoints
+(
+    chain_id smallint NOT NULL,
+    last_l1_block bigint NOT NULL, -- the last layer1 block whose block events have been watched when the batch is applied
+    last_l2_block bigint NOT NULL, -- the last applied layer2 block
+    ops_hash bytea NOT NULL, -- the sync hash of all applied ops up to last_l2_block
+    updated_at timestamp with time zone NOT NULL,
+
+    PRIMARY KEY (chain_id)
+);
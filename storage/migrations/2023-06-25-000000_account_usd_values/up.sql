@@ -0,0 +1,10 @@
+-- the cached total USD value of each account, recomputed by `StatsSchema::refresh_account_usd_values`
+-- a table instead of a materialized view, since the values depend on the default price of the unpriced tokens
+CREATE TABLE account_usd_values
+(
+    account_id bigint NOT NULL,
+    usd_value numeric NOT NULL,
+
+    PRIMARY KEY (account_id)
+);
+CREATE INDEX account_usd_values_value_idx ON account_usd_values USING btree (usd_value DESC);
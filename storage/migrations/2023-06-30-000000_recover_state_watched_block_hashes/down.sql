@@ -0,0 +1 @@
+DROP TABLE recover_state_watched_block_hashes;
@@ -0,0 +1,9 @@
+CREATE TABLE recover_state_watched_block_hashes
+(
+    chain_id smallint NOT NULL,
+    block_number bigint NOT NULL, -- the watched layer1 block
+    block_hash bytea NOT NULL, -- the hash of the layer1 block when it was watched, compared to detect reorgs
+    last_l2_block bigint NOT NULL, -- the last layer2 block applied from the block events up to the layer1 block
+
+    PRIMARY KEY (chain_id, block_number)
+);
@@ -0,0 +1 @@
+DROP TABLE token_price_history;
@@ -0,0 +1,10 @@
+-- every price stored to token_price, kept for auditing the token prices at a past time
+CREATE TABLE token_price_history (
+    id bigserial NOT NULL,
+    token_id integer NOT NULL, -- token id
+    usd_price numeric NOT NULL, -- token price
+    recorded_at timestamp with time zone NOT NULL, -- the time the price was stored
+
+    PRIMARY KEY (id)
+);
+CREATE INDEX token_price_history_token_time_idx ON token_price_history USING btree (token_id, recorded_at);
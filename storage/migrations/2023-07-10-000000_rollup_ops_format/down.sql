@@ -0,0 +1,4 @@
+DELETE FROM recover_state_rollup_ops WHERE operation_format <> 'json';
+ALTER TABLE recover_state_rollup_ops DROP COLUMN operation_format;
+ALTER TABLE recover_state_rollup_ops DROP COLUMN operation_bytes;
+ALTER TABLE recover_state_rollup_ops ALTER COLUMN operation SET NOT NULL;
@@ -0,0 +1,4 @@
+-- the ops are stored either as json in `operation` or as bincode in `operation_bytes`, according to `operation_format`
+ALTER TABLE recover_state_rollup_ops ALTER COLUMN operation DROP NOT NULL;
+ALTER TABLE recover_state_rollup_ops ADD COLUMN operation_bytes bytea;
+ALTER TABLE recover_state_rollup_ops ADD COLUMN operation_format text NOT NULL DEFAULT 'json';
@@ -0,0 +1 @@
+DROP TABLE state_updates_prune_point;
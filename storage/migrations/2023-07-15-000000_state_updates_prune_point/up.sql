@@ -0,0 +1,8 @@
+-- the balance, order and pubkey updates of the blocks below `pruned_before` have been pruned, a single row
+CREATE TABLE state_updates_prune_point
+(
+    id boolean NOT NULL DEFAULT true CHECK (id),
+    pruned_before bigint NOT NULL,
+
+    PRIMARY KEY (id)
+);
@@ -1,10 +1,12 @@
 // Built-in deps
 use chrono::{DateTime, Utc};
 use sqlx::types::BigDecimal;
+use std::collections::HashMap;
 use std::time::Instant;
 // Local imports
 use self::records::*;
 use crate::chain::block::BlockSchema;
+use crate::chain::state::StateSchema;
 use crate::{QueryResult, StorageProcessor};
 
 pub mod records;
@@ -102,6 +104,43 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(account)
     }
 
+    /// Obtains the accounts of the addresses in one query, in ascending order of id.
+    /// The addresses without account are absent from the result.
+    pub async fn accounts_by_addresses(
+        &mut self,
+        addresses: &[Vec<u8>],
+    ) -> QueryResult<Vec<StorageAccount>> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        let start = Instant::now();
+
+        let accounts = sqlx::query_as!(
+            StorageAccount,
+            r#"SELECT id,nonce,address,pubkey_hash,account_type as "account_type: AccountType",chain_id,last_block FROM accounts WHERE address = ANY($1) ORDER BY id"#,
+            addresses
+        )
+            .fetch_all(self.0.conn())
+            .await?;
+
+        metrics::histogram!("sql.chain.account.accounts_by_addresses", start.elapsed());
+        Ok(accounts)
+    }
+
+    /// Same as `accounts_by_addresses`, but keyed by the address bytes.
+    pub async fn batch_accounts_by_address(
+        &mut self,
+        addresses: &[Vec<u8>],
+    ) -> QueryResult<HashMap<Vec<u8>, StorageAccount>> {
+        let accounts = self
+            .accounts_by_addresses(addresses)
+            .await?
+            .into_iter()
+            .map(|account| (account.address.clone(), account))
+            .collect();
+        Ok(accounts)
+    }
+
     /// Obtains sub account token balance
     pub async fn sub_account_token_balance(
         &mut self,
@@ -129,6 +168,38 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(token_balance)
     }
 
+    /// Obtains the balances of all the specified `(account_id, sub_account_id, coin_id)` keys in one query,
+    /// the keys without a stored balance are absent from the result.
+    pub async fn balances_for_keys(
+        &mut self,
+        keys: &[(i64, i32, i32)],
+    ) -> QueryResult<Vec<StorageBalance>> {
+        let start = Instant::now();
+
+        let mut account_ids = Vec::with_capacity(keys.len());
+        let mut sub_account_ids = Vec::with_capacity(keys.len());
+        let mut coin_ids = Vec::with_capacity(keys.len());
+        for (account_id, sub_account_id, coin_id) in keys {
+            account_ids.push(*account_id);
+            sub_account_ids.push(*sub_account_id);
+            coin_ids.push(*coin_id);
+        }
+        let balances = sqlx::query_as!(
+            StorageBalance,
+            r#"SELECT * FROM balances WHERE (account_id, sub_account_id, coin_id) IN (
+                SELECT * FROM unnest($1::bigint[], $2::integer[], $3::integer[])
+            )"#,
+            &account_ids,
+            &sub_account_ids,
+            &coin_ids
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.chain.account.balances_for_keys", start.elapsed());
+        Ok(balances)
+    }
+
     /// Obtains balances for the account by its id and sub account id.
     pub async fn account_balances(
         &mut self,
@@ -160,6 +231,108 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(balances)
     }
 
+    /// Obtains a page of the balances of the account ordered by sub account and token,
+    /// along with the number of the balances of all pages.
+    pub async fn account_balances_paginated(
+        &mut self,
+        account_id: i64,
+        sub_account_id: Option<i32>,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<(i64, Vec<StorageBalance>)> {
+        let start = Instant::now();
+        // The total is counted by the same query as the page, so they see the same balances.
+        let records = sqlx::query!(
+            r#"SELECT account_id, sub_account_id, coin_id, balance, count(*) OVER () AS "total_count!"
+            FROM balances
+            WHERE account_id = $1 AND ($2::integer IS NULL OR sub_account_id = $2)
+            ORDER BY sub_account_id, coin_id
+            LIMIT $3 OFFSET $4"#,
+            account_id,
+            sub_account_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+        let total_count = match records.first() {
+            Some(record) => record.total_count,
+            // No row carries the total beyond the last page, count it alone.
+            None => {
+                sqlx::query!(
+                    r#"SELECT count(*) AS "count!" FROM balances
+                    WHERE account_id = $1 AND ($2::integer IS NULL OR sub_account_id = $2)"#,
+                    account_id,
+                    sub_account_id
+                )
+                .fetch_one(self.0.conn())
+                .await?
+                .count
+            }
+        };
+        let balances = records
+            .into_iter()
+            .map(|record| StorageBalance {
+                account_id: record.account_id,
+                sub_account_id: record.sub_account_id,
+                coin_id: record.coin_id,
+                balance: record.balance,
+            })
+            .collect();
+
+        metrics::histogram!(
+            "sql.chain.account.account_balances_paginated",
+            start.elapsed()
+        );
+        Ok((total_count, balances))
+    }
+
+    /// Obtains the balances of the accounts in one query, keyed by the account id.
+    /// The accounts without balances are absent from the result.
+    pub async fn balances_for_accounts(
+        &mut self,
+        account_ids: &[i64],
+        sub_account_id: Option<i32>,
+    ) -> QueryResult<HashMap<i64, Vec<StorageBalance>>> {
+        if account_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let start = Instant::now();
+
+        let balances = match sub_account_id {
+            Some(sub_account_id) => {
+                sqlx::query_as!(
+                    StorageBalance,
+                    r#"SELECT * FROM balances
+                    WHERE account_id = ANY($1) and sub_account_id = $2"#,
+                    account_ids,
+                    sub_account_id
+                )
+                .fetch_all(self.0.conn())
+                .await?
+            }
+            None => {
+                sqlx::query_as!(
+                    StorageBalance,
+                    r#"SELECT * FROM balances WHERE account_id = ANY($1)"#,
+                    account_ids
+                )
+                .fetch_all(self.0.conn())
+                .await?
+            }
+        };
+        let mut balances_for_id: HashMap<i64, Vec<StorageBalance>> = HashMap::new();
+        for balance in balances {
+            balances_for_id
+                .entry(balance.account_id)
+                .or_default()
+                .push(balance);
+        }
+
+        metrics::histogram!("sql.chain.account.balances_for_accounts", start.elapsed());
+        Ok(balances_for_id)
+    }
+
     /// Obtains order slots for the account by its id and sub account id.
     pub async fn account_order_slots(
         &mut self,
@@ -187,6 +360,51 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(orders)
     }
 
+    /// Obtains the order slots of the accounts in one query, keyed by the account id.
+    /// The accounts without order slots are absent from the result.
+    pub async fn order_slots_for_accounts(
+        &mut self,
+        account_ids: &[i64],
+        sub_account_id: Option<i32>,
+    ) -> QueryResult<HashMap<i64, Vec<StorageOrderNonce>>> {
+        if account_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let start = Instant::now();
+
+        let orders = match sub_account_id {
+            Some(sub_account_id) => {
+                sqlx::query_as!(
+                    StorageOrderNonce,
+                    r#"SELECT * FROM account_order_nonces WHERE account_id = ANY($1) and sub_account_id = $2"#,
+                    account_ids,
+                    sub_account_id
+                )
+                .fetch_all(self.0.conn())
+                .await?
+            }
+            None => {
+                sqlx::query_as!(
+                    StorageOrderNonce,
+                    r#"SELECT * FROM account_order_nonces WHERE account_id = ANY($1)"#,
+                    account_ids
+                )
+                .fetch_all(self.0.conn())
+                .await?
+            }
+        };
+        let mut order_slots: HashMap<i64, Vec<StorageOrderNonce>> = HashMap::new();
+        for order in orders {
+            order_slots.entry(order.account_id).or_default().push(order);
+        }
+
+        metrics::histogram!(
+            "sql.chain.account.order_slots_for_accounts",
+            start.elapsed()
+        );
+        Ok(order_slots)
+    }
+
     pub async fn earliest_account_balance_updates_from_block(
         &mut self,
         account_id: i64,
@@ -299,6 +517,9 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(updates)
     }
 
+    /// Recovers the snapshot of the account at `block_number`(the last block if not specified)
+    /// by reverting the updates after it. Fails if `block_number` is below the prune point
+    /// of `StateSchema::prune_updates_before`, the pruned updates can't be reverted.
     pub async fn account_snapshot(
         &mut self,
         account_id: i64,
@@ -313,6 +534,9 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
             Some(block_number) => block_number,
             None => block_schema.get_last_block_number().await?,
         };
+        StateSchema(&mut transaction)
+            .ensure_updates_retained_after(block_number)
+            .await?;
 
         let mut account_schema = AccountSchema(&mut transaction);
 
@@ -352,58 +576,14 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
                 // Commit after get all data to reduce the time of transaction
                 transaction.commit().await?;
 
-                // Recovery snapshot
-                // Note, we need to merge account_balance_updates and account_pubkey_updates to recovery nonce
-                for u in balance_updates {
-                    // Recovery nonce
-                    if account.nonce > u.old_nonce {
-                        account.nonce = u.old_nonce;
-                    }
-                    // Recovery balance for each (account_id, sub_account_id, coin_id)
-                    let balance = &mut balances
-                        .iter_mut()
-                        .find(|b| {
-                            b.account_id == u.account_id
-                                && b.sub_account_id == u.sub_account_id
-                                && b.coin_id == u.coin_id
-                        })
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "Balance not found in db but update [id = {}] exist",
-                                u.balance_update_id
-                            )
-                        });
-                    balance.balance = u.old_balance;
-                }
-                if let Some(update) = pubkey_update {
-                    if account.nonce > update.old_nonce {
-                        account.nonce = update.old_nonce;
-                    }
-                };
-
-                for u in order_updates {
-                    // Recovery slot for each (account_id, sub_account_id, slot_id)
-                    let order_slot = &mut order_slots
-                        .iter_mut()
-                        .find(|o| {
-                            o.account_id == u.account_id
-                                && o.sub_account_id == u.sub_account_id
-                                && o.slot_id == u.slot_id
-                        })
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "Order slot not found in db but update [id = {}] exist",
-                                u.update_order_id
-                            )
-                        });
-                    // `old_order_nonce` in db for example
-                    // "[64,\"0\"]"
-                    let json_string: String = serde_json::from_value(u.old_order_nonce).unwrap();
-                    let (order_nonce, residue): (i64, BigDecimal) =
-                        serde_json::from_str(&json_string).unwrap();
-                    order_slot.order_nonce = order_nonce;
-                    order_slot.residue = residue;
-                }
+                rewind_account_updates(
+                    account,
+                    &mut balances,
+                    &mut order_slots,
+                    balance_updates,
+                    pubkey_update,
+                    order_updates,
+                );
             }
             None => {
                 transaction.commit().await?;
@@ -418,6 +598,147 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         })
     }
 
+    /// Same as `account_snapshot`, but recovers the snapshots of all the accounts in one transaction
+    /// by the bulk queries, keyed by the account id. The accounts that don't exist are absent from the result.
+    ///
+    /// The batch exit api of the server doesn't use it: the exit tasks are built from the in-memory
+    /// recovered state of the last block, which is the only state the proofs can be generated against.
+    pub async fn batch_account_snapshots(
+        &mut self,
+        account_ids: &[i64],
+        sub_account_id: Option<i32>,
+        block_number: Option<i64>,
+    ) -> QueryResult<HashMap<i64, AccountSnapshot>> {
+        if account_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let start = Instant::now();
+        // Query state and earliest updates in a transaction
+        let mut transaction = self.0.start_transaction().await?;
+
+        let block_number = match block_number {
+            Some(block_number) => block_number,
+            None => {
+                BlockSchema(&mut transaction)
+                    .get_last_block_number()
+                    .await?
+            }
+        };
+        StateSchema(&mut transaction)
+            .ensure_updates_retained_after(block_number)
+            .await?;
+
+        let accounts = sqlx::query_as!(
+            StorageAccount,
+            r#"SELECT id,nonce,address,pubkey_hash,account_type as "account_type: AccountType",chain_id,last_block FROM accounts WHERE id = ANY($1)"#,
+            account_ids
+        )
+            .fetch_all(transaction.conn())
+            .await?;
+        let account_ids: Vec<i64> = accounts.iter().map(|account| account.id).collect();
+
+        let mut account_schema = AccountSchema(&mut transaction);
+        let mut balances = account_schema
+            .balances_for_accounts(&account_ids, sub_account_id)
+            .await?;
+        let mut order_slots = account_schema
+            .order_slots_for_accounts(&account_ids, sub_account_id)
+            .await?;
+
+        // The earliest updates after `block_number` of each balance, order slot and pubkey.
+        let balance_updates = sqlx::query_as!(
+            StorageAccountUpdate,
+            r#"
+                SELECT a.* FROM account_balance_updates a INNER JOIN
+                (SELECT min(balance_update_id) FROM account_balance_updates
+                WHERE account_id = ANY($1) AND ($2::integer IS NULL OR sub_account_id = $2) AND block_number > $3
+                GROUP BY account_id, sub_account_id, coin_id) b
+                ON a.balance_update_id = b.min
+            "#,
+            &account_ids,
+            sub_account_id,
+            block_number
+        )
+        .fetch_all(transaction.conn())
+        .await?;
+        let order_updates = sqlx::query_as!(
+            StorageAccountOrderUpdate,
+            r#"SELECT a.* FROM account_order_updates a INNER JOIN
+            (SELECT min(order_nonce_update_id) FROM account_order_updates
+            WHERE account_id = ANY($1) AND ($2::integer IS NULL OR sub_account_id = $2) AND block_number > $3
+            GROUP BY account_id, sub_account_id, slot_id) b
+            ON a.order_nonce_update_id = b.min"#,
+            &account_ids,
+            sub_account_id,
+            block_number
+        )
+        .fetch_all(transaction.conn())
+        .await?;
+        let pubkey_updates = sqlx::query_as!(
+            StorageAccountPubkeyUpdate,
+            r#"SELECT a.* FROM account_pubkey_updates a INNER JOIN
+            (SELECT min(pubkey_update_id) FROM account_pubkey_updates
+            WHERE account_id = ANY($1) AND block_number > $2
+            GROUP BY account_id) b
+            ON a.pubkey_update_id = b.min"#,
+            &account_ids,
+            block_number
+        )
+        .fetch_all(transaction.conn())
+        .await?;
+
+        // Commit after get all data to reduce the time of transaction
+        transaction.commit().await?;
+
+        let mut balance_updates_for_id: HashMap<i64, Vec<StorageAccountUpdate>> = HashMap::new();
+        for update in balance_updates {
+            balance_updates_for_id
+                .entry(update.account_id)
+                .or_default()
+                .push(update);
+        }
+        let mut order_updates_for_id: HashMap<i64, Vec<StorageAccountOrderUpdate>> = HashMap::new();
+        for update in order_updates {
+            order_updates_for_id
+                .entry(update.account_id)
+                .or_default()
+                .push(update);
+        }
+        let mut pubkey_update_for_id: HashMap<i64, StorageAccountPubkeyUpdate> = pubkey_updates
+            .into_iter()
+            .map(|update| (update.account_id, update))
+            .collect();
+
+        let mut snapshots = HashMap::with_capacity(accounts.len());
+        for mut account in accounts {
+            let account_id = account.id;
+            let mut balances = balances.remove(&account_id).unwrap_or_default();
+            let mut order_slots = order_slots.remove(&account_id).unwrap_or_default();
+            rewind_account_updates(
+                &mut account,
+                &mut balances,
+                &mut order_slots,
+                balance_updates_for_id
+                    .remove(&account_id)
+                    .unwrap_or_default(),
+                pubkey_update_for_id.remove(&account_id),
+                order_updates_for_id.remove(&account_id).unwrap_or_default(),
+            );
+            snapshots.insert(
+                account_id,
+                AccountSnapshot {
+                    account: Some(account),
+                    balances,
+                    order_slots,
+                    block_number,
+                },
+            );
+        }
+
+        metrics::histogram!("sql.chain.account.batch_account_snapshots", start.elapsed());
+        Ok(snapshots)
+    }
+
     pub async fn sub_account_balances(
         &mut self,
         account_id: i64,
@@ -439,6 +760,28 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(account_balances)
     }
 
+    /// Obtains the distinct sub accounts of the account that hold a nonzero balance, in ascending order.
+    pub async fn sub_accounts_with_balances(&mut self, account_id: i64) -> QueryResult<Vec<i32>> {
+        let start = Instant::now();
+
+        let sub_account_ids = sqlx::query!(
+            r#"SELECT DISTINCT sub_account_id FROM balances WHERE account_id = $1 AND balance != 0
+            ORDER BY sub_account_id"#,
+            account_id
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|r| r.sub_account_id)
+        .collect();
+
+        metrics::histogram!(
+            "sql.chain.account.sub_accounts_with_balances",
+            start.elapsed()
+        );
+        Ok(sub_account_ids)
+    }
+
     pub async fn sub_account_balance_of_token(
         &mut self,
         account_id: i64,
@@ -474,6 +817,59 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(entry.created_at)
     }
 
+    /// Obtains the accounts created in the blocks of `[from_block, to_block]`,
+    /// ordered by the block number and the order in the block.
+    pub async fn accounts_created_in_range(
+        &mut self,
+        from_block: i64,
+        to_block: i64,
+    ) -> QueryResult<Vec<StorageAccountCreation>> {
+        let start = Instant::now();
+
+        let account_creates = sqlx::query_as!(
+            StorageAccountCreation,
+            r#"SELECT * FROM account_creates WHERE block_number BETWEEN $1 AND $2
+            ORDER BY block_number, update_order_id"#,
+            from_block,
+            to_block
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.chain.account.accounts_created_in_range",
+            start.elapsed()
+        );
+        Ok(account_creates)
+    }
+
+    /// Counts the accounts created in each block of `[from_block, to_block]`,
+    /// the blocks without created account are absent from the result.
+    pub async fn count_accounts_created_by_block(
+        &mut self,
+        from_block: i64,
+        to_block: i64,
+    ) -> QueryResult<Vec<AccountCreatesCount>> {
+        let start = Instant::now();
+
+        let counts = sqlx::query_as!(
+            AccountCreatesCount,
+            r#"SELECT block_number, count(*) AS "count!" FROM account_creates
+            WHERE block_number BETWEEN $1 AND $2
+            GROUP BY block_number ORDER BY block_number"#,
+            from_block,
+            to_block
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.chain.account.count_accounts_created_by_block",
+            start.elapsed()
+        );
+        Ok(counts)
+    }
+
     pub async fn is_white_submitter(
         &mut self,
         sub_account_ids: &[i32],
@@ -540,3 +936,428 @@ impl<'a, 'c> AccountSchema<'a, 'c> {
         Ok(white_submitters)
     }
 }
+
+/// Recovers the snapshot of the account, its balances and order slots by the earliest updates
+/// after the snapshot block, the old values of which are the values at the snapshot block.
+fn rewind_account_updates(
+    account: &mut StorageAccount,
+    balances: &mut [StorageBalance],
+    order_slots: &mut [StorageOrderNonce],
+    balance_updates: Vec<StorageAccountUpdate>,
+    pubkey_update: Option<StorageAccountPubkeyUpdate>,
+    order_updates: Vec<StorageAccountOrderUpdate>,
+) {
+    // Note, we need to merge account_balance_updates and account_pubkey_updates to recovery nonce
+    for u in balance_updates {
+        // Recovery nonce
+        if account.nonce > u.old_nonce {
+            account.nonce = u.old_nonce;
+        }
+        // Recovery balance for each (account_id, sub_account_id, coin_id)
+        let balance = &mut balances
+            .iter_mut()
+            .find(|b| {
+                b.account_id == u.account_id
+                    && b.sub_account_id == u.sub_account_id
+                    && b.coin_id == u.coin_id
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Balance not found in db but update [id = {}] exist",
+                    u.balance_update_id
+                )
+            });
+        balance.balance = u.old_balance;
+    }
+    if let Some(update) = pubkey_update {
+        if account.nonce > update.old_nonce {
+            account.nonce = update.old_nonce;
+        }
+    };
+
+    for u in order_updates {
+        // Recovery slot for each (account_id, sub_account_id, slot_id)
+        let order_slot = &mut order_slots
+            .iter_mut()
+            .find(|o| {
+                o.account_id == u.account_id
+                    && o.sub_account_id == u.sub_account_id
+                    && o.slot_id == u.slot_id
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "Order slot not found in db but update [id = {}] exist",
+                    u.update_order_id
+                )
+            });
+        // `old_order_nonce` in db for example
+        // "[64,\"0\"]"
+        let json_string: String = serde_json::from_value(u.old_order_nonce).unwrap();
+        let (order_nonce, residue): (i64, BigDecimal) = serde_json::from_str(&json_string).unwrap();
+        order_slot.order_nonce = order_nonce;
+        order_slot.residue = residue;
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use super::records::{AccountCreatesCount, StorageBalance};
+    use crate::{insert_test_account, QueryResult, StorageProcessor};
+    use sqlx::types::BigDecimal;
+
+    #[tokio::test]
+    async fn batch_accounts_by_address() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+
+        // Returns empty map without querying.
+        let accounts = storage
+            .chain()
+            .account_schema()
+            .batch_accounts_by_address(&[])
+            .await?;
+        assert!(accounts.is_empty());
+
+        // The existing accounts are keyed by address, and the nonexistent ones are absent.
+        let nonexistent = vec![0xffu8; 32];
+        let mut addresses = sqlx::query!("SELECT address FROM accounts LIMIT 2")
+            .fetch_all(storage.conn())
+            .await?
+            .into_iter()
+            .map(|record| record.address)
+            .collect::<Vec<_>>();
+        addresses.push(nonexistent.clone());
+        let accounts = storage
+            .chain()
+            .account_schema()
+            .batch_accounts_by_address(&addresses)
+            .await?;
+        assert_eq!(accounts.len(), addresses.len() - 1);
+        assert!(!accounts.contains_key(&nonexistent));
+        for (address, account) in accounts {
+            assert_eq!(address, account.address);
+        }
+
+        // The full rows are returned in ascending order of id.
+        let accounts = storage
+            .chain()
+            .account_schema()
+            .accounts_by_addresses(&addresses)
+            .await?;
+        assert_eq!(accounts.len(), addresses.len() - 1);
+        assert!(accounts.windows(2).all(|pair| pair[0].id < pair[1].id));
+        for account in accounts {
+            let expected = storage
+                .chain()
+                .account_schema()
+                .account_by_address(&account.address)
+                .await?
+                .unwrap();
+            assert_eq!(account.id, expected.id);
+            assert_eq!(account.account_type, expected.account_type);
+            assert_eq!(account.chain_id, expected.chain_id);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accounts_created_in_range() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let first_block = 5_000_000i64;
+        // Two accounts in the first block, one in the third block.
+        for (account_id, block_number, update_order_id) in [
+            (5_000_000i64, first_block, 0),
+            (5_000_001, first_block, 1),
+            (5_000_002, first_block + 2, 0),
+        ] {
+            sqlx::query!(
+                "INSERT INTO account_creates (account_id, address, block_number, update_order_id, tx_hash)
+                VALUES ($1, $2, $3, $4, $5)",
+                account_id,
+                vec![account_id as u8; 20],
+                block_number,
+                update_order_id,
+                vec![0u8; 32]
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        let mut account_schema = transaction.chain().account_schema();
+        let account_creates = account_schema
+            .accounts_created_in_range(first_block, first_block + 2)
+            .await?;
+        assert_eq!(
+            account_creates
+                .iter()
+                .map(|create| create.account_id)
+                .collect::<Vec<_>>(),
+            vec![5_000_000, 5_000_001, 5_000_002]
+        );
+        let account_creates = account_schema
+            .accounts_created_in_range(first_block + 1, first_block + 2)
+            .await?;
+        assert_eq!(account_creates.len(), 1);
+
+        let counts = account_schema
+            .count_accounts_created_by_block(first_block, first_block + 2)
+            .await?;
+        assert_eq!(
+            counts,
+            vec![
+                AccountCreatesCount {
+                    block_number: first_block,
+                    count: 2
+                },
+                AccountCreatesCount {
+                    block_number: first_block + 2,
+                    count: 1
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn order_slots_for_accounts() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        for account_id in 6_000_000i64..6_000_003 {
+            insert_test_account(&mut transaction, account_id).await?;
+        }
+        for (account_id, sub_account_id, slot_id) in [
+            (6_000_000i64, 0i32, 0i32),
+            (6_000_000, 0, 1),
+            (6_000_000, 1, 0),
+            (6_000_001, 1, 0),
+        ] {
+            sqlx::query!(
+                "INSERT INTO account_order_nonces (account_id, sub_account_id, slot_id, order_nonce, residue)
+                VALUES ($1, $2, $3, $4, $5)",
+                account_id,
+                sub_account_id,
+                slot_id,
+                1i64,
+                BigDecimal::from(0)
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        let mut account_schema = transaction.chain().account_schema();
+        assert!(account_schema
+            .order_slots_for_accounts(&[], None)
+            .await?
+            .is_empty());
+
+        // The account without order slots is absent.
+        let account_ids = [6_000_000, 6_000_001, 6_000_002];
+        let order_slots = account_schema
+            .order_slots_for_accounts(&account_ids, None)
+            .await?;
+        assert_eq!(order_slots.len(), 2);
+        assert_eq!(order_slots[&6_000_000].len(), 3);
+        assert_eq!(order_slots[&6_000_001].len(), 1);
+
+        let order_slots = account_schema
+            .order_slots_for_accounts(&account_ids, Some(0))
+            .await?;
+        assert_eq!(order_slots.len(), 1);
+        assert_eq!(order_slots[&6_000_000].len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sub_accounts_with_balances() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        insert_test_account(&mut transaction, 8_000_000).await?;
+        // Sub account 3 holds two tokens, the balance of sub account 1 has been withdrawn.
+        for (sub_account_id, coin_id, balance) in [(3, 1, 100), (3, 2, 5), (1, 1, 0), (0, 2, 7)] {
+            sqlx::query!(
+                "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES (8000000, $1, $2, $3)",
+                sub_account_id,
+                coin_id,
+                BigDecimal::from(balance)
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        let mut account_schema = transaction.chain().account_schema();
+        let sub_account_ids = account_schema.sub_accounts_with_balances(8_000_000).await?;
+        assert_eq!(sub_account_ids, vec![0, 3]);
+        let sub_account_ids = account_schema.sub_accounts_with_balances(8_000_001).await?;
+        assert!(sub_account_ids.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn account_balances_paginated() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        insert_test_account(&mut transaction, 8_000_000).await?;
+        for (sub_account_id, coin_id) in [(1, 3), (0, 2), (1, 1), (0, 1), (1, 2)] {
+            sqlx::query!(
+                "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES (8000000, $1, $2, 10)",
+                sub_account_id,
+                coin_id
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        let mut account_schema = transaction.chain().account_schema();
+        let page = |balances: Vec<StorageBalance>| {
+            balances
+                .into_iter()
+                .map(|balance| (balance.sub_account_id, balance.coin_id))
+                .collect::<Vec<_>>()
+        };
+        let (total, balances) = account_schema
+            .account_balances_paginated(8_000_000, None, 2, 0)
+            .await?;
+        assert_eq!((total, page(balances)), (5, vec![(0, 1), (0, 2)]));
+        let (total, balances) = account_schema
+            .account_balances_paginated(8_000_000, None, 2, 4)
+            .await?;
+        assert_eq!((total, page(balances)), (5, vec![(1, 3)]));
+        let (total, balances) = account_schema
+            .account_balances_paginated(8_000_000, Some(1), 2, 1)
+            .await?;
+        assert_eq!((total, page(balances)), (3, vec![(1, 2), (1, 3)]));
+        // The total is still known beyond the last page.
+        let (total, balances) = account_schema
+            .account_balances_paginated(8_000_000, None, 2, 10)
+            .await?;
+        assert_eq!((total, page(balances)), (5, vec![]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn balances_for_keys() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        insert_test_account(&mut transaction, 8_000_000).await?;
+        for (sub_account_id, coin_id, balance) in [(3, 1, 100), (3, 2, 5), (0, 2, 7)] {
+            sqlx::query!(
+                "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES (8000000, $1, $2, $3)",
+                sub_account_id,
+                coin_id,
+                BigDecimal::from(balance)
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        // The keys are matched as a whole, so (0, 1) is absent though sub account 0 and token 1
+        // both have balances.
+        let mut balances: Vec<_> = transaction
+            .chain()
+            .account_schema()
+            .balances_for_keys(&[
+                (8_000_000, 3, 1),
+                (8_000_000, 0, 2),
+                (8_000_000, 0, 1),
+                (8_000_000, 3, 9),
+                (8_000_001, 3, 1),
+            ])
+            .await?
+            .into_iter()
+            .map(|balance| {
+                (
+                    balance.account_id,
+                    balance.sub_account_id,
+                    balance.coin_id,
+                    balance.balance,
+                )
+            })
+            .collect();
+        balances.sort();
+        assert_eq!(
+            balances,
+            vec![
+                (8_000_000, 0, 2, BigDecimal::from(7)),
+                (8_000_000, 3, 1, BigDecimal::from(100)),
+            ]
+        );
+        let balances = transaction
+            .chain()
+            .account_schema()
+            .balances_for_keys(&[])
+            .await?;
+        assert!(balances.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batch_account_snapshots() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        for account_id in [7_000_000i64, 7_000_001] {
+            insert_test_account(&mut transaction, account_id).await?;
+            sqlx::query!("UPDATE accounts SET nonce = 5 WHERE id = $1", account_id)
+                .execute(transaction.conn())
+                .await?;
+            for sub_account_id in [0, 1] {
+                sqlx::query!(
+                    "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES ($1, $2, 1, 150)",
+                    account_id,
+                    sub_account_id
+                )
+                .execute(transaction.conn())
+                .await?;
+            }
+        }
+        // The balance of sub account 0 of the first account is updated from 100 to 150 in block 10.
+        sqlx::query!(
+            "INSERT INTO account_balance_updates (account_id, block_number, coin_id, sub_account_id, old_balance, new_balance, old_nonce, new_nonce, update_order_id, tx_hash)
+            VALUES (7000000, 10, 1, 0, 100, 150, 3, 5, 0, $1)",
+            vec![0u8; 32]
+        )
+        .execute(transaction.conn())
+        .await?;
+
+        let mut account_schema = transaction.chain().account_schema();
+        assert!(account_schema
+            .batch_account_snapshots(&[], None, Some(9))
+            .await?
+            .is_empty());
+        for sub_account_id in [None, Some(0), Some(1)] {
+            // The account that doesn't exist is absent.
+            let snapshots = account_schema
+                .batch_account_snapshots(
+                    &[7_000_000, 7_000_001, 7_000_002],
+                    sub_account_id,
+                    Some(9),
+                )
+                .await?;
+            assert_eq!(snapshots.len(), 2);
+            for (account_id, snapshot) in snapshots {
+                let expected = account_schema
+                    .account_snapshot(account_id, sub_account_id, Some(9))
+                    .await?;
+                let account = snapshot.account.unwrap();
+                assert_eq!(account.nonce, expected.account.unwrap().nonce);
+                assert_eq!(snapshot.block_number, 9);
+                let balances = |balances: Vec<super::records::StorageBalance>| {
+                    let mut balances: Vec<_> = balances
+                        .into_iter()
+                        .map(|b| (b.sub_account_id, b.coin_id, b.balance))
+                        .collect();
+                    balances.sort();
+                    balances
+                };
+                assert_eq!(balances(snapshot.balances), balances(expected.balances));
+            }
+        }
+        let snapshot = account_schema
+            .batch_account_snapshots(&[7_000_000], Some(0), Some(9))
+            .await?
+            .remove(&7_000_000)
+            .unwrap();
+        assert_eq!(snapshot.account.unwrap().nonce, 3);
+        assert_eq!(snapshot.balances[0].balance, BigDecimal::from(100));
+        Ok(())
+    }
+}
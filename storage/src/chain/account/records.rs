@@ -185,6 +185,13 @@ pub struct AccountCreatedAt {
     pub created_at: DateTime<Utc>,
 }
 
+/// The number of accounts created in a block.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, Eq)]
+pub struct AccountCreatesCount {
+    pub block_number: i64,
+    pub count: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct AccountSnapshot {
     pub account: Option<StorageAccount>,
@@ -1,6 +1,8 @@
 //! Module with additional conversion methods for the storage records.
 //! These methods are only needed for the `block` module, so they're kept in a
 //! private module.
+// Built-in imports
+use std::fmt::Display;
 // External imports
 use num::bigint::ToBigInt;
 use sqlx::types::BigDecimal;
@@ -10,6 +12,23 @@ use zklink_types::{block::ExecutedTx, BlockNumber, ZkLinkOp, ZkLinkTx};
 // Local imports
 use crate::chain::operations::records::StoredAggregatedOperation;
 use crate::chain::operations::records::{NewExecutedTransaction, StoredExecutedTransaction};
+use crate::QueryResult;
+
+/// Converts the value between the block type and the type of the storage column,
+/// returns error if the value is out of the range of the target type instead of truncating it.
+pub(crate) fn checked_convert<T, U>(value: T, name: &str) -> QueryResult<U>
+where
+    T: Copy + Display,
+    U: TryFrom<T>,
+{
+    U::try_from(value).map_err(|_| {
+        anyhow::format_err!(
+            "The {} {} is out of the range of the storage column",
+            name,
+            value
+        )
+    })
+}
 
 impl StoredExecutedTransaction {
     pub fn into_executed_tx(self) -> Result<ExecutedTx, anyhow::Error> {
@@ -23,14 +42,14 @@ impl StoredExecutedTransaction {
             // the sql has set condition success=true, which ensure op must exist
             op: franklin_op.unwrap(),
             fail_reason: self.fail_reason,
-            block_index: Some(self.block_index as u32),
+            block_index: Some(checked_convert(self.block_index, "block index")?),
             created_at: self.created_at,
         })
     }
 }
 
 impl NewExecutedTransaction {
-    pub fn prepare_stored_tx(exec_tx: ExecutedTx, block: BlockNumber) -> Self {
+    pub fn prepare_stored_tx(exec_tx: ExecutedTx, block: BlockNumber) -> QueryResult<Self> {
         let tx_data = serde_json::to_value(&exec_tx.tx).unwrap();
         let operation = serde_json::to_value(&exec_tx.op).unwrap();
         let op = exec_tx.op;
@@ -50,11 +69,11 @@ impl NewExecutedTransaction {
         let nonce = *exec_tx.tx.nonce() as i64;
 
         let amount = BigDecimal::from(amount.to_bigint().unwrap());
-        let mut block_index = exec_tx.block_index.map(|idx| idx as i32);
-        if block_index.is_none() {
-            block_index = Some(0);
-        }
-        Self {
+        let block_index = match exec_tx.block_index {
+            Some(block_index) => checked_convert(block_index, "block index")?,
+            None => 0,
+        };
+        Ok(Self {
             op_type,
             chain_id,
             block_number: i64::from(*block),
@@ -62,11 +81,11 @@ impl NewExecutedTransaction {
             operation,
             success: exec_tx.success,
             fail_reason: exec_tx.fail_reason,
-            block_index,
+            block_index: Some(block_index),
             nonce,
             amount,
             tx_data,
-        }
+        })
     }
 
     pub fn prepare_stored_failed_tx(exec_tx: FailedExecutedTx, block: BlockNumber) -> Self {
@@ -97,3 +116,40 @@ impl StoredAggregatedOperation {
         (self.id, (self.from_block, self.to_block))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zklink_types::U256;
+
+    use super::checked_convert;
+
+    #[test]
+    fn checked_convert_boundaries() {
+        // block index: u32 <-> integer
+        assert_eq!(
+            checked_convert::<u32, i32>(i32::MAX as u32, "block index").unwrap(),
+            i32::MAX
+        );
+        assert!(checked_convert::<u32, i32>(i32::MAX as u32 + 1, "block index").is_err());
+        assert!(checked_convert::<u32, i32>(u32::MAX, "block index").is_err());
+        assert_eq!(checked_convert::<i32, u32>(0, "block index").unwrap(), 0);
+        assert!(checked_convert::<i32, u32>(-1, "block index").is_err());
+
+        // block size: usize <-> bigint
+        assert_eq!(
+            checked_convert::<i64, usize>(i64::MAX, "block size").unwrap(),
+            i64::MAX as usize
+        );
+        assert!(checked_convert::<i64, usize>(-1, "block size").is_err());
+        assert!(checked_convert::<usize, i64>(usize::MAX, "block size").is_err());
+
+        // gas limit: U256 <-> bigint
+        let max_gas_limit = U256::from(i64::MAX);
+        assert_eq!(
+            checked_convert::<U256, i64>(max_gas_limit, "gas limit").unwrap(),
+            i64::MAX
+        );
+        assert!(checked_convert::<U256, i64>(max_gas_limit + 1, "gas limit").is_err());
+        assert!(checked_convert::<i64, u64>(-1, "gas limit").is_err());
+    }
+}
@@ -1,13 +1,17 @@
 // Built-in deps
+use std::collections::HashMap;
 use std::time::{Duration, Instant, UNIX_EPOCH};
 // External imports
+use sqlx::types::BigDecimal;
 // Workspace imports
 use zklink_crypto::convert::FeConvert;
+use zklink_crypto::params::FEE_ACCOUNT_ID;
 use zklink_types::{
     block::{Block, ExecutedTx},
-    AccountId, BlockNumber, Fr, H256, U256,
+    AccountId, BlockNumber, Fr, TransferOp, TransferToNewOp, H256, U256,
 };
 // Local imports
+use self::conversion::checked_convert;
 use self::records::{StorageBlock, StorageBlockState};
 use crate::chain::account::records::{
     StorageAccountCreation, StorageAccountOrderUpdate, StorageAccountPubkeyUpdate,
@@ -44,7 +48,7 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
     ) -> QueryResult<()> {
         for tx in operations {
             let is_priority_operation = tx.get_executed_op().is_priority_operation();
-            let new_tx = NewExecutedTransaction::prepare_stored_tx(tx, block_number);
+            let new_tx = NewExecutedTransaction::prepare_stored_tx(tx, block_number)?;
             if is_priority_operation {
                 // Store the executed priority operation in the corresponding schema.
                 OperationsSchema(self.0).update_priority_tx(new_tx).await?;
@@ -88,7 +92,54 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
         Ok(block)
     }
 
+    /// Returns the blocks numbered in `[from, to]` in ascending order of number, skipping `offset` blocks
+    /// and returning at most `limit` blocks, it's empty if `from` is greater than `to`.
+    pub async fn get_blocks_in_range(
+        &mut self,
+        from: BlockNumber,
+        to: BlockNumber,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<Vec<StorageBlock>> {
+        let start = Instant::now();
+        let blocks = sqlx::query_as!(
+            StorageBlock,
+            "SELECT * FROM blocks WHERE number BETWEEN $1 AND $2 ORDER BY number ASC LIMIT $3 OFFSET $4",
+            i64::from(*from),
+            i64::from(*to),
+            limit,
+            offset
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.chain.block.get_blocks_in_range", start.elapsed());
+        Ok(blocks)
+    }
+
+    /// Returns the number of the blocks numbered in `[from, to]`, 0 if `from` is greater than `to`.
+    pub async fn count_blocks_in_range(
+        &mut self,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> QueryResult<i64> {
+        let start = Instant::now();
+        let count = sqlx::query!(
+            r#"SELECT count(*) as "count!" FROM blocks WHERE number BETWEEN $1 AND $2"#,
+            i64::from(*from),
+            i64::from(*to)
+        )
+        .fetch_one(self.0.conn())
+        .await?
+        .count;
+
+        metrics::histogram!("sql.chain.block.count_blocks_in_range", start.elapsed());
+        Ok(count)
+    }
+
     // Helper method for retrieving blocks from the database.
+    /// Returns the max block number, or 0 if there are no blocks at all.
+    /// Use `has_genesis_block` to distinguish the empty database from the genesis block.
     pub async fn get_last_block_number(&mut self) -> QueryResult<i64> {
         // we use fetch_one because there should be at least one block(the genesis block) stored in table blocks
         let block_number = sqlx::query!("SELECT max(number) from blocks",)
@@ -99,6 +150,26 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
         Ok(block_number)
     }
 
+    /// Checks whether the genesis block has been stored, it is false before the state is initialized.
+    pub async fn has_genesis_block(&mut self) -> QueryResult<bool> {
+        Ok(self.genesis_block_number().await?.is_some())
+    }
+
+    /// Returns the number of the genesis block, i.e. the first stored block(not necessarily block 0,
+    /// e.g. the restore forked from a snapshot), `None` before the state is initialized.
+    pub async fn genesis_block_number(&mut self) -> QueryResult<Option<i64>> {
+        let block_number = sqlx::query!("SELECT min(number) FROM blocks")
+            .fetch_one(self.0.conn())
+            .await?
+            .min;
+        Ok(block_number)
+    }
+
+    /// Checks whether the block is the genesis block.
+    pub async fn is_genesis_block(&mut self, block: i64) -> QueryResult<bool> {
+        Ok(self.genesis_block_number().await? == Some(block))
+    }
+
     // Helper method for retrieving block state from the database
     pub async fn get_block_state(&mut self) -> QueryResult<StorageBlockState> {
         let mut transaction = self.0.start_transaction().await?;
@@ -158,8 +229,17 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
     /// Returns `None` if the block with provided number does not exist yet.
     pub async fn get_block(&mut self, block: i64) -> QueryResult<Option<Block>> {
         let start = Instant::now();
-        // Load block header.
-        let Some(stored_block) = self.get_storage_block(block).await? else {
+        // Load block header, along with whether it's the genesis block.
+        let Some(stored_block) = sqlx::query!(
+            r#"SELECT root_hash, fee_account_id, block_size, ops_composition_number, created_at,
+            commitment, sync_hash, commit_gas_limit, verify_gas_limit,
+            number = (SELECT min(number) FROM blocks) AS "is_genesis!"
+            FROM blocks WHERE number = $1"#,
+            block
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        else {
             return Ok(None);
         };
 
@@ -174,19 +254,30 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
         let sync_hash = H256::from_slice(&stored_block.sync_hash);
         // Return the obtained block in the expected format.
 
-        let mut timestamp = stored_block.created_at.timestamp();
-        if block == 0 {
-            timestamp = 0;
-        }
+        // The timestamp of the genesis block is always 0.
+        let timestamp = if stored_block.is_genesis {
+            0
+        } else {
+            stored_block.created_at.timestamp()
+        };
         let result = Block::new(
             BlockNumber(block as u32),
             new_root_hash,
             AccountId(stored_block.fee_account_id as u32),
             block_transactions,
-            stored_block.block_size as usize,
-            stored_block.ops_composition_number as usize,
-            U256::from(stored_block.commit_gas_limit as u64),
-            U256::from(stored_block.verify_gas_limit as u64),
+            checked_convert(stored_block.block_size, "block size")?,
+            checked_convert(
+                stored_block.ops_composition_number,
+                "ops composition number",
+            )?,
+            U256::from(checked_convert::<_, u64>(
+                stored_block.commit_gas_limit,
+                "commit gas limit",
+            )?),
+            U256::from(checked_convert::<_, u64>(
+                stored_block.verify_gas_limit,
+                "verify gas limit",
+            )?),
             commitment,
             sync_hash,
             timestamp as u64,
@@ -213,10 +304,9 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
             .await?;
 
         // Transform executed operations to be `ExecutedOperations`.
-        let executed_ops = executed_ops
-            .into_iter()
-            .filter_map(|stored_exec| stored_exec.into_executed_tx().ok());
-        executed_operations.extend(executed_ops);
+        for stored_exec in executed_ops {
+            executed_operations.push(stored_exec.into_executed_tx()?);
+        }
 
         // Sort the operations, so all the failed operations will be at the very end
         // of the list.
@@ -234,6 +324,29 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
         Ok(executed_operations)
     }
 
+    /// Checks that the executed ops stored for the block reconstruct its committed pubdata,
+    /// by recomputing the block commitment from them and comparing it to `blocks.commitment`.
+    pub async fn verify_block_pubdata(&mut self, block_number: i64) -> QueryResult<bool> {
+        let start = Instant::now();
+        // The commitment of genesis block is not computed from any pubdata.
+        if self.is_genesis_block(block_number).await? {
+            return Ok(true);
+        }
+        let Some(block) = self.get_block(block_number).await? else {
+            anyhow::bail!("Block {} not found", block_number);
+        };
+        let Some(previous_block) = self.get_storage_block(block_number - 1).await? else {
+            anyhow::bail!("Previous block of {} not found", block_number);
+        };
+
+        // The stored root hash is the big-endian encoding that the commitment takes.
+        let old_state_hash = H256::from_slice(&previous_block.root_hash);
+        let verified = block.get_block_commitment(old_state_hash) == block.block_commitment;
+
+        metrics::histogram!("sql.chain.block.verify_block_pubdata", start.elapsed());
+        Ok(verified)
+    }
+
     /// Returns the number of last block for which proof has been confirmed on Ethereum.
     /// Essentially, it's number of last block for which updates were applied to the chain state.
     pub async fn get_last_verified_confirmed_block(&mut self) -> QueryResult<i64> {
@@ -253,10 +366,11 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
         let root_hash = block.new_root_hash.to_bytes();
         let fee_account_id = i64::from(*block.fee_account);
 
-        let block_size = block.block_chunks_size as i64;
-        let ops_composition_number = block.ops_composition_number as i64;
-        let commit_gas_limit = block.commit_gas_limit.as_u64() as i64;
-        let verify_gas_limit = block.verify_gas_limit.as_u64() as i64;
+        let block_size = checked_convert(block.block_chunks_size, "block size")?;
+        let ops_composition_number =
+            checked_convert(block.ops_composition_number, "ops composition number")?;
+        let commit_gas_limit = checked_convert(block.commit_gas_limit, "commit gas limit")?;
+        let verify_gas_limit = checked_convert(block.verify_gas_limit, "verify gas limit")?;
         let commitment = block.block_commitment.as_bytes().to_vec();
         let sync_hash = block.sync_hash.as_bytes().to_vec();
         // Creates a new SystemTime from the specified number of whole seconds
@@ -487,4 +601,369 @@ impl<'a, 'c> BlockSchema<'a, 'c> {
 
         Ok(updates)
     }
+
+    /// Returns the total amount of `token_id` moved by the successful transfers
+    /// in the blocks `[from_block, to_block]`, the fees are not included.
+    pub async fn get_block_volume_by_token(
+        &mut self,
+        from_block: i64,
+        to_block: i64,
+        token_id: i32,
+    ) -> QueryResult<BigDecimal> {
+        let start = Instant::now();
+
+        let volume = sqlx::query!(
+            r#"SELECT COALESCE(SUM(amount), 0) AS "volume!" FROM submit_txs
+            WHERE block_number BETWEEN $1 AND $2 AND success = true AND (op_type = $3 OR op_type = $4)
+            AND (tx_data->>'token')::integer = $5"#,
+            from_block,
+            to_block,
+            TransferOp::OP_CODE as i16,
+            TransferToNewOp::OP_CODE as i16,
+            token_id
+        )
+        .fetch_one(self.0.conn())
+        .await?
+        .volume;
+
+        metrics::histogram!("sql.chain.block.get_block_volume_by_token", start.elapsed());
+        Ok(volume)
+    }
+
+    /// Returns the total amount of every token moved by the successful transfers in the blocks
+    /// `[from_block, to_block]`, keyed by the coin id of the balance updates. The amount is
+    /// summed up by the balance increases of the receivers, so the fees are not included.
+    pub async fn get_block_volume_all_tokens(
+        &mut self,
+        from_block: i64,
+        to_block: i64,
+    ) -> QueryResult<HashMap<i32, BigDecimal>> {
+        let start = Instant::now();
+
+        let volumes = sqlx::query!(
+            r#"SELECT u.coin_id, SUM(u.new_balance - u.old_balance) AS "volume!"
+            FROM account_balance_updates AS u
+            INNER JOIN submit_txs AS t ON t.tx_hash = u.tx_hash
+            WHERE u.block_number BETWEEN $1 AND $2 AND t.success = true AND (t.op_type = $3 OR t.op_type = $4)
+            AND u.new_balance > u.old_balance AND u.account_id <> $5
+            GROUP BY u.coin_id"#,
+            from_block,
+            to_block,
+            TransferOp::OP_CODE as i16,
+            TransferToNewOp::OP_CODE as i16,
+            *FEE_ACCOUNT_ID as i64
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| (record.coin_id, record.volume))
+        .collect();
+
+        metrics::histogram!(
+            "sql.chain.block.get_block_volume_all_tokens",
+            start.elapsed()
+        );
+        Ok(volumes)
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use chrono::Utc;
+    use sqlx::types::BigDecimal;
+    use zklink_types::block::Block;
+    use zklink_types::{
+        AccountId, BlockNumber, ChainId, DepositOp, ExecutedTx, Nonce, SubAccountId, TokenId,
+        TransferOp, TransferToNewOp, Withdraw, WithdrawOp, ZkLinkAddress, ZkLinkOp, H256,
+    };
+
+    use crate::{QueryResult, StorageProcessor};
+
+    const GENESIS_BLOCK: u32 = 1_000;
+
+    fn create_withdraw(account_id: AccountId) -> ExecutedTx {
+        let withdraw_op = ZkLinkOp::Withdraw(Box::new(WithdrawOp {
+            tx: Withdraw::new(
+                account_id,
+                SubAccountId(0),
+                ChainId(1),
+                ZkLinkAddress::from([7u8; 20].to_vec()),
+                TokenId(18),
+                TokenId(18),
+                100u32.into(),
+                0u32.into(),
+                Nonce(0),
+                false,
+                0,
+                None,
+                Default::default(),
+            ),
+            account_id,
+            l1_target_token_after_mapping: TokenId(18),
+        }));
+        ExecutedTx {
+            tx: withdraw_op.try_get_tx().unwrap(),
+            success: true,
+            op: withdraw_op,
+            fail_reason: None,
+            block_index: Some(0),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn non_zero_genesis_block() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM blocks")
+            .execute(transaction.conn())
+            .await?;
+        assert!(
+            !transaction
+                .chain()
+                .block_schema()
+                .has_genesis_block()
+                .await?
+        );
+
+        for block_number in [GENESIS_BLOCK, GENESIS_BLOCK + 1] {
+            let block = Block {
+                block_number: BlockNumber(block_number),
+                timestamp: 1_700_000_000,
+                ..Default::default()
+            };
+            transaction.chain().block_schema().save_block(block).await?;
+        }
+
+        let mut block_schema = transaction.chain().block_schema();
+        assert!(block_schema.has_genesis_block().await?);
+        assert_eq!(
+            block_schema.genesis_block_number().await?,
+            Some(i64::from(GENESIS_BLOCK))
+        );
+        assert!(block_schema.is_genesis_block(GENESIS_BLOCK.into()).await?);
+        assert!(!block_schema.is_genesis_block(0).await?);
+
+        // Only the timestamp of the genesis block is reset.
+        let genesis = block_schema.get_block(GENESIS_BLOCK.into()).await?.unwrap();
+        assert_eq!(genesis.timestamp, 0);
+        let block = block_schema
+            .get_block(i64::from(GENESIS_BLOCK + 1))
+            .await?
+            .unwrap();
+        assert_eq!(block.timestamp, 1_700_000_000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocks_in_range_by_pages() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM blocks")
+            .execute(transaction.conn())
+            .await?;
+        for block_number in GENESIS_BLOCK..GENESIS_BLOCK + 5 {
+            let block = Block {
+                block_number: BlockNumber(block_number),
+                ..Default::default()
+            };
+            transaction.chain().block_schema().save_block(block).await?;
+        }
+
+        let mut block_schema = transaction.chain().block_schema();
+        let (from, to) = (
+            BlockNumber(GENESIS_BLOCK + 1),
+            BlockNumber(GENESIS_BLOCK + 3),
+        );
+        assert_eq!(block_schema.count_blocks_in_range(from, to).await?, 3);
+        let first_page = block_schema.get_blocks_in_range(from, to, 2, 0).await?;
+        let second_page = block_schema.get_blocks_in_range(from, to, 2, 2).await?;
+        let numbers: Vec<_> = first_page
+            .iter()
+            .chain(second_page.iter())
+            .map(|block| block.number)
+            .collect();
+        assert_eq!(
+            numbers,
+            (GENESIS_BLOCK + 1..=GENESIS_BLOCK + 3)
+                .map(i64::from)
+                .collect::<Vec<_>>()
+        );
+
+        // The reversed range and the range without blocks are empty rather than errors.
+        assert_eq!(block_schema.count_blocks_in_range(to, from).await?, 0);
+        assert!(block_schema
+            .get_blocks_in_range(to, from, 10, 0)
+            .await?
+            .is_empty());
+        let (from, to) = (
+            BlockNumber(GENESIS_BLOCK + 10),
+            BlockNumber(GENESIS_BLOCK + 20),
+        );
+        assert_eq!(block_schema.count_blocks_in_range(from, to).await?, 0);
+        assert!(block_schema
+            .get_blocks_in_range(from, to, 10, 0)
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_block_pubdata() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM blocks")
+            .execute(transaction.conn())
+            .await?;
+        let genesis = Block {
+            block_number: BlockNumber(GENESIS_BLOCK),
+            ..Default::default()
+        };
+        let previous_root = genesis.get_eth_encoded_root();
+        transaction
+            .chain()
+            .block_schema()
+            .save_block(genesis)
+            .await?;
+
+        let mut block = Block {
+            block_number: BlockNumber(GENESIS_BLOCK + 1),
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        block.block_commitment = block.get_block_commitment(previous_root);
+        transaction.chain().block_schema().save_block(block).await?;
+        // The pubdata of the block is made of its executed withdrawal.
+        let mut block = Block {
+            block_number: BlockNumber(GENESIS_BLOCK + 2),
+            block_transactions: vec![create_withdraw(AccountId(1))],
+            block_chunks_size: WithdrawOp::CHUNKS * 2,
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        block.block_commitment = block.get_block_commitment(previous_root);
+        transaction.chain().block_schema().save_block(block).await?;
+        // The commitment doesn't match the pubdata of the block.
+        let block = Block {
+            block_number: BlockNumber(GENESIS_BLOCK + 3),
+            timestamp: 1_700_000_000,
+            block_commitment: H256::repeat_byte(1),
+            ..Default::default()
+        };
+        transaction.chain().block_schema().save_block(block).await?;
+
+        let mut block_schema = transaction.chain().block_schema();
+        assert!(
+            block_schema
+                .verify_block_pubdata(GENESIS_BLOCK.into())
+                .await?
+        );
+        assert!(
+            block_schema
+                .verify_block_pubdata(i64::from(GENESIS_BLOCK + 1))
+                .await?
+        );
+        assert!(
+            block_schema
+                .verify_block_pubdata(i64::from(GENESIS_BLOCK + 2))
+                .await?
+        );
+        assert!(
+            !block_schema
+                .verify_block_pubdata(i64::from(GENESIS_BLOCK + 3))
+                .await?
+        );
+        assert!(block_schema
+            .verify_block_pubdata(i64::from(GENESIS_BLOCK + 4))
+            .await
+            .is_err());
+
+        // The block no longer verifies once its withdrawal is missing from the stored ops.
+        sqlx::query!(
+            "UPDATE submit_txs SET success = false WHERE block_number = $1",
+            i64::from(GENESIS_BLOCK + 2)
+        )
+        .execute(transaction.conn())
+        .await?;
+        assert!(
+            !transaction
+                .chain()
+                .block_schema()
+                .verify_block_pubdata(i64::from(GENESIS_BLOCK + 2))
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn block_volume_of_transfers() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let block_number = 9_000_000i64;
+        // (op code, token, amount, success, receiver)
+        let txs = [
+            (TransferOp::OP_CODE, 1, 100, true, 10i64),
+            (TransferToNewOp::OP_CODE, 1, 50, true, 11),
+            (TransferOp::OP_CODE, 1, 70, false, 10),
+            (TransferOp::OP_CODE, 2, 30, true, 11),
+            (DepositOp::OP_CODE, 1, 1000, true, 10),
+        ];
+        for (index, (op_code, token, amount, success, receiver)) in txs.into_iter().enumerate() {
+            let tx_hash = vec![index as u8 + 0xa0; 32];
+            sqlx::query!(
+                "INSERT INTO submit_txs
+                (chain_id, op_type, from_account, to_account, nonce, amount, tx_data, tx_hash, created_at, executed, success, block_number, block_index)
+                VALUES (0, $1, $2, $2, 0, $3, $4, $5, now(), true, $6, $7, $8)",
+                op_code as i16,
+                vec![0u8; 20],
+                BigDecimal::from(amount),
+                serde_json::json!({ "token": token, "amount": amount.to_string() }),
+                tx_hash,
+                success,
+                block_number,
+                index as i32
+            )
+            .execute(transaction.conn())
+            .await?;
+            if !success {
+                continue;
+            }
+            // The balance increases of the receiver and the fee account.
+            for (account_id, increase) in [(receiver, amount), (0, 1)] {
+                sqlx::query!(
+                    "INSERT INTO account_balance_updates (account_id, block_number, coin_id, sub_account_id, old_balance, new_balance, old_nonce, new_nonce, update_order_id, tx_hash)
+                    VALUES ($1, $2, $3, 0, 0, $4, 0, 0, $5, $6)",
+                    account_id,
+                    block_number,
+                    token,
+                    BigDecimal::from(increase),
+                    index as i32,
+                    tx_hash
+                )
+                .execute(transaction.conn())
+                .await?;
+            }
+        }
+
+        let mut block_schema = transaction.chain().block_schema();
+        for (token, volume) in [(1, 150), (2, 30), (3, 0)] {
+            assert_eq!(
+                block_schema
+                    .get_block_volume_by_token(block_number, block_number, token)
+                    .await?,
+                BigDecimal::from(volume)
+            );
+        }
+        let volumes = block_schema
+            .get_block_volume_all_tokens(block_number, block_number)
+            .await?;
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(volumes[&1], BigDecimal::from(150));
+        assert_eq!(volumes[&2], BigDecimal::from(30));
+        assert!(block_schema
+            .get_block_volume_all_tokens(block_number + 1, block_number + 1)
+            .await?
+            .is_empty());
+        Ok(())
+    }
 }
@@ -8,15 +8,18 @@ use crate::chain::operations::records::StoredOnChainTx;
 use zklink_utils::{BytesToHexSerde, OptionBytesToHexSerde, SyncBlockPrefix, ZeroxPrefix};
 // Local imports
 
-#[derive(Debug, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct StorageBlock {
     pub number: i64,
+    #[serde(with = "BytesToHexSerde::<ZeroxPrefix>")]
     pub root_hash: Vec<u8>,
     pub fee_account_id: i64,
     pub block_size: i64,
     pub ops_composition_number: i64,
     pub created_at: DateTime<Utc>,
+    #[serde(with = "BytesToHexSerde::<ZeroxPrefix>")]
     pub commitment: Vec<u8>,
+    #[serde(with = "BytesToHexSerde::<ZeroxPrefix>")]
     pub sync_hash: Vec<u8>,
     pub commit_gas_limit: i64,
     pub verify_gas_limit: i64,
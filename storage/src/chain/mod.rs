@@ -2,6 +2,7 @@ pub mod account;
 pub mod block;
 pub mod operations;
 pub mod state;
+pub mod stats;
 use super::StorageProcessor;
 
 /// `ChainIntermediator` is a structure providing methods to
@@ -25,4 +26,8 @@ impl<'a, 'c> ChainIntermediator<'a, 'c> {
     pub fn state_schema(self) -> state::StateSchema<'a, 'c> {
         state::StateSchema(self.0)
     }
+
+    pub fn stats_schema(self) -> stats::StatsSchema<'a, 'c> {
+        stats::StatsSchema(self.0)
+    }
 }
@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 // Built-in deps
 use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
 use std::time::Instant;
 // Workspace imports
 use zklink_types::{
-    BlockNumber, ChainId, DepositOp, FullExitOp, TransferOp, TransferToNewOp, WithdrawOp,
-    ZkLinkAddress, ZkLinkTx, ZkLinkTxType,
+    BlockNumber, ChainId, DepositOp, FullExitOp, ZkLinkAddress, ZkLinkTx, ZkLinkTxType,
 };
 // Local imports
-use self::records::{NewExecutedTransaction, StoredAggregatedOperation, StoredSubmitTransaction};
+use self::records::{
+    NewExecutedTransaction, StoredAggregatedOperation, StoredSubmitTransaction, TxDelta,
+};
 use crate::chain::account::records::{
     StorageAccountCreation, StorageAccountOrderUpdate, StorageAccountPubkeyUpdate,
     StorageAccountUpdate, StorageStateUpdates,
@@ -18,6 +20,10 @@ use crate::{QueryResult, StorageProcessor};
 
 pub mod records;
 
+/// The maximum rows inserted by a statement of `bulk_submit_priority_txs`,
+/// which keeps the bound parameters far below the limit of postgres(65535).
+pub const SUBMIT_TXS_BATCH_SIZE: usize = 1000;
+
 /// Operations schema is capable of storing and loading the transactions.
 /// Every kind of transaction (non-executed, executed, and executed priority tx)
 /// can be either saved or loaded from the database.
@@ -30,18 +36,52 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         &mut self,
         txs: Vec<StoredSubmitTransaction>,
     ) -> QueryResult<()> {
-        let mut transaction = self.0.start_transaction().await?;
+        self.bulk_submit_priority_txs(txs).await
+    }
 
-        for tx in txs.into_iter() {
-            transaction
-                .chain()
-                .operations_schema()
-                .add_new_submit_tx(tx)
-                .await?;
+    /// Inserts the transactions by multi-row statements of at most `SUBMIT_TXS_BATCH_SIZE` rows
+    /// in a single transaction, the transactions whose hash already exists are skipped.
+    pub async fn bulk_submit_priority_txs(
+        &mut self,
+        txs: Vec<StoredSubmitTransaction>,
+    ) -> QueryResult<()> {
+        if txs.is_empty() {
+            return Ok(());
         }
+        let start = Instant::now();
 
+        let mut transaction = self.0.start_transaction().await?;
+        for batch in txs.chunks(SUBMIT_TXS_BATCH_SIZE) {
+            let mut query_builder = QueryBuilder::<Postgres>::new(
+                "INSERT INTO submit_txs (chain_id, op_type, from_account, to_account, nonce, amount, \
+                tx_data, operation, eth_signature, tx_hash, created_at, executed, success, block_number, block_index) ",
+            );
+            query_builder.push_values(batch, |mut row, tx| {
+                row.push_bind(tx.chain_id)
+                    .push_bind(tx.op_type)
+                    .push_bind(&tx.from_account)
+                    .push_bind(&tx.to_account)
+                    .push_bind(tx.nonce)
+                    .push_bind(&tx.amount)
+                    .push_bind(&tx.tx_data)
+                    .push_bind(&tx.operation)
+                    .push_bind(&tx.eth_signature)
+                    .push_bind(&tx.tx_hash)
+                    .push_bind(tx.created_at)
+                    .push_bind(tx.executed)
+                    .push_bind(tx.success)
+                    .push_bind(tx.block_number)
+                    .push_bind(tx.block_index);
+            });
+            query_builder.push(" ON CONFLICT DO NOTHING");
+            query_builder.build().execute(transaction.conn()).await?;
+        }
         transaction.commit().await?;
 
+        metrics::histogram!(
+            "sql.chain.operations.bulk_submit_priority_txs",
+            start.elapsed()
+        );
         Ok(())
     }
 
@@ -163,6 +203,9 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
     }
 
     /// Retrieves priority transaction from the database given priority transaction serial id.
+    #[deprecated(
+        note = "serial ids collide across chains, use `get_priority_tx_by_chain_and_serial` instead"
+    )]
     pub async fn get_priority_tx_by_serial_id(
         &mut self,
         serial_id: i64,
@@ -183,7 +226,36 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(tx_data)
     }
 
+    /// Retrieves priority transaction from the database given the chain and its priority serial id.
+    pub async fn get_priority_tx_by_chain_and_serial(
+        &mut self,
+        chain_id: i16,
+        serial_id: i64,
+    ) -> QueryResult<Option<ZkLinkTx>> {
+        let start = Instant::now();
+
+        let tx_data = sqlx::query!(
+            "SELECT tx_data FROM submit_txs WHERE chain_id = $1 AND nonce = $2 \
+            AND (op_type = $3 OR op_type = $4)",
+            chain_id,
+            serial_id,
+            DepositOp::OP_CODE as i16,
+            FullExitOp::OP_CODE as i16
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|tx_data| serde_json::from_value(tx_data.tx_data).unwrap());
+
+        metrics::histogram!(
+            "sql.chain.operations.get_priority_tx_by_chain_and_serial",
+            start.elapsed()
+        );
+        Ok(tx_data)
+    }
+
     /// Retrieves unprocessed priority transaction from the database given address.
+    /// The iteration order of the map is arbitrary, use `get_unprocessed_priority_txs_by_address`
+    /// when they have to be processed in order.
     pub async fn get_unprocessed_priority_tx_by_address(
         &mut self,
         address: &[u8],
@@ -207,6 +279,41 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(tx_data)
     }
 
+    /// Retrieves unprocessed priority transactions from the database given address,
+    /// in ascending order of serial id(the serial ids of different chains are ordered by chain id).
+    pub async fn get_unprocessed_priority_txs_by_address(
+        &mut self,
+        address: &[u8],
+    ) -> QueryResult<Vec<(u64, ZkLinkTx)>> {
+        let start = Instant::now();
+
+        let tx_data = sqlx::query!(
+            "SELECT tx_data, nonce FROM submit_txs
+            WHERE to_account = $1 AND (op_type = $2 OR op_type = $3) AND executed = false
+            ORDER BY nonce ASC, chain_id ASC;
+            ",
+            address,
+            DepositOp::OP_CODE as i16,
+            FullExitOp::OP_CODE as i16
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| {
+            (
+                record.nonce as u64,
+                serde_json::from_value(record.tx_data).unwrap(),
+            )
+        })
+        .collect();
+
+        metrics::histogram!(
+            "sql.chain.operations.get_unprocessed_priority_txs_by_address",
+            start.elapsed()
+        );
+        Ok(tx_data)
+    }
+
     /// Retrieves all unprocessed priority transactions from the database.
     pub async fn get_unprocessed_priority_txs(
         &mut self,
@@ -241,6 +348,30 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(tx_data)
     }
 
+    /// Retrieves all deposits of the recipient that have never been executed in layer2.
+    pub async fn unexecuted_deposits_by_address(
+        &mut self,
+        address: &[u8],
+    ) -> QueryResult<Vec<StoredSubmitTransaction>> {
+        let start = Instant::now();
+
+        let deposits = sqlx::query_as!(
+            StoredSubmitTransaction,
+            "SELECT * FROM submit_txs WHERE to_account = $1 AND op_type = $2 AND executed = false \
+            ORDER BY chain_id ASC, nonce ASC",
+            address,
+            DepositOp::OP_CODE as i16
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.chain.operations.unexecuted_deposits_by_address",
+            start.elapsed()
+        );
+        Ok(deposits)
+    }
+
     /// Retrieves priority transaction from the database given priority transaction serial id.
     pub async fn get_last_serial_id(&mut self, chain_id: i16) -> QueryResult<i64> {
         let start = Instant::now();
@@ -260,7 +391,66 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(tx_data)
     }
 
-    /// Retrieves transaction from the database given tx_type
+    /// Retrieves the next expected serial id(`max(nonce)+1` of the executed priority transactions)
+    /// of all chains in a single query, the chains without any executed priority transaction are absent.
+    pub async fn next_serial_ids(&mut self) -> QueryResult<HashMap<i16, i64>> {
+        let start = Instant::now();
+
+        let next_serial_ids = sqlx::query!(
+            r#"SELECT chain_id, max(nonce) + 1 AS "next_serial_id!" FROM submit_txs
+            WHERE (op_type = $1 or op_type = $2) and executed = true
+            GROUP BY chain_id"#,
+            DepositOp::OP_CODE as i16,
+            FullExitOp::OP_CODE as i16
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| (record.chain_id, record.next_serial_id))
+        .collect();
+
+        metrics::histogram!("sql.chain.operations.next_serial_ids", start.elapsed());
+        Ok(next_serial_ids)
+    }
+
+    /// Audits the executed txs in the blocks `[from_block, to_block]`, returns the tx hashes whose
+    /// `amount` column diverges from the `amount` inside the serialized `tx_data`.
+    ///
+    /// Only the txs that carry `amount` in `tx_data`(deposit, transfer, withdraw) are audited,
+    /// the amount of the others(e.g. full exit, forced exit) is derived from the operation.
+    pub async fn audit_amount_consistency(
+        &mut self,
+        from_block: i64,
+        to_block: i64,
+    ) -> QueryResult<Vec<Vec<u8>>> {
+        let start = Instant::now();
+
+        let tx_hashes = sqlx::query!(
+            r#"SELECT tx_hash FROM submit_txs
+            WHERE block_number BETWEEN $1 AND $2 AND executed = true AND success = true
+            AND tx_data->>'amount' IS NOT NULL
+            AND amount <> (tx_data->>'amount')::numeric
+            ORDER BY block_number, block_index"#,
+            from_block,
+            to_block
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| record.tx_hash)
+        .collect();
+
+        metrics::histogram!(
+            "sql.chain.operations.audit_amount_consistency",
+            start.elapsed()
+        );
+        Ok(tx_hashes)
+    }
+
+    /// Retrieves transaction from the database given tx_type, the txs are matched by the address of:
+    /// - Deposit: the receiver(`to_account`)
+    /// - Withdraw, ChangePubKey: the initiator(`from_account`)
+    /// - Transfer, FullExit, ForcedExit, OrderMatching: either `from_account` or `to_account`
     pub async fn get_tx_history(
         &mut self,
         tx_type: ZkLinkTxType,
@@ -268,40 +458,33 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         page_index: i64,
         count: i64,
     ) -> QueryResult<(i64, Vec<StoredSubmitTransaction>)> {
+        let start = Instant::now();
         let address = address.as_bytes();
-        let total_num = match tx_type {
-            ZkLinkTxType::Deposit => sqlx::query!(
-                r#"SELECT count(*) FROM submit_txs
-                     WHERE op_type = $1 AND to_account = $2"#,
-                DepositOp::OP_CODE as i16,
-                address,
-            )
-            .fetch_one(self.0.conn())
-            .await?
-            .count
-            .unwrap_or(0),
-            ZkLinkTxType::Withdraw => sqlx::query!(
-                r#"SELECT count(*) FROM submit_txs
-                     WHERE op_type = $1 AND from_account = $2"#,
-                WithdrawOp::OP_CODE as i16,
-                address,
-            )
-            .fetch_one(self.0.conn())
-            .await?
-            .count
-            .unwrap_or(0),
-            ZkLinkTxType::Transfer => sqlx::query!(
-                r#"SELECT count(*) FROM submit_txs
-                     WHERE op_type = ANY($1) AND (from_account = $2 OR to_account = $2)"#,
-                &[TransferOp::OP_CODE as i16, TransferToNewOp::OP_CODE as i16],
-                address,
-            )
-            .fetch_one(self.0.conn())
-            .await?
-            .count
-            .unwrap_or(0),
-            _ => 0,
+        let op_types: Vec<i16> = tx_type
+            .op_codes()
+            .into_iter()
+            .map(|op_code| op_code as i16)
+            .collect();
+        let (match_from, match_to) = match tx_type {
+            ZkLinkTxType::Deposit => (false, true),
+            ZkLinkTxType::Withdraw | ZkLinkTxType::ChangePubKey => (true, false),
+            ZkLinkTxType::Transfer
+            | ZkLinkTxType::FullExit
+            | ZkLinkTxType::ForcedExit
+            | ZkLinkTxType::OrderMatching => (true, true),
         };
+        let total_num = sqlx::query!(
+            r#"SELECT count(*) FROM submit_txs
+                 WHERE op_type = ANY($1) AND (($2 AND from_account = $4) OR ($3 AND to_account = $4))"#,
+            &op_types,
+            match_from,
+            match_to,
+            address,
+        )
+        .fetch_one(self.0.conn())
+        .await?
+        .count
+        .unwrap_or(0);
         // no result
         if total_num == 0 {
             return Ok((0, vec![]));
@@ -318,61 +501,59 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
             return Ok((total_page_num, vec![]));
         }
         let offset = page_index * count;
-        let ops = match tx_type {
-            ZkLinkTxType::Deposit => {
-                sqlx::query_as!(
-                    StoredSubmitTransaction,
-                    r#"SELECT a.* FROM submit_txs a INNER JOIN
-                    (SELECT id FROM submit_txs
-                     WHERE op_type = $1 AND to_account = $2
-                     ORDER BY id DESC OFFSET $3 LIMIT $4) b
-                     ON a.id = b.id"#,
-                    DepositOp::OP_CODE as i16,
-                    address,
-                    offset,
-                    count,
-                )
-                .fetch_all(self.0.conn())
-                .await?
-            }
-            ZkLinkTxType::Withdraw => {
-                sqlx::query_as!(
-                    StoredSubmitTransaction,
-                    r#"SELECT a.* FROM submit_txs a INNER JOIN
-                     (SELECT id FROM submit_txs
-                     WHERE op_type = $1 AND from_account = $2
-                     ORDER BY id DESC OFFSET $3 LIMIT $4) b
-                     ON a.id = b.id"#,
-                    WithdrawOp::OP_CODE as i16,
-                    address,
-                    offset,
-                    count,
-                )
-                .fetch_all(self.0.conn())
-                .await?
-            }
-            ZkLinkTxType::Transfer => {
-                sqlx::query_as!(
-                    StoredSubmitTransaction,
-                    r#"SELECT a.* FROM submit_txs a INNER JOIN
-                     (SELECT id FROM submit_txs
-                     WHERE op_type = ANY($1) AND (from_account = $2 OR to_account = $2)
-                     ORDER BY id DESC OFFSET $3 LIMIT $4) b
-                     ON a.id = b.id"#,
-                    &[TransferOp::OP_CODE as i16, TransferToNewOp::OP_CODE as i16],
-                    address,
-                    offset,
-                    count,
-                )
-                .fetch_all(self.0.conn())
-                .await?
-            }
-            _ => vec![],
-        };
+        let ops = sqlx::query_as!(
+            StoredSubmitTransaction,
+            r#"SELECT a.* FROM submit_txs a INNER JOIN
+             (SELECT id FROM submit_txs
+             WHERE op_type = ANY($1) AND (($2 AND from_account = $4) OR ($3 AND to_account = $4))
+             ORDER BY id DESC OFFSET $5 LIMIT $6) b
+             ON a.id = b.id
+             ORDER BY a.id DESC"#,
+            &op_types,
+            match_from,
+            match_to,
+            address,
+            offset,
+            count,
+        )
+        .fetch_all(self.0.conn())
+        .await?;
 
+        self.0
+            .record_query_time("sql.chain.operations.get_tx_history", start);
         Ok((total_page_num, ops))
     }
 
+    /// Reconstructs the balance changes of the token of the account made by the transactions
+    /// in the blocks `[from_block, to_block]`, ordered as they were applied. A transaction updating
+    /// the balance several times(e.g. amount and fee) produces a delta for each update.
+    pub async fn account_tx_timeline(
+        &mut self,
+        account_id: i64,
+        token_id: i32,
+        from_block: i64,
+        to_block: i64,
+    ) -> QueryResult<Vec<TxDelta>> {
+        let start = Instant::now();
+        let timeline = sqlx::query_as!(
+            TxDelta,
+            r#"SELECT u.tx_hash, s.op_type, u.block_number, u.sub_account_id, u.old_balance, u.new_balance
+            FROM account_balance_updates u INNER JOIN submit_txs s ON s.tx_hash = u.tx_hash
+            WHERE u.account_id = $1 AND u.coin_id = $2 AND u.block_number BETWEEN $3 AND $4
+            ORDER BY u.block_number, u.update_order_id, u.balance_update_id"#,
+            account_id,
+            token_id,
+            from_block,
+            to_block
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        self.0
+            .record_query_time("sql.chain.operations.account_tx_timeline", start);
+        Ok(timeline)
+    }
+
     pub async fn confirm_aggregated_operations(&mut self, op_ids: Vec<i64>) -> QueryResult<()> {
         sqlx::query!(
             "UPDATE aggregate_operations SET confirmed = true WHERE id = ANY($1)",
@@ -505,6 +686,59 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Returns the ids of the eth operations that are not bound to any aggregated operation.
+    pub async fn find_orphaned_eth_ops(&mut self) -> QueryResult<Vec<i64>> {
+        let start = Instant::now();
+        let eth_op_ids = sqlx::query!(
+            r#"SELECT e.id FROM eth_operations AS e
+            WHERE NOT EXISTS (SELECT 1 FROM eth_aggregated_ops_binding AS b WHERE b.eth_op_id = e.id)
+            ORDER BY e.id"#
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+
+        metrics::histogram!(
+            "sql.chain.operations.find_orphaned_eth_ops",
+            start.elapsed()
+        );
+        Ok(eth_op_ids)
+    }
+
+    /// Removes the eth operations that are not bound to any aggregated operation along with
+    /// their layer1 tx hashes, returns the ids of the removed eth operations.
+    pub async fn cleanup_orphaned_eth_ops(&mut self) -> QueryResult<Vec<i64>> {
+        let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
+
+        let eth_op_ids = transaction
+            .chain()
+            .operations_schema()
+            .find_orphaned_eth_ops()
+            .await?;
+        if !eth_op_ids.is_empty() {
+            sqlx::query!(
+                "DELETE FROM eth_tx_hashes WHERE eth_op_id = ANY($1)",
+                &eth_op_ids
+            )
+            .execute(transaction.conn())
+            .await?;
+            sqlx::query!("DELETE FROM eth_operations WHERE id = ANY($1)", &eth_op_ids)
+                .execute(transaction.conn())
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        metrics::histogram!(
+            "sql.chain.operations.cleanup_orphaned_eth_ops",
+            start.elapsed()
+        );
+        Ok(eth_op_ids)
+    }
+
     pub async fn get_last_affected_block_by_aggregated_action(
         &mut self,
         aggregated_action: AggType,
@@ -722,3 +956,492 @@ impl<'a, 'c> OperationsSchema<'a, 'c> {
         Ok(updates)
     }
 }
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use chrono::Utc;
+    use zklink_types::{
+        ChainId, ChangePubKeyOp, Deposit, DepositOp, ForcedExitOp, FullExitOp, OrderMatchingOp,
+        TransferOp, TransferToNewOp, WithdrawOp, ZkLinkAddress, ZkLinkTx, ZkLinkTxType,
+    };
+
+    use super::records::{AggType, StoredAggregatedOperation, StoredSubmitTransaction, TxDelta};
+    use crate::{QueryResult, StorageProcessor};
+    use sqlx::types::BigDecimal;
+
+    fn submit_tx(
+        op_code: u8,
+        from: &ZkLinkAddress,
+        to: &ZkLinkAddress,
+        index: u8,
+    ) -> StoredSubmitTransaction {
+        let mut tx_hash = vec![0xee; 32];
+        tx_hash[31] = index;
+        StoredSubmitTransaction {
+            op_type: op_code as i16,
+            from_account: from.as_bytes().to_vec(),
+            to_account: to.as_bytes().to_vec(),
+            tx_data: serde_json::json!({}),
+            tx_hash,
+            created_at: Utc::now(),
+            ..Default::default()
+        }
+    }
+
+    /// The unexecuted deposit of the priority op `serial_id` of the chain.
+    fn priority_deposit(
+        chain_id: u8,
+        serial_id: u64,
+        to: &ZkLinkAddress,
+        index: u8,
+    ) -> StoredSubmitTransaction {
+        let from = ZkLinkAddress::from(vec![0xe5; 20]);
+        let deposit = Deposit {
+            from_chain_id: ChainId(chain_id),
+            from: from.clone(),
+            sub_account_id: Default::default(),
+            l1_source_token: Default::default(),
+            amount: 1u32.into(),
+            to: to.clone(),
+            serial_id,
+            l2_target_token: Default::default(),
+            eth_hash: Default::default(),
+        };
+        StoredSubmitTransaction {
+            chain_id: chain_id as i16,
+            nonce: serial_id as i64,
+            tx_data: serde_json::to_value(ZkLinkTx::Deposit(Box::new(deposit))).unwrap(),
+            ..submit_tx(DepositOp::OP_CODE, &from, to, index)
+        }
+    }
+
+    #[tokio::test]
+    async fn tx_history_of_all_tx_types() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let account = ZkLinkAddress::from(vec![0xa1; 20]);
+        let other = ZkLinkAddress::from(vec![0xb2; 20]);
+
+        // (op code, from, to) of the txs, and the expected history number of `account` per tx type.
+        let txs = [
+            (DepositOp::OP_CODE, &other, &account),
+            (DepositOp::OP_CODE, &other, &account),
+            (DepositOp::OP_CODE, &other, &account),
+            (DepositOp::OP_CODE, &account, &other),
+            (WithdrawOp::OP_CODE, &account, &other),
+            (WithdrawOp::OP_CODE, &other, &account),
+            (ChangePubKeyOp::OP_CODE, &account, &account),
+            (ChangePubKeyOp::OP_CODE, &account, &other),
+            (ChangePubKeyOp::OP_CODE, &other, &account),
+            (FullExitOp::OP_CODE, &account, &account),
+            (FullExitOp::OP_CODE, &other, &account),
+            (ForcedExitOp::OP_CODE, &other, &account),
+            (OrderMatchingOp::OP_CODE, &account, &other),
+            (OrderMatchingOp::OP_CODE, &other, &account),
+            (OrderMatchingOp::OP_CODE, &other, &other),
+            (TransferOp::OP_CODE, &account, &other),
+            (TransferToNewOp::OP_CODE, &other, &account),
+        ];
+        for (index, (op_code, from, to)) in txs.into_iter().enumerate() {
+            transaction
+                .chain()
+                .operations_schema()
+                .add_new_submit_tx(submit_tx(op_code, from, to, index as u8))
+                .await?;
+        }
+        let expected = [
+            (ZkLinkTxType::Deposit, 3),
+            (ZkLinkTxType::Withdraw, 1),
+            (ZkLinkTxType::ChangePubKey, 2),
+            (ZkLinkTxType::FullExit, 2),
+            (ZkLinkTxType::ForcedExit, 1),
+            (ZkLinkTxType::OrderMatching, 2),
+            (ZkLinkTxType::Transfer, 2),
+        ];
+
+        for (tx_type, total_num) in expected {
+            let op_codes: Vec<i16> = tx_type
+                .op_codes()
+                .into_iter()
+                .map(|op_code| op_code as i16)
+                .collect();
+            // All pages of size 2 cover the history once in descending order of id.
+            let mut history = Vec::new();
+            let mut page_index = 0;
+            loop {
+                let (total_page_num, txs) = transaction
+                    .chain()
+                    .operations_schema()
+                    .get_tx_history(tx_type.clone(), &account, page_index, 2)
+                    .await?;
+                assert_eq!(total_page_num, (total_num + 1) / 2);
+                if txs.is_empty() {
+                    break;
+                }
+                assert!(txs.len() <= 2);
+                history.extend(txs);
+                page_index += 1;
+            }
+            assert_eq!(history.len() as i64, total_num, "{:?}", tx_type);
+            assert!(history.windows(2).all(|txs| txs[0].id > txs[1].id));
+            assert!(history.iter().all(|tx| op_codes.contains(&tx.op_type)));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_submit_priority_txs() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let chain_id = 200;
+        let account = ZkLinkAddress::from(vec![0xc3; 20]);
+        let txs: Vec<_> = (0..5000i64)
+            .map(|serial_id| {
+                let mut tx_hash = vec![0xdd; 32];
+                tx_hash[24..].copy_from_slice(&serial_id.to_be_bytes());
+                StoredSubmitTransaction {
+                    chain_id,
+                    nonce: serial_id,
+                    executed: true,
+                    tx_hash,
+                    ..submit_tx(DepositOp::OP_CODE, &account, &account, 0)
+                }
+            })
+            .collect();
+
+        transaction
+            .chain()
+            .operations_schema()
+            .bulk_submit_priority_txs(Vec::new())
+            .await?;
+        transaction
+            .chain()
+            .operations_schema()
+            .bulk_submit_priority_txs(txs.clone())
+            .await?;
+        assert_eq!(
+            transaction
+                .chain()
+                .operations_schema()
+                .get_last_serial_id(chain_id)
+                .await?,
+            4999
+        );
+        // The resubmitted txs are skipped.
+        transaction
+            .chain()
+            .operations_schema()
+            .submit_priority_txs(txs[..10].to_vec())
+            .await?;
+        let count = sqlx::query!(
+            "SELECT count(*) FROM submit_txs WHERE chain_id = $1",
+            chain_id
+        )
+        .fetch_one(transaction.conn())
+        .await?
+        .count
+        .unwrap_or_default();
+        assert_eq!(count, 5000);
+        for tx in txs.iter().step_by(499) {
+            assert!(transaction
+                .chain()
+                .operations_schema()
+                .get_submit_tx_by_hash(&tx.tx_hash)
+                .await?
+                .is_some());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unprocessed_priority_txs_in_serial_id_order() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let account = ZkLinkAddress::from(vec![0xd4; 20]);
+        let other = ZkLinkAddress::from(vec![0xe5; 20]);
+
+        // (chain id, serial id, to, executed) of the deposits, submitted out of order.
+        let deposits = [
+            (2, 5, &account, false),
+            (1, 7, &account, false),
+            (2, 3, &account, false),
+            (1, 3, &account, false),
+            (1, 1, &account, true),
+            (1, 2, &other, false),
+        ];
+        for (index, (chain_id, serial_id, to, executed)) in deposits.into_iter().enumerate() {
+            transaction
+                .chain()
+                .operations_schema()
+                .add_new_submit_tx(StoredSubmitTransaction {
+                    executed,
+                    ..priority_deposit(chain_id, serial_id, to, index as u8)
+                })
+                .await?;
+        }
+
+        // The serial ids shared by the chains are ordered by chain id.
+        let txs = transaction
+            .chain()
+            .operations_schema()
+            .get_unprocessed_priority_txs_by_address(account.as_bytes())
+            .await?;
+        let txs: Vec<_> = txs
+            .into_iter()
+            .map(|(serial_id, tx)| match tx {
+                ZkLinkTx::Deposit(deposit) => (serial_id, *deposit.from_chain_id),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(txs, vec![(3, 1), (3, 2), (5, 2), (7, 1)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn priority_tx_by_chain_and_serial() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let (chain_id, other_chain_id, serial_id) = (201, 202, 9_000_000);
+        // The priority ops of both chains share the serial id.
+        for (index, (chain_id, to)) in [(chain_id, [0xa1; 20]), (other_chain_id, [0xa2; 20])]
+            .into_iter()
+            .enumerate()
+        {
+            transaction
+                .chain()
+                .operations_schema()
+                .add_new_submit_tx(priority_deposit(
+                    chain_id,
+                    serial_id,
+                    &ZkLinkAddress::from(to.to_vec()),
+                    index as u8,
+                ))
+                .await?;
+        }
+
+        let deposit_to = |tx: Option<ZkLinkTx>| match tx {
+            Some(ZkLinkTx::Deposit(deposit)) => Some(deposit.to),
+            _ => None,
+        };
+        for (chain_id, to) in [(chain_id, [0xa1; 20]), (other_chain_id, [0xa2; 20])] {
+            let tx = transaction
+                .chain()
+                .operations_schema()
+                .get_priority_tx_by_chain_and_serial(chain_id as i16, serial_id as i64)
+                .await?;
+            assert_eq!(deposit_to(tx), Some(ZkLinkAddress::from(to.to_vec())));
+        }
+        let tx = transaction
+            .chain()
+            .operations_schema()
+            .get_priority_tx_by_chain_and_serial(chain_id as i16, serial_id as i64 + 1)
+            .await?;
+        assert!(tx.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn audit_amount_consistency() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let account = ZkLinkAddress::from(vec![0xf6; 20]);
+        let block_number = 9_000_000;
+
+        // (block number, amount column, amount of tx_data, executed, success) of the txs.
+        let txs = [
+            (block_number, 100, Some("100"), true, true),
+            (block_number, 100, Some("99"), true, true),
+            (block_number + 1, 7, Some("70"), true, true),
+            (block_number + 1, 5, None, true, true),
+            (block_number + 1, 5, Some("6"), true, false),
+            (block_number + 1, 5, Some("6"), false, false),
+            (block_number + 2, 1, Some("2"), true, true),
+        ];
+        let mut tx_hashes = Vec::new();
+        for (index, (block_number, amount, tx_amount, executed, success)) in
+            txs.into_iter().enumerate()
+        {
+            let tx = StoredSubmitTransaction {
+                amount: BigDecimal::from(amount),
+                tx_data: tx_amount.map_or_else(
+                    || serde_json::json!({}),
+                    |amount| serde_json::json!({ "amount": amount }),
+                ),
+                executed,
+                success,
+                block_number,
+                block_index: index as i32,
+                ..submit_tx(TransferOp::OP_CODE, &account, &account, 0xa0 + index as u8)
+            };
+            tx_hashes.push(tx.tx_hash.clone());
+            transaction
+                .chain()
+                .operations_schema()
+                .add_new_submit_tx(tx)
+                .await?;
+        }
+
+        // Only the diverged amounts of the successfully executed txs in the range are reported.
+        let diverged = transaction
+            .chain()
+            .operations_schema()
+            .audit_amount_consistency(block_number, block_number + 1)
+            .await?;
+        assert_eq!(diverged, vec![tx_hashes[1].clone(), tx_hashes[2].clone()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn orphaned_eth_ops() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let orphans_before = transaction
+            .chain()
+            .operations_schema()
+            .find_orphaned_eth_ops()
+            .await?;
+
+        // The eth ops stored along with the aggregated op are bound to it.
+        let raw_txs = [
+            (ChainId(1), serde_json::json!({})),
+            (ChainId(2), serde_json::json!({})),
+        ]
+        .into_iter()
+        .collect();
+        transaction
+            .chain()
+            .operations_schema()
+            .store_aggregate_op_and_eth_op(
+                StoredAggregatedOperation {
+                    action_type: AggType::CommitBlocks,
+                    from_block: 1,
+                    to_block: 1,
+                    created_at: Utc::now(),
+                    ..Default::default()
+                },
+                raw_txs,
+                100_000,
+            )
+            .await?;
+        // The eth op without binding, along with its tx hash.
+        let orphan_id = sqlx::query!(
+            "INSERT INTO eth_operations
+            (op_type, chain_id, sent, confirmed, last_deadline_block, last_used_gas_price, raw_tx, gas_limit)
+            VALUES ('CommitBlocks', 1, true, false, 0, 0, '{}', 100000)
+            RETURNING id"
+        )
+        .fetch_one(transaction.conn())
+        .await?
+        .id;
+        sqlx::query!(
+            "INSERT INTO eth_tx_hashes (chain_id, eth_op_id, tx_hash, gas_price) VALUES (1, $1, $2, 0)",
+            orphan_id,
+            vec![0xaa; 32]
+        )
+        .execute(transaction.conn())
+        .await?;
+
+        let orphans = transaction
+            .chain()
+            .operations_schema()
+            .find_orphaned_eth_ops()
+            .await?;
+        let mut expected = orphans_before.clone();
+        expected.push(orphan_id);
+        assert_eq!(orphans, expected);
+
+        let removed = transaction
+            .chain()
+            .operations_schema()
+            .cleanup_orphaned_eth_ops()
+            .await?;
+        assert_eq!(removed, expected);
+        assert!(transaction
+            .chain()
+            .operations_schema()
+            .find_orphaned_eth_ops()
+            .await?
+            .is_empty());
+        let bound_eth_ops = sqlx::query!("SELECT count(*) FROM eth_aggregated_ops_binding")
+            .fetch_one(transaction.conn())
+            .await?
+            .count
+            .unwrap_or_default();
+        let eth_ops = sqlx::query!("SELECT count(*) FROM eth_operations")
+            .fetch_one(transaction.conn())
+            .await?
+            .count
+            .unwrap_or_default();
+        assert_eq!(eth_ops, bound_eth_ops);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn account_tx_timeline() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let account = ZkLinkAddress::from(vec![0xa1; 20]);
+        let other = ZkLinkAddress::from(vec![0xb2; 20]);
+        let (account_id, token_id) = (9_000_000i64, 1);
+
+        // (block, op code, sub account, token, old balance, new balance) of the balance updates,
+        // each update is made by its own tx.
+        let updates = [
+            (9_000_000i64, DepositOp::OP_CODE, 0, token_id, 0, 100),
+            (9_000_000, TransferOp::OP_CODE, 0, token_id, 100, 70),
+            (9_000_000, TransferOp::OP_CODE, 0, token_id + 1, 0, 5),
+            (9_000_001, WithdrawOp::OP_CODE, 1, token_id, 30, 0),
+            (9_000_002, DepositOp::OP_CODE, 0, token_id, 70, 80),
+        ];
+        for (index, (block_number, op_code, sub_account_id, coin_id, old, new)) in
+            updates.into_iter().enumerate()
+        {
+            let tx = StoredSubmitTransaction {
+                block_number,
+                ..submit_tx(op_code, &other, &account, 0xd0 + index as u8)
+            };
+            let tx_hash = tx.tx_hash.clone();
+            transaction
+                .chain()
+                .operations_schema()
+                .add_new_submit_tx(tx)
+                .await?;
+            sqlx::query!(
+                "INSERT INTO account_balance_updates (account_id, block_number, coin_id, sub_account_id, old_balance, new_balance, old_nonce, new_nonce, update_order_id, tx_hash)
+                VALUES ($1, $2, $3, $4, $5, $6, 0, 0, $7, $8)",
+                account_id,
+                block_number,
+                coin_id,
+                sub_account_id,
+                BigDecimal::from(old),
+                BigDecimal::from(new),
+                index as i32,
+                tx_hash
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        let timeline = transaction
+            .chain()
+            .operations_schema()
+            .account_tx_timeline(account_id, token_id, 9_000_000, 9_000_001)
+            .await?;
+        let expected: Vec<_> = [0, 1, 3]
+            .into_iter()
+            .map(|index| {
+                let (block_number, op_code, sub_account_id, _, old, new) = updates[index];
+                TxDelta {
+                    tx_hash: submit_tx(op_code, &other, &account, 0xd0 + index as u8).tx_hash,
+                    op_type: op_code as i16,
+                    block_number,
+                    sub_account_id,
+                    old_balance: BigDecimal::from(old),
+                    new_balance: BigDecimal::from(new),
+                }
+            })
+            .collect();
+        assert_eq!(timeline, expected);
+        Ok(())
+    }
+}
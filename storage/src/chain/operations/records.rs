@@ -236,3 +236,14 @@ pub struct StoredOnChainTx {
     pub chain_id: i16,
     pub final_hash: Option<Vec<u8>>,
 }
+
+/// The balance change of an account made by a transaction, see `OperationsSchema::account_tx_timeline`.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct TxDelta {
+    pub tx_hash: Vec<u8>,
+    pub op_type: i16,
+    pub block_number: i64,
+    pub sub_account_id: i32,
+    pub old_balance: BigDecimal,
+    pub new_balance: BigDecimal,
+}
@@ -1,5 +1,10 @@
 // Built-in deps
-use std::{cmp, collections::HashMap, time::Instant};
+use std::io::{Read, Write};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
 // External imports
 use num::bigint::ToBigInt;
 use num::BigInt;
@@ -7,8 +12,10 @@ use sqlx::types::BigDecimal;
 use tracing::{debug, info};
 // Workspace imports
 use zklink_types::{
+    block::StoredBlockInfo,
     helpers::{apply_updates, reverse_updates},
-    AccountId, AccountMap, AccountUpdate, AccountUpdates, BlockNumber, ChainId, PubKeyHash, H256,
+    Account, AccountId, AccountMap, AccountUpdate, AccountUpdates, BlockNumber, ChainId,
+    PubKeyHash, H256,
 };
 // Local imports
 use crate::chain::{
@@ -17,6 +24,12 @@ use crate::chain::{
 };
 use crate::diff::StorageAccountDiff;
 use crate::{QueryResult, StorageProcessor};
+use records::AccountDiff;
+
+pub mod records;
+
+/// The default number of accounts loaded at once by `StateSchema::load_last_state`.
+pub const LOAD_STATE_CHUNK_SIZE: usize = 32768;
 
 /// State schema is capable of managing... well, the state of the chain.
 ///
@@ -180,27 +193,135 @@ impl<'a, 'c> StateSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Deletes the balance, order and pubkey updates of the blocks below `block_number`,
+    /// returns the number of deleted rows. The final state of them is kept in `balances` and `accounts`,
+    /// but the historical `account_snapshot` of the blocks below `block_number` can't be recovered anymore.
+    /// The prune point is recorded, so the snapshots and reverts below it fail rather than rewinding
+    /// from the partial updates.
+    ///
+    /// Only the updates of the verified blocks(already applied to the state) can be pruned.
+    pub async fn prune_updates_before(&mut self, block_number: i64) -> QueryResult<u64> {
+        let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
+
+        let last_verified_block = transaction
+            .chain()
+            .block_schema()
+            .get_last_verified_confirmed_block()
+            .await?;
+        anyhow::ensure!(
+            block_number <= last_verified_block + 1,
+            "Can't prune the updates before block {}, the last verified block is {}",
+            block_number,
+            last_verified_block
+        );
+
+        let mut pruned = sqlx::query!(
+            "DELETE FROM account_balance_updates WHERE block_number < $1",
+            block_number
+        )
+        .execute(transaction.conn())
+        .await?
+        .rows_affected();
+        pruned += sqlx::query!(
+            "DELETE FROM account_order_updates WHERE block_number < $1",
+            block_number
+        )
+        .execute(transaction.conn())
+        .await?
+        .rows_affected();
+        pruned += sqlx::query!(
+            "DELETE FROM account_pubkey_updates WHERE block_number < $1",
+            block_number
+        )
+        .execute(transaction.conn())
+        .await?
+        .rows_affected();
+        sqlx::query!(
+            "INSERT INTO state_updates_prune_point (pruned_before) VALUES ($1) \
+            ON CONFLICT (id) DO UPDATE SET pruned_before = GREATEST(state_updates_prune_point.pruned_before, $1)",
+            block_number
+        )
+        .execute(transaction.conn())
+        .await?;
+
+        transaction.commit().await?;
+        metrics::histogram!("sql.chain.state.prune_updates_before", start.elapsed());
+        Ok(pruned)
+    }
+
+    /// Returns the block below which the balance, order and pubkey updates have been pruned, 0 if never pruned.
+    pub async fn pruned_before(&mut self) -> QueryResult<i64> {
+        let start = Instant::now();
+        let pruned_before = sqlx::query!("SELECT pruned_before FROM state_updates_prune_point")
+            .fetch_optional(self.0.conn())
+            .await?
+            .map_or(0, |record| record.pruned_before);
+
+        metrics::histogram!("sql.chain.state.pruned_before", start.elapsed());
+        Ok(pruned_before)
+    }
+
+    /// Fails if some of the updates of the blocks after `block_number` have been pruned,
+    /// the state at `block_number` can't be recovered by rewinding them.
+    pub async fn ensure_updates_retained_after(&mut self, block_number: i64) -> QueryResult<()> {
+        let pruned_before = self.pruned_before().await?;
+        anyhow::ensure!(
+            block_number + 1 >= pruned_before,
+            "The state at block {} can't be recovered, the updates before block {} are pruned",
+            block_number,
+            pruned_before
+        );
+        Ok(())
+    }
+
+    /// Sets the account types and chain ids of the accounts in one statement,
+    /// the last one of the updates of the same account wins(same as setting them one by one).
     pub async fn apply_account_type_updates(
         &mut self,
         account_types: Vec<(AccountId, AccountType, ChainId)>,
     ) -> QueryResult<()> {
-        let mut transaction = self.0.start_transaction().await?;
-        for (id, account_type, chain_id) in account_types {
-            transaction
-                .chain()
-                .account_schema()
-                .set_account_type(*id as i64, account_type, chain_id.0 as i16)
-                .await?;
+        let start = Instant::now();
+        // An account can only be updated once by the statement.
+        let account_types: HashMap<_, _> = account_types
+            .into_iter()
+            .map(|(id, account_type, chain_id)| (id, (account_type, chain_id)))
+            .collect();
+
+        let mut account_ids = Vec::with_capacity(account_types.len());
+        let mut types = Vec::with_capacity(account_types.len());
+        let mut chain_ids = Vec::with_capacity(account_types.len());
+        for (id, (account_type, chain_id)) in account_types {
+            account_ids.push(i64::from(*id));
+            types.push(account_type.to_string());
+            chain_ids.push(*chain_id as i16);
         }
-        transaction.commit().await?;
+        sqlx::query!(
+            r#"UPDATE accounts
+            SET account_type = v.account_type::layer1_account_type, chain_id = v.chain_id
+            FROM unnest($1::bigint[], $2::text[], $3::smallint[]) AS v(id, account_type, chain_id)
+            WHERE accounts.id = v.id"#,
+            &account_ids,
+            &types,
+            &chain_ids
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.chain.state.apply_account_type_updates",
+            start.elapsed()
+        );
         Ok(())
     }
 
     /// Applies the previously stored list of account changes to the stored state.
+    /// Must be called within the transaction, the changes are applied in a savepoint of it.
     pub async fn apply_state_update(&mut self, block_number: BlockNumber) -> QueryResult<()> {
         let start = Instant::now();
         info!("Applying {:?} state update for storage", block_number);
-        let mut transaction = self.0.start_transaction().await?;
+        let skip_invariant_check = self.0.skip_state_invariant_check();
+        let mut savepoint = self.0.savepoint("apply_state_update").await?;
 
         // Collect the stored updates. This includes collecting entries from three tables:
         // `account_creates` (for creating/removing accounts),
@@ -211,7 +332,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             "SELECT * FROM account_balance_updates WHERE block_number = $1",
             i64::from(*block_number)
         )
-        .fetch_all(transaction.conn())
+        .fetch_all(savepoint.conn())
         .await?;
 
         let account_creation_diff = sqlx::query_as!(
@@ -222,7 +343,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             ",
             i64::from(*block_number)
         )
-        .fetch_all(transaction.conn())
+        .fetch_all(savepoint.conn())
         .await?;
 
         let account_change_pubkey_diff = sqlx::query_as!(
@@ -233,7 +354,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             ",
             i64::from(*block_number)
         )
-        .fetch_all(transaction.conn())
+        .fetch_all(savepoint.conn())
         .await?;
 
         let account_order_diff = sqlx::query_as!(
@@ -241,7 +362,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             "SELECT * FROM account_order_updates WHERE block_number = $1 ",
             i64::from(*block_number),
         )
-        .fetch_all(transaction.conn())
+        .fetch_all(savepoint.conn())
         .await?;
 
         // Collect the updates into one list of `StorageAccountDiff`.
@@ -270,6 +391,17 @@ impl<'a, 'c> StateSchema<'a, 'c> {
 
         debug!("Sorted account update list: {:?}", account_updates);
 
+        // Rejects the invalid updates before applying any of them.
+        if !skip_invariant_check {
+            let checked = account_updates
+                .iter()
+                .try_for_each(StorageAccountDiff::check_invariants);
+            if let Err(err) = checked {
+                savepoint.rollback().await?;
+                return Err(err);
+            }
+        }
+
         // Then go through the collected list of changes and apply them by one.
         for acc_update in account_updates.into_iter() {
             match acc_update {
@@ -287,7 +419,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                         upd.sub_account_id,
                         upd.new_balance.clone(),
                     )
-                    .execute(transaction.conn())
+                    .execute(savepoint.conn())
                     .await?;
 
                     sqlx::query!(
@@ -300,7 +432,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                         upd.new_nonce,
                         upd.account_id,
                     )
-                    .execute(transaction.conn())
+                    .execute(savepoint.conn())
                     .await?;
                 }
 
@@ -318,7 +450,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                         AccountType::Unknown as AccountType,
                         0,
                     )
-                        .execute(transaction.conn())
+                        .execute(savepoint.conn())
                         .await?;
                 }
                 StorageAccountDiff::ChangePubKey(upd) => {
@@ -333,7 +465,7 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                         upd.new_pubkey_hash,
                         upd.account_id,
                     )
-                    .execute(transaction.conn())
+                    .execute(savepoint.conn())
                     .await?;
                 }
                 StorageAccountDiff::ChangeOrderNonce(upd) => {
@@ -353,13 +485,13 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                         new_order_nonce.1,
                         upd.sub_account_id,
                     )
-                        .execute(transaction.conn())
+                        .execute(savepoint.conn())
                         .await?;
                 }
             }
         }
 
-        transaction.commit().await?;
+        savepoint.release().await?;
 
         metrics::histogram!("sql.chain.state.apply_state_update", start.elapsed());
         Ok(())
@@ -368,13 +500,27 @@ impl<'a, 'c> StateSchema<'a, 'c> {
     /// Loads the state account map state along
     /// with a block number to which this state applies.
     pub async fn load_circuit_state(&mut self, block: i64) -> QueryResult<(i64, AccountMap)> {
+        self.load_circuit_state_streaming(block, LOAD_STATE_CHUNK_SIZE)
+            .await
+    }
+
+    /// Same as `load_circuit_state`, but loads the verified state by `load_last_state_streaming`
+    /// with the given chunk size.
+    pub async fn load_circuit_state_streaming(
+        &mut self,
+        block: i64,
+        chunk_size: usize,
+    ) -> QueryResult<(i64, AccountMap)> {
         let start = Instant::now();
         let mut transaction = self.0.start_transaction().await?;
 
-        let (last_block, mut accounts) = StateSchema(&mut transaction).load_last_state().await?;
+        let (last_block, mut accounts) = StateSchema(&mut transaction)
+            .load_last_state_streaming(chunk_size)
+            .await?;
         debug!(
-            "Verified state block: {}, accounts: {:#?}",
-            last_block, accounts
+            "Verified state block: {}, accounts number: {}",
+            last_block,
+            accounts.len()
         );
 
         let state_diff = StateSchema(&mut transaction)
@@ -397,30 +543,52 @@ impl<'a, 'c> StateSchema<'a, 'c> {
 
     /// Loads the verified account map state along with a block number
     /// to which this state applies.
-    /// If the provided block number is `None`, then the latest committed
-    /// state will be loaded.
     pub async fn load_last_state(&mut self) -> QueryResult<(i64, AccountMap)> {
+        self.load_last_state_streaming(LOAD_STATE_CHUNK_SIZE).await
+    }
+
+    /// Same as `load_last_state`, but loads the accounts page by page in ascending order of id,
+    /// so that only `chunk_size` stored accounts(with their balances and order nonces) are held
+    /// besides the account map being built, instead of the whole `accounts` table.
+    ///
+    /// The pages are queried by the last loaded id rather than streamed by one query,
+    /// because the stream holds the connection that the balances of the page are queried by.
+    pub async fn load_last_state_streaming(
+        &mut self,
+        chunk_size: usize,
+    ) -> QueryResult<(i64, AccountMap)> {
         let start = Instant::now();
+        anyhow::ensure!(
+            chunk_size > 0,
+            "The chunk size of loading state must be positive"
+        );
         let mut transaction = self.0.start_transaction().await?;
 
         let last_block = BlockSchema(&mut transaction)
             .get_last_block_number()
             .await?;
 
-        // todo fetch all accounts at once will be failed when accounts table is big. we can fetch by page or store the tree at local file system
-        let accounts = sqlx::query_as!(
-            StorageAccount,
-            r#"
-            SELECT id, nonce, last_block, address, pubkey_hash, account_type as "account_type!: AccountType", chain_id FROM accounts
-            "#)
+        let mut account_map = AccountMap::default();
+        let mut last_account_id = -1;
+        loop {
+            let stored_accounts = sqlx::query_as!(
+                StorageAccount,
+                r#"
+                SELECT id, nonce, last_block, address, pubkey_hash, account_type as "account_type!: AccountType", chain_id FROM accounts
+                WHERE id > $1
+                ORDER BY id ASC
+                LIMIT $2
+                "#,
+                last_account_id,
+                chunk_size as i64
+            )
             .fetch_all(transaction.conn())
             .await?;
+            let Some(last_stored_account) = stored_accounts.last() else {
+                break;
+            };
+            last_account_id = last_stored_account.id;
 
-        let mut account_map = AccountMap::default();
-
-        // Get the balance of 2^15=32768 accounts at once
-        // See https://doc.rust-lang.org/nightly/std/slice/struct.Chunks.html
-        for stored_accounts in accounts.chunks(2usize.pow(15)) {
             let stored_account_ids: Vec<_> = stored_accounts.iter().map(|acc| acc.id).collect();
             let balances = sqlx::query_as!(
                 StorageBalance,
@@ -455,17 +623,21 @@ impl<'a, 'c> StateSchema<'a, 'c> {
                     .or_insert_with(|| vec![order_nonce]);
             }
 
-            for stored_account in stored_accounts {
+            for stored_account in &stored_accounts {
                 let id = AccountId(stored_account.id as u32);
                 let balances = balances_for_id.remove(&id).unwrap_or_default();
                 let order_nonces = order_nonces_for_id.remove(&id).unwrap_or_default();
                 let (id, account) = restore_account(stored_account, balances, order_nonces);
                 account_map.insert(id, account);
             }
+            if stored_accounts.len() < chunk_size {
+                break;
+            }
         }
 
         transaction.commit().await?;
-        metrics::histogram!("sql.chain.state.load_verified_state", start.elapsed());
+        self.0
+            .record_query_time("sql.chain.state.load_verified_state", start);
         Ok((last_block, account_map))
     }
 
@@ -638,7 +810,8 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             start_block
         };
 
-        metrics::histogram!("sql.chain.state.load_state_diff", start.elapsed());
+        self.0
+            .record_query_time("sql.chain.state.load_state_diff", start);
 
         // We don't want to return an empty list to avoid the confusion, so return
         // `None` if there are no changes.
@@ -648,4 +821,293 @@ impl<'a, 'c> StateSchema<'a, 'c> {
             Ok(None)
         }
     }
+
+    /// Reconstructs the `StoredBlockInfo` of the chain for the historical block
+    /// from the stored block and its executed operations.
+    pub async fn stored_block_info_at(
+        &mut self,
+        block_number: i64,
+        chain_id: ChainId,
+    ) -> QueryResult<StoredBlockInfo> {
+        let start = Instant::now();
+        let block = self
+            .0
+            .chain()
+            .block_schema()
+            .get_block(block_number)
+            .await?
+            .ok_or_else(|| anyhow::format_err!("Block {} does not exist", block_number))?;
+
+        metrics::histogram!("sql.chain.state.stored_block_info_at", start.elapsed());
+        Ok(block.stored_block_info(chain_id))
+    }
+
+    /// Batched version of `stored_block_info_at`, returns the infos in the order of the given blocks.
+    pub async fn stored_block_infos_at(
+        &mut self,
+        block_numbers: &[i64],
+        chain_id: ChainId,
+    ) -> QueryResult<Vec<StoredBlockInfo>> {
+        let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
+        let mut stored_block_infos = Vec::with_capacity(block_numbers.len());
+        for &block_number in block_numbers {
+            let stored_block_info = StateSchema(&mut transaction)
+                .stored_block_info_at(block_number, chain_id)
+                .await?;
+            stored_block_infos.push(stored_block_info);
+        }
+        transaction.commit().await?;
+
+        metrics::histogram!("sql.chain.state.stored_block_infos_at", start.elapsed());
+        Ok(stored_block_infos)
+    }
+
+    /// Exports the last state of all accounts as JSON(`[[account_id, account], ...]` in ascending order of id),
+    /// which is the portable snapshot format read by `compare_with_snapshot`.
+    pub async fn export_snapshot(&mut self, writer: impl Write) -> QueryResult<()> {
+        let start = Instant::now();
+        let (_, accounts) = self.load_last_state().await?;
+        let accounts: BTreeMap<AccountId, Account> = accounts.into_iter().collect();
+        let accounts: Vec<(AccountId, Account)> = accounts.into_iter().collect();
+        serde_json::to_writer(writer, &accounts)?;
+
+        metrics::histogram!("sql.chain.state.export_snapshot", start.elapsed());
+        Ok(())
+    }
+
+    /// Compares the last state of all accounts with the snapshot exported by `export_snapshot`
+    /// (or another tool producing the same format), returns the differing accounts in ascending order of id.
+    pub async fn compare_with_snapshot(
+        &mut self,
+        reader: impl Read,
+    ) -> QueryResult<Vec<AccountDiff>> {
+        let start = Instant::now();
+        let snapshot: Vec<(AccountId, Account)> = serde_json::from_reader(reader)?;
+        let mut snapshot: BTreeMap<AccountId, Account> = snapshot.into_iter().collect();
+        let (_, accounts) = self.load_last_state().await?;
+        let accounts: BTreeMap<AccountId, Account> = accounts.into_iter().collect();
+
+        let mut diffs = Vec::new();
+        for (account_id, account) in accounts {
+            let snapshot_account = snapshot.remove(&account_id);
+            if snapshot_account.as_ref() != Some(&account) {
+                diffs.push(AccountDiff {
+                    account_id,
+                    storage: Some(account),
+                    snapshot: snapshot_account,
+                });
+            }
+        }
+        // The rest accounts only exist in the snapshot
+        diffs.extend(
+            snapshot
+                .into_iter()
+                .map(|(account_id, account)| AccountDiff {
+                    account_id,
+                    storage: None,
+                    snapshot: Some(account),
+                }),
+        );
+        diffs.sort_by_key(|diff| diff.account_id);
+
+        metrics::histogram!("sql.chain.state.compare_with_snapshot", start.elapsed());
+        Ok(diffs)
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use zklink_crypto::convert::FeConvert;
+    use zklink_crypto::Fr;
+    use zklink_types::block::Block;
+    use zklink_types::{Account, AccountId, BlockNumber, ChainId, H256};
+
+    use super::records::AccountDiff;
+    use crate::chain::account::records::AccountType;
+    use crate::{insert_test_account, QueryResult, StorageProcessor};
+
+    const FIRST_BLOCK: u32 = 1_000_000;
+
+    #[tokio::test]
+    async fn stored_block_infos_at_historical_blocks() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let blocks: Vec<_> = (0..3)
+            .map(|index| Block {
+                block_number: BlockNumber(FIRST_BLOCK + index),
+                new_root_hash: Fr::from_u64(u64::from(index) + 1),
+                block_commitment: H256::repeat_byte(index as u8),
+                sync_hash: H256::repeat_byte(0xff - index as u8),
+                timestamp: 1_700_000_000 + u64::from(index),
+                ..Default::default()
+            })
+            .collect();
+        for block in blocks.iter().cloned() {
+            transaction.chain().block_schema().save_block(block).await?;
+        }
+
+        // Query in a different order from the saving one.
+        let block_numbers = [FIRST_BLOCK + 2, FIRST_BLOCK, FIRST_BLOCK + 1].map(i64::from);
+        let stored_block_infos = transaction
+            .chain()
+            .state_schema()
+            .stored_block_infos_at(&block_numbers, ChainId(1))
+            .await?;
+        assert_eq!(stored_block_infos.len(), block_numbers.len());
+        for (stored_block_info, block_number) in stored_block_infos.iter().zip(block_numbers) {
+            let block = &blocks[(block_number - i64::from(FIRST_BLOCK)) as usize];
+            assert_eq!(*stored_block_info, block.stored_block_info(ChainId(1)));
+        }
+
+        // Fails if any of the blocks does not exist.
+        let nonexistent_block = i64::from(FIRST_BLOCK + 3);
+        assert!(transaction
+            .chain()
+            .state_schema()
+            .stored_block_infos_at(&[i64::from(FIRST_BLOCK), nonexistent_block], ChainId(1))
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prune_updates_of_unverified_blocks() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+
+        // The updates of the blocks that are not verified yet can't be pruned.
+        assert!(transaction
+            .chain()
+            .state_schema()
+            .prune_updates_before(i64::MAX)
+            .await
+            .is_err());
+
+        // There is no update before the genesis block.
+        let pruned = transaction
+            .chain()
+            .state_schema()
+            .prune_updates_before(0)
+            .await?;
+        assert_eq!(pruned, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn load_last_state_by_pages() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let (last_block, accounts) = storage.chain().state_schema().load_last_state().await?;
+
+        // Any page size loads the same state, including the pages not dividing the accounts number.
+        for chunk_size in [1, 2, 3, accounts.len() + 1] {
+            let (paged_last_block, paged_accounts) = storage
+                .chain()
+                .state_schema()
+                .load_last_state_streaming(chunk_size)
+                .await?;
+            assert_eq!(paged_last_block, last_block);
+            assert_eq!(paged_accounts, accounts);
+        }
+        assert!(storage
+            .chain()
+            .state_schema()
+            .load_last_state_streaming(0)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batched_account_type_updates_same_as_per_row() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let account_ids: Vec<i64> = (2_000_000..2_000_004).collect();
+        for &account_id in &account_ids {
+            insert_test_account(&mut transaction, account_id).await?;
+        }
+        // The mixed types, and the later update of the same account wins.
+        let updates = vec![
+            (AccountId(2_000_000), AccountType::EthOwned, ChainId(1)),
+            (AccountId(2_000_001), AccountType::EthCREATE2, ChainId(2)),
+            (AccountId(2_000_002), AccountType::StarkContract, ChainId(3)),
+            (AccountId(2_000_000), AccountType::EthCREATE2, ChainId(4)),
+        ];
+
+        async fn account_types(
+            storage: &mut StorageProcessor<'_>,
+            account_ids: &[i64],
+        ) -> QueryResult<Vec<(AccountType, i16)>> {
+            let mut account_types = Vec::new();
+            for account_id in account_ids {
+                let account = storage
+                    .chain()
+                    .account_schema()
+                    .account_by_id(*account_id)
+                    .await?
+                    .expect("Account should be inserted");
+                account_types.push((account.account_type, account.chain_id));
+            }
+            Ok(account_types)
+        }
+
+        let mut savepoint = transaction.savepoint("batched").await?;
+        savepoint
+            .chain()
+            .state_schema()
+            .apply_account_type_updates(updates.clone())
+            .await?;
+        let batched = account_types(&mut savepoint, &account_ids).await?;
+        savepoint.rollback().await?;
+
+        for (id, account_type, chain_id) in updates {
+            transaction
+                .chain()
+                .account_schema()
+                .set_account_type(i64::from(*id), account_type, *chain_id as i16)
+                .await?;
+        }
+        let per_row = account_types(&mut transaction, &account_ids).await?;
+        assert_eq!(batched, per_row);
+        assert_eq!(per_row[3], (AccountType::Unknown, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compare_with_exported_snapshot() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+
+        // The exported snapshot matches the storage.
+        let mut exported = Vec::new();
+        storage
+            .chain()
+            .state_schema()
+            .export_snapshot(&mut exported)
+            .await?;
+        let diffs = storage
+            .chain()
+            .state_schema()
+            .compare_with_snapshot(exported.as_slice())
+            .await?;
+        assert!(diffs.is_empty());
+
+        // The account only in the snapshot is reported.
+        let mut snapshot: Vec<(AccountId, Account)> = serde_json::from_slice(&exported)?;
+        let account_id = AccountId(u32::MAX);
+        snapshot.push((account_id, Account::default()));
+        let diffs = storage
+            .chain()
+            .state_schema()
+            .compare_with_snapshot(serde_json::to_vec(&snapshot)?.as_slice())
+            .await?;
+        assert_eq!(
+            diffs,
+            vec![AccountDiff {
+                account_id,
+                storage: None,
+                snapshot: Some(Account::default()),
+            }]
+        );
+        Ok(())
+    }
 }
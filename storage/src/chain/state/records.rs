@@ -0,0 +1,13 @@
+// External imports
+use serde::{Deserialize, Serialize};
+// Workspace imports
+use zklink_types::{Account, AccountId};
+
+/// The account that differs between the storage and the snapshot,
+/// the side where the account is absent is `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    pub account_id: AccountId,
+    pub storage: Option<Account>,
+    pub snapshot: Option<Account>,
+}
@@ -1,50 +1,248 @@
 // Built-in deps
 use std::time::Instant;
 // External imports
-
+use sqlx::types::BigDecimal;
 // Workspace imports
-use zklink_types::BlockNumber;
+use zklink_crypto::params::TOKEN_MAX_PRECISION;
+
 // Local imports
 use crate::{QueryResult, StorageProcessor};
 
+/// The tables of the recovered state and the exit proofs, whose sizes are reported by `table_sizes`.
+pub const KNOWN_TABLES: &[&str] = &[
+    "submit_txs",
+    "accounts",
+    "balances",
+    "account_order_nonces",
+    "account_creates",
+    "account_balance_updates",
+    "account_order_updates",
+    "account_pubkey_updates",
+    "blocks",
+    "aggregate_operations",
+    "eth_operations",
+    "tokens",
+    "exit_proofs",
+    "recover_state_rollup_ops",
+    "recover_state_events_state",
+    "recover_state_storage_state_update",
+    "account_usd_values",
+];
+
 /// Auxiliary schema encapsulating the stats counting logic for the storage tables.
 #[derive(Debug)]
 pub struct StatsSchema<'a, 'c>(pub &'a mut StorageProcessor<'c>);
 
 impl<'a, 'c> StatsSchema<'a, 'c> {
-    /// Returns the amount of blocks that don't have proofs yet.
-    pub async fn count_outstanding_proofs(&mut self, after_block: BlockNumber) -> QueryResult<u32> {
+    /// Returns the total size in bytes(including indexes and toast) of every known table,
+    /// in descending order of size.
+    pub async fn table_sizes(&mut self) -> QueryResult<Vec<(String, i64)>> {
+        let start = Instant::now();
+        let sizes = sqlx::query!(
+            r#"SELECT t.name AS "name!", pg_total_relation_size(t.name::regclass) AS "size!"
+            FROM unnest($1::text[]) AS t(name)
+            ORDER BY 2 DESC"#,
+            KNOWN_TABLES
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| (record.name, record.size))
+        .collect();
+
+        metrics::histogram!("sql.chain.stats.table_sizes", start.elapsed());
+        Ok(sizes)
+    }
+
+    /// Returns the `limit` accounts with the largest total USD value across all the sub accounts and tokens,
+    /// in descending order of value. The balances are priced by the `token_price` table, the unpriced(zero price)
+    /// tokens take `default_price`, and are skipped if it's `None`.
+    ///
+    /// It aggregates the whole `balances` table, see `top_accounts_by_cached_value` for the cheap variant.
+    pub async fn top_accounts_by_value(
+        &mut self,
+        limit: i64,
+        default_price: Option<BigDecimal>,
+    ) -> QueryResult<Vec<(i64, BigDecimal)>> {
+        let start = Instant::now();
+        let accounts = sqlx::query!(
+            r#"SELECT b.account_id,
+                sum(b.balance * COALESCE(NULLIF(p.usd_price, 0), $2)) / power(10::numeric, $1::integer) AS "usd_value!"
+            FROM balances b INNER JOIN token_price p ON p.token_id = b.coin_id
+            WHERE p.usd_price <> 0 OR $2::numeric IS NOT NULL
+            GROUP BY b.account_id
+            ORDER BY 2 DESC, b.account_id
+            LIMIT $3"#,
+            TOKEN_MAX_PRECISION as i32,
+            default_price,
+            limit
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| (record.account_id, record.usd_value))
+        .collect();
+
+        metrics::histogram!("sql.chain.stats.top_accounts_by_value", start.elapsed());
+        Ok(accounts)
+    }
+
+    /// Same as `top_accounts_by_value`, but reads the `account_usd_values` table,
+    /// so the values(and the default price) are as of the last `refresh_account_usd_values`.
+    pub async fn top_accounts_by_cached_value(
+        &mut self,
+        limit: i64,
+    ) -> QueryResult<Vec<(i64, BigDecimal)>> {
         let start = Instant::now();
-        let count = sqlx::query!(
-            "SELECT COUNT(*) FROM executed_transactions WHERE block_number > $1",
-            i64::from(*after_block)
+        let accounts = sqlx::query!(
+            r#"SELECT account_id, usd_value FROM account_usd_values
+            ORDER BY usd_value DESC, account_id
+            LIMIT $1"#,
+            limit
         )
-        .fetch_one(self.0.conn())
+        .fetch_all(self.0.conn())
         .await?
-        .count
-        .unwrap_or(0);
+        .into_iter()
+        .map(|record| (record.account_id, record.usd_value))
+        .collect();
 
-        metrics::histogram!("sql.chain.stats.count_outstanding_proofs", start.elapsed());
-        Ok(count as u32)
+        metrics::histogram!(
+            "sql.chain.stats.top_accounts_by_cached_value",
+            start.elapsed()
+        );
+        Ok(accounts)
     }
 
-    /// Returns the amount of executed transactions (both usual and priority).
-    pub async fn count_total_transactions(&mut self) -> QueryResult<u32> {
+    /// Recomputes the `account_usd_values` table from the current balances and prices(see `top_accounts_by_value`
+    /// for `default_price`), the readers of the table see the previous values until the refresh is committed.
+    pub async fn refresh_account_usd_values(
+        &mut self,
+        default_price: Option<BigDecimal>,
+    ) -> QueryResult<()> {
         let start = Instant::now();
-        let count_tx =
-            sqlx::query!("SELECT COUNT(*) FROM executed_transactions WHERE success = true",)
-                .fetch_one(self.0.conn())
-                .await?
-                .count
-                .unwrap_or(0);
-
-        let prior_ops = sqlx::query!("SELECT COUNT(*) FROM executed_priority_operations",)
-            .fetch_one(self.0.conn())
-            .await?
-            .count
-            .unwrap_or(0);
-
-        metrics::histogram!("sql.chain.stats.count_total_transactions", start.elapsed());
-        Ok((count_tx + prior_ops) as u32)
+        let mut transaction = self.0.start_transaction().await?;
+        sqlx::query!("DELETE FROM account_usd_values")
+            .execute(transaction.conn())
+            .await?;
+        sqlx::query!(
+            r#"INSERT INTO account_usd_values (account_id, usd_value)
+            SELECT b.account_id,
+                sum(b.balance * COALESCE(NULLIF(p.usd_price, 0), $2)) / power(10::numeric, $1::integer)
+            FROM balances b INNER JOIN token_price p ON p.token_id = b.coin_id
+            WHERE p.usd_price <> 0 OR $2::numeric IS NOT NULL
+            GROUP BY b.account_id"#,
+            TOKEN_MAX_PRECISION as i32,
+            default_price
+        )
+        .execute(transaction.conn())
+        .await?;
+        transaction.commit().await?;
+
+        metrics::histogram!(
+            "sql.chain.stats.refresh_account_usd_values",
+            start.elapsed()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::KNOWN_TABLES;
+    use crate::{insert_test_account, QueryResult, StorageProcessor};
+
+    #[tokio::test]
+    async fn table_sizes_of_known_tables() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let sizes = storage.chain().stats_schema().table_sizes().await?;
+
+        assert_eq!(sizes.len(), KNOWN_TABLES.len());
+        assert!(sizes.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn top_accounts_by_usd_value() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        // Token 60_000 is priced at 2 USD, token 60_001 is registered without price.
+        sqlx::query!(
+            "INSERT INTO token_price (token_id, symbol, price_id, usd_price, last_update_time)
+            VALUES (60000, 'WHALE', 'whale', 2, now()), (60001, 'NEW', '', 0, now())"
+        )
+        .execute(transaction.conn())
+        .await?;
+        // The values are far above the existing accounts, 10^30 units of 18 decimals is 10^12 tokens.
+        let whale = BigDecimal::from(10u64.pow(15)) * BigDecimal::from(10u64.pow(15));
+        let balances = [
+            (3_000_000i64, 0, 60000, whale.clone()),
+            (3_000_000, 1, 60000, whale.clone()),
+            (3_000_000, 0, 60001, whale.clone() * BigDecimal::from(100)),
+            (3_000_001, 0, 60000, whale.clone() * BigDecimal::from(3)),
+            (3_000_002, 0, 60001, whale.clone() * BigDecimal::from(100)),
+        ];
+        for account_id in 3_000_000i64..3_000_003 {
+            insert_test_account(&mut transaction, account_id).await?;
+        }
+        for (account_id, sub_account_id, coin_id, balance) in balances {
+            sqlx::query!(
+                "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES ($1, $2, $3, $4)",
+                account_id,
+                sub_account_id,
+                coin_id,
+                balance
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+        let expected = vec![
+            (3_000_001, BigDecimal::from(6 * 10u64.pow(12))),
+            (3_000_000, BigDecimal::from(4 * 10u64.pow(12))),
+        ];
+
+        let top = transaction
+            .chain()
+            .stats_schema()
+            .top_accounts_by_value(2, None)
+            .await?;
+        assert_eq!(top, expected);
+
+        transaction
+            .chain()
+            .stats_schema()
+            .refresh_account_usd_values(None)
+            .await?;
+        let cached = transaction
+            .chain()
+            .stats_schema()
+            .top_accounts_by_cached_value(2)
+            .await?;
+        assert_eq!(cached, expected);
+
+        // The unpriced token 60_001 takes the default price of 1 USD.
+        let expected = vec![
+            (3_000_000, BigDecimal::from(104 * 10u64.pow(12))),
+            (3_000_002, BigDecimal::from(100 * 10u64.pow(12))),
+        ];
+        let top = transaction
+            .chain()
+            .stats_schema()
+            .top_accounts_by_value(2, Some(BigDecimal::from(1)))
+            .await?;
+        assert_eq!(top, expected);
+        transaction
+            .chain()
+            .stats_schema()
+            .refresh_account_usd_values(Some(BigDecimal::from(1)))
+            .await?;
+        let cached = transaction
+            .chain()
+            .stats_schema()
+            .top_accounts_by_cached_value(2)
+            .await?;
+        assert_eq!(cached, expected);
+        Ok(())
     }
 }
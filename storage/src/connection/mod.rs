@@ -1,15 +1,18 @@
 // Built-in deps
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use std::{fmt, time::Instant};
 // External imports
 use async_trait::async_trait;
 use deadpool::managed::{Manager, PoolConfig, RecycleResult, Timeouts};
 use deadpool::Runtime;
-use sqlx::{Connection, Error as SqlxError, PgConnection};
+use serde::Serialize;
+use sqlx::{Connection, Error as SqlxError, Executor, PgConnection};
 use tokio::time::sleep;
 use tracing::log::warn;
 // Local imports
-use crate::StorageProcessor;
+use crate::{QueryResult, StorageProcessor, SLOW_TX_THRESHOLD};
 
 pub mod holder;
 
@@ -20,17 +23,56 @@ pub type PooledConnection = deadpool::managed::Object<DbPool>;
 #[derive(Clone)]
 pub struct DbPool {
     url: String,
+    /// The sessions of the read-only pool reject the write queries.
+    readonly: bool,
 }
 
-const DB_CONNECTION_RETRIES: usize = 30000;
+/// The default number of retries to get a connection in `access_storage_with_retry`.
+const DEFAULT_CONNECTION_RETRIES: usize = 120;
+/// The default delay between the retries to get a connection in `access_storage_with_retry`.
+const DEFAULT_CONNECTION_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// The minimum interval between the warnings of the failed retries.
+const RETRY_LOG_INTERVAL: Duration = Duration::from_secs(10);
+/// The interval of checking whether the pool should be rebuilt with a new url.
+const FAILOVER_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// The number of consecutive connection failures that the database is considered unreachable.
+const FAILOVER_FAILURES_THRESHOLD: usize = 5;
+
+/// The live connection metrics of the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PoolStatus {
+    /// The number of idle connections ready to be taken.
+    pub available: usize,
+    /// The number of connections currently in the pool(idle and in use).
+    pub size: usize,
+    /// The number of callers waiting for a connection.
+    pub waiting: usize,
+    pub max_size: usize,
+}
+
+impl From<deadpool::Status> for PoolStatus {
+    fn from(status: deadpool::Status) -> Self {
+        // A negative `available` is the number of the callers waiting for a connection.
+        Self {
+            available: status.available.max(0) as usize,
+            size: status.size,
+            waiting: (-status.available).max(0) as usize,
+            max_size: status.max_size,
+        }
+    }
+}
 
 impl DbPool {
-    fn create(url: impl Into<String>, max_size: usize) -> Pool {
+    fn create(url: impl Into<String>, max_size: usize, readonly: bool) -> Pool {
         let pool_config = PoolConfig {
             max_size,
             timeouts: Timeouts::wait_millis(20_000), // wait 20 seconds before returning error
         };
-        Pool::builder(DbPool { url: url.into() })
+        let manager = DbPool {
+            url: url.into(),
+            readonly,
+        };
+        Pool::builder(manager)
             .config(pool_config)
             .runtime(Runtime::Tokio1)
             .build()
@@ -44,9 +86,21 @@ impl Manager for DbPool {
     type Error = SqlxError;
 
     async fn create(&self) -> Result<PgConnection, SqlxError> {
-        PgConnection::connect(&self.url).await
+        let mut conn = PgConnection::connect(&self.url).await?;
+        if self.readonly {
+            conn.execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                .await?;
+        }
+        Ok(conn)
     }
     async fn recycle(&self, obj: &mut PgConnection) -> RecycleResult<SqlxError> {
+        // Restores the session made read-only by `access_storage_readonly` in debug builds.
+        if cfg!(debug_assertions) && !self.readonly {
+            obj.execute("SET default_transaction_read_only = off")
+                .await?;
+        }
+        // The ping also flushes the `ROLLBACK` queued by a dropped transaction(e.g. a cancelled request),
+        // so that a recycled connection never stays in a half-open transaction.
         Ok(obj.ping().await?)
     }
 }
@@ -56,9 +110,20 @@ impl Manager for DbPool {
 ///
 /// The size of the pool and the database URL are configured via environment
 /// variables `DATABASE_POOL_SIZE` and `DATABASE_URL` respectively.
+///
+/// The read-only queries can be served by a second pool pointed at a read replica,
+/// see `new_with_replica` and `access_storage_readonly`.
 #[derive(Clone)]
 pub struct ConnectionPool {
-    pool: Pool,
+    pool: Arc<RwLock<(String, Pool)>>,
+    read_pool: Option<Pool>,
+    max_size: usize,
+    consecutive_failures: Arc<AtomicUsize>,
+    connection_retries: usize,
+    connection_retry_delay: Duration,
+    slow_query_threshold: Option<Duration>,
+    slow_tx_threshold: Duration,
+    skip_state_invariant_check: bool,
 }
 
 impl fmt::Debug for ConnectionPool {
@@ -72,64 +137,317 @@ impl ConnectionPool {
     /// creates a new `ConnectionPool` object.
     /// max_size - number of connections in pool
     pub fn new(database_url: String, max_size: usize) -> Self {
-        let pool = DbPool::create(database_url, max_size);
+        let pool = DbPool::create(database_url.clone(), max_size, false);
+
+        Self {
+            pool: Arc::new(RwLock::new((database_url, pool))),
+            read_pool: None,
+            max_size,
+            consecutive_failures: Default::default(),
+            connection_retries: DEFAULT_CONNECTION_RETRIES,
+            connection_retry_delay: DEFAULT_CONNECTION_RETRY_DELAY,
+            slow_query_threshold: None,
+            slow_tx_threshold: SLOW_TX_THRESHOLD,
+            skip_state_invariant_check: false,
+        }
+    }
+
+    /// Establishes the pool of the connections to the primary database for writing, and
+    /// the pool of the connections to the read replica for `access_storage_readonly`.
+    pub fn new_with_replica(write_url: String, read_url: String, max_size: usize) -> Self {
+        Self::new(write_url, max_size).with_replica(read_url, max_size)
+    }
+
+    /// Sets the read replica used by `access_storage_readonly`, with its own pool size.
+    pub fn with_replica(mut self, read_url: String, read_pool_size: usize) -> Self {
+        self.read_pool = Some(DbPool::create(read_url, read_pool_size, true));
+        self
+    }
+
+    /// Sets the number of retries and the delay between them used by `access_storage_with_retry`.
+    pub fn with_retry_policy(mut self, retries: usize, retry_delay: Duration) -> Self {
+        self.connection_retries = retries;
+        self.connection_retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets the threshold over which the queries recorded by `StorageProcessor::record_query_time`
+    /// are logged as slow queries, never logged if `None`.
+    pub fn with_slow_query_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_query_threshold = threshold;
+        self
+    }
+
+    /// Sets the threshold over which the transactions started from the `StorageProcessor`s
+    /// of this pool are logged as slow transactions on commit, `SLOW_TX_THRESHOLD` if `None`.
+    pub fn with_slow_tx_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_tx_threshold = threshold.unwrap_or(SLOW_TX_THRESHOLD);
+        self
+    }
+
+    /// Skips the state invariant check of `StateSchema::apply_state_update` for the `StorageProcessor`s
+    /// of this pool, for replaying the known-good historical data faster.
+    pub fn with_skip_state_invariant_check(mut self, skip: bool) -> Self {
+        self.skip_state_invariant_check = skip;
+        self
+    }
+
+    /// Rebuilds the underlying pool with the new database url, the connections of the old pool
+    /// in use are still valid until released, and all the later connections come from the new pool.
+    pub fn reconfigure(&self, new_url: String) {
+        let new_pool = DbPool::create(new_url.clone(), self.max_size, false);
+        let (_, old_pool) = std::mem::replace(
+            &mut *self.pool.write().expect("Pool lock poisoned"),
+            (new_url, new_pool),
+        );
+        old_pool.close();
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Watches the connection failures, and rebuilds the pool once the connections keep failing
+    /// and the url returned by `url_source` has changed, e.g. the database failed over to a new host.
+    pub async fn watch_failover<F>(self, url_source: F)
+    where
+        F: Fn() -> Option<String>,
+    {
+        let mut interval = tokio::time::interval(FAILOVER_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if self.consecutive_failures.load(Ordering::Relaxed) < FAILOVER_FAILURES_THRESHOLD {
+                continue;
+            }
+            match url_source() {
+                Some(new_url) if new_url != self.url() => {
+                    warn!("Database is unreachable and its url has changed, rebuilding the pool");
+                    self.reconfigure(new_url);
+                }
+                _ => {}
+            }
+        }
+    }
 
-        Self { pool }
+    /// Returns the live connection metrics of the primary pool.
+    pub fn pool_status(&self) -> PoolStatus {
+        self.pool
+            .read()
+            .expect("Pool lock poisoned")
+            .1
+            .status()
+            .into()
+    }
+
+    fn url(&self) -> String {
+        self.pool.read().expect("Pool lock poisoned").0.clone()
+    }
+
+    async fn get(&self) -> Result<PooledConnection, deadpool::managed::PoolError<SqlxError>> {
+        let pool = self.pool.read().expect("Pool lock poisoned").1.clone();
+        let connection = pool.get().await;
+        if connection.is_ok() {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        connection
     }
 
     /// Creates a `StorageProcessor` entity over a recoverable connection.
     /// Upon a database outage connection will block the thread until
     /// it will be able to recover the connection (or, if connection cannot
     /// be restored after several retries, this will be considered as
-    /// irrecoverable database error and result in panic).
+    /// irrecoverable database error and returned to the caller).
     ///
     /// This method is intended to be used in crucial contexts, where the
     /// database access is must-have (e.g. block worker).
-    pub async fn access_storage_with_retry(&self) -> StorageProcessor<'_> {
+    pub async fn access_storage_with_retry(&self) -> QueryResult<StorageProcessor<'_>> {
+        let status = self.pool_status();
+        if status.waiting > status.max_size / 2 {
+            warn!(
+                "Connection pool is under pressure, {} callers are waiting for {} connections",
+                status.waiting, status.max_size
+            );
+        }
         let start = Instant::now();
-        let connection = self.get_pooled_connection().await;
+        let connection = self.get_pooled_connection().await?;
         metrics::histogram!("sql.connection_acquire", start.elapsed());
 
-        StorageProcessor::from_pool(connection)
+        Ok(StorageProcessor::from_pool(connection)
+            .with_slow_query_threshold(self.slow_query_threshold)
+            .with_slow_tx_threshold(self.slow_tx_threshold)
+            .with_skip_state_invariant_check(self.skip_state_invariant_check))
     }
 
     pub async fn access_storage(&self) -> anyhow::Result<StorageProcessor<'_>> {
         let start = Instant::now();
         let connection = self
-            .pool
             .get()
             .await
             .map_err(|e| anyhow::format_err!("Failed to get connection to db: {}", e))?;
         metrics::histogram!("sql.connection_acquire", start.elapsed());
 
-        Ok(StorageProcessor::from_pool(connection))
+        Ok(StorageProcessor::from_pool(connection)
+            .with_slow_query_threshold(self.slow_query_threshold)
+            .with_slow_tx_threshold(self.slow_tx_threshold)
+            .with_skip_state_invariant_check(self.skip_state_invariant_check))
     }
 
-    async fn get_pooled_connection(&self) -> PooledConnection {
-        let mut retry_count = 0;
+    /// Creates a read-only `StorageProcessor` from the read replica pool, falls back to
+    /// the primary pool if no replica is configured.
+    ///
+    /// The replica may lag behind the primary database, and the schemas accessed through
+    /// the returned processor must not call the write queries: they are rejected by the
+    /// read-only sessions of the replica pool.
+    pub async fn access_storage_readonly(&self) -> QueryResult<StorageProcessor<'_>> {
+        let Some(read_pool) = &self.read_pool else {
+            let mut storage = self.access_storage().await?.into_readonly();
+            // The session of the primary connection is read-only until recycled in debug builds,
+            // so the writes through the read-only processor fail even without a replica.
+            if cfg!(debug_assertions) {
+                storage
+                    .conn()
+                    .execute("SET default_transaction_read_only = on")
+                    .await?;
+            }
+            return Ok(storage);
+        };
+        let start = Instant::now();
+        let connection = read_pool
+            .get()
+            .await
+            .map_err(|e| anyhow::format_err!("Failed to get connection to read replica: {}", e))?;
+        metrics::histogram!("sql.read_connection_acquire", start.elapsed());
 
-        let one_second = Duration::from_secs(1);
+        Ok(StorageProcessor::from_pool(connection)
+            .with_slow_query_threshold(self.slow_query_threshold)
+            .with_slow_tx_threshold(self.slow_tx_threshold)
+            .into_readonly())
+    }
+
+    async fn get_pooled_connection(&self) -> QueryResult<PooledConnection> {
+        let mut retry_count = 0;
+        let mut last_log: Option<Instant> = None;
 
-        while retry_count < DB_CONNECTION_RETRIES {
-            let connection = self.pool.get().await;
+        while retry_count < self.connection_retries {
+            let connection = self.get().await;
 
             match connection {
-                Ok(connection) => return connection,
+                Ok(connection) => return Ok(connection),
                 Err(e) => {
-                    warn!(
-                        "Failed to get connection to db: {}. \
-                        Backing off for 1 second, retry_count: {:?}",
-                        e, retry_count
-                    );
+                    // Throttles the warnings so that a long outage does not flood the log
+                    if last_log.map_or(true, |last_log| last_log.elapsed() >= RETRY_LOG_INTERVAL) {
+                        warn!(
+                            "Failed to get connection to db: {}. \
+                            Backing off for {:?}, retry_count: {}/{}",
+                            e, self.connection_retry_delay, retry_count, self.connection_retries
+                        );
+                        last_log = Some(Instant::now());
+                    }
                     retry_count += 1
                 }
             }
 
-            // Backing off for one second if facing an error
-            sleep(one_second).await;
+            // Backing off if facing an error
+            sleep(self.connection_retry_delay).await;
         }
 
         // Attempting to get the pooled connection for the last time
-        self.pool.get().await.unwrap()
+        self.get().await.map_err(|e| {
+            anyhow::format_err!(
+                "Failed to get connection to db after {} retries: {}",
+                self.connection_retries,
+                e
+            )
+        })
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use super::*;
+    use crate::TEST_DATABASE_URL;
+
+    #[tokio::test]
+    async fn reconfigure_to_reachable_url() {
+        let pool = ConnectionPool::new("postgres://postgres@localhost:1/plasma".to_string(), 1);
+        for _ in 0..FAILOVER_FAILURES_THRESHOLD {
+            assert!(pool.access_storage().await.is_err());
+        }
+        assert_eq!(
+            pool.consecutive_failures.load(Ordering::Relaxed),
+            FAILOVER_FAILURES_THRESHOLD
+        );
+
+        // The clones share the rebuilt pool.
+        let cloned_pool = pool.clone();
+        pool.reconfigure(TEST_DATABASE_URL.to_string());
+        assert!(cloned_pool.access_storage().await.is_ok());
+        assert_eq!(cloned_pool.url(), TEST_DATABASE_URL);
+        assert_eq!(pool.consecutive_failures.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn pool_status_counts_waiting_callers() {
+        let pool = ConnectionPool::new(TEST_DATABASE_URL.to_string(), 2);
+        let (release_sender, release_receiver) = tokio::sync::watch::channel(false);
+        let mut holders = Vec::new();
+        for _ in 0..3 {
+            let pool = pool.clone();
+            let mut release_receiver = release_receiver.clone();
+            holders.push(tokio::spawn(async move {
+                let _storage = pool.access_storage().await.unwrap();
+                while !*release_receiver.borrow() {
+                    release_receiver.changed().await.unwrap();
+                }
+            }));
+        }
+        // Waits until the third task is blocked on the connections held by the others.
+        while pool.pool_status().waiting == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let status = pool.pool_status();
+        assert_eq!(status.size, 2);
+        assert_eq!(status.available, 0);
+        assert_eq!(status.waiting, 1);
+        assert_eq!(status.max_size, 2);
+
+        release_sender.send(true).unwrap();
+        for holder in holders {
+            holder.await.unwrap();
+        }
+        assert_eq!(pool.pool_status().waiting, 0);
+        assert_eq!(pool.pool_status().available, 2);
+    }
+
+    #[tokio::test]
+    async fn readonly_storage_rejects_writes() -> QueryResult<()> {
+        const WRITE_QUERY: &str = "UPDATE exit_proofs SET id = id WHERE false";
+
+        // The sessions of the replica pool are read-only.
+        let pool = ConnectionPool::new_with_replica(
+            TEST_DATABASE_URL.to_string(),
+            TEST_DATABASE_URL.to_string(),
+            1,
+        );
+        let mut storage = pool.access_storage_readonly().await?;
+        assert!(storage.is_readonly());
+        assert!(storage.conn().execute(WRITE_QUERY).await.is_err());
+        assert!(!pool.access_storage().await?.is_readonly());
+
+        // Falls back to the primary pool, the writes are rejected in debug builds.
+        let pool = ConnectionPool::new(TEST_DATABASE_URL.to_string(), 1);
+        let mut storage = pool.access_storage_readonly().await?;
+        let result = storage.conn().execute(WRITE_QUERY).await;
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        let mut transaction = storage.start_transaction().await?;
+        assert!(transaction.is_readonly());
+        let result = transaction.conn().execute(WRITE_QUERY).await;
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        drop(transaction);
+        drop(storage);
+
+        // The recycled primary connection is writable again.
+        let mut storage = pool.access_storage().await?;
+        storage.conn().execute(WRITE_QUERY).await?;
+        Ok(())
     }
 }
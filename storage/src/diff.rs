@@ -145,6 +145,41 @@ impl StorageAccountDiff {
         }
     }
 
+    /// Checks the invariants that any valid state transition holds: the balance never goes negative,
+    /// the nonce never decreases, and changing the pubkey always increases the nonce.
+    pub fn check_invariants(&self) -> anyhow::Result<()> {
+        match self {
+            StorageAccountDiff::BalanceUpdate(upd) => {
+                anyhow::ensure!(
+                    upd.new_balance >= BigDecimal::from(0),
+                    "State invariant violation: negative balance {} of account {} in block {}",
+                    upd.new_balance,
+                    upd.account_id,
+                    upd.block_number
+                );
+                anyhow::ensure!(
+                    upd.new_nonce >= upd.old_nonce,
+                    "State invariant violation: nonce of account {} decreased from {} to {} in block {}",
+                    upd.account_id,
+                    upd.old_nonce,
+                    upd.new_nonce,
+                    upd.block_number
+                );
+            }
+            StorageAccountDiff::ChangePubKey(upd) => {
+                anyhow::ensure!(
+                    upd.new_nonce > upd.old_nonce,
+                    "State invariant violation: nonce of account {} not increased from {} by ChangePubKey in block {}",
+                    upd.account_id,
+                    upd.old_nonce,
+                    upd.block_number
+                );
+            }
+            StorageAccountDiff::Create(_) | StorageAccountDiff::ChangeOrderNonce(_) => {}
+        }
+        Ok(())
+    }
+
     /// Returns the block index to which the operation belongs.
     pub fn block_number(&self) -> i64 {
         *match self {
@@ -162,3 +197,50 @@ impl StorageAccountDiff {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance_update(new_balance: i64, old_nonce: i64, new_nonce: i64) -> StorageAccountDiff {
+        StorageAccountDiff::BalanceUpdate(StorageAccountUpdate {
+            balance_update_id: 0,
+            account_id: 1,
+            sub_account_id: 0,
+            coin_id: 18,
+            old_balance: BigDecimal::from(10),
+            new_balance: BigDecimal::from(new_balance),
+            old_nonce,
+            new_nonce,
+            block_number: 100,
+            update_order_id: 0,
+            tx_hash: Vec::new(),
+        })
+    }
+
+    fn change_pubkey(old_nonce: i64, new_nonce: i64) -> StorageAccountDiff {
+        StorageAccountDiff::ChangePubKey(StorageAccountPubkeyUpdate {
+            pubkey_update_id: 0,
+            account_id: 1,
+            old_pubkey_hash: Vec::new(),
+            new_pubkey_hash: Vec::new(),
+            old_nonce,
+            new_nonce,
+            block_number: 100,
+            update_order_id: 0,
+            tx_hash: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn state_invariants() {
+        assert!(balance_update(0, 1, 1).check_invariants().is_ok());
+        assert!(balance_update(5, 1, 2).check_invariants().is_ok());
+        assert!(change_pubkey(1, 2).check_invariants().is_ok());
+
+        let err = balance_update(-1, 1, 1).check_invariants().unwrap_err();
+        assert!(err.to_string().contains("account 1 in block 100"));
+        assert!(balance_update(5, 2, 1).check_invariants().is_err());
+        assert!(change_pubkey(1, 1).check_invariants().is_err());
+    }
+}
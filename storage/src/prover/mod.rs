@@ -1,7 +1,8 @@
 // Built-in deps
 use std::time::Instant;
 // External imports
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgListener;
 use tracing::info;
 // Workspace imports
 // Local imports
@@ -10,6 +11,22 @@ use records::*;
 
 pub mod records;
 
+/// The postgres channel that `store_exit_proof` notifies the completed proofs to.
+pub const EXIT_PROOF_COMPLETED_CHANNEL: &str = "exit_proof_completed";
+/// The postgres channel that `cancel_this_exit_proof_task` notifies the failed tasks to.
+pub const EXIT_PROOF_FAILED_CHANNEL: &str = "exit_proof_failed";
+
+/// Listens to the completed proofs published by `store_exit_proof`(maybe in the other process)
+/// and the failed tasks. The payload of the completed proof notification is the json of
+/// `CompletedExitProof`, and the payload of the failed task notification is the task id.
+pub async fn listen_exit_proof_events(database_url: &str) -> QueryResult<PgListener> {
+    let mut listener = PgListener::connect(database_url).await?;
+    listener
+        .listen_all([EXIT_PROOF_COMPLETED_CHANNEL, EXIT_PROOF_FAILED_CHANNEL])
+        .await?;
+    Ok(listener)
+}
+
 /// Prover schema is capable of handling the prover-related informations,
 /// such as started prover jobs, registered provers and proofs for blocks.
 #[derive(Debug)]
@@ -25,6 +42,31 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(exit_proofs)
     }
 
+    /// Loads the exit proofs(including the unfinished tasks) in ascending order of id,
+    /// skipping the first `offset` ones, so that the whole table can be walked page by page.
+    pub async fn get_stored_exit_proofs_paged(
+        &mut self,
+        offset: i64,
+        limit: i64,
+    ) -> QueryResult<Vec<StoredExitProof>> {
+        let start = Instant::now();
+
+        let exit_proofs = sqlx::query_as!(
+            StoredExitProof,
+            r#"SELECT * FROM exit_proofs ORDER BY id ASC LIMIT $1 OFFSET $2"#,
+            limit,
+            offset
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.recover_state.get_stored_exit_proofs_paged",
+            start.elapsed()
+        );
+        Ok(exit_proofs)
+    }
+
     pub async fn get_proofs_by_page(
         &mut self,
         page: i64,
@@ -43,6 +85,35 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(exit_proofs)
     }
 
+    /// Loads the completed proofs after the cursor `(finished_at, id)` in order of completion(ascending order of
+    /// `finished_at`, and of id for the proofs finished at the same time). The cursor of the next page is the
+    /// `(finished_at, id)` of the last loaded proof.
+    pub async fn get_completed_proofs_after(
+        &mut self,
+        finished_at: DateTime<Utc>,
+        id: i64,
+        limit: i64,
+    ) -> QueryResult<Vec<StoredExitProof>> {
+        let start = Instant::now();
+
+        let exit_proofs = sqlx::query_as!(
+            StoredExitProof,
+            r#"SELECT * FROM exit_proofs WHERE (finished_at, id) > ($1::timestamptz, $2::bigint) AND proof IS NOT NULL
+            ORDER BY finished_at ASC, id ASC LIMIT $3"#,
+            finished_at,
+            id,
+            limit
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.recover_state.get_completed_proofs_after",
+            start.elapsed()
+        );
+        Ok(exit_proofs)
+    }
+
     pub async fn get_total_completed_proofs_num(&mut self) -> QueryResult<i64> {
         let num = sqlx::query!(r#"SELECT COUNT(*) FROM exit_proofs WHERE proof IS NOT NULL"#,)
             .fetch_one(self.0.conn())
@@ -98,41 +169,57 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(stored_exit_proofs)
     }
 
-    /// Start the task of the specified generating exit proof.
-    async fn start_this_exit_proof_task(&mut self, exit_info: StoredExitInfo) -> QueryResult<()> {
+    /// Cancel the task of the specified running exit proof.
+    ///
+    /// The task is soft-deleted by setting `cancelled_at`, so it will not be claimed again
+    /// by `load_exit_proof_task` until it is re-enabled by `resurrect_task`.
+    pub async fn cancel_this_exit_proof_task(
+        &mut self,
+        exit_info: StoredExitInfo,
+    ) -> QueryResult<()> {
         let start = Instant::now();
 
-        let created_at: chrono::DateTime<chrono::Local> = chrono::Local::now();
-        sqlx::query!(
-            "UPDATE exit_proofs SET created_at=$6 WHERE chain_id=$1 AND account_id=$2 \
-            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5",
+        let id = sqlx::query!(
+            "UPDATE exit_proofs SET created_at=NULL, cancelled_at=current_timestamp \
+            WHERE chain_id=$1 AND account_id=$2 \
+            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5 RETURNING id",
             exit_info.chain_id,
             exit_info.account_id,
             exit_info.sub_account_id,
             exit_info.l1_target_token,
             exit_info.l2_source_token,
-            created_at
         )
-        .execute(self.0.conn())
-        .await?;
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|record| record.id);
+
+        // Publishes the failed task to the subscribers(delivered when committed)
+        if let Some(id) = id {
+            sqlx::query!(
+                "SELECT pg_notify($1, $2)",
+                EXIT_PROOF_FAILED_CHANNEL,
+                id.to_string()
+            )
+            .execute(self.0.conn())
+            .await?;
+        }
 
         metrics::histogram!(
-            "sql.recover_state.start_this_exit_proof_task",
+            "sql.recover_state.cancel_this_exit_proof_task",
             start.elapsed()
         );
         Ok(())
     }
 
-    /// Cancel the task of the specified running exit proof.
-    pub async fn cancel_this_exit_proof_task(
-        &mut self,
-        exit_info: StoredExitInfo,
-    ) -> QueryResult<()> {
+    /// Re-enable the cancelled task, so that it can be loaded by the prover again.
+    pub async fn resurrect_task(&mut self, exit_info: StoredExitInfo) -> QueryResult<()> {
         let start = Instant::now();
 
         sqlx::query!(
-            "UPDATE exit_proofs SET created_at=NULL WHERE chain_id=$1 AND account_id=$2 \
-            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5",
+            "UPDATE exit_proofs SET created_at=NULL, cancelled_at=NULL \
+            WHERE chain_id=$1 AND account_id=$2 \
+            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5 \
+            AND cancelled_at IS NOT NULL",
             exit_info.chain_id,
             exit_info.account_id,
             exit_info.sub_account_id,
@@ -142,13 +229,35 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         .execute(self.0.conn())
         .await?;
 
-        metrics::histogram!(
-            "sql.recover_state.cancel_this_exit_proof_task",
-            start.elapsed()
-        );
+        metrics::histogram!("sql.recover_state.resurrect_task", start.elapsed());
         Ok(())
     }
 
+    /// Requeue the task of the specified exit proof, so that the proof will be regenerated
+    /// (e.g. the proof was generated against a wrong root). A cancelled task is re-enabled too.
+    /// Returns `false` if the task does not exist.
+    pub async fn requeue_proof(&mut self, exit_info: StoredExitInfo) -> QueryResult<bool> {
+        let start = Instant::now();
+
+        let rows = sqlx::query!(
+            "UPDATE exit_proofs SET proof=NULL, amount=NULL, finished_at=NULL, created_at=NULL, \
+            started_at=NULL, proof_size_bytes=NULL, prove_duration_ms=NULL, cancelled_at=NULL \
+            WHERE chain_id=$1 AND account_id=$2 \
+            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5",
+            exit_info.chain_id,
+            exit_info.account_id,
+            exit_info.sub_account_id,
+            exit_info.l1_target_token,
+            exit_info.l2_source_token,
+        )
+        .execute(self.0.conn())
+        .await?
+        .rows_affected();
+
+        metrics::histogram!("sql.recover_state.requeue_proof", start.elapsed());
+        Ok(rows > 0)
+    }
+
     /// update created_at by proof id(for created_at as heartbeat update time).
     pub async fn update_heartbeat_time(&mut self, proof_id: i64) -> QueryResult<()> {
         let start = Instant::now();
@@ -184,27 +293,40 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(())
     }
 
-    /// Loads the tasks that have never been started.
-    pub async fn load_exit_proof_task(&mut self) -> QueryResult<Option<StoredExitProof>> {
+    /// Claims the task of the highest priority that has never been started, and returns it
+    /// with the start time set.
+    ///
+    /// If `order_by_balance`, the tasks of the larger balances of the exiting token are claimed first,
+    /// so that the large holders exit before the deadline. The tasks without balance come last.
+    ///
+    /// The task is selected and started by a single statement, the rows locked by the other
+    /// workers are skipped, so every task is claimed by exactly one worker.
+    pub async fn load_exit_proof_task(
+        &mut self,
+        order_by_balance: bool,
+    ) -> QueryResult<Option<StoredExitProof>> {
         let start = Instant::now();
-        let mut transaction = self.0.start_transaction().await?;
 
+        let created_at: chrono::DateTime<chrono::Local> = chrono::Local::now();
         let stored_exit_proof = sqlx::query_as!(
             StoredExitProof,
-            "SELECT * FROM exit_proofs WHERE created_at IS NULL AND finished_at IS NULL \
-            ORDER BY id ASC LIMIT 1 \
-            FOR UPDATE SKIP LOCKED\
-            ",
+            "UPDATE exit_proofs SET created_at = $1, started_at = $1 \
+            WHERE id = (SELECT exit_proofs.id FROM exit_proofs \
+            LEFT JOIN balances ON balances.account_id = exit_proofs.account_id \
+            AND balances.sub_account_id = exit_proofs.sub_account_id \
+            AND balances.coin_id = exit_proofs.l2_source_token \
+            WHERE exit_proofs.created_at IS NULL AND exit_proofs.finished_at IS NULL \
+            AND exit_proofs.cancelled_at IS NULL \
+            ORDER BY CASE WHEN $2 THEN balances.balance END DESC NULLS LAST, \
+            exit_proofs.priority DESC, exit_proofs.id ASC LIMIT 1 \
+            FOR UPDATE OF exit_proofs SKIP LOCKED) \
+            RETURNING *",
+            created_at,
+            order_by_balance
         )
-        .fetch_optional(transaction.conn())
+        .fetch_optional(self.0.conn())
         .await?;
-        if let Some(exit_proof) = &stored_exit_proof {
-            ProverSchema(&mut transaction)
-                .start_this_exit_proof_task(exit_proof.into())
-                .await?;
-        }
 
-        transaction.commit().await?;
         metrics::histogram!("sql.recover_state.load_exit_proofs", start.elapsed());
         Ok(stored_exit_proof)
     }
@@ -231,6 +353,57 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(target_task_id)
     }
 
+    /// Query the task id, whether its proof has been generated and whether it has been cancelled by exit info.
+    /// Returns `None` if the task does not exist.
+    pub async fn get_task_status(
+        &mut self,
+        task: StoredExitInfo,
+    ) -> QueryResult<Option<StoredTaskStatus>> {
+        let start = Instant::now();
+
+        let task_status = sqlx::query_as!(
+            StoredTaskStatus,
+            "SELECT id, proof IS NOT NULL AS \"completed!\", cancelled_at IS NOT NULL AS \"cancelled!\" \
+            FROM exit_proofs WHERE chain_id=$1 \
+            AND account_id=$2 AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5",
+            task.chain_id,
+            task.account_id,
+            task.sub_account_id,
+            task.l1_target_token,
+            task.l2_source_token,
+        )
+        .fetch_optional(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.recover_state.get_task_status", start.elapsed());
+        Ok(task_status)
+    }
+
+    /// Query the finished(completed or failed) tasks among the specified tasks,
+    /// the failed tasks are `cancelled`.
+    pub async fn get_finished_tasks_status(
+        &mut self,
+        task_ids: &[i64],
+    ) -> QueryResult<Vec<StoredTaskStatus>> {
+        let start = Instant::now();
+
+        let tasks_status = sqlx::query_as!(
+            StoredTaskStatus,
+            "SELECT id, finished_at IS NOT NULL AS \"completed!\", \
+            cancelled_at IS NOT NULL AS \"cancelled!\" FROM exit_proofs WHERE id = ANY($1) AND (finished_at IS NOT NULL OR cancelled_at IS NOT NULL) \
+            ORDER BY id",
+            task_ids
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.recover_state.get_finished_tasks_status",
+            start.elapsed()
+        );
+        Ok(tasks_status)
+    }
+
     /// Query which task the prover is currently working on.
     pub async fn get_running_max_task_id(&mut self) -> QueryResult<i64> {
         let start = Instant::now();
@@ -251,7 +424,8 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         let start = Instant::now();
 
         let tasks_count = sqlx::query!(
-            "SELECT count(id) FROM exit_proofs WHERE created_at IS NULL AND finished_at IS NULL",
+            "SELECT count(id) FROM exit_proofs WHERE created_at IS NULL AND finished_at IS NULL \
+            AND cancelled_at IS NULL",
         )
         .fetch_one(self.0.conn())
         .await?
@@ -262,6 +436,42 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(tasks_count)
     }
 
+    /// Query the number of unstarted tasks that will be processed before the specified task,
+    /// i.e. the tasks claimed earlier by [`Self::load_exit_proof_task`] with the same `order_by_balance`.
+    /// Returns `None` if the task does not exist or is no longer waiting(running, completed or cancelled).
+    pub async fn task_queue_position(
+        &mut self,
+        task_id: i64,
+        order_by_balance: bool,
+    ) -> QueryResult<Option<i64>> {
+        let start = Instant::now();
+
+        // The waiting tasks are ranked by the same order as they are claimed.
+        let position = sqlx::query!(
+            r#"SELECT queue.position AS "position!" FROM (
+                SELECT exit_proofs.id, row_number() OVER (
+                    ORDER BY CASE WHEN $2 THEN balances.balance END DESC NULLS LAST,
+                    exit_proofs.priority DESC, exit_proofs.id ASC
+                ) - 1 AS position
+                FROM exit_proofs
+                LEFT JOIN balances ON balances.account_id = exit_proofs.account_id
+                AND balances.sub_account_id = exit_proofs.sub_account_id
+                AND balances.coin_id = exit_proofs.l2_source_token
+                WHERE exit_proofs.created_at IS NULL AND exit_proofs.finished_at IS NULL
+                AND exit_proofs.cancelled_at IS NULL
+            ) queue
+            WHERE queue.id = $1"#,
+            task_id,
+            order_by_balance
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|record| record.position);
+
+        metrics::histogram!("sql.recover_state.task_queue_position", start.elapsed());
+        Ok(position)
+    }
+
     /// Count the number of tasks running
     pub async fn count_running_tasks(&mut self) -> QueryResult<i64> {
         let start = Instant::now();
@@ -279,6 +489,53 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(count)
     }
 
+    /// Query the pending, running and completed task counts along with the average proving duration
+    /// and total withdrawal amount of the completed proofs, scanning `exit_proofs` only once.
+    pub async fn get_prover_stats(&mut self) -> QueryResult<StoredProverStats> {
+        let start = Instant::now();
+
+        let stats = sqlx::query_as!(
+            StoredProverStats,
+            r#"SELECT
+                count(*) FILTER (
+                    WHERE created_at IS NULL AND finished_at IS NULL AND cancelled_at IS NULL
+                ) AS "pending!",
+                count(*) FILTER (WHERE created_at IS NOT NULL AND finished_at IS NULL) AS "running!",
+                count(*) FILTER (WHERE proof IS NOT NULL) AS "completed!",
+                avg(prove_duration_ms) FILTER (WHERE proof IS NOT NULL)::bigint AS avg_duration_ms
+            FROM exit_proofs"#
+        )
+        .fetch_one(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.recover_state.get_prover_stats", start.elapsed());
+        Ok(stats)
+    }
+
+    /// Returns the sum of the withdrawal amounts of the completed proofs of every layer2 source token,
+    /// in ascending order of token. The amounts of different tokens are never added up.
+    pub async fn completed_withdraw_amounts(&mut self) -> QueryResult<Vec<(i32, BigDecimal)>> {
+        let start = Instant::now();
+
+        let amounts = sqlx::query!(
+            r#"SELECT l2_source_token, coalesce(sum(amount), 0) AS "amount!" FROM exit_proofs
+            WHERE proof IS NOT NULL
+            GROUP BY l2_source_token
+            ORDER BY l2_source_token"#
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| (record.l2_source_token, record.amount))
+        .collect();
+
+        metrics::histogram!(
+            "sql.recover_state.completed_withdraw_amounts",
+            start.elapsed()
+        );
+        Ok(amounts)
+    }
+
     /// Changes created_at to null for previously unfinished tasks
     pub async fn process_unfinished_tasks(&mut self) -> QueryResult<()> {
         let start = Instant::now();
@@ -299,33 +556,114 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         let start = Instant::now();
 
         let finished_at = Utc::now();
-        // counts tasks that have been started but not completed.
-        sqlx::query!(
-            "UPDATE exit_proofs SET proof=$6, amount=$7, finished_at=$8 WHERE chain_id=$1 AND account_id=$2 \
-            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5",
-            proof.chain_id, proof.account_id,
-            proof.sub_account_id, proof.l1_target_token, proof.l2_source_token,
-            proof.proof, proof.amount, finished_at
+        let proof_size_bytes = match &proof.proof {
+            Some(proof) => Some(serde_json::to_vec(proof)?.len() as i64),
+            None => None,
+        };
+        // The duration is measured from `started_at`, since `created_at` is refreshed by heartbeat.
+        let id = sqlx::query!(
+            "UPDATE exit_proofs SET proof=$6, amount=$7, finished_at=$8, proof_size_bytes=$9, \
+            prove_duration_ms=(EXTRACT(EPOCH FROM ($8 - started_at)) * 1000)::bigint \
+            WHERE chain_id=$1 AND account_id=$2 \
+            AND sub_account_id=$3 AND l1_target_token=$4 AND l2_source_token=$5 RETURNING id",
+            proof.chain_id,
+            proof.account_id,
+            proof.sub_account_id,
+            proof.l1_target_token,
+            proof.l2_source_token,
+            proof.proof,
+            proof.amount,
+            finished_at,
+            proof_size_bytes
         )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|record| record.id);
+
+        // Publishes the completed proof to the subscribers(delivered when committed)
+        if let Some(id) = id {
+            let completed_proof = CompletedExitProof {
+                id,
+                exit_info: (&proof).into(),
+            };
+            sqlx::query!(
+                "SELECT pg_notify($1, $2)",
+                EXIT_PROOF_COMPLETED_CHANNEL,
+                serde_json::to_string(&completed_proof)?
+            )
             .execute(self.0.conn())
             .await?;
+        }
 
         metrics::histogram!("sql.recover_state.store_exit_proof", start.elapsed());
         Ok(())
     }
 
-    /// Inserts task that generated exit proof.
-    pub async fn insert_exit_task(&mut self, task: StoredExitInfo) -> QueryResult<i64> {
-        info!("Insert new exit task: {}", task);
+    /// Query the time when the proof was finished by id, `None` if the proof isn't finished or doesn't exist.
+    pub async fn proof_finished_at(&mut self, id: i64) -> QueryResult<Option<DateTime<Utc>>> {
+        let start = Instant::now();
+
+        let finished_at = sqlx::query!("SELECT finished_at FROM exit_proofs WHERE id=$1", id)
+            .fetch_optional(self.0.conn())
+            .await?
+            .and_then(|record| record.finished_at);
+
+        metrics::histogram!("sql.recover_state.proof_finished_at", start.elapsed());
+        Ok(finished_at)
+    }
+
+    /// Query the size and generating duration of the proof by id,
+    /// the metadata is `None` until the proof is stored.
+    pub async fn proof_metadata(&mut self, id: i64) -> QueryResult<Option<StoredProofMetadata>> {
+        let start = Instant::now();
+
+        let metadata = sqlx::query_as!(
+            StoredProofMetadata,
+            "SELECT id, proof_size_bytes, prove_duration_ms FROM exit_proofs WHERE id=$1",
+            id
+        )
+        .fetch_optional(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.recover_state.proof_metadata", start.elapsed());
+        Ok(metadata)
+    }
+
+    /// Inserts task that generated exit proof with the default priority(0).
+    ///
+    /// `request_id` is the correlation id of the api request that created the task,
+    /// it is used to trace the task through the prover.
+    pub async fn insert_exit_task(
+        &mut self,
+        task: StoredExitInfo,
+        request_id: Option<&str>,
+    ) -> QueryResult<i64> {
+        self.insert_exit_task_with_priority(task, 0, request_id)
+            .await
+    }
+
+    /// Inserts task that generated exit proof, the tasks of higher `priority` are claimed first
+    /// by `load_exit_proof_task`. The priority of the existing task is only raised, never lowered.
+    pub async fn insert_exit_task_with_priority(
+        &mut self,
+        task: StoredExitInfo,
+        priority: i16,
+        request_id: Option<&str>,
+    ) -> QueryResult<i64> {
+        info!(
+            "Insert new exit task: {}, priority: {}, request_id: {:?}",
+            task, priority, request_id
+        );
         let start = Instant::now();
 
         // counts tasks that have been started but not completed.
         let id = sqlx::query!(
-            "INSERT INTO exit_proofs (chain_id, account_id, sub_account_id, l1_target_token, l2_source_token) \
-            VALUES ($1, $2, $3, $4, $5)\
+            "INSERT INTO exit_proofs (chain_id, account_id, sub_account_id, l1_target_token, l2_source_token, request_id, priority) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7)\
             ON CONFLICT (chain_id, account_id, sub_account_id, l1_target_token, l2_source_token) \
-            DO UPDATE SET chain_id = $1 RETURNING id",
+            DO UPDATE SET priority = GREATEST(exit_proofs.priority, $7) RETURNING id",
             task.chain_id, task.account_id, task.sub_account_id, task.l1_target_token, task.l2_source_token,
+            request_id, priority,
         )
             .fetch_one(self.0.conn())
             .await?
@@ -338,6 +676,8 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
     pub async fn insert_batch_exit_tasks(
         &mut self,
         batch_exit_tasks: Vec<StoredExitInfo>,
+        priority: i16,
+        request_id: Option<&str>,
     ) -> QueryResult<Vec<i64>> {
         let mut tasks_ids = Vec::with_capacity(batch_exit_tasks.len());
         let mut transaction = self.0.start_transaction().await?;
@@ -345,7 +685,7 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
             tasks_ids.push(
                 transaction
                     .prover_schema()
-                    .insert_exit_task(exit_task)
+                    .insert_exit_task_with_priority(exit_task, priority, request_id)
                     .await?,
             );
         }
@@ -354,3 +694,872 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(tasks_ids)
     }
 }
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use super::records::{
+        StoredExitInfo, StoredExitProof, StoredProofMetadata, StoredProverStats, StoredTaskStatus,
+    };
+    use crate::{
+        insert_test_account, ConnectionPool, QueryResult, StorageProcessor, TEST_DATABASE_URL,
+    };
+    use sqlx::types::BigDecimal;
+    use std::sync::Arc;
+    use tokio::sync::Barrier;
+
+    fn exit_info() -> StoredExitInfo {
+        StoredExitInfo {
+            chain_id: 1,
+            account_id: 10,
+            sub_account_id: 1,
+            l1_target_token: 17,
+            l2_source_token: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn task_status_not_exist_generating_completed() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+
+        // The task has never been submitted.
+        let status = transaction
+            .prover_schema()
+            .get_task_status(exit_info())
+            .await?;
+        assert_eq!(status, None);
+
+        // The task exists, but the proof has not been generated.
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        let status = transaction
+            .prover_schema()
+            .get_task_status(exit_info())
+            .await?;
+        assert_eq!(
+            status,
+            Some(StoredTaskStatus {
+                id,
+                completed: false,
+                cancelled: false,
+            })
+        );
+
+        // The proof has been stored.
+        let info = exit_info();
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                id,
+                chain_id: info.chain_id,
+                account_id: info.account_id,
+                sub_account_id: info.sub_account_id,
+                l1_target_token: info.l1_target_token,
+                l2_source_token: info.l2_source_token,
+                proof: Some(serde_json::json!({})),
+                amount: Some(Default::default()),
+                created_at: None,
+                finished_at: None,
+                cancelled_at: None,
+                request_id: None,
+                started_at: None,
+                proof_size_bytes: None,
+                prove_duration_ms: None,
+                priority: 0,
+            })
+            .await?;
+        let status = transaction
+            .prover_schema()
+            .get_task_status(exit_info())
+            .await?;
+        assert_eq!(
+            status,
+            Some(StoredTaskStatus {
+                id,
+                completed: true,
+                cancelled: false,
+            })
+        );
+
+        // The second task fails, and the third one is still waiting.
+        let failed_info = StoredExitInfo {
+            account_id: 11,
+            ..exit_info()
+        };
+        let failed = transaction
+            .prover_schema()
+            .insert_exit_task(failed_info.clone(), None)
+            .await?;
+        transaction
+            .prover_schema()
+            .cancel_this_exit_proof_task(failed_info.clone())
+            .await?;
+        let status = transaction
+            .prover_schema()
+            .get_task_status(failed_info)
+            .await?;
+        assert_eq!(
+            status,
+            Some(StoredTaskStatus {
+                id: failed,
+                completed: false,
+                cancelled: true,
+            })
+        );
+        let waiting = transaction
+            .prover_schema()
+            .insert_exit_task(
+                StoredExitInfo {
+                    account_id: 12,
+                    ..exit_info()
+                },
+                None,
+            )
+            .await?;
+        let tasks_status = transaction
+            .prover_schema()
+            .get_finished_tasks_status(&[waiting, failed, id])
+            .await?;
+        assert_eq!(
+            tasks_status,
+            vec![
+                StoredTaskStatus {
+                    id,
+                    completed: true,
+                    cancelled: false,
+                },
+                StoredTaskStatus {
+                    id: failed,
+                    completed: false,
+                    cancelled: true,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn requeued_proof_is_reclaimable() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        assert_eq!(task.map(|t| t.id), Some(id));
+
+        let info = exit_info();
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                id,
+                chain_id: info.chain_id,
+                account_id: info.account_id,
+                sub_account_id: info.sub_account_id,
+                l1_target_token: info.l1_target_token,
+                l2_source_token: info.l2_source_token,
+                proof: Some(serde_json::json!({})),
+                amount: Some(Default::default()),
+                created_at: None,
+                finished_at: None,
+                cancelled_at: None,
+                request_id: None,
+                started_at: None,
+                proof_size_bytes: None,
+                prove_duration_ms: None,
+                priority: 0,
+            })
+            .await?;
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        assert!(task.is_none());
+
+        assert!(
+            transaction
+                .prover_schema()
+                .requeue_proof(exit_info())
+                .await?
+        );
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?
+            .expect("The requeued task must be reclaimable");
+        assert_eq!(task.id, id);
+        assert!(task.proof.is_none());
+        assert!(task.amount.is_none());
+        assert!(task.finished_at.is_none());
+
+        // The cancelled task is reclaimable after being requeued.
+        transaction
+            .prover_schema()
+            .cancel_this_exit_proof_task(exit_info())
+            .await?;
+        assert!(transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?
+            .is_none());
+        assert!(
+            transaction
+                .prover_schema()
+                .requeue_proof(exit_info())
+                .await?
+        );
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?
+            .expect("The requeued cancelled task must be reclaimable");
+        assert_eq!(task.id, id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn task_queue_position() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        let first = transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        let second = transaction
+            .prover_schema()
+            .insert_exit_task(
+                StoredExitInfo {
+                    account_id: 11,
+                    ..exit_info()
+                },
+                None,
+            )
+            .await?;
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(first, false)
+            .await?;
+        assert_eq!(position, Some(0));
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(second, false)
+            .await?;
+        assert_eq!(position, Some(1));
+
+        // The first task starts running, so it leaves the queue.
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        assert_eq!(task.map(|t| t.id), Some(first));
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(first, false)
+            .await?;
+        assert_eq!(position, None);
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(second, false)
+            .await?;
+        assert_eq!(position, Some(0));
+
+        // The task has never been submitted.
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(second + 1, false)
+            .await?;
+        assert_eq!(position, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stored_proof_metadata() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        let metadata = transaction.prover_schema().proof_metadata(id).await?;
+        assert_eq!(
+            metadata,
+            Some(StoredProofMetadata {
+                id,
+                proof_size_bytes: None,
+                prove_duration_ms: None
+            })
+        );
+
+        let info = exit_info();
+        let proof = serde_json::json!({ "inputs": ["0x01"] });
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                id,
+                chain_id: info.chain_id,
+                account_id: info.account_id,
+                sub_account_id: info.sub_account_id,
+                l1_target_token: info.l1_target_token,
+                l2_source_token: info.l2_source_token,
+                proof: Some(proof.clone()),
+                amount: Some(Default::default()),
+                created_at: None,
+                finished_at: None,
+                cancelled_at: None,
+                request_id: None,
+                started_at: None,
+                proof_size_bytes: None,
+                prove_duration_ms: None,
+                priority: 0,
+            })
+            .await?;
+        let metadata = transaction
+            .prover_schema()
+            .proof_metadata(id)
+            .await?
+            .expect("The stored proof must have metadata");
+        assert_eq!(
+            metadata.proof_size_bytes,
+            Some(serde_json::to_vec(&proof)?.len() as i64)
+        );
+        assert!(metadata.prove_duration_ms.unwrap() >= 0);
+
+        // The metadata is cleared along with the requeued proof.
+        transaction
+            .prover_schema()
+            .requeue_proof(exit_info())
+            .await?;
+        let metadata = transaction.prover_schema().proof_metadata(id).await?;
+        assert_eq!(metadata.and_then(|m| m.proof_size_bytes), None);
+
+        // The proof has never been submitted.
+        let metadata = transaction.prover_schema().proof_metadata(id + 1).await?;
+        assert_eq!(metadata, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tasks_count_by_status() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        // Four tasks: one gets completed, one keeps running, one is cancelled and one stays in the queue.
+        let exit_task = |account_id| StoredExitInfo {
+            account_id,
+            ..exit_info()
+        };
+        for account_id in 1..=4 {
+            transaction
+                .prover_schema()
+                .insert_exit_task(exit_task(account_id), None)
+                .await?;
+        }
+        let completed = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?
+            .expect("There must be an unclaimed task");
+        transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                proof: Some(serde_json::json!({ "inputs": ["0x01"] })),
+                amount: Some(BigDecimal::from(100)),
+                ..completed
+            })
+            .await?;
+        transaction
+            .prover_schema()
+            .cancel_this_exit_proof_task(exit_task(4))
+            .await?;
+
+        let mut prover_schema = transaction.prover_schema();
+        assert_eq!(prover_schema.get_pending_tasks_count().await?, 1);
+        assert_eq!(prover_schema.count_running_tasks().await?, 1);
+        assert_eq!(prover_schema.get_total_completed_proofs_num().await?, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prover_stats() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        let stats = transaction.prover_schema().get_prover_stats().await?;
+        assert_eq!(
+            stats,
+            StoredProverStats {
+                pending: 0,
+                running: 0,
+                completed: 0,
+                avg_duration_ms: None,
+            }
+        );
+        assert!(transaction
+            .prover_schema()
+            .completed_withdraw_amounts()
+            .await?
+            .is_empty());
+
+        // Three tasks: one gets completed, one keeps running and one stays in the queue,
+        // and a task of another token gets completed.
+        for account_id in 1..=3 {
+            let exit_info = StoredExitInfo {
+                account_id,
+                ..exit_info()
+            };
+            transaction
+                .prover_schema()
+                .insert_exit_task(exit_info, None)
+                .await?;
+        }
+        let completed = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?
+            .expect("There must be an unclaimed task");
+        transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                proof: Some(serde_json::json!({ "inputs": ["0x01"] })),
+                amount: Some(BigDecimal::from(100)),
+                ..completed
+            })
+            .await?;
+
+        let other_token = StoredExitInfo {
+            account_id: 4,
+            l2_source_token: 2,
+            ..exit_info()
+        };
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task(other_token.clone(), None)
+            .await?;
+        transaction
+            .prover_schema()
+            .store_exit_proof(StoredExitProof {
+                id,
+                chain_id: other_token.chain_id,
+                account_id: other_token.account_id,
+                sub_account_id: other_token.sub_account_id,
+                l1_target_token: other_token.l1_target_token,
+                l2_source_token: other_token.l2_source_token,
+                proof: Some(serde_json::json!({ "inputs": ["0x01"] })),
+                amount: Some(BigDecimal::from(7)),
+                created_at: None,
+                finished_at: None,
+                cancelled_at: None,
+                request_id: None,
+                started_at: None,
+                proof_size_bytes: None,
+                prove_duration_ms: None,
+                priority: 0,
+            })
+            .await?;
+
+        let stats = transaction.prover_schema().get_prover_stats().await?;
+        assert_eq!(stats.pending, 1);
+        assert_eq!(stats.running, 1);
+        assert_eq!(stats.completed, 2);
+        assert!(stats.avg_duration_ms.unwrap() >= 0);
+        let amounts = transaction
+            .prover_schema()
+            .completed_withdraw_amounts()
+            .await?;
+        assert_eq!(
+            amounts,
+            vec![(1, BigDecimal::from(100)), (2, BigDecimal::from(7))]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn high_priority_task_is_claimed_first() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        let low = transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        let high = transaction
+            .prover_schema()
+            .insert_exit_task_with_priority(
+                StoredExitInfo {
+                    account_id: 11,
+                    ..exit_info()
+                },
+                5,
+                None,
+            )
+            .await?;
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(high, true)
+            .await?;
+        assert_eq!(position, Some(0));
+        let position = transaction
+            .prover_schema()
+            .task_queue_position(low, true)
+            .await?;
+        assert_eq!(position, Some(1));
+
+        // The priority of the existing task is raised, but never lowered.
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task_with_priority(exit_info(), 7, None)
+            .await?;
+        assert_eq!(id, low);
+        transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(), None)
+            .await?;
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        assert_eq!(task.map(|t| (t.id, t.priority)), Some((low, 7)));
+        let task = transaction
+            .prover_schema()
+            .load_exit_proof_task(true)
+            .await?;
+        assert_eq!(task.map(|t| (t.id, t.priority)), Some((high, 5)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn larger_balance_task_is_claimed_first() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        // The third account has no balance of the exiting token, but the highest priority.
+        let mut ids = Vec::new();
+        for (account_id, balance, priority) in [
+            (9_000_000i64, Some(5), 0),
+            (9_000_001, Some(500), 0),
+            (9_000_002, None, 5),
+        ] {
+            insert_test_account(&mut transaction, account_id).await?;
+            if let Some(balance) = balance {
+                sqlx::query!(
+                    "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES ($1, 1, 1, $2)",
+                    account_id,
+                    BigDecimal::from(balance)
+                )
+                .execute(transaction.conn())
+                .await?;
+            }
+            let id = transaction
+                .prover_schema()
+                .insert_exit_task_with_priority(
+                    StoredExitInfo {
+                        account_id,
+                        ..exit_info()
+                    },
+                    priority,
+                    None,
+                )
+                .await?;
+            ids.push(id);
+        }
+
+        for (order_by_balance, expected_ids) in [
+            (true, vec![ids[1], ids[0], ids[2]]),
+            (false, vec![ids[2], ids[0], ids[1]]),
+        ] {
+            sqlx::query!("UPDATE exit_proofs SET created_at = NULL, started_at = NULL")
+                .execute(transaction.conn())
+                .await?;
+            let mut claimed_ids = Vec::new();
+            while let Some(task) = transaction
+                .prover_schema()
+                .load_exit_proof_task(order_by_balance)
+                .await?
+            {
+                claimed_ids.push(task.id);
+            }
+            assert_eq!(claimed_ids, expected_ids);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn funded_task_queue_position() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+
+        // The funded task is submitted after the unfunded one.
+        let (unfunded_account_id, funded_account_id) = (9_000_010i64, 9_000_011i64);
+        insert_test_account(&mut transaction, unfunded_account_id).await?;
+        insert_test_account(&mut transaction, funded_account_id).await?;
+        sqlx::query!(
+            "INSERT INTO balances (account_id, sub_account_id, coin_id, balance) VALUES ($1, 1, 1, 100)",
+            funded_account_id
+        )
+        .execute(transaction.conn())
+        .await?;
+        let mut ids = Vec::new();
+        for account_id in [unfunded_account_id, funded_account_id] {
+            let id = transaction
+                .prover_schema()
+                .insert_exit_task(
+                    StoredExitInfo {
+                        account_id,
+                        ..exit_info()
+                    },
+                    None,
+                )
+                .await?;
+            ids.push(id);
+        }
+
+        // The funded task jumps ahead only if the tasks are ordered by balance.
+        for (order_by_balance, expected_positions) in
+            [(true, [Some(1), Some(0)]), (false, [Some(0), Some(1)])]
+        {
+            let mut positions = [None; 2];
+            for (position, id) in positions.iter_mut().zip(&ids) {
+                *position = transaction
+                    .prover_schema()
+                    .task_queue_position(*id, order_by_balance)
+                    .await?;
+            }
+            assert_eq!(positions, expected_positions);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_workers_claim_distinct_tasks() -> QueryResult<()> {
+        // The tasks are claimed by the separate connections, so they are committed and removed manually.
+        let pool = ConnectionPool::new(TEST_DATABASE_URL.to_string(), 3);
+        let mut storage = pool.access_storage().await?;
+        let ids = storage
+            .prover_schema()
+            .insert_batch_exit_tasks(
+                vec![
+                    StoredExitInfo {
+                        account_id: 1_000_001,
+                        ..exit_info()
+                    },
+                    StoredExitInfo {
+                        account_id: 1_000_002,
+                        ..exit_info()
+                    },
+                ],
+                i16::MAX,
+                None,
+            )
+            .await?;
+
+        // Every worker keeps its claimed task locked until both workers have claimed one.
+        let barrier = Arc::new(Barrier::new(2));
+        let claimed = async {
+            let mut workers = Vec::new();
+            for _ in 0..2 {
+                let pool = pool.clone();
+                let barrier = barrier.clone();
+                workers.push(tokio::spawn(async move {
+                    let mut storage = pool.access_storage().await?;
+                    let mut transaction = storage.start_transaction().await?;
+                    let task = transaction
+                        .prover_schema()
+                        .load_exit_proof_task(true)
+                        .await?;
+                    barrier.wait().await;
+                    transaction.commit().await?;
+                    QueryResult::Ok(task.map(|t| t.id))
+                }));
+            }
+            let mut claimed_ids = Vec::new();
+            for worker in workers {
+                claimed_ids.push(worker.await??);
+            }
+            QueryResult::Ok(claimed_ids)
+        }
+        .await;
+
+        // The inserted tasks are removed even if a worker failed.
+        sqlx::query!("DELETE FROM exit_proofs WHERE id = ANY($1)", &ids)
+            .execute(storage.conn())
+            .await?;
+
+        let mut claimed_ids = claimed?;
+        claimed_ids.sort();
+        assert_eq!(claimed_ids, ids.into_iter().map(Some).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stored_exit_proofs_by_pages() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+        let mut ids = Vec::new();
+        for account_id in 10..15 {
+            let id = transaction
+                .prover_schema()
+                .insert_exit_task(
+                    StoredExitInfo {
+                        account_id,
+                        ..exit_info()
+                    },
+                    None,
+                )
+                .await?;
+            ids.push(id);
+        }
+
+        // The pages of any size cover all the proofs exactly once.
+        let mut paged_ids = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = transaction
+                .prover_schema()
+                .get_stored_exit_proofs_paged(offset, 2)
+                .await?;
+            if page.is_empty() {
+                break;
+            }
+            offset += page.len() as i64;
+            paged_ids.extend(page.into_iter().map(|proof| proof.id));
+        }
+        assert_eq!(paged_ids, ids);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn completed_proofs_after_in_order_of_completion() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM exit_proofs")
+            .execute(transaction.conn())
+            .await?;
+        let infos = (10..14)
+            .map(|account_id| StoredExitInfo {
+                account_id,
+                ..exit_info()
+            })
+            .collect::<Vec<_>>();
+        let mut ids = Vec::new();
+        for info in &infos {
+            ids.push(
+                transaction
+                    .prover_schema()
+                    .insert_exit_task(info.clone(), None)
+                    .await?,
+            );
+        }
+        // The proofs are completed in the reverse order of their ids.
+        for (id, info) in ids.iter().zip(&infos).rev() {
+            transaction
+                .prover_schema()
+                .store_exit_proof(StoredExitProof {
+                    id: *id,
+                    chain_id: info.chain_id,
+                    account_id: info.account_id,
+                    sub_account_id: info.sub_account_id,
+                    l1_target_token: info.l1_target_token,
+                    l2_source_token: info.l2_source_token,
+                    proof: Some(serde_json::json!({ "inputs": ["0x01"] })),
+                    amount: Some(Default::default()),
+                    created_at: None,
+                    finished_at: None,
+                    cancelled_at: None,
+                    request_id: None,
+                    started_at: None,
+                    proof_size_bytes: None,
+                    prove_duration_ms: None,
+                    priority: 0,
+                })
+                .await?;
+        }
+
+        // The proofs completed after the first completed one(the largest id) are replayed page by page,
+        // including the ones of the smaller ids.
+        let mut cursor_id = ids[3];
+        let mut cursor = transaction
+            .prover_schema()
+            .proof_finished_at(cursor_id)
+            .await?
+            .expect("The proof must be finished");
+        let mut replayed_ids = Vec::new();
+        loop {
+            let page = transaction
+                .prover_schema()
+                .get_completed_proofs_after(cursor, cursor_id, 2)
+                .await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = last.finished_at.unwrap();
+            cursor_id = last.id;
+            replayed_ids.extend(page.iter().map(|proof| proof.id));
+        }
+        assert_eq!(replayed_ids, vec![ids[2], ids[1], ids[0]]);
+
+        // The unfinished proof has no cursor.
+        let id = transaction
+            .prover_schema()
+            .insert_exit_task(
+                StoredExitInfo {
+                    account_id: 14,
+                    ..exit_info()
+                },
+                None,
+            )
+            .await?;
+        assert_eq!(
+            transaction.prover_schema().proof_finished_at(id).await?,
+            None
+        );
+        Ok(())
+    }
+}
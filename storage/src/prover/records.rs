@@ -17,6 +17,12 @@ pub struct StoredExitProof {
     pub amount: Option<BigDecimal>,
     pub created_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub request_id: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub proof_size_bytes: Option<i64>,
+    pub prove_duration_ms: Option<i64>,
+    pub priority: i16,
 }
 
 impl Display for StoredExitProof {
@@ -28,6 +34,33 @@ impl Display for StoredExitProof {
     }
 }
 
+/// The size and generating duration of the completed exit proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct StoredProofMetadata {
+    pub id: i64,
+    pub proof_size_bytes: Option<i64>,
+    pub prove_duration_ms: Option<i64>,
+}
+
+/// The aggregate statistics of all the exit proof tasks, fetched in a single query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct StoredProverStats {
+    pub pending: i64,
+    pub running: i64,
+    pub completed: i64,
+    /// The average generating duration of the completed proofs, `None` if there is no metadata yet.
+    pub avg_duration_ms: Option<i64>,
+}
+
+/// The existence and completion of an exit proof task, fetched in a single query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct StoredTaskStatus {
+    pub id: i64,
+    pub completed: bool,
+    /// The task failed and was cancelled by the prover, it's not claimed again until requeued.
+    pub cancelled: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct StoredExitInfo {
     pub chain_id: i16,
@@ -46,6 +79,23 @@ impl Display for StoredExitInfo {
     }
 }
 
+/// The notification published when the proof of exit task is completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedExitProof {
+    pub id: i64,
+    #[serde(flatten)]
+    pub exit_info: StoredExitInfo,
+}
+
+impl From<&StoredExitProof> for CompletedExitProof {
+    fn from(value: &StoredExitProof) -> Self {
+        Self {
+            id: value.id,
+            exit_info: value.into(),
+        }
+    }
+}
+
 impl From<&StoredExitProof> for StoredExitInfo {
     fn from(value: &StoredExitProof) -> Self {
         Self {
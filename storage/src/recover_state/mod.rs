@@ -3,11 +3,13 @@ use std::time::Instant;
 use tracing::info;
 use zklink_types::{AccountId, AccountUpdate, BlockNumber, ChainId, H256};
 // External imports
+use chrono::Utc;
 // Workspace imports
+use recover_state_config::RollupOpsFormat;
 // Local imports
 use self::records::{
-    NewBlockEvent, NewRollupOpsBlock, NewStorageState, StoredBlockEvent, StoredRollupOpsBlock,
-    StoredStorageState,
+    EncodedRollupOps, NewBlockEvent, NewRollupOpsBlock, NewStorageState, RecoveryCheckpoint,
+    StoredBlockEvent, StoredRollupOpsBlock, StoredStorageState, WatchedBlockHash,
 };
 use crate::chain::operations::records::StoredAggregatedOperation;
 use crate::chain::operations::OperationsSchema;
@@ -67,16 +69,18 @@ impl<'a, 'c> RecoverSchema<'a, 'c> {
         Ok(())
     }
 
-    pub async fn load_rollup_ops_blocks(&mut self) -> QueryResult<Vec<StoredRollupOpsBlock>> {
+    /// Loads the stored rollup ops blocks after `after_block`, i.e. the blocks not applied to the tree state yet.
+    pub async fn load_rollup_ops_blocks(
+        &mut self,
+        after_block: i64,
+    ) -> QueryResult<Vec<StoredRollupOpsBlock>> {
         let start = Instant::now();
-        // For each block aggregate its operations from the
-        // `recover_state_rollup_block_ops` table into array and
-        // match it by the block number from `recover_state_rollup_blocks`.
         // The contract version is obtained from block events.
         let stored_blocks = sqlx::query_as!(
             StoredRollupOpsBlock,
-            "SELECT block_num, operation, fee_account, created_at, previous_block_root_hash, contract_version \
-            FROM recover_state_rollup_ops ORDER BY block_num ASC"
+            "SELECT block_num, operation, fee_account, created_at, previous_block_root_hash, contract_version, \
+            operation_bytes, operation_format FROM recover_state_rollup_ops WHERE block_num > $1 ORDER BY block_num ASC",
+            after_block
         )
             .fetch_all(self.0.conn())
             .await?;
@@ -84,6 +88,37 @@ impl<'a, 'c> RecoverSchema<'a, 'c> {
         Ok(stored_blocks)
     }
 
+    /// Query the last block whose rollup ops have been saved, the ops of the later blocks
+    /// are the only ones that need to be fetched from layer1.
+    pub async fn last_rollup_ops_block(&mut self) -> QueryResult<Option<i64>> {
+        let start = Instant::now();
+        let last_block = sqlx::query!("SELECT MAX(block_num) FROM recover_state_rollup_ops")
+            .fetch_one(self.0.conn())
+            .await?
+            .max;
+        metrics::histogram!("sql.recover_state.last_rollup_ops_block", start.elapsed());
+        Ok(last_block)
+    }
+
+    /// Deletes the rollup ops of the blocks up to `last_applied_block`, the applied ones are never loaded again.
+    pub async fn remove_applied_rollup_ops(
+        &mut self,
+        last_applied_block: BlockNumber,
+    ) -> QueryResult<()> {
+        let start = Instant::now();
+        sqlx::query!(
+            "DELETE FROM recover_state_rollup_ops WHERE block_num <= $1",
+            i64::from(*last_applied_block)
+        )
+        .execute(self.0.conn())
+        .await?;
+        metrics::histogram!(
+            "sql.recover_state.remove_applied_rollup_ops",
+            start.elapsed()
+        );
+        Ok(())
+    }
+
     /// update the last seen layer1 block number.
     pub async fn update_last_watched_block_number(
         &mut self,
@@ -163,6 +198,315 @@ impl<'a, 'c> RecoverSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Records the progress of recovering from the chain in a single statement,
+    /// so that the checkpoint is either fully replaced or kept as before.
+    pub async fn save_recovery_checkpoint(
+        &mut self,
+        chain_id: ChainId,
+        last_l1_block: u64,
+        last_l2_block: BlockNumber,
+        ops_hash: H256,
+    ) -> QueryResult<()> {
+        let start = Instant::now();
+
+        sqlx::query!(
+            "INSERT INTO recover_state_checkpoints (chain_id, last_l1_block, last_l2_block, ops_hash, updated_at) \
+            VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (chain_id) DO UPDATE SET last_l1_block = $2, last_l2_block = $3, ops_hash = $4, updated_at = $5",
+            *chain_id as i16,
+            last_l1_block as i64,
+            i64::from(*last_l2_block),
+            ops_hash.as_bytes(),
+            Utc::now()
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        metrics::histogram!(
+            "sql.recover_state.save_recovery_checkpoint",
+            start.elapsed()
+        );
+        Ok(())
+    }
+
+    /// Loads the last recorded progress of recovering from the chain.
+    pub async fn load_recovery_checkpoint(
+        &mut self,
+        chain_id: ChainId,
+    ) -> QueryResult<Option<RecoveryCheckpoint>> {
+        let start = Instant::now();
+
+        let checkpoint = sqlx::query!(
+            "SELECT last_l1_block, last_l2_block, ops_hash FROM recover_state_checkpoints WHERE chain_id = $1",
+            *chain_id as i16
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|record| RecoveryCheckpoint {
+            chain_id,
+            last_l1_block: record.last_l1_block as u64,
+            last_l2_block: BlockNumber(record.last_l2_block as u32),
+            ops_hash: H256::from_slice(&record.ops_hash),
+        });
+
+        metrics::histogram!(
+            "sql.recover_state.load_recovery_checkpoint",
+            start.elapsed()
+        );
+        Ok(checkpoint)
+    }
+
+    /// Records the hash of the watched layer1 block, and removes the records more than
+    /// `retained_blocks` layer1 blocks older than it, which are never re-checked.
+    pub async fn save_watched_block_hash(
+        &mut self,
+        watched: &WatchedBlockHash,
+        retained_blocks: u64,
+    ) -> QueryResult<()> {
+        let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
+
+        sqlx::query!(
+            "INSERT INTO recover_state_watched_block_hashes (chain_id, block_number, block_hash, last_l2_block) \
+            VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (chain_id, block_number) DO UPDATE SET block_hash = $3, last_l2_block = $4",
+            *watched.chain_id as i16,
+            watched.l1_block as i64,
+            watched.block_hash.as_bytes(),
+            i64::from(*watched.last_l2_block)
+        )
+        .execute(transaction.conn())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM recover_state_watched_block_hashes WHERE chain_id = $1 AND block_number < $2",
+            *watched.chain_id as i16,
+            watched.l1_block.saturating_sub(retained_blocks) as i64
+        )
+        .execute(transaction.conn())
+        .await?;
+        transaction.commit().await?;
+
+        metrics::histogram!("sql.recover_state.save_watched_block_hash", start.elapsed());
+        Ok(())
+    }
+
+    /// Loads the recorded hashes of the watched layer1 blocks from `from_l1_block`, in ascending order.
+    pub async fn load_watched_block_hashes(
+        &mut self,
+        chain_id: ChainId,
+        from_l1_block: u64,
+    ) -> QueryResult<Vec<WatchedBlockHash>> {
+        let start = Instant::now();
+
+        let hashes = sqlx::query!(
+            "SELECT block_number, block_hash, last_l2_block FROM recover_state_watched_block_hashes \
+            WHERE chain_id = $1 AND block_number >= $2 ORDER BY block_number ASC",
+            *chain_id as i16,
+            from_l1_block as i64
+        )
+        .fetch_all(self.0.conn())
+        .await?
+        .into_iter()
+        .map(|record| WatchedBlockHash {
+            chain_id,
+            l1_block: record.block_number as u64,
+            block_hash: H256::from_slice(&record.block_hash),
+            last_l2_block: BlockNumber(record.last_l2_block as u32),
+        })
+        .collect();
+
+        metrics::histogram!(
+            "sql.recover_state.load_watched_block_hashes",
+            start.elapsed()
+        );
+        Ok(hashes)
+    }
+
+    /// Reverts the recovered state to the layer1 block just before `fork_l1_block` that was reorged.
+    ///
+    /// The layer2 block recorded with the last watched block hash before the fork is the safe point,
+    /// returns it or `None` if there is no such record, then nothing is reverted.
+    pub async fn revert_to_fork(
+        &mut self,
+        chain_id: ChainId,
+        fork_l1_block: u64,
+    ) -> QueryResult<Option<BlockNumber>> {
+        let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
+
+        // The hashes of the reorged blocks are never valid again.
+        sqlx::query!(
+            "DELETE FROM recover_state_watched_block_hashes WHERE chain_id = $1 AND block_number >= $2",
+            *chain_id as i16,
+            fork_l1_block as i64
+        )
+        .execute(transaction.conn())
+        .await?;
+        let safe_l2_block = sqlx::query!(
+            "SELECT last_l2_block FROM recover_state_watched_block_hashes \
+            WHERE chain_id = $1 ORDER BY block_number DESC LIMIT 1",
+            *chain_id as i16
+        )
+        .fetch_optional(transaction.conn())
+        .await?
+        .map(|record| BlockNumber(record.last_l2_block as u32));
+        if let Some(safe_l2_block) = safe_l2_block {
+            RecoverSchema(&mut transaction)
+                .revert_to_block(safe_l2_block)
+                .await?;
+        }
+        transaction.commit().await?;
+
+        metrics::histogram!("sql.recover_state.revert_to_fork", start.elapsed());
+        Ok(safe_l2_block)
+    }
+
+    /// Reverts the recovered state to `l2_block`: the rollup ops, state updates, blocks and aggregated
+    /// operations above it are deleted, and the applied `accounts`, `balances` and `account_order_nonces`
+    /// are restored to the values before the first deleted update.
+    ///
+    /// The block events cursor of each chain is reset to the last watched layer1 block recorded
+    /// at or before `l2_block`, so that the block events above it are watched again.
+    ///
+    /// Everything is done in a single transaction, so it's safe to retry after a failure.
+    /// Fails if `l2_block` is below the prune point of `StateSchema::prune_updates_before`.
+    pub async fn revert_to_block(&mut self, l2_block: BlockNumber) -> QueryResult<()> {
+        let start = Instant::now();
+        let new_state = self.new_storage_state("None");
+        let mut transaction = self.0.start_transaction().await?;
+        let l2_block = i64::from(*l2_block);
+        StateSchema(&mut transaction)
+            .ensure_updates_retained_after(l2_block)
+            .await?;
+
+        // Restore the applied state before the updates are deleted, the earliest update
+        // above `l2_block` of each balance(nonce, pubkey, order slot) holds the value to restore.
+        sqlx::query!(
+            "UPDATE balances b SET balance = u.old_balance FROM ( \
+                SELECT DISTINCT ON (account_id, sub_account_id, coin_id) account_id, sub_account_id, coin_id, old_balance \
+                FROM account_balance_updates WHERE block_number > $1 \
+                ORDER BY account_id, sub_account_id, coin_id, block_number, update_order_id \
+            ) u WHERE b.account_id = u.account_id AND b.sub_account_id = u.sub_account_id AND b.coin_id = u.coin_id",
+            l2_block
+        )
+        .execute(transaction.conn())
+        .await?;
+        sqlx::query!(
+            "UPDATE accounts a SET nonce = u.old_nonce FROM ( \
+                SELECT DISTINCT ON (account_id) account_id, old_nonce FROM ( \
+                    SELECT account_id, old_nonce, block_number, update_order_id FROM account_balance_updates WHERE block_number > $1 \
+                    UNION ALL \
+                    SELECT account_id, old_nonce, block_number, update_order_id FROM account_pubkey_updates WHERE block_number > $1 \
+                ) updates ORDER BY account_id, block_number, update_order_id \
+            ) u WHERE a.id = u.account_id",
+            l2_block
+        )
+        .execute(transaction.conn())
+        .await?;
+        sqlx::query!(
+            "UPDATE accounts a SET pubkey_hash = u.old_pubkey_hash FROM ( \
+                SELECT DISTINCT ON (account_id) account_id, old_pubkey_hash FROM account_pubkey_updates \
+                WHERE block_number > $1 ORDER BY account_id, block_number, update_order_id \
+            ) u WHERE a.id = u.account_id",
+            l2_block
+        )
+        .execute(transaction.conn())
+        .await?;
+        // The order nonce is stored as a json string of `[order_nonce, residue]`.
+        sqlx::query!(
+            "UPDATE account_order_nonces o \
+            SET order_nonce = ((u.old_order_nonce #>> '{}')::jsonb ->> 0)::bigint, \
+                residue = ((u.old_order_nonce #>> '{}')::jsonb ->> 1)::numeric \
+            FROM ( \
+                SELECT DISTINCT ON (account_id, sub_account_id, slot_id) account_id, sub_account_id, slot_id, old_order_nonce \
+                FROM account_order_updates WHERE block_number > $1 \
+                ORDER BY account_id, sub_account_id, slot_id, block_number, update_order_id \
+            ) u WHERE o.account_id = u.account_id AND o.sub_account_id = u.sub_account_id AND o.slot_id = u.slot_id",
+            l2_block
+        )
+        .execute(transaction.conn())
+        .await?;
+        // The balances and order nonces of the created accounts are deleted by cascade.
+        sqlx::query!(
+            "DELETE FROM accounts WHERE id IN (SELECT account_id FROM account_creates WHERE block_number > $1)",
+            l2_block
+        )
+        .execute(transaction.conn())
+        .await?;
+
+        RecoverSchema(&mut transaction)
+            .delete_blocks_after(l2_block)
+            .await?;
+        RecoverSchema(&mut transaction)
+            .update_storage_state(new_state)
+            .await?;
+
+        transaction.commit().await?;
+        metrics::histogram!("sql.recover_state.revert_to_block", start.elapsed());
+        Ok(())
+    }
+
+    /// Deletes the rollup ops, state updates, blocks and aggregated operations above `l2_block`,
+    /// and resets the block events cursors to the layer1 blocks watched at or before it.
+    async fn delete_blocks_after(&mut self, l2_block: i64) -> QueryResult<()> {
+        for table in [
+            "account_creates",
+            "account_balance_updates",
+            "account_order_updates",
+            "account_pubkey_updates",
+        ] {
+            sqlx::query(&format!("DELETE FROM {} WHERE block_number > $1", table))
+                .bind(l2_block)
+                .execute(self.0.conn())
+                .await?;
+        }
+        sqlx::query!("DELETE FROM blocks WHERE number > $1", l2_block)
+            .execute(self.0.conn())
+            .await?;
+        sqlx::query!(
+            "DELETE FROM aggregate_operations WHERE to_block > $1",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM recover_state_rollup_ops WHERE block_num > $1",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM recover_state_events_state WHERE end_block_num > $1",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        // Reset the block events cursors and drop the hashes recorded after them.
+        sqlx::query!(
+            "UPDATE recover_state_last_watched_block w SET block_number = h.block_number FROM ( \
+                SELECT chain_id, max(block_number) AS block_number FROM recover_state_watched_block_hashes \
+                WHERE last_l2_block <= $1 GROUP BY chain_id \
+            ) h WHERE w.chain_id = h.chain_id AND w.event_type = 'block'",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM recover_state_watched_block_hashes WHERE last_l2_block > $1",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM recover_state_checkpoints WHERE last_l2_block > $1",
+            l2_block
+        )
+        .execute(self.0.conn())
+        .await?;
+        Ok(())
+    }
+
     pub fn new_storage_state(&self, state: impl ToString) -> NewStorageState {
         info!("Enter {:?} storage state", state.to_string());
         NewStorageState {
@@ -229,29 +573,32 @@ impl<'a, 'c> RecoverSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Saves the rollup ops blocks, the ops are stored in `format`.
+    /// The blocks saved before are kept until they are applied, so that a crash never loses the fetched ops,
+    /// and the ones saved again(e.g. refetched after a restart) are overwritten.
     pub async fn save_rollup_ops(
         &mut self,
         rollup_blocks: &[NewRollupOpsBlock<'_>],
+        format: RollupOpsFormat,
     ) -> QueryResult<()> {
         let start = Instant::now();
         let new_state = self.new_storage_state("Operations");
         let mut transaction = self.0.start_transaction().await?;
-        // Clean up the blocks table after applying last batch blocks.
-        sqlx::query!("DELETE FROM recover_state_rollup_ops")
-            .execute(transaction.conn())
-            .await?;
-
         for block in rollup_blocks {
-            let operations = serde_json::to_value(block.ops).unwrap();
+            let encoded = EncodedRollupOps::encode(block.ops, format)?;
             sqlx::query!(
-                "INSERT INTO recover_state_rollup_ops (block_num, operation, fee_account, created_at, previous_block_root_hash, contract_version)
-                VALUES ($1, $2, $3, $4, $5, $6)",
+                "INSERT INTO recover_state_rollup_ops (block_num, operation, fee_account, created_at, previous_block_root_hash, contract_version, operation_bytes, operation_format)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (block_num) DO UPDATE SET operation = $2, fee_account = $3, created_at = $4,
+                previous_block_root_hash = $5, contract_version = $6, operation_bytes = $7, operation_format = $8",
                 i64::from(*block.block_num),
-                operations,
+                encoded.operation,
                 i64::from(*block.fee_account),
                 block.timestamp,
                 block.previous_block_root_hash.as_bytes(),
-                block.contract_version
+                block.contract_version,
+                encoded.operation_bytes,
+                encoded.operation_format
             )
             .execute(transaction.conn())
             .await?;
@@ -404,4 +751,454 @@ impl<'a, 'c> RecoverSchema<'a, 'c> {
         metrics::histogram!("sql.recover_state.insert_user", start.elapsed());
         Ok(rows_affected == 0)
     }
+
+    /// Locks the pending exit proof tasks of the account until the end of the transaction, so that
+    /// the concurrent requests of the same address count and insert their tasks one after another.
+    pub async fn lock_pending_tasks(&mut self, account_id: i64) -> QueryResult<()> {
+        let start = Instant::now();
+        // The macro can't describe the `void` result of the function, so the query is unchecked.
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(account_id)
+            .execute(self.0.conn())
+            .await?;
+
+        metrics::histogram!("sql.recover_state.lock_pending_tasks", start.elapsed());
+        Ok(())
+    }
+
+    /// Counts the exit proof tasks of the account owning the address that are not claimed by any prover yet.
+    pub async fn count_pending_tasks_by_address(&mut self, address: &[u8]) -> QueryResult<i64> {
+        let start = Instant::now();
+        let tasks_count = sqlx::query!(
+            r#"SELECT count(e.id) AS "count!" FROM exit_proofs e
+            INNER JOIN accounts a ON a.id = e.account_id
+            WHERE a.address = $1
+            AND e.created_at IS NULL AND e.finished_at IS NULL AND e.cancelled_at IS NULL"#,
+            address
+        )
+        .fetch_one(self.0.conn())
+        .await?
+        .count;
+
+        metrics::histogram!(
+            "sql.recover_state.count_pending_tasks_by_address",
+            start.elapsed()
+        );
+        Ok(tasks_count)
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use num::BigUint;
+    use sqlx::types::BigDecimal;
+    use zklink_types::{
+        AccountId, AccountUpdate, BlockNumber, ChainId, Nonce, SubAccountId, TokenId,
+        ZkLinkAddress, H256,
+    };
+
+    use recover_state_config::RollupOpsFormat;
+
+    use super::records::{NewRollupOpsBlock, RecoveryCheckpoint, WatchedBlockHash};
+    use crate::chain::operations::records::{AggType, StoredAggregatedOperation};
+    use crate::prover::records::StoredExitInfo;
+    use crate::{insert_test_account, QueryResult, StorageProcessor};
+
+    const FIRST_BLOCK: u32 = 1_000_000;
+    const ACCOUNT_ID: AccountId = AccountId(1_000_000);
+
+    /// Creates the account in the first block, then deposits in each of the later blocks.
+    fn blocks_updates() -> Vec<(BlockNumber, Vec<(AccountId, AccountUpdate, H256)>)> {
+        let create = AccountUpdate::Create {
+            address: ZkLinkAddress::from(vec![0xab; 20]),
+            nonce: Nonce(0),
+        };
+        let deposit = |block_index: u32| AccountUpdate::UpdateBalance {
+            old_nonce: Nonce(0),
+            new_nonce: Nonce(0),
+            balance_update: (
+                TokenId(18),
+                SubAccountId(0),
+                BigUint::from(100 * (block_index - 1)),
+                BigUint::from(100 * block_index),
+            ),
+        };
+        let mut blocks_updates = vec![(
+            BlockNumber(FIRST_BLOCK),
+            vec![(ACCOUNT_ID, create, H256::zero())],
+        )];
+        blocks_updates.extend((1..8).map(|block_index| {
+            (
+                BlockNumber(FIRST_BLOCK + block_index),
+                vec![(ACCOUNT_ID, deposit(block_index), H256::zero())],
+            )
+        }));
+        blocks_updates
+    }
+
+    async fn account_state(
+        storage: &mut StorageProcessor<'_>,
+    ) -> QueryResult<(i64, i64, Vec<(i32, i32, BigDecimal)>)> {
+        let account = storage
+            .chain()
+            .account_schema()
+            .account_by_id(i64::from(*ACCOUNT_ID))
+            .await?
+            .expect("Account should be created");
+        let mut balances: Vec<_> = storage
+            .chain()
+            .account_schema()
+            .account_balances(i64::from(*ACCOUNT_ID), None)
+            .await?
+            .into_iter()
+            .map(|balance| (balance.sub_account_id, balance.coin_id, balance.balance))
+            .collect();
+        balances.sort();
+        Ok((account.nonce, account.last_block, balances))
+    }
+
+    /// Commits the state updates and the confirmed operations of `blocks_updates`,
+    /// returning the account state after each block.
+    async fn seed_blocks(
+        storage: &mut StorageProcessor<'_>,
+    ) -> QueryResult<Vec<(i64, i64, Vec<(i32, i32, BigDecimal)>)>> {
+        let mut states = Vec::new();
+        for (block_number, accounts_updated) in blocks_updates() {
+            let aggregated_operation = |action_type| StoredAggregatedOperation {
+                id: 0,
+                action_type,
+                from_block: i64::from(*block_number),
+                to_block: i64::from(*block_number),
+                created_at: chrono::Utc::now(),
+                confirmed: true,
+            };
+            storage
+                .chain()
+                .state_schema()
+                .commit_state_update(block_number, &accounts_updated)
+                .await?;
+            storage
+                .recover_schema()
+                .save_block_operations(
+                    &aggregated_operation(AggType::CommitBlocks),
+                    &aggregated_operation(AggType::ExecuteBlocks),
+                )
+                .await?;
+            states.push(account_state(storage).await?);
+        }
+        Ok(states)
+    }
+
+    #[tokio::test]
+    async fn rollup_ops_resumed_after_crash() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM recover_state_rollup_ops")
+            .execute(transaction.conn())
+            .await?;
+        let ops_block = |block_num: u32, fee_account: u32| NewRollupOpsBlock {
+            block_num: BlockNumber(block_num),
+            ops: &[],
+            fee_account: AccountId(fee_account),
+            timestamp: None,
+            previous_block_root_hash: H256::zero(),
+            contract_version: 1,
+        };
+        assert_eq!(
+            transaction.recover_schema().last_rollup_ops_block().await?,
+            None
+        );
+
+        // Block 1 has been applied when the driver crashes, block 2 is saved but not applied.
+        transaction
+            .recover_schema()
+            .save_rollup_ops(&[ops_block(1, 1), ops_block(2, 1)], RollupOpsFormat::Json)
+            .await?;
+        assert_eq!(
+            transaction.recover_schema().last_rollup_ops_block().await?,
+            Some(2)
+        );
+        let unapplied = transaction
+            .recover_schema()
+            .load_rollup_ops_blocks(1)
+            .await?;
+        assert_eq!(unapplied.len(), 1);
+        assert_eq!(unapplied[0].block_num, 2);
+
+        // After the restart only the later blocks are fetched, and the saved blocks are kept.
+        transaction
+            .recover_schema()
+            .save_rollup_ops(&[ops_block(3, 1)], RollupOpsFormat::Json)
+            .await?;
+        let blocks = transaction
+            .recover_schema()
+            .load_rollup_ops_blocks(0)
+            .await?;
+        let block_nums: Vec<_> = blocks.iter().map(|block| block.block_num).collect();
+        assert_eq!(block_nums, vec![1, 2, 3]);
+
+        // The block saved again is overwritten instead of conflicting.
+        transaction
+            .recover_schema()
+            .save_rollup_ops(&[ops_block(3, 2)], RollupOpsFormat::Bincode)
+            .await?;
+        let blocks = transaction
+            .recover_schema()
+            .load_rollup_ops_blocks(2)
+            .await?;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].fee_account, 2);
+        assert_eq!(
+            blocks[0].operation_format,
+            RollupOpsFormat::Bincode.as_str()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_and_load_recovery_checkpoint() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let chain_id = ChainId(200);
+
+        let checkpoint = transaction
+            .recover_schema()
+            .load_recovery_checkpoint(chain_id)
+            .await?;
+        assert_eq!(checkpoint, None);
+
+        for (last_l1_block, last_l2_block) in [(100, 10), (200, 25)] {
+            transaction
+                .recover_schema()
+                .save_recovery_checkpoint(
+                    chain_id,
+                    last_l1_block,
+                    BlockNumber(last_l2_block),
+                    H256::repeat_byte(last_l2_block as u8),
+                )
+                .await?;
+        }
+        // The later checkpoint replaces the former one.
+        let checkpoint = transaction
+            .recover_schema()
+            .load_recovery_checkpoint(chain_id)
+            .await?;
+        assert_eq!(
+            checkpoint,
+            Some(RecoveryCheckpoint {
+                chain_id,
+                last_l1_block: 200,
+                last_l2_block: BlockNumber(25),
+                ops_hash: H256::repeat_byte(25),
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revert_to_fork_restores_state() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let chain_id = ChainId(201);
+        transaction
+            .recover_schema()
+            .insert_last_watched_block_number(*chain_id as i16, "block", 0, 0)
+            .await?;
+
+        let states = seed_blocks(&mut transaction).await?;
+        for (index, (block_number, _)) in blocks_updates().iter().enumerate() {
+            // Each layer1 block(100, 110, ...) includes the events of one layer2 block.
+            let watched = WatchedBlockHash {
+                chain_id,
+                l1_block: 100 + 10 * index as u64,
+                block_hash: H256::repeat_byte(index as u8),
+                last_l2_block: *block_number,
+            };
+            transaction
+                .recover_schema()
+                .save_watched_block_hash(&watched, 1_000)
+                .await?;
+        }
+
+        // The layer1 block 135 is reorged, the last watched block before it is 130.
+        let safe_l2_block = transaction
+            .recover_schema()
+            .revert_to_fork(chain_id, 135)
+            .await?;
+        assert_eq!(safe_l2_block, Some(BlockNumber(FIRST_BLOCK + 3)));
+        assert_eq!(account_state(&mut transaction).await?, states[3]);
+        assert_eq!(
+            transaction
+                .recover_schema()
+                .last_watched_block_number(*chain_id as i16, "block")
+                .await?,
+            Some((130, 0))
+        );
+        let hashes = transaction
+            .recover_schema()
+            .load_watched_block_hashes(chain_id, 0)
+            .await?;
+        assert_eq!(hashes.last().map(|hash| hash.l1_block), Some(130));
+
+        // The state updates above the safe block are deleted, so the created account is removed
+        // after reverting to the block before it.
+        transaction
+            .recover_schema()
+            .revert_to_block(BlockNumber(FIRST_BLOCK - 1))
+            .await?;
+        assert!(transaction
+            .chain()
+            .account_schema()
+            .account_by_id(i64::from(*ACCOUNT_ID))
+            .await?
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pruned_updates_refuse_older_reverts() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let states = seed_blocks(&mut transaction).await?;
+
+        // The deposits of the blocks [FIRST_BLOCK + 1, FIRST_BLOCK + 3] are pruned.
+        let prune_before = i64::from(FIRST_BLOCK + 4);
+        let pruned = transaction
+            .chain()
+            .state_schema()
+            .prune_updates_before(prune_before)
+            .await?;
+        assert!(pruned >= 3);
+        let retained = sqlx::query!(
+            "SELECT count(*) FILTER (WHERE block_number < $2) AS \"pruned!\", count(*) AS \"total!\" \
+            FROM account_balance_updates WHERE account_id = $1",
+            i64::from(*ACCOUNT_ID),
+            prune_before
+        )
+        .fetch_one(transaction.conn())
+        .await?;
+        assert_eq!((retained.pruned, retained.total), (0, 4));
+        assert_eq!(
+            transaction.chain().state_schema().pruned_before().await?,
+            prune_before
+        );
+
+        // The snapshots and reverts below the prune point fail instead of rewinding the partial updates.
+        let account_id = i64::from(*ACCOUNT_ID);
+        assert!(transaction
+            .chain()
+            .account_schema()
+            .account_snapshot(account_id, None, Some(prune_before - 2))
+            .await
+            .is_err());
+        assert!(transaction
+            .recover_schema()
+            .revert_to_block(BlockNumber(FIRST_BLOCK + 2))
+            .await
+            .is_err());
+        let snapshot = transaction
+            .chain()
+            .account_schema()
+            .account_snapshot(account_id, None, Some(prune_before - 1))
+            .await?;
+        assert_eq!(snapshot.balances[0].balance, BigDecimal::from(300));
+        transaction
+            .recover_schema()
+            .revert_to_block(BlockNumber(FIRST_BLOCK + 3))
+            .await?;
+        assert_eq!(account_state(&mut transaction).await?, states[3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revert_to_block_is_idempotent() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let states = seed_blocks(&mut transaction).await?;
+        transaction
+            .chain()
+            .account_schema()
+            .add_white_submitter(0, i64::from(*ACCOUNT_ID))
+            .await?;
+
+        // Reverting again is a no-op.
+        for _ in 0..2 {
+            transaction
+                .recover_schema()
+                .revert_to_block(BlockNumber(FIRST_BLOCK + 2))
+                .await?;
+            assert_eq!(account_state(&mut transaction).await?, states[2]);
+        }
+        let updates = sqlx::query!(
+            "SELECT count(*) AS \"count!\" FROM account_balance_updates WHERE block_number > $1",
+            i64::from(FIRST_BLOCK + 2)
+        )
+        .fetch_one(transaction.conn())
+        .await?;
+        assert_eq!(updates.count, 0);
+        // The whitelist of the retained account survives the revert.
+        let whitelist = sqlx::query!(
+            "SELECT count(*) AS \"count!\" FROM tx_submitter_whitelist WHERE submitter_account_id = $1",
+            i64::from(*ACCOUNT_ID)
+        )
+        .fetch_one(transaction.conn())
+        .await?;
+        assert_eq!(whitelist.count, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn count_pending_tasks_by_address() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let (account_id, other_account_id) = (4_000_000i64, 4_000_001i64);
+        insert_test_account(&mut transaction, account_id).await?;
+        insert_test_account(&mut transaction, other_account_id).await?;
+        let address = account_id.to_be_bytes();
+        let exit_info = |account_id, l2_source_token| StoredExitInfo {
+            chain_id: 1,
+            account_id,
+            sub_account_id: 0,
+            l1_target_token: l2_source_token,
+            l2_source_token,
+        };
+        for token in 18..21 {
+            transaction
+                .prover_schema()
+                .insert_exit_task(exit_info(account_id, token), None)
+                .await?;
+        }
+        transaction
+            .prover_schema()
+            .insert_exit_task(exit_info(other_account_id, 18), None)
+            .await?;
+        assert_eq!(
+            transaction
+                .recover_schema()
+                .count_pending_tasks_by_address(&address)
+                .await?,
+            3
+        );
+
+        // The cancelled tasks are not pending.
+        transaction
+            .prover_schema()
+            .cancel_this_exit_proof_task(exit_info(account_id, 18))
+            .await?;
+        assert_eq!(
+            transaction
+                .recover_schema()
+                .count_pending_tasks_by_address(&address)
+                .await?,
+            2
+        );
+        assert_eq!(
+            transaction
+                .recover_schema()
+                .count_pending_tasks_by_address(&[0xe5; 20])
+                .await?,
+            0
+        );
+        Ok(())
+    }
 }
@@ -1,10 +1,13 @@
 use chrono::{DateTime, Utc};
+use std::str::FromStr;
 // External imports
+use anyhow::format_err;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::FromRow;
 // Workspace imports
-use zklink_types::{AccountId, BlockNumber, ZkLinkOp, H256};
+use recover_state_config::RollupOpsFormat;
+use zklink_types::{AccountId, BlockNumber, ChainId, ZkLinkOp, H256};
 // Workspace imports
 // Local imports
 
@@ -18,14 +21,70 @@ pub struct NewRollupOpsBlock<'a> {
     pub contract_version: i16,
 }
 
+/// The ops of a rollup block encoded in the stored representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedRollupOps {
+    pub operation: Option<Value>,
+    pub operation_bytes: Option<Vec<u8>>,
+    pub operation_format: &'static str,
+}
+
+impl EncodedRollupOps {
+    /// Encodes the ops as JSON or as bincode of their public data, the ops restored
+    /// from the public data of the blocks are decoded back from it without any loss.
+    pub fn encode(ops: &[ZkLinkOp], format: RollupOpsFormat) -> anyhow::Result<Self> {
+        let (operation, operation_bytes) = match format {
+            RollupOpsFormat::Json => (Some(serde_json::to_value(ops)?), None),
+            RollupOpsFormat::Bincode => {
+                let public_data: Vec<_> = ops.iter().map(ZkLinkOp::public_data).collect();
+                (None, Some(bincode::serialize(&public_data)?))
+            }
+        };
+        Ok(Self {
+            operation,
+            operation_bytes,
+            operation_format: format.as_str(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub struct StoredRollupOpsBlock {
     pub block_num: i64,
-    pub operation: Value,
+    pub operation: Option<Value>,
     pub fee_account: i64,
     pub created_at: Option<DateTime<Utc>>,
     pub previous_block_root_hash: Vec<u8>,
     pub contract_version: i16,
+    pub operation_bytes: Option<Vec<u8>>,
+    pub operation_format: String,
+}
+
+impl StoredRollupOpsBlock {
+    /// Decodes the ops according to the format they were stored in.
+    pub fn ops(&self) -> anyhow::Result<Vec<ZkLinkOp>> {
+        let missing = || {
+            format_err!(
+                "The {} ops of block {} are missing",
+                self.operation_format,
+                self.block_num
+            )
+        };
+        match RollupOpsFormat::from_str(&self.operation_format).map_err(|e| format_err!(e))? {
+            RollupOpsFormat::Json => {
+                let operation = self.operation.clone().ok_or_else(missing)?;
+                Ok(serde_json::from_value(operation)?)
+            }
+            RollupOpsFormat::Bincode => {
+                let operation_bytes = self.operation_bytes.as_ref().ok_or_else(missing)?;
+                let public_data: Vec<Vec<u8>> = bincode::deserialize(operation_bytes)?;
+                public_data
+                    .iter()
+                    .map(|data| ZkLinkOp::from_public_data(data))
+                    .collect()
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -57,3 +116,125 @@ pub struct StoredBlockEvent {
     pub end_block_num: i64,
     pub contract_version: i16,
 }
+
+/// The progress of recovering from the chain, saved after each applied batch of rollup ops blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryCheckpoint {
+    pub chain_id: ChainId,
+    /// The last layer1 block whose block events have been watched.
+    pub last_l1_block: u64,
+    /// The last applied layer2 block.
+    pub last_l2_block: BlockNumber,
+    /// The sync hash of all the applied ops up to `last_l2_block`.
+    pub ops_hash: H256,
+}
+
+/// The hash of a watched layer1 block, re-fetched later to detect the reorgs of the layer1 chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedBlockHash {
+    pub chain_id: ChainId,
+    pub l1_block: u64,
+    pub block_hash: H256,
+    /// The last layer2 block applied from the block events up to `l1_block`.
+    pub last_l2_block: BlockNumber,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use recover_state_config::RollupOpsFormat;
+    use zklink_types::{AccountId, Deposit, DepositOp, ZkLinkAddress, ZkLinkOp};
+
+    use super::{EncodedRollupOps, StoredRollupOpsBlock};
+
+    /// The deposits restored from the public data, as the ops saved by the restore driver.
+    fn restored_deposits(count: u32) -> Vec<ZkLinkOp> {
+        (0..count)
+            .map(|index| {
+                let deposit = ZkLinkOp::Deposit(Box::new(DepositOp {
+                    tx: Deposit {
+                        from_chain_id: Default::default(),
+                        from: ZkLinkAddress::from(vec![0xab; 20]),
+                        sub_account_id: Default::default(),
+                        l1_source_token: Default::default(),
+                        amount: (index + 1).into(),
+                        to: ZkLinkAddress::from(vec![0xcd; 20]),
+                        serial_id: index as u64,
+                        l2_target_token: Default::default(),
+                        eth_hash: Default::default(),
+                    },
+                    account_id: AccountId(index),
+                    l1_source_token_after_mapping: Default::default(),
+                }));
+                ZkLinkOp::from_public_data(&deposit.public_data()).unwrap()
+            })
+            .collect()
+    }
+
+    fn stored_block(encoded: EncodedRollupOps) -> StoredRollupOpsBlock {
+        StoredRollupOpsBlock {
+            block_num: 1,
+            operation: encoded.operation,
+            fee_account: 0,
+            created_at: None,
+            previous_block_root_hash: vec![0; 32],
+            contract_version: 0,
+            operation_bytes: encoded.operation_bytes,
+            operation_format: encoded.operation_format.to_string(),
+        }
+    }
+
+    #[test]
+    fn rollup_ops_formats_roundtrip() {
+        let ops = restored_deposits(20);
+        let expected = serde_json::to_value(&ops).unwrap();
+
+        for format in [RollupOpsFormat::Json, RollupOpsFormat::Bincode] {
+            let encoded = EncodedRollupOps::encode(&ops, format).unwrap();
+            let decoded = stored_block(encoded).ops().unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), expected);
+        }
+    }
+
+    /// Prints the stored size and the encode/decode time of both formats for a block of 2000 ops,
+    /// run with `cargo test -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn rollup_ops_formats_size_and_speed() {
+        let ops = restored_deposits(2000);
+
+        for format in [RollupOpsFormat::Json, RollupOpsFormat::Bincode] {
+            let start = Instant::now();
+            let encoded = EncodedRollupOps::encode(&ops, format).unwrap();
+            let encode_time = start.elapsed();
+            let size = match &encoded.operation {
+                Some(operation) => serde_json::to_vec(operation).unwrap().len(),
+                None => encoded.operation_bytes.as_ref().unwrap().len(),
+            };
+            let block = stored_block(encoded);
+            let start = Instant::now();
+            let decoded = block.ops().unwrap();
+            let decode_time = start.elapsed();
+            assert_eq!(decoded.len(), ops.len());
+            println!(
+                "{}: {} bytes, encoded in {:?}, decoded in {:?}",
+                format.as_str(),
+                size,
+                encode_time,
+                decode_time
+            );
+        }
+    }
+
+    #[test]
+    fn missing_rollup_ops() {
+        let mut block = stored_block(
+            EncodedRollupOps::encode(&restored_deposits(1), RollupOpsFormat::Bincode).unwrap(),
+        );
+        block.operation_format = "json".to_string();
+        assert!(block.ops().is_err());
+        block.operation_format = "cbor".to_string();
+        assert!(block.ops().is_err());
+    }
+}
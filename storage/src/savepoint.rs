@@ -0,0 +1,158 @@
+// Built-in deps
+use std::ops::{Deref, DerefMut};
+// External imports
+use tracing::warn;
+// Workspace imports
+// Local imports
+use crate::{QueryResult, StorageProcessor};
+
+/// The named savepoint of the transaction created by `StorageProcessor::savepoint`,
+/// the schemas can be accessed through it like the `StorageProcessor` of the transaction.
+///
+/// The savepoint must be finished by `release` or `rollback`. Since it can't be done
+/// asynchronously on drop, the dropped unfinished savepoint marks the transaction
+/// so that its `commit` fails instead of committing the partial changes.
+#[derive(Debug)]
+pub struct SavepointGuard<'a, 'c> {
+    processor: &'a mut StorageProcessor<'c>,
+    name: String,
+    finished: bool,
+}
+
+impl<'a, 'c> SavepointGuard<'a, 'c> {
+    pub(crate) async fn new(
+        processor: &'a mut StorageProcessor<'c>,
+        name: &str,
+    ) -> QueryResult<SavepointGuard<'a, 'c>> {
+        anyhow::ensure!(
+            processor.in_transaction(),
+            "Savepoint {} can only be created in the transaction",
+            name
+        );
+        anyhow::ensure!(
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "Invalid savepoint name: {}",
+            name
+        );
+        sqlx::query(&format!("SAVEPOINT {}", name))
+            .execute(processor.conn())
+            .await?;
+
+        Ok(Self {
+            processor,
+            name: name.to_string(),
+            finished: false,
+        })
+    }
+
+    /// Keeps the changes since the savepoint as part of the transaction.
+    pub async fn release(mut self) -> QueryResult<()> {
+        sqlx::query(&format!("RELEASE SAVEPOINT {}", self.name))
+            .execute(self.processor.conn())
+            .await?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Discards the changes since the savepoint, the transaction can go on.
+    pub async fn rollback(mut self) -> QueryResult<()> {
+        sqlx::query(&format!("ROLLBACK TO SAVEPOINT {}", self.name))
+            .execute(self.processor.conn())
+            .await?;
+        sqlx::query(&format!("RELEASE SAVEPOINT {}", self.name))
+            .execute(self.processor.conn())
+            .await?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<'a, 'c> Deref for SavepointGuard<'a, 'c> {
+    type Target = StorageProcessor<'c>;
+
+    fn deref(&self) -> &Self::Target {
+        self.processor
+    }
+}
+
+impl<'a, 'c> DerefMut for SavepointGuard<'a, 'c> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.processor
+    }
+}
+
+impl<'a, 'c> Drop for SavepointGuard<'a, 'c> {
+    fn drop(&mut self) {
+        if !self.finished {
+            warn!(
+                "Savepoint {} is dropped without release or rollback",
+                self.name
+            );
+            self.processor.unfinished_savepoint = Some(self.name.clone());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use crate::{QueryResult, StorageProcessor};
+
+    async fn count_rows(storage: &mut StorageProcessor<'_>) -> QueryResult<i64> {
+        let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM savepoint_test")
+            .fetch_one(storage.conn())
+            .await?;
+        Ok(count)
+    }
+
+    async fn insert_row(storage: &mut StorageProcessor<'_>) -> QueryResult<()> {
+        sqlx::query("INSERT INTO savepoint_test VALUES (1)")
+            .execute(storage.conn())
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn savepoint_release_and_rollback() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        assert!(storage.savepoint("outside_transaction").await.is_err());
+
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query("CREATE TEMP TABLE savepoint_test (id INT) ON COMMIT DROP")
+            .execute(transaction.conn())
+            .await?;
+        assert!(transaction.savepoint("invalid name").await.is_err());
+
+        // The released changes are kept.
+        let mut savepoint = transaction.savepoint("released").await?;
+        insert_row(&mut savepoint).await?;
+        savepoint.release().await?;
+        assert_eq!(count_rows(&mut transaction).await?, 1);
+
+        // The rolled back changes are discarded, and the transaction goes on.
+        let mut savepoint = transaction.savepoint("rolled_back").await?;
+        insert_row(&mut savepoint).await?;
+        insert_row(&mut savepoint).await?;
+        savepoint.rollback().await?;
+        assert_eq!(count_rows(&mut transaction).await?, 1);
+        insert_row(&mut transaction).await?;
+        assert_eq!(count_rows(&mut transaction).await?, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unfinished_savepoint_fails_commit() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query("CREATE TEMP TABLE savepoint_test (id INT) ON COMMIT DROP")
+            .execute(transaction.conn())
+            .await?;
+
+        let mut savepoint = transaction.savepoint("unfinished").await?;
+        insert_row(&mut savepoint).await?;
+        drop(savepoint);
+        assert!(transaction.commit().await.is_err());
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 // Built-in deps
+use std::io::{Read, Write};
 use std::time::Instant;
-use zklink_types::{Token, TokenId};
+// External imports
+use chrono::{DateTime, Utc};
+use sqlx::types::BigDecimal;
+use zklink_types::{ChainId, Token, TokenId};
 // Workspace imports
 use self::records::{DbToken, DbTokenOfChain};
 use crate::{QueryResult, StorageProcessor};
@@ -15,9 +19,10 @@ mod utils;
 pub struct TokensSchema<'a, 'c>(pub &'a mut StorageProcessor<'c>);
 
 impl<'a, 'c> TokensSchema<'a, 'c> {
-    /// Persists the token_price in the database.
+    /// Persists the token_price in the database, the price is also appended to the price history.
     pub async fn store_token_price(&mut self, token: DbToken) -> QueryResult<()> {
         let start = Instant::now();
+        let mut transaction = self.0.start_transaction().await?;
         sqlx::query!(
             r#"
             INSERT INTO token_price ( token_id, symbol, price_id, usd_price, last_update_time )
@@ -30,13 +35,66 @@ impl<'a, 'c> TokensSchema<'a, 'c> {
             token.usd_price,
             token.last_update_time
         )
-        .execute(self.0.conn())
+        .execute(transaction.conn())
         .await?;
+        TokensSchema(&mut transaction)
+            .append_token_price_history(token.token_id, token.usd_price)
+            .await?;
+        transaction.commit().await?;
 
         metrics::histogram!("sql.token.store_token", start.elapsed());
         Ok(())
     }
 
+    /// Appends the price of the token to `token_price_history` without modifying `token_price`.
+    pub async fn append_token_price_history(
+        &mut self,
+        token_id: i32,
+        usd_price: BigDecimal,
+    ) -> QueryResult<()> {
+        let start = Instant::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO token_price_history ( token_id, usd_price, recorded_at )
+            VALUES ( $1, $2, $3 )
+            "#,
+            token_id,
+            usd_price,
+            Utc::now()
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.token.append_token_price_history", start.elapsed());
+        Ok(())
+    }
+
+    /// Returns the latest price of the token recorded at or before `timestamp`,
+    /// `None` if no price was recorded by then.
+    pub async fn get_token_price_at(
+        &mut self,
+        token_id: i32,
+        timestamp: DateTime<Utc>,
+    ) -> QueryResult<Option<BigDecimal>> {
+        let start = Instant::now();
+        let usd_price = sqlx::query!(
+            r#"
+            SELECT usd_price FROM token_price_history
+            WHERE token_id = $1 AND recorded_at <= $2
+            ORDER BY recorded_at DESC, id DESC
+            LIMIT 1
+            "#,
+            token_id,
+            timestamp
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|record| record.usd_price);
+
+        metrics::histogram!("sql.token.get_token_price_at", start.elapsed());
+        Ok(usd_price)
+    }
+
     /// load token from token_price table
     pub async fn load_tokens_price(&mut self) -> QueryResult<Vec<DbToken>> {
         let start = Instant::now();
@@ -54,20 +112,33 @@ impl<'a, 'c> TokensSchema<'a, 'c> {
         Ok(tokens)
     }
 
-    /// Update token `usd_price` and `last_update_time` to token_price table
+    /// Update token `usd_price` and `last_update_time` to token_price table,
+    /// the price is also appended to the price history if it has changed.
     pub async fn update_token_price(&mut self, token: DbToken) -> QueryResult<()> {
         let start = Instant::now();
-        sqlx::query!(
+        let mut transaction = self.0.start_transaction().await?;
+        // The joined row is the snapshot before the update, so the old price is returned.
+        let old_price = sqlx::query!(
             r#"
             UPDATE token_price SET usd_price = $1, last_update_time = $2
-            where token_id = $3
+            FROM token_price AS old
+            WHERE token_price.token_id = $3 AND old.token_id = token_price.token_id
+            RETURNING old.usd_price AS old_price
             "#,
             token.usd_price,
             token.last_update_time,
             token.token_id
         )
-        .execute(self.0.conn())
-        .await?;
+        .fetch_optional(transaction.conn())
+        .await?
+        .map(|record| record.old_price);
+        // Only the changes of the stored prices are recorded in the history.
+        if matches!(old_price, Some(old_price) if old_price != token.usd_price) {
+            TokensSchema(&mut transaction)
+                .append_token_price_history(token.token_id, token.usd_price)
+                .await?;
+        }
+        transaction.commit().await?;
 
         metrics::histogram!("sql.token.store_token", start.elapsed());
         Ok(())
@@ -121,6 +192,36 @@ impl<'a, 'c> TokensSchema<'a, 'c> {
         Ok(token_by_id)
     }
 
+    /// Finds the tokens whose decimals differ across chains, along with the decimals of each chain.
+    /// The circuit assumes a token has the same decimals on all chains.
+    pub async fn find_decimal_mismatches(
+        &mut self,
+    ) -> QueryResult<Vec<(TokenId, Vec<(ChainId, i16)>)>> {
+        let start = Instant::now();
+        let chain_tokens = sqlx::query!(
+            r#"
+            SELECT id, chain_id, decimals FROM tokens
+            WHERE id IN (SELECT id FROM tokens GROUP BY id HAVING count(DISTINCT decimals) > 1)
+            ORDER BY id, chain_id
+            "#
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        let mut mismatches: Vec<(TokenId, Vec<(ChainId, i16)>)> = Vec::new();
+        for token in chain_tokens {
+            let token_id = TokenId(token.id as u32);
+            let chain_decimals = (ChainId(token.chain_id as u8), token.decimals);
+            match mismatches.last_mut() {
+                Some((id, decimals)) if *id == token_id => decimals.push(chain_decimals),
+                _ => mismatches.push((token_id, vec![chain_decimals])),
+            }
+        }
+
+        metrics::histogram!("sql.token.find_decimal_mismatches", start.elapsed());
+        Ok(mismatches)
+    }
+
     /// Loads all the stored tokens from the database.
     /// Alongside with the tokens added via `store_token` method, the default `ETH` token
     /// is returned.
@@ -148,6 +249,27 @@ impl<'a, 'c> TokensSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Exports all the rows of `tokens` table as JSON, which can be imported by `import_chain_tokens`.
+    pub async fn export_chain_tokens(&mut self, writer: impl Write) -> QueryResult<()> {
+        let start = Instant::now();
+        let mut chain_tokens = self.load_chain_tokens().await?;
+        chain_tokens.sort_by_key(|token| (token.id, token.chain_id));
+        serde_json::to_writer_pretty(writer, &chain_tokens)?;
+
+        metrics::histogram!("sql.token.export_chain_tokens", start.elapsed());
+        Ok(())
+    }
+
+    /// Imports the rows of `tokens` table from JSON exported by `export_chain_tokens`.
+    pub async fn import_chain_tokens(&mut self, reader: impl Read) -> QueryResult<()> {
+        let start = Instant::now();
+        let chain_tokens: Vec<DbTokenOfChain> = serde_json::from_reader(reader)?;
+        self.save_tokens(chain_tokens).await?;
+
+        metrics::histogram!("sql.token.import_chain_tokens", start.elapsed());
+        Ok(())
+    }
+
     /// Get token from Database by id
     pub async fn get_token(&mut self, token_id: i32) -> QueryResult<Option<DbToken>> {
         let start = Instant::now();
@@ -184,4 +306,275 @@ impl<'a, 'c> TokensSchema<'a, 'c> {
 
         Ok(chain_token)
     }
+
+    /// Loads the token of the layer1 `address` on the chain, which is the reverse lookup of `get_chain_token`.
+    pub async fn get_token_by_address_and_chain(
+        &mut self,
+        address: &[u8],
+        chain_id: i16,
+    ) -> QueryResult<Option<DbTokenOfChain>> {
+        let start = Instant::now();
+        let chain_token = sqlx::query_as!(
+            DbTokenOfChain,
+            r#"
+            SELECT * FROM tokens WHERE address = $1 AND chain_id = $2
+            "#,
+            address,
+            chain_id
+        )
+        .fetch_optional(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.token.get_token_by_address_and_chain", start.elapsed());
+        Ok(chain_token)
+    }
+
+    /// Loads all the tokens registered on the chain, ordered by token id.
+    pub async fn get_tokens_by_chain(&mut self, chain_id: i16) -> QueryResult<Vec<DbTokenOfChain>> {
+        let start = Instant::now();
+        let chain_tokens = sqlx::query_as!(
+            DbTokenOfChain,
+            r#"
+            SELECT * FROM tokens WHERE chain_id = $1 ORDER BY id
+            "#,
+            chain_id
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        metrics::histogram!("sql.token.get_tokens_by_chain", start.elapsed());
+        Ok(chain_tokens)
+    }
+}
+
+#[cfg(all(test, feature = "db_test"))]
+mod tests {
+    use chrono::Utc;
+    use sqlx::types::BigDecimal;
+
+    use super::records::{DbToken, DbTokenOfChain};
+    use crate::{QueryResult, StorageProcessor};
+    use zklink_types::{ChainId, TokenId};
+
+    #[tokio::test]
+    async fn chain_tokens_export_import_roundtrip() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let chain_tokens = vec![
+            DbTokenOfChain {
+                id: 1,
+                chain_id: 1,
+                address: vec![1u8; 20],
+                decimals: 18,
+                fast_withdraw: false,
+            },
+            DbTokenOfChain {
+                id: 1,
+                chain_id: 2,
+                address: vec![2u8; 20],
+                decimals: 6,
+                fast_withdraw: true,
+            },
+        ];
+        transaction
+            .tokens_schema()
+            .save_tokens(chain_tokens.clone())
+            .await?;
+
+        let mut exported = Vec::new();
+        transaction
+            .tokens_schema()
+            .export_chain_tokens(&mut exported)
+            .await?;
+        sqlx::query!("DELETE FROM tokens")
+            .execute(transaction.conn())
+            .await?;
+        transaction
+            .tokens_schema()
+            .import_chain_tokens(exported.as_slice())
+            .await?;
+
+        let mut imported = transaction.tokens_schema().load_chain_tokens().await?;
+        imported.sort_by_key(|token| (token.id, token.chain_id));
+        assert_eq!(imported, chain_tokens);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chain_tokens_lookup() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM tokens")
+            .execute(transaction.conn())
+            .await?;
+        for token_id in 1..=2 {
+            transaction
+                .tokens_schema()
+                .store_token_price(DbToken {
+                    token_id,
+                    symbol: format!("TK{}", token_id),
+                    price_id: String::new(),
+                    usd_price: BigDecimal::from(1),
+                    last_update_time: Utc::now(),
+                })
+                .await?;
+        }
+        let chain_token = |id: i32, chain_id: i16, address: u8| DbTokenOfChain {
+            id,
+            chain_id,
+            address: vec![address; 20],
+            decimals: 18,
+            fast_withdraw: false,
+        };
+        // The same address on different chains is registered as different tokens.
+        let chain_tokens = vec![
+            chain_token(1, 1, 1),
+            chain_token(2, 1, 2),
+            chain_token(1, 2, 2),
+        ];
+        transaction
+            .tokens_schema()
+            .save_tokens(chain_tokens.clone())
+            .await?;
+
+        let mut tokens_schema = transaction.tokens_schema();
+        assert_eq!(
+            tokens_schema
+                .get_token_by_address_and_chain(&[2u8; 20], 1)
+                .await?,
+            Some(chain_tokens[1].clone())
+        );
+        assert_eq!(
+            tokens_schema
+                .get_token_by_address_and_chain(&[2u8; 20], 2)
+                .await?,
+            Some(chain_tokens[2].clone())
+        );
+        assert_eq!(
+            tokens_schema
+                .get_token_by_address_and_chain(&[1u8; 20], 2)
+                .await?,
+            None
+        );
+        assert_eq!(
+            tokens_schema.get_tokens_by_chain(1).await?,
+            chain_tokens[..2].to_vec()
+        );
+        assert!(tokens_schema.get_tokens_by_chain(3).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_decimal_mismatches() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        sqlx::query!("DELETE FROM tokens")
+            .execute(transaction.conn())
+            .await?;
+        let chain_token = |id: i32, chain_id: i16, decimals: i16| DbTokenOfChain {
+            id,
+            chain_id,
+            address: vec![chain_id as u8; 20],
+            decimals,
+            fast_withdraw: false,
+        };
+        transaction
+            .tokens_schema()
+            .save_tokens(vec![chain_token(1, 1, 18), chain_token(1, 2, 18)])
+            .await?;
+        assert!(transaction
+            .tokens_schema()
+            .find_decimal_mismatches()
+            .await?
+            .is_empty());
+
+        transaction
+            .tokens_schema()
+            .save_tokens(vec![chain_token(1, 3, 6)])
+            .await?;
+        assert_eq!(
+            transaction
+                .tokens_schema()
+                .find_decimal_mismatches()
+                .await?,
+            vec![(
+                TokenId(1),
+                vec![(ChainId(1), 18), (ChainId(2), 18), (ChainId(3), 6)]
+            )]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_price_history() -> QueryResult<()> {
+        let mut storage = StorageProcessor::mock().await?;
+        let mut transaction = storage.start_transaction().await?;
+        let token_id = 60_000;
+        let token = |usd_price: u32| DbToken {
+            token_id,
+            symbol: "HIST".to_string(),
+            price_id: "hist".to_string(),
+            usd_price: BigDecimal::from(usd_price),
+            last_update_time: Utc::now(),
+        };
+
+        let before_stored = Utc::now();
+        transaction
+            .tokens_schema()
+            .store_token_price(token(1))
+            .await?;
+        let after_stored = Utc::now();
+        transaction
+            .tokens_schema()
+            .update_token_price(token(2))
+            .await?;
+
+        let mut tokens_schema = transaction.tokens_schema();
+        // The price recorded in the history is not affected by the later updates.
+        assert_eq!(
+            tokens_schema
+                .get_token_price_at(token_id, before_stored - chrono::Duration::seconds(1))
+                .await?,
+            None
+        );
+        assert_eq!(
+            tokens_schema
+                .get_token_price_at(token_id, after_stored)
+                .await?,
+            Some(BigDecimal::from(1))
+        );
+        assert_eq!(
+            tokens_schema
+                .get_token_price_at(token_id, Utc::now())
+                .await?,
+            Some(BigDecimal::from(2))
+        );
+        assert_eq!(
+            tokens_schema.get_token(token_id).await?.unwrap().usd_price,
+            BigDecimal::from(2)
+        );
+
+        // The unchanged price and the token without a stored price are not recorded.
+        transaction
+            .tokens_schema()
+            .update_token_price(token(2))
+            .await?;
+        transaction
+            .tokens_schema()
+            .update_token_price(DbToken {
+                token_id: token_id + 1,
+                ..token(3)
+            })
+            .await?;
+        let history_count = sqlx::query!(
+            "SELECT COUNT(*) FROM token_price_history WHERE token_id = $1 OR token_id = $2",
+            token_id,
+            token_id + 1
+        )
+        .fetch_one(transaction.conn())
+        .await?
+        .count;
+        assert_eq!(history_count, Some(2));
+        Ok(())
+    }
 }
@@ -135,6 +135,30 @@ where
     E::Fr::from_big_uint(quotient)
 }
 
+/// Checks the withdraw amount computed by `div_fr_with_arbitrary_precision` and
+/// `multiplication_fr_with_arbitrary_precision` against `chain_balance * balance / sum`
+/// computed exactly with the rational arithmetic.
+///
+/// Truncating the withdraw ratio to `precision` loses less than `chain_balance / 10^precision`,
+/// so the amount is never more than the exact one and at most that much plus one less.
+pub fn withdraw_amount_within_precision<E: Engine>(
+    balance: E::Fr,
+    sum: E::Fr,
+    chain_balance: E::Fr,
+    withdraw_amount: E::Fr,
+    precision: u64,
+) -> bool
+where
+    E::Fr: FeConvert,
+{
+    let chain_balance = chain_balance.into_big_uint();
+    let exact_amount = &chain_balance * balance.into_big_uint() / sum.into_big_uint();
+    let withdraw_amount = withdraw_amount.into_big_uint();
+    let max_deviation = chain_balance / BigUint::from(10u8).pow(precision as u32) + BigUint::one();
+
+    withdraw_amount <= exact_amount && exact_amount - withdraw_amount <= max_deviation
+}
+
 pub fn multiplication_and_sqrt<E: Engine>(a: E::Fr, b: E::Fr) -> Option<E::Fr>
 where
     E::Fr: FeConvert,
@@ -124,6 +124,52 @@ fn get_global_asset_account_audit_paths(
         .unzip()
 }
 
+/// Sum the balances of the global asset account of all chains that the withdraw ratio is based on
+fn sum_global_balances(l2_source_token: TokenId, global_balances: &[Vec<Fr>]) -> Fr {
+    global_balances.iter().fold(Fr::zero(), |mut acc, bal| {
+        acc.add_assign(
+            &bal.iter()
+                .enumerate()
+                .fold(Fr::zero(), |mut acc, (index, bal)| {
+                    if *l2_source_token == USD_TOKEN_ID || index == 0 {
+                        acc.add_assign(bal);
+                    }
+                    acc
+                }),
+        );
+        acc
+    })
+}
+
+/// Get the balance of the exiting account and the sum of the global asset account balances,
+/// the withdraw amount is the chain balance multiplied by `balance / sum`.
+pub fn get_exit_balance_and_sum(
+    account_tree: &CircuitAccountTree,
+    account_id: AccountId,
+    sub_account_id: SubAccountId,
+    l2_source_token: TokenId,
+    l1_target_token: TokenId,
+    max_chain_num: usize,
+) -> (Fr, Fr) {
+    let (_, l1_target_token_after_mapping) =
+        check_source_and_target_token(l2_source_token, l1_target_token);
+    let (_, balance, _) = get_leaf_values(
+        account_tree,
+        *account_id,
+        (*sub_account_id, *l2_source_token, 0),
+    );
+    let (_, (global_balances, _)) = get_global_asset_account_witnesses(
+        l2_source_token,
+        l1_target_token_after_mapping,
+        max_chain_num,
+        account_tree,
+    );
+    (
+        balance,
+        sum_global_balances(l2_source_token, &global_balances),
+    )
+}
+
 /// Get all chain audit datas of global asset account
 fn get_global_account_audit_datas(
     l2_source_token: TokenId,
@@ -233,29 +279,33 @@ pub fn create_exit_circuit_with_public_input(
             max_chain_num,
             account_tree,
         );
-    let sum = global_balances.iter().fold(Fr::zero(), |mut acc, bal| {
-        acc.add_assign(
-            &bal.iter()
-                .enumerate()
-                .fold(Fr::zero(), |mut acc, (index, bal)| {
-                    if *l2_source_token == USD_TOKEN_ID || index == 0 {
-                        acc.add_assign(bal);
-                    }
-                    acc
-                }),
-        );
-        acc
-    });
+    let sum = sum_global_balances(l2_source_token, &global_balances);
     let l1_token_index = [0, (*l1_target_token_after_mapping - 2) as usize]
         [(*l2_source_token == USD_TOKEN_ID) as usize];
+    let chain_balance = global_balances[(*chain_id - 1) as usize][l1_token_index];
     let withdraw_ratio =
         div_fr_with_arbitrary_precision::<Engine>(balance, sum, TOKEN_MAX_PRECISION).unwrap();
     let withdraw_amount = multiplication_fr_with_arbitrary_precision::<Engine>(
-        global_balances[(*chain_id - 1) as usize][l1_token_index],
+        chain_balance,
         withdraw_ratio,
         TOKEN_MAX_PRECISION,
     )
     .unwrap();
+    #[cfg(feature = "withdraw_amount_check")]
+    assert!(
+        withdraw_amount_within_precision::<Engine>(
+            balance,
+            sum,
+            chain_balance,
+            withdraw_amount,
+            TOKEN_MAX_PRECISION
+        ),
+        "Withdraw amount {} mismatches the exact one, balance: {}, sum: {}, chain balance: {}",
+        withdraw_amount.into_big_uint(),
+        balance.into_big_uint(),
+        sum.into_big_uint(),
+        chain_balance.into_big_uint()
+    );
 
     append_be_fixed_width(&mut pubdata_commitment, &account_address, ADDRESS_WIDTH);
     append_be_fixed_width(
@@ -317,3 +367,145 @@ pub fn create_exit_circuit_with_public_input(
         withdraw_amount.into_big_uint(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use num::One;
+    use zklink_crypto::circuit::account::{Balance, CircuitAccount};
+    use zklink_crypto::params::account_tree_depth;
+    use zklink_types::utils::calculate_actual_token;
+
+    use super::*;
+
+    fn withdraw_amount(balance: &BigUint, sum: &BigUint, chain_balance: &BigUint) -> bool {
+        let (balance, sum, chain_balance) = (
+            Fr::from_big_uint(balance.clone()).unwrap(),
+            Fr::from_big_uint(sum.clone()).unwrap(),
+            Fr::from_big_uint(chain_balance.clone()).unwrap(),
+        );
+        let withdraw_ratio =
+            div_fr_with_arbitrary_precision::<Engine>(balance, sum, TOKEN_MAX_PRECISION).unwrap();
+        let withdraw_amount = multiplication_fr_with_arbitrary_precision::<Engine>(
+            chain_balance,
+            withdraw_ratio,
+            TOKEN_MAX_PRECISION,
+        )
+        .unwrap();
+        withdraw_amount_within_precision::<Engine>(
+            balance,
+            sum,
+            chain_balance,
+            withdraw_amount,
+            TOKEN_MAX_PRECISION,
+        )
+    }
+
+    #[test]
+    fn withdraw_amount_within_precision_at_edge_balances() {
+        let max_balance = (BigUint::one() << BALANCE_BIT_WIDTH) - BigUint::one();
+        let one = BigUint::one();
+        let cases = [
+            // dust
+            (one.clone(), max_balance.clone(), max_balance.clone()),
+            (one.clone(), BigUint::from(3u8), BigUint::from(1u8)),
+            (one.clone(), BigUint::from(3u8), BigUint::from(10u8).pow(18)),
+            // the whole balance
+            (
+                max_balance.clone(),
+                max_balance.clone(),
+                max_balance.clone(),
+            ),
+            (one.clone(), one.clone(), max_balance.clone()),
+            // max
+            (
+                max_balance.clone() - BigUint::one(),
+                max_balance.clone(),
+                max_balance.clone() / BigUint::from(7u8),
+            ),
+            (
+                BigUint::from(10u8).pow(30),
+                BigUint::from(3u8) * BigUint::from(10u8).pow(30),
+                max_balance,
+            ),
+        ];
+        for (balance, sum, chain_balance) in cases {
+            assert!(
+                withdraw_amount(&balance, &sum, &chain_balance),
+                "balance: {}, sum: {}, chain balance: {}",
+                balance,
+                sum,
+                chain_balance
+            );
+        }
+    }
+
+    #[test]
+    fn withdraw_amount_mismatch_detected() {
+        let (balance, sum, chain_balance) =
+            (Fr::from_u64(1), Fr::from_u64(2), Fr::from_u64(1_000_000));
+        // More than the exact amount
+        assert!(!withdraw_amount_within_precision::<Engine>(
+            balance,
+            sum,
+            chain_balance,
+            Fr::from_u64(500_001),
+            TOKEN_MAX_PRECISION
+        ));
+        // Less than the exact amount beyond the precision
+        assert!(!withdraw_amount_within_precision::<Engine>(
+            balance,
+            sum,
+            chain_balance,
+            Fr::from_u64(499_998),
+            TOKEN_MAX_PRECISION
+        ));
+        assert!(withdraw_amount_within_precision::<Engine>(
+            balance,
+            sum,
+            chain_balance,
+            Fr::from_u64(500_000),
+            TOKEN_MAX_PRECISION
+        ));
+    }
+
+    #[test]
+    fn exit_balance_and_sum_of_zero_balance_account() {
+        let (account_id, sub_account_id, token_id) = (AccountId(10), SubAccountId(1), TokenId(18));
+        let max_chain_num = 4;
+        let mut account_tree = CircuitAccountTree::new(account_tree_depth());
+        account_tree.insert(*account_id, CircuitAccount::default());
+        account_tree.insert(*GLOBAL_ASSET_ACCOUNT_ID, CircuitAccount::default());
+
+        // Neither the account nor the global asset account has any balance.
+        let (balance, sum) = get_exit_balance_and_sum(
+            &account_tree,
+            account_id,
+            sub_account_id,
+            token_id,
+            token_id,
+            max_chain_num,
+        );
+        assert!(balance.is_zero());
+        assert!(sum.is_zero());
+
+        // The token is deposited to the second chain by the other accounts.
+        let mut global_account = CircuitAccount::default();
+        global_account.subtree.insert(
+            *calculate_actual_token(SubAccountId(2), token_id),
+            Balance {
+                value: Fr::from_u64(100),
+            },
+        );
+        account_tree.insert(*GLOBAL_ASSET_ACCOUNT_ID, global_account);
+        let (balance, sum) = get_exit_balance_and_sum(
+            &account_tree,
+            account_id,
+            sub_account_id,
+            token_id,
+            token_id,
+            max_chain_num,
+        );
+        assert!(balance.is_zero());
+        assert_eq!(sum, Fr::from_u64(100));
+    }
+}
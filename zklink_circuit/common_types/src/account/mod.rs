@@ -5,6 +5,7 @@ use crate::ZkLinkAddress;
 use num::{BigUint, Zero};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use zklink_basic_types::SlotId;
 use zklink_crypto::circuit::account::{Balance, CircuitAccount, CircuitTidyOrder};
 use zklink_crypto::convert::FeConvert;
@@ -33,6 +34,26 @@ impl PartialEq for BalanceNode {
     }
 }
 
+/// Error of subtracting more than the existing token balance of an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceUnderflow {
+    pub token: TokenId,
+    pub balance: BigUint,
+    pub amount: BigUint,
+}
+
+impl fmt::Display for BalanceUnderflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Balance underflow of token {}: balance {} is less than amount {}",
+            *self.token, self.balance, self.amount
+        )
+    }
+}
+
+impl std::error::Error for BalanceUnderflow {}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct TidyOrder {
     /// Slot nonce
@@ -217,6 +238,25 @@ impl Account {
         self.balances.insert(token, balance_node);
     }
 
+    /// Subtracts the provided amount from the token balance, leaving the balance
+    /// untouched and returning an error if the amount is greater than the balance.
+    pub fn checked_sub_balance(
+        &mut self,
+        token: TokenId,
+        amount: &BigUint,
+    ) -> Result<(), BalanceUnderflow> {
+        let balance = self.get_balance(token);
+        if &balance < amount {
+            return Err(BalanceUnderflow {
+                token,
+                balance,
+                amount: amount.clone(),
+            });
+        }
+        self.sub_balance(token, amount);
+        Ok(())
+    }
+
     /// Given the list of updates to apply, changes the account state.
     pub fn apply_updates(mut account: Option<Self>, updates: &[AccountUpdate]) -> Option<Self> {
         for update in updates {
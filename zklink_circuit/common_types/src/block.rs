@@ -75,7 +75,7 @@ pub struct Block {
 }
 
 /// StoredBlockInfo is defined in Storage.sol
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct StoredBlockInfo {
     pub block_number: BlockNumber,             // Rollup block number
     pub priority_operations: u64,              // Number of priority operations processed
@@ -53,7 +53,7 @@ pub mod utils;
 #[cfg(test)]
 mod tests;
 
-pub use self::account::{Account, AccountUpdate, PubKeyHash};
+pub use self::account::{Account, AccountUpdate, BalanceUnderflow, PubKeyHash};
 pub use self::block::ExecutedTx;
 pub use self::layer_one::*;
 pub use self::operations::{
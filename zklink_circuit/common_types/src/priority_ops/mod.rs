@@ -2,7 +2,7 @@
 
 use std::convert::TryInto;
 
-use anyhow::{bail, ensure};
+use anyhow::{bail, ensure, format_err};
 use num::BigUint;
 use serde::{Deserialize, Serialize};
 use zklink_basic_types::{SubAccountId, H256};
@@ -119,7 +119,7 @@ impl ZkLinkPriorityOp {
                         "DepositOp PubData length mismatch"
                     );
                     let (token, left) = pub_data_left.split_at(TOKEN_BIT_WIDTH / 8);
-                    (u16::from_be_bytes(token.try_into().unwrap()), left)
+                    (u16::from_be_bytes(token.try_into()?), left)
                 };
 
                 // l2_target_token
@@ -129,7 +129,7 @@ impl ZkLinkPriorityOp {
                         "DepositOp PubData length mismatch"
                     );
                     let (token, left) = pub_data_left.split_at(TOKEN_BIT_WIDTH / 8);
-                    (u16::from_be_bytes(token.try_into().unwrap()), left)
+                    (u16::from_be_bytes(token.try_into()?), left)
                 };
 
                 // amount
@@ -139,7 +139,7 @@ impl ZkLinkPriorityOp {
                         "PubData length mismatch"
                     );
                     let (amount, left) = pub_data_left.split_at(BALANCE_BIT_WIDTH / 8);
-                    let amount = u128::from_be_bytes(amount.try_into().unwrap());
+                    let amount = u128::from_be_bytes(amount.try_into()?);
                     (BigUint::from(amount), left)
                 };
 
@@ -159,7 +159,7 @@ impl ZkLinkPriorityOp {
                 );
 
                 Ok(Self::Deposit(PriorityDeposit {
-                    chain_id: u8::from_be_bytes(chain_id.try_into().unwrap()),
+                    chain_id: u8::from_be_bytes(chain_id.try_into()?),
                     from: sender,
                     l1_source_token: TokenId(real_token as u32),
                     l2_target_token: TokenId(user_token as u32),
@@ -191,7 +191,10 @@ impl ZkLinkPriorityOp {
                         "FullExitOp PubData length mismatch"
                     );
                     let (account_id, left) = pub_data_left.split_at(ACCOUNT_ID_BIT_WIDTH / 8);
-                    (u32::from_bytes(account_id).unwrap(), left)
+                    let account_id = u32::from_bytes(account_id).ok_or_else(|| {
+                        format_err!("Cant get account id from FullExitOp PubData")
+                    })?;
+                    (account_id, left)
                 };
 
                 // sub_account_id
@@ -219,7 +222,7 @@ impl ZkLinkPriorityOp {
                         "FullExitOp PubData length mismatch"
                     );
                     let (token, left) = pub_data_left.split_at(TOKEN_BIT_WIDTH / 8);
-                    (u16::from_be_bytes(token.try_into().unwrap()), left)
+                    (u16::from_be_bytes(token.try_into()?), left)
                 };
 
                 // l2_target_token
@@ -229,7 +232,7 @@ impl ZkLinkPriorityOp {
                         "FullExitOp PubData length mismatch"
                     );
                     let (token, left) = pub_data_left.split_at(TOKEN_BIT_WIDTH / 8);
-                    (u16::from_be_bytes(token.try_into().unwrap()), left)
+                    (u16::from_be_bytes(token.try_into()?), left)
                 };
 
                 // amount
@@ -240,7 +243,7 @@ impl ZkLinkPriorityOp {
                 );
 
                 Ok(Self::FullExit(PriorityFullExit {
-                    chain_id: u8::from_be_bytes(chain_id.try_into().unwrap()),
+                    chain_id: u8::from_be_bytes(chain_id.try_into()?),
                     account_id: AccountId(account_id),
                     sub_account_id: SubAccountId(sub_account_id[0]),
                     initiator: sender,
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    operations::{DepositOp, FullExitOp},
     priority_ops::{PriorityFullExit, PriorityOp},
-    AccountId, SerialId, TokenId, ZkLinkPriorityOp, H256,
+    AccountId, SerialId, TokenId, ZkLinkOp, ZkLinkPriorityOp, H256,
 };
 
 /// Tests the migration of `PriorityOp::eth_hash` from the `Vec<u8>` to `H256` type
@@ -118,3 +119,88 @@ mod backward_compatibility {
         let _new_value: PriorityOp = serde_json::from_value(old_serialized).unwrap();
     }
 }
+
+/// Feeds the random bytes to the pubdata parsers, malformed contract data must be rejected without panic.
+/// The buffers of the exact length of each op type reach the field parsing, the others are rejected by length.
+mod malformed_pubdata {
+    use super::*;
+    use crate::operations::{
+        ChangePubKeyOp, ForcedExitOp, NoopOp, OrderMatchingOp, TransferOp, TransferToNewOp,
+        WithdrawOp,
+    };
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use zklink_crypto::params::{
+        ACCOUNT_ID_BIT_WIDTH, BALANCE_BIT_WIDTH, CHAIN_ID_BIT_WIDTH, CHUNK_BYTES,
+        ETH_ADDRESS_BIT_WIDTH, SUB_ACCOUNT_ID_BIT_WIDTH, TOKEN_BIT_WIDTH, TX_TYPE_BIT_WIDTH,
+    };
+
+    /// The length of the pubdata of both the deposit and the full exit emitted by the contract.
+    const PRIORITY_OP_PUB_DATA_LEN: usize = (TX_TYPE_BIT_WIDTH
+        + CHAIN_ID_BIT_WIDTH
+        + ACCOUNT_ID_BIT_WIDTH
+        + SUB_ACCOUNT_ID_BIT_WIDTH
+        + TOKEN_BIT_WIDTH * 2
+        + BALANCE_BIT_WIDTH
+        + ETH_ADDRESS_BIT_WIDTH)
+        / 8;
+
+    /// The op type id and the pubdata of the priority queue logs.
+    fn priority_op_pub_data() -> impl Strategy<Value = (u8, Vec<u8>)> {
+        prop_oneof![
+            (
+                Just(DepositOp::OP_CODE),
+                vec(any::<u8>(), PRIORITY_OP_PUB_DATA_LEN)
+            ),
+            (
+                Just(FullExitOp::OP_CODE),
+                vec(any::<u8>(), PRIORITY_OP_PUB_DATA_LEN)
+            ),
+            (any::<u8>(), vec(any::<u8>(), 0..128)),
+        ]
+    }
+
+    /// The public data of the ops in the block, starting with the op type.
+    fn public_data() -> impl Strategy<Value = Vec<u8>> {
+        let exact_len = prop::sample::select(vec![
+            (NoopOp::OP_CODE, NoopOp::CHUNKS),
+            (DepositOp::OP_CODE, DepositOp::CHUNKS),
+            (TransferToNewOp::OP_CODE, TransferToNewOp::CHUNKS),
+            (TransferOp::OP_CODE, TransferOp::CHUNKS),
+            (WithdrawOp::OP_CODE, WithdrawOp::CHUNKS),
+            (FullExitOp::OP_CODE, FullExitOp::CHUNKS),
+            (ChangePubKeyOp::OP_CODE, ChangePubKeyOp::CHUNKS),
+            (ForcedExitOp::OP_CODE, ForcedExitOp::CHUNKS),
+            (OrderMatchingOp::OP_CODE, OrderMatchingOp::CHUNKS),
+        ])
+        .prop_flat_map(|(op_code, chunks)| {
+            vec(any::<u8>(), chunks * CHUNK_BYTES - 1).prop_map(move |pub_data| {
+                let mut bytes = vec![op_code];
+                bytes.extend(pub_data);
+                bytes
+            })
+        });
+        prop_oneof![exact_len, vec(any::<u8>(), 0..256)]
+    }
+
+    proptest! {
+        #[test]
+        fn parse_priority_queue_logs_never_panics(
+            (op_type_id, pub_data) in priority_op_pub_data(),
+            serial_id in any::<u64>(),
+        ) {
+            let _ = ZkLinkPriorityOp::parse_from_priority_queue_logs(
+                &pub_data,
+                op_type_id,
+                Default::default(),
+                serial_id,
+                H256::zero(),
+            );
+        }
+
+        #[test]
+        fn parse_public_data_never_panics(bytes in public_data()) {
+            let _ = ZkLinkOp::from_public_data(&bytes);
+        }
+    }
+}
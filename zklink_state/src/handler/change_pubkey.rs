@@ -40,8 +40,7 @@ impl TxHandler<ChangePubKey> for ZkLinkState {
         account.pub_key_hash = op.tx.new_pk_hash;
 
         // Subtract fees.
-        ensure!(old_balance >= op.tx.fee, "Not enough balance");
-        account.sub_balance(actual_fee_token, &op.tx.fee);
+        account.checked_sub_balance(actual_fee_token, &op.tx.fee)?;
 
         let new_pub_key_hash = account.pub_key_hash;
         let new_nonce = account.nonce;
@@ -121,7 +121,7 @@ impl TxHandler<ForcedExit> for ZkLinkState {
         *initiator_account.nonce += 1;
 
         // Withdraw funds from the target account (note that target account nonce is not affected).
-        target_account.sub_balance(real_token, &op.withdraw_amount);
+        target_account.checked_sub_balance(real_token, &op.withdraw_amount)?;
 
         // Store required data to generate account updates later.
         let initiator_new_balance = initiator_account.get_balance(real_fee_token);
@@ -158,7 +158,7 @@ impl TxHandler<ForcedExit> for ZkLinkState {
         ));
         {
             let global_old_amount = global_account.get_balance(global_real_token);
-            global_account.sub_balance(global_real_token, &op.withdraw_amount);
+            global_account.checked_sub_balance(global_real_token, &op.withdraw_amount)?;
             let global_new_amount = global_account.get_balance(global_real_token);
 
             updates.push((
@@ -82,7 +82,7 @@ impl TxHandler<FullExit> for ZkLinkState {
                 Self::get_actual_token_by_sub_account(op.tx.sub_account_id, op.tx.l2_source_token);
             let old_balance = account.get_balance(real_token);
             let old_nonce = account.nonce;
-            account.sub_balance(real_token, &op.exit_amount);
+            account.checked_sub_balance(real_token, &op.exit_amount)?;
             let new_balance = account.get_balance(real_token);
             let new_nonce = account.nonce;
 
@@ -107,7 +107,7 @@ impl TxHandler<FullExit> for ZkLinkState {
                 op.l1_target_token_after_mapping,
             );
             let global_old_amount = global_account.get_balance(real_token);
-            global_account.sub_balance(real_token, &op.exit_amount);
+            global_account.checked_sub_balance(real_token, &op.exit_amount)?;
             let global_new_amount = global_account.get_balance(real_token);
 
             updates.push((
@@ -153,7 +153,7 @@ impl ZkLinkState {
             ));
             // modified balance
             let old_balance = maker_account.get_balance(maker_sell_token);
-            maker_account.sub_balance(maker_sell_token, &maker_sell_amount);
+            maker_account.checked_sub_balance(maker_sell_token, &maker_sell_amount)?;
             let new_balance = maker_account.get_balance(maker_sell_token);
             updates.push((
                 op.tx.maker.account_id,
@@ -217,7 +217,7 @@ impl ZkLinkState {
             ));
             // modified balance
             let old_balance = taker_account.get_balance(taker_sell_token);
-            taker_account.sub_balance(taker_sell_token, &taker_sell_amount);
+            taker_account.checked_sub_balance(taker_sell_token, &taker_sell_amount)?;
             let new_balance = taker_account.get_balance(taker_sell_token);
             updates.push((
                 op.tx.taker.account_id,
@@ -265,7 +265,7 @@ impl ZkLinkState {
             // 5.update submitter account balance
             let old_balance = submitter_account.get_balance(actual_fee_token);
             let old_nonce = submitter_account.nonce;
-            submitter_account.sub_balance(actual_fee_token, &op.tx.fee);
+            submitter_account.checked_sub_balance(actual_fee_token, &op.tx.fee)?;
             let new_balance = submitter_account.get_balance(actual_fee_token);
             updates.push((
                 op.tx.account_id,
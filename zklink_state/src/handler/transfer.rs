@@ -71,12 +71,8 @@ impl ZkLinkState {
         let from_old_balance = from_account.get_balance(from_real_token);
         let from_old_nonce = from_account.nonce;
         ensure!(op.tx.nonce == from_old_nonce, "Nonce does not match");
-        ensure!(
-            from_old_balance >= &op.tx.amount + &op.tx.fee,
-            "Insufficient balance"
-        );
 
-        from_account.sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee));
+        from_account.checked_sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee))?;
         *from_account.nonce += 1;
 
         let from_new_balance = from_account.get_balance(from_real_token);
@@ -139,12 +135,8 @@ impl ZkLinkState {
         let old_balance = account.get_balance(from_real_token);
         let old_nonce = account.nonce;
         ensure!(op.tx.nonce == old_nonce, "Nonce mismatch");
-        ensure!(
-            old_balance >= &op.tx.amount + &op.tx.fee,
-            "Transfer account is not enough balance"
-        );
 
-        account.sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee));
+        account.checked_sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee))?;
         *account.nonce += 1;
         let new_balance = account.get_balance(from_real_token);
         let new_nonce = account.nonce;
@@ -207,11 +199,7 @@ impl ZkLinkState {
         let from_old_balance = from_account.get_balance(from_real_token);
         let from_old_nonce = from_account.nonce;
         ensure!(op.tx.nonce == from_old_nonce, "Nonce does not match");
-        ensure!(
-            from_old_balance >= &op.tx.amount + &op.tx.fee,
-            "Insufficient balance"
-        );
-        from_account.sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee));
+        from_account.checked_sub_balance(from_real_token, &(&op.tx.amount + &op.tx.fee))?;
         *from_account.nonce += 1;
         let from_new_balance = from_account.get_balance(from_real_token);
         let from_new_nonce = from_account.nonce;
@@ -50,12 +50,8 @@ impl TxHandler<Withdraw> for ZkLinkState {
             let from_old_balance = from_account.get_balance(actual_token);
             let from_old_nonce = from_account.nonce;
             ensure!(op.tx.nonce == from_old_nonce, "Nonce does not match");
-            ensure!(
-                from_old_balance >= &op.tx.amount + &op.tx.fee,
-                "Insufficient balance"
-            );
 
-            from_account.sub_balance(actual_token, &(&op.tx.amount + &op.tx.fee));
+            from_account.checked_sub_balance(actual_token, &(&op.tx.amount + &op.tx.fee))?;
             *from_account.nonce += 1;
 
             let from_new_balance = from_account.get_balance(actual_token);
@@ -82,11 +78,8 @@ impl TxHandler<Withdraw> for ZkLinkState {
                 op.l1_target_token_after_mapping,
             );
             let global_old_amount = global_account.get_balance(actual_token);
-            ensure!(
-                global_old_amount >= op.tx.amount,
-                "Withdrawal amount is greater than l1 withdrawal limit"
-            );
-            global_account.sub_balance(actual_token, &op.tx.amount);
+            // The withdrawal amount can't be greater than the l1 withdrawal limit.
+            global_account.checked_sub_balance(actual_token, &op.tx.amount)?;
             let global_new_amount = global_account.get_balance(actual_token);
             updates.push((
                 GLOBAL_ASSET_ACCOUNT_ID,